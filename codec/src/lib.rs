@@ -12,6 +12,7 @@
 #![cfg_attr(feature = "cargo-clippy", allow(clippy::range_plus_one))]
 
 use anyhow::{bail, Context as _, Error};
+use config::keyassignment::PaneDirection;
 use mux::client::{ClientId, ClientInfo};
 use mux::pane::PaneId;
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
@@ -22,6 +23,7 @@ use rangeset::*;
 use serde::{Deserialize, Serialize};
 use smol::io::AsyncWriteExt;
 use smol::prelude::*;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io::Cursor;
 use std::ops::Range;
@@ -405,7 +407,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 21;
+pub const CODEC_VERSION: usize = 24;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -451,6 +453,28 @@ pdu! {
     SetFocusedPane: 45,
     GetImageCell: 46,
     GetImageCellResponse: 47,
+    SendKeyUp: 48,
+    SftpTransfer: 49,
+    SftpTransferResponse: 50,
+    SaveSession: 51,
+    SaveSessionResponse: 52,
+    RestoreSession: 53,
+    RestoreSessionResponse: 54,
+    GetPaneRenderableDimensions: 55,
+    GetPaneRenderableDimensionsResponse: 56,
+    GetPalette: 57,
+    GetPaletteResponse: 58,
+    ActivatePane: 59,
+    ActivatePaneDirection: 60,
+    AdjustPaneSize: 61,
+    MovePaneToNewTab: 62,
+    MovePaneToNewTabResponse: 63,
+    SetTabTitle: 64,
+    SetWindowTitle: 65,
+    ActivateTab: 66,
+    RenameWorkspace: 67,
+    SubscribeToMuxEvents: 68,
+    MuxEvent: 69,
 }
 
 impl Pdu {
@@ -576,6 +600,9 @@ pub struct ListPanes {}
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct ListPanesResponse {
     pub tabs: Vec<PaneNode>,
+    /// The title of each of the tabs; parallel to `tabs`
+    pub tab_titles: Vec<String>,
+    pub window_titles: HashMap<WindowId, String>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -635,6 +662,66 @@ pub struct SendKeyDown {
     pub input_serial: InputSerial,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SendKeyUp {
+    pub pane_id: TabId,
+    pub event: termwiz::input::KeyEvent,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SftpTransferDirection {
+    Download,
+    Upload,
+}
+
+/// Copies a file between the local host and the remote host of
+/// a pane that belongs to an ssh domain.
+/// Relative remote paths are resolved relative to the remote
+/// cwd of the pane.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SftpTransfer {
+    pub pane_id: PaneId,
+    pub direction: SftpTransferDirection,
+    pub remote_path: String,
+    pub local_path: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SftpTransferResponse {
+    /// The paths that were used after resolving relative paths
+    /// and directories
+    pub remote_path: String,
+    pub local_path: String,
+    pub bytes: u64,
+}
+
+/// Saves a snapshot of the windows, tabs and panes in the mux.
+/// If `path` is not specified, the configured session file is used.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SaveSession {
+    pub path: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SaveSessionResponse {
+    pub path: String,
+    pub num_windows: usize,
+    pub num_panes: usize,
+}
+
+/// Recreates the windows, tabs and panes from a saved snapshot,
+/// alongside any that are already present in the mux.
+/// If `path` is not specified, the configured session file is used.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct RestoreSession {
+    pub path: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct RestoreSessionResponse {
+    pub window_ids: Vec<WindowId>,
+}
+
 /// InputSerial is used to sequence input requests with output events.
 /// It started life as a monotonic sequence number but evolved into
 /// the number of milliseconds since the unix epoch.
@@ -772,6 +859,186 @@ pub struct GetLines {
     pub lines: Vec<Range<StableRowIndex>>,
 }
 
+/// Returns the current dimensions and cursor position of a pane,
+/// so that callers can compute the ranges of lines to request
+/// via `GetLines`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneRenderableDimensions {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneRenderableDimensionsResponse {
+    pub pane_id: PaneId,
+    pub cursor_position: StableCursorPosition,
+    pub dimensions: RenderableDimensions,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPalette {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaletteResponse {
+    pub pane_id: PaneId,
+    pub palette: ColorPalette,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ActivatePane {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ActivatePaneDirection {
+    pub pane_id: PaneId,
+    pub direction: PaneDirection,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct AdjustPaneSize {
+    pub pane_id: PaneId,
+    pub direction: PaneDirection,
+    pub amount: usize,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MovePaneToNewTab {
+    pub pane_id: PaneId,
+    /// If None, the new tab is added to the window that
+    /// contains the pane, unless new_window is true
+    pub window_id: Option<WindowId>,
+    pub new_window: bool,
+    /// The workspace for the new window; defaults to the
+    /// workspace of the window that contains the pane
+    pub workspace: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MovePaneToNewTabResponse {
+    pub tab_id: TabId,
+    pub window_id: WindowId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetTabTitle {
+    pub tab_id: TabId,
+    pub title: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetWindowTitle {
+    pub window_id: WindowId,
+    pub title: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ActivateTab {
+    pub tab_id: TabId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct RenameWorkspace {
+    pub old_workspace: String,
+    pub new_workspace: String,
+}
+
+/// The kinds of mux notification that can be streamed to a client
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum MuxEventKind {
+    PaneOutput,
+    PaneAdded,
+    PaneRemoved,
+    WindowCreated,
+    WindowRemoved,
+    WindowInvalidated,
+    WindowWorkspaceChanged,
+    ActiveWorkspaceChanged,
+    Alert,
+    Empty,
+}
+
+impl MuxEventKind {
+    pub const ALL: &'static [MuxEventKind] = &[
+        Self::PaneOutput,
+        Self::PaneAdded,
+        Self::PaneRemoved,
+        Self::WindowCreated,
+        Self::WindowRemoved,
+        Self::WindowInvalidated,
+        Self::WindowWorkspaceChanged,
+        Self::ActiveWorkspaceChanged,
+        Self::Alert,
+        Self::Empty,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PaneOutput => "pane-output",
+            Self::PaneAdded => "pane-added",
+            Self::PaneRemoved => "pane-removed",
+            Self::WindowCreated => "window-created",
+            Self::WindowRemoved => "window-removed",
+            Self::WindowInvalidated => "window-invalidated",
+            Self::WindowWorkspaceChanged => "window-workspace-changed",
+            Self::ActiveWorkspaceChanged => "active-workspace-changed",
+            Self::Alert => "alert",
+            Self::Empty => "empty",
+        }
+    }
+}
+
+impl std::str::FromStr for MuxEventKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|kind| kind.as_str() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|kind| kind.as_str()).collect();
+                format!(
+                    "{} is not a valid event type; use one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Asks the server to stream MuxEvent PDUs to this client.
+/// An empty list matches everything; otherwise only events that
+/// match all of the non-empty lists are sent.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct SubscribeToMuxEvents {
+    pub pane_ids: Vec<PaneId>,
+    pub window_ids: Vec<WindowId>,
+    pub kinds: Vec<MuxEventKind>,
+}
+
+impl SubscribeToMuxEvents {
+    pub fn matches(&self, event: &MuxEvent) -> bool {
+        fn allowed<T: PartialEq>(list: &[T], item: Option<T>) -> bool {
+            list.is_empty() || item.map(|item| list.contains(&item)).unwrap_or(false)
+        }
+        allowed(&self.kinds, Some(event.kind))
+            && allowed(&self.pane_ids, event.pane_id)
+            && allowed(&self.window_ids, event.window_id)
+    }
+}
+
+/// A mux notification, sent unilaterally to clients that
+/// have sent SubscribeToMuxEvents
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct MuxEvent {
+    pub kind: MuxEventKind,
+    pub pane_id: Option<PaneId>,
+    pub window_id: Option<WindowId>,
+    pub workspace: Option<String>,
+    pub alert: Option<Alert>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 struct CellCoordinates {
     line_idx: usize,
@@ -1093,4 +1360,57 @@ mod test {
             Pdu::decode(encoded.as_slice()).unwrap()
         );
     }
+
+    #[test]
+    fn mux_event_kinds() {
+        for kind in MuxEventKind::ALL {
+            assert_eq!(kind.as_str().parse::<MuxEventKind>().unwrap(), *kind);
+        }
+        assert!("bogus".parse::<MuxEventKind>().is_err());
+    }
+
+    #[test]
+    fn mux_event_filter() {
+        let event = MuxEvent {
+            kind: MuxEventKind::PaneOutput,
+            pane_id: Some(1),
+            window_id: Some(2),
+            workspace: None,
+            alert: None,
+        };
+        let filter =
+            |pane_ids: Vec<PaneId>, window_ids: Vec<WindowId>, kinds| SubscribeToMuxEvents {
+                pane_ids,
+                window_ids,
+                kinds,
+            };
+
+        assert!(filter(vec![], vec![], vec![]).matches(&event));
+        assert!(filter(vec![1], vec![2], vec![MuxEventKind::PaneOutput]).matches(&event));
+        assert!(!filter(vec![3], vec![], vec![]).matches(&event));
+        assert!(!filter(vec![], vec![3], vec![]).matches(&event));
+        assert!(!filter(vec![], vec![], vec![MuxEventKind::Alert]).matches(&event));
+
+        let empty = MuxEvent {
+            kind: MuxEventKind::Empty,
+            pane_id: None,
+            window_id: None,
+            workspace: None,
+            alert: None,
+        };
+        assert!(filter(vec![], vec![], vec![]).matches(&empty));
+        assert!(!filter(vec![1], vec![], vec![]).matches(&empty));
+
+        let mut encoded = Vec::new();
+        Pdu::MuxEvent(event.clone())
+            .encode(&mut encoded, 0)
+            .unwrap();
+        assert_eq!(
+            Pdu::decode(encoded.as_slice()).unwrap(),
+            DecodedPdu {
+                serial: 0,
+                pdu: Pdu::MuxEvent(event)
+            }
+        );
+    }
 }
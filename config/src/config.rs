@@ -246,6 +246,18 @@ pub struct Config {
     #[serde(default = "default_mux_output_parser_buffer_size")]
     pub mux_output_parser_buffer_size: usize,
 
+    /// When an application enables synchronized output, its output is
+    /// held until it disables it again.  If that doesn't happen within
+    /// this many milliseconds, the held output is released so that a
+    /// stuck application doesn't freeze the pane.
+    #[serde(default = "default_synchronized_output_timeout_ms")]
+    pub synchronized_output_timeout_ms: u64,
+
+    /// The maximum number of bytes of output that will be held while
+    /// synchronized output is active before it is released anyway.
+    #[serde(default = "default_synchronized_output_max_bytes")]
+    pub synchronized_output_max_bytes: usize,
+
     #[serde(default = "default_mux_env_remove", deserialize_with = "de_vec_table")]
     pub mux_env_remove: Vec<String>,
 
@@ -279,6 +291,9 @@ pub struct Config {
     #[serde(default)]
     pub daemon_options: DaemonOptions,
 
+    #[serde(default)]
+    pub mux_session: MuxSessionOptions,
+
     /// If set to true, send the system specific composed key when
     /// the ALT key is held down.  If set to false
     /// then send the key with the ALT modifier (this is typically
@@ -520,6 +535,12 @@ pub struct Config {
     #[serde(default)]
     pub enable_csi_u_key_encoding: bool,
 
+    /// When set to true, applications may negotiate the use of the
+    /// kitty keyboard protocol as described in
+    /// https://sw.kovidgoyal.net/kitty/keyboard-protocol/
+    #[serde(default)]
+    pub enable_kitty_keyboard: bool,
+
     #[serde(default)]
     pub window_close_confirmation: WindowCloseConfirmation,
 
@@ -1082,6 +1103,14 @@ fn default_mux_output_parser_buffer_size() -> usize {
     128 * 1024
 }
 
+fn default_synchronized_output_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_synchronized_output_max_bytes() -> usize {
+    4 * 1024 * 1024
+}
+
 fn default_ratelimit_line_prefetches_per_second() -> u32 {
     10
 }
@@ -1150,6 +1179,14 @@ pub(crate) fn compute_runtime_dir() -> anyhow::Result<PathBuf> {
     Ok(crate::HOME_DIR.join(".local/share/wezterm"))
 }
 
+/// Unlike the runtime dir, this is expected to survive a reboot
+pub(crate) fn compute_data_dir() -> PathBuf {
+    match dirs_next::data_dir() {
+        Some(data) => data.join("wezterm"),
+        None => crate::HOME_DIR.join(".local/share/wezterm"),
+    }
+}
+
 pub fn pki_dir() -> anyhow::Result<PathBuf> {
     compute_runtime_dir().map(|d| d.join("pki"))
 }
@@ -3,6 +3,7 @@ use bitflags::*;
 use enum_display_derive::Display;
 use luahelper::impl_lua_conversion;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::fmt::Display;
 use termwiz::color::RgbColor;
@@ -364,6 +365,10 @@ pub struct FontAttributes {
     pub freetype_load_flags: Option<FreeTypeLoadFlags>,
     #[serde(default)]
     pub scale: Option<NotNan<f64>>,
+    /// Explicit design coordinates for the axes of a variable font,
+    /// keyed by the OpenType axis tag, eg: `wght` or `CASL`
+    #[serde(default)]
+    pub axes: Option<BTreeMap<String, NotNan<f64>>>,
 }
 impl_lua_conversion!(FontAttributes);
 
@@ -391,6 +396,7 @@ impl FontAttributes {
             freetype_render_target: None,
             freetype_load_flags: None,
             scale: None,
+            axes: None,
         }
     }
 
@@ -407,6 +413,7 @@ impl FontAttributes {
             freetype_render_target: None,
             freetype_load_flags: None,
             scale: None,
+            axes: None,
         }
     }
 }
@@ -425,6 +432,7 @@ impl Default for FontAttributes {
             freetype_render_target: None,
             freetype_load_flags: None,
             scale: None,
+            axes: None,
         }
     }
 }
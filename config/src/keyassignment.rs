@@ -91,6 +91,7 @@ pub enum SelectionMode {
     Word,
     Line,
     SemanticZone,
+    Block,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -317,6 +318,7 @@ pub enum KeyAssignment {
     SplitVertical(SpawnCommand),
     ShowLauncher,
     ShowLauncherArgs(LauncherActionArgs),
+    ShowSftpBrowser,
     ClearScrollback(ScrollbackEraseMode),
     Search(Pattern),
     ActivateCopyMode,
@@ -641,6 +643,14 @@ impl InputMap {
                     },
                     SelectTextAtMouseCursor(SelectionMode::Cell)
                 ],
+                [
+                    Modifiers::ALT,
+                    MouseEventTrigger::Down {
+                        streak: 1,
+                        button: MouseButton::Left
+                    },
+                    SelectTextAtMouseCursor(SelectionMode::Block)
+                ],
                 [
                     Modifiers::SHIFT,
                     MouseEventTrigger::Down {
@@ -649,6 +659,14 @@ impl InputMap {
                     },
                     ExtendSelectionToMouseCursor(None)
                 ],
+                [
+                    Modifiers::ALT | Modifiers::SHIFT,
+                    MouseEventTrigger::Down {
+                        streak: 1,
+                        button: MouseButton::Left
+                    },
+                    ExtendSelectionToMouseCursor(Some(SelectionMode::Block))
+                ],
                 [
                     Modifiers::SHIFT,
                     MouseEventTrigger::Up {
@@ -669,6 +687,14 @@ impl InputMap {
                         ClipboardCopyDestination::PrimarySelection
                     )
                 ],
+                [
+                    Modifiers::ALT,
+                    MouseEventTrigger::Up {
+                        streak: 1,
+                        button: MouseButton::Left
+                    },
+                    CompleteSelection(ClipboardCopyDestination::PrimarySelection)
+                ],
                 [
                     Modifiers::NONE,
                     MouseEventTrigger::Up {
@@ -709,6 +735,14 @@ impl InputMap {
                     },
                     ExtendSelectionToMouseCursor(Some(SelectionMode::Line))
                 ],
+                [
+                    Modifiers::ALT,
+                    MouseEventTrigger::Drag {
+                        streak: 1,
+                        button: MouseButton::Left
+                    },
+                    ExtendSelectionToMouseCursor(Some(SelectionMode::Block))
+                ],
                 [
                     Modifiers::NONE,
                     MouseEventTrigger::Down {
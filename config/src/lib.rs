@@ -31,6 +31,7 @@ mod frontend;
 pub mod keyassignment;
 mod keys;
 pub mod lua;
+mod mux_session;
 mod ssh;
 mod terminal;
 mod tls;
@@ -47,6 +48,7 @@ pub use daemon::*;
 pub use font::*;
 pub use frontend::*;
 pub use keys::*;
+pub use mux_session::*;
 pub use ssh::*;
 pub use terminal::*;
 pub use tls::*;
@@ -9,7 +9,7 @@ use mlua::{FromLua, Lua, Table, ToLua, ToLuaMulti, Value, Variadic};
 use ordered_float::NotNan;
 use serde::*;
 use smol::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::path::Path;
 use termwiz::cell::{grapheme_column_width, unicode_column_width, AttributeChange, CellAttributes};
@@ -505,6 +505,8 @@ struct LuaFontAttributes {
     pub freetype_load_flags: Option<String>,
     #[serde(default)]
     pub scale: Option<NotNan<f64>>,
+    #[serde(default)]
+    pub axes: Option<BTreeMap<String, NotNan<f64>>>,
 }
 impl<'lua> FromLua<'lua> for LuaFontAttributes {
     fn from_lua(value: Value<'lua>, _lua: &'lua Lua) -> Result<Self, mlua::Error> {
@@ -547,6 +549,36 @@ fn disable_ligatures_for_menlo_or_monaco(mut attrs: FontAttributes) -> FontAttri
     attrs
 }
 
+/// Variable font axes are identified by OpenType tags, which are
+/// between 1 and 4 printable ASCII characters, and their values are
+/// 16.16 fixed point numbers.
+/// <https://docs.microsoft.com/en-us/typography/opentype/spec/otvaroverview>
+fn validate_font_axes(
+    axes: Option<BTreeMap<String, NotNan<f64>>>,
+) -> mlua::Result<Option<BTreeMap<String, NotNan<f64>>>> {
+    if let Some(axes) = &axes {
+        for (tag, value) in axes {
+            if tag.is_empty()
+                || tag.len() > 4
+                || !tag.chars().all(|c| c.is_ascii_graphic() || c == ' ')
+            {
+                return Err(mlua::Error::external(format!(
+                    "invalid font axis tag `{}`; axis tags are 1-4 ASCII characters, eg: `wght`",
+                    tag
+                )));
+            }
+            if !(-32768.0..32768.0).contains(&value.into_inner()) {
+                return Err(mlua::Error::external(format!(
+                    "font axis `{}` value {} is out of range; \
+                     values must be between -32768 and 32767",
+                    tag, value
+                )));
+            }
+        }
+    }
+    Ok(axes)
+}
+
 /// Given a simple font family name, returns a text style instance.
 /// The second optional argument is a list of the other TextStyle
 /// fields, which at the time of writing includes only the
@@ -593,6 +625,7 @@ fn font<'lua>(
                 None => None,
             },
             scale: attrs.scale,
+            axes: validate_font_axes(attrs.axes)?,
         }));
 
     Ok(text_style)
@@ -643,6 +676,7 @@ fn font_with_fallback<'lua>(
                     None => None,
                 },
                 scale: attrs.scale,
+                axes: validate_font_axes(attrs.axes)?,
             }));
     }
 
@@ -992,6 +1026,42 @@ mod test {
     use super::*;
     use std::sync::{Arc, Mutex};
 
+    fn axes(axes: &[(&str, f64)]) -> Option<BTreeMap<String, NotNan<f64>>> {
+        Some(
+            axes.iter()
+                .map(|(tag, value)| (tag.to_string(), NotNan::new(*value).unwrap()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn font_axes() {
+        assert_eq!(validate_font_axes(None).unwrap(), None);
+        let valid = axes(&[
+            ("wght", 450.),
+            ("CASL", 1.),
+            ("ab", -32768.),
+            ("a b", 32767.5),
+        ]);
+        assert_eq!(validate_font_axes(valid.clone()).unwrap(), valid);
+
+        for invalid in &[
+            axes(&[("", 1.)]),
+            axes(&[("weight", 400.)]),
+            axes(&[("w\tht", 400.)]),
+            axes(&[("w\u{e9}", 400.)]),
+            axes(&[("wght", 32768.)]),
+            axes(&[("wght", -32768.5)]),
+            axes(&[("wght", 400.), ("opsz", 1e9)]),
+        ] {
+            assert!(
+                validate_font_axes(invalid.clone()).is_err(),
+                "{:?} should be rejected",
+                invalid
+            );
+        }
+    }
+
     #[test]
     fn can_register_and_emit_multiple_events() -> anyhow::Result<()> {
         let _ = pretty_env_logger::formatted_builder()
@@ -0,0 +1,49 @@
+use crate::*;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MuxSessionOptions {
+    /// When true, the mux server restores the saved session when
+    /// it starts up, and saves the session periodically while running
+    #[serde(default)]
+    pub persist: bool,
+    /// Where the session is saved
+    pub session_file: Option<PathBuf>,
+    /// How often the session is saved while the mux server is running
+    #[serde(default = "default_save_interval_seconds")]
+    pub save_interval_seconds: u64,
+    /// How many lines of scrollback to save for each pane.
+    /// Scrollback is not saved by default.
+    #[serde(default)]
+    pub scrollback_lines: usize,
+    /// When true, the foreground command that was running in a pane,
+    /// such as an editor, is run again in the restored shell
+    #[serde(default)]
+    pub restore_foreground_commands: bool,
+}
+impl_lua_conversion!(MuxSessionOptions);
+
+impl Default for MuxSessionOptions {
+    fn default() -> Self {
+        Self {
+            persist: false,
+            session_file: None,
+            save_interval_seconds: default_save_interval_seconds(),
+            scrollback_lines: 0,
+            restore_foreground_commands: false,
+        }
+    }
+}
+
+fn default_save_interval_seconds() -> u64 {
+    60
+}
+
+impl MuxSessionOptions {
+    pub fn session_file(&self) -> PathBuf {
+        self.session_file
+            .as_ref()
+            .cloned()
+            .unwrap_or_else(|| compute_data_dir().join("mux-session.json"))
+    }
+}
@@ -89,6 +89,21 @@ pub struct SshDomain {
     #[serde(default)]
     pub ssh_option: HashMap<String, String>,
 
+    /// Ports to forward to the remote host, in the same form
+    /// as ssh's `-L` option, eg: "5432:db.internal:5432"
+    #[serde(default)]
+    pub local_forward: Vec<String>,
+
+    /// Ports on the remote host to forward back to us, in the same
+    /// form as ssh's `-R` option, eg: "8080:localhost:80"
+    #[serde(default)]
+    pub remote_forward: Vec<String>,
+
+    /// Local ports on which to run a SOCKS proxy that connects
+    /// via the remote host, in the same form as ssh's `-D` option
+    #[serde(default)]
+    pub dynamic_forward: Vec<String>,
+
     pub default_prog: Option<Vec<String>>,
 
     #[serde(default)]
@@ -6,7 +6,7 @@ use anyhow::{anyhow, Context, Error};
 use config::keyassignment::SpawnTabDomain;
 use config::{configuration, ExitBehavior};
 use domain::{Domain, DomainId, DomainState};
-use filedescriptor::{socketpair, AsRawSocketDescriptor, FileDescriptor};
+use filedescriptor::{poll, pollfd, socketpair, AsRawSocketDescriptor, FileDescriptor, POLLIN};
 #[cfg(unix)]
 use libc::{SOL_SOCKET, SO_RCVBUF, SO_SNDBUF};
 use log::error;
@@ -20,10 +20,11 @@ use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use termwiz::escape::csi::{DecPrivateMode, DecPrivateModeCode, Device, Mode};
-use termwiz::escape::{Action, CSI};
+use termwiz::escape::{Action, DeviceControlMode, CSI};
 use thiserror::*;
+use url::Url;
 use wezterm_term::{Clipboard, ClipboardSelection, DownloadHandler};
 #[cfg(windows)]
 use winapi::um::winsock2::{SOL_SOCKET, SO_RCVBUF, SO_SNDBUF};
@@ -35,6 +36,8 @@ pub mod domain;
 pub mod localpane;
 pub mod pane;
 pub mod renderable;
+pub mod sftp;
+pub mod snapshot;
 pub mod ssh;
 pub mod tab;
 pub mod termwiztermtab;
@@ -121,13 +124,82 @@ fn send_actions_to_mux(pane_id: PaneId, dead: &Arc<AtomicBool>, actions: Vec<Act
     histogram!("send_actions_to_mux.rate", 1.);
 }
 
+/// Returns true if the action begins a synchronized output update,
+/// either via DECSET 2026 or the BSU form of DCS = 1 s ST.
+fn is_begin_synchronized_update(action: &Action) -> bool {
+    match action {
+        Action::CSI(CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(
+            DecPrivateModeCode::SynchronizedOutput,
+        )))) => true,
+        Action::DeviceControl(DeviceControlMode::ShortDeviceControl(dcs)) => {
+            dcs.byte == b's' && dcs.intermediates == [b'='] && dcs.params == [1]
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if the action ends a synchronized output update,
+/// either via DECRST 2026, the ESU form of DCS = 2 s ST, or
+/// DECSTR soft reset.
+fn is_end_synchronized_update(action: &Action) -> bool {
+    match action {
+        Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+            DecPrivateModeCode::SynchronizedOutput,
+        )))) => true,
+        Action::CSI(CSI::Device(dev)) => matches!(**dev, Device::SoftReset),
+        Action::DeviceControl(DeviceControlMode::ShortDeviceControl(dcs)) => {
+            dcs.byte == b's' && dcs.intermediates == [b'='] && dcs.params == [2]
+        }
+        _ => false,
+    }
+}
+
+/// Returns an action that ends a synchronized output update.
+/// It is applied when we release held output without the application
+/// having ended the update, so that the terminal no longer reports
+/// synchronized output as active.
+fn end_synchronized_update() -> Action {
+    Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+        DecPrivateModeCode::SynchronizedOutput,
+    ))))
+}
+
 fn parse_buffered_data(pane_id: PaneId, dead: &Arc<AtomicBool>, mut rx: FileDescriptor) {
     let mut buf = vec![0; configuration().mux_output_parser_buffer_size];
     let mut parser = termwiz::escape::parser::Parser::new();
     let mut actions = vec![];
-    let mut hold = false;
+    // When synchronized output is active, holds the time at which
+    // it started, so that we can bound how long we hold output
+    let mut hold: Option<Instant> = None;
+    let mut held_bytes = 0;
 
     loop {
+        if let Some(start) = hold {
+            let timeout = Duration::from_millis(configuration().synchronized_output_timeout_ms);
+            let remaining = timeout.saturating_sub(start.elapsed());
+            let mut pfd = [pollfd {
+                fd: rx.as_socket_descriptor(),
+                events: POLLIN,
+                revents: 0,
+            }];
+            if let Ok(0) = poll(&mut pfd, Some(remaining)) {
+                // The application didn't end the synchronized update in
+                // time; it may have crashed or be stuck, so release what
+                // we have rather than freezing the pane.
+                log::debug!(
+                    "pane {} synchronized output timed out after {:?}",
+                    pane_id,
+                    start.elapsed()
+                );
+                metrics::counter!("parse_buffered_data.sync.timeout", 1);
+                hold = None;
+                held_bytes = 0;
+                actions.push(end_synchronized_update());
+                send_actions_to_mux(pane_id, dead, std::mem::take(&mut actions));
+                continue;
+            }
+        }
+
         match rx.read(&mut buf) {
             Ok(size) if size == 0 => {
                 dead.store(true, Ordering::Relaxed);
@@ -140,36 +212,62 @@ fn parse_buffered_data(pane_id: PaneId, dead: &Arc<AtomicBool>, mut rx: FileDesc
             Ok(size) => {
                 parser.parse(&buf[0..size], |action| {
                     let mut flush = false;
-                    match &action {
-                        Action::CSI(CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(
-                            DecPrivateModeCode::SynchronizedOutput,
-                        )))) => {
-                            hold = true;
-
-                            // Flush prior actions
-                            if !actions.is_empty() {
-                                send_actions_to_mux(pane_id, dead, std::mem::take(&mut actions));
-                            }
-                        }
-                        Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(
-                            DecPrivateMode::Code(DecPrivateModeCode::SynchronizedOutput),
-                        ))) => {
-                            hold = false;
-                            flush = true;
+                    if is_begin_synchronized_update(&action) {
+                        if hold.is_none() {
+                            hold = Some(Instant::now());
+                            held_bytes = 0;
                         }
-                        Action::CSI(CSI::Device(dev)) if matches!(**dev, Device::SoftReset) => {
-                            hold = false;
-                            flush = true;
+
+                        // Flush prior actions, and let the terminal know
+                        // that we are holding output so that it can
+                        // report the correct DECRQM status
+                        actions.push(action);
+                        send_actions_to_mux(pane_id, dead, std::mem::take(&mut actions));
+                        return;
+                    }
+
+                    if is_end_synchronized_update(&action) {
+                        if let Some(start) = hold.take() {
+                            histogram!("parse_buffered_data.sync.hold.latency", start.elapsed());
+                            histogram!("parse_buffered_data.sync.held.size", held_bytes as f64);
                         }
-                        _ => {}
-                    };
+                        flush = true;
+                    } else if hold.is_some()
+                        && matches!(
+                            &action,
+                            Action::CSI(CSI::Mode(Mode::QueryDecPrivateMode(
+                                DecPrivateMode::Code(DecPrivateModeCode::SynchronizedOutput)
+                            )))
+                        )
+                    {
+                        // Answer the query now rather than after the held
+                        // output has been released
+                        send_actions_to_mux(pane_id, dead, vec![action]);
+                        return;
+                    }
                     actions.push(action);
 
                     if flush && !actions.is_empty() {
                         send_actions_to_mux(pane_id, dead, std::mem::take(&mut actions));
                     }
                 });
-                if !actions.is_empty() && !hold {
+
+                if hold.is_some() {
+                    held_bytes += size;
+                    if held_bytes > configuration().synchronized_output_max_bytes {
+                        log::debug!(
+                            "pane {} synchronized output exceeded {} bytes",
+                            pane_id,
+                            held_bytes
+                        );
+                        metrics::counter!("parse_buffered_data.sync.overflow", 1);
+                        hold = None;
+                        held_bytes = 0;
+                        actions.push(end_synchronized_update());
+                    }
+                }
+
+                if !actions.is_empty() && hold.is_none() {
                     send_actions_to_mux(pane_id, dead, std::mem::take(&mut actions));
                 }
 
@@ -466,6 +564,40 @@ impl Mux {
         }
     }
 
+    /// Renames the workspace `old` to `new`, moving all of its windows
+    /// and updating the clients that have it as their active workspace.
+    pub fn rename_workspace(&self, old: &str, new: &str) -> anyhow::Result<()> {
+        if new.is_empty() {
+            anyhow::bail!("workspace name must not be empty");
+        }
+        if old == new {
+            return Ok(());
+        }
+        if !self.iter_workspaces().iter().any(|name| name == old) {
+            anyhow::bail!("no such workspace {}", old);
+        }
+
+        for window_id in self.iter_windows_in_workspace(old) {
+            if let Some(mut window) = self.get_window_mut(window_id) {
+                window.set_workspace(new);
+            }
+        }
+
+        let renamed: Vec<Arc<ClientId>> = self
+            .clients
+            .borrow()
+            .values()
+            .filter(|info| info.active_workspace.as_deref() == Some(old))
+            .map(|info| info.client_id.clone())
+            .collect();
+        for ident in renamed {
+            self.set_active_workspace_for_client(&ident, new);
+        }
+
+        self.recompute_pane_count();
+        Ok(())
+    }
+
     /// Assigns the active workspace name for the current identity
     pub fn set_active_workspace(&self, workspace: &str) {
         if let Some(ident) = self.identity.borrow().clone() {
@@ -772,6 +904,75 @@ impl Mux {
         Ok(())
     }
 
+    /// Removes `pane_id` from its containing tab and places it into a
+    /// new tab of its own.  The new tab is added to `window_id`, or to a
+    /// new window in `workspace` if `window_id` is None.
+    /// Returns the new tab and the id of the window that contains it.
+    pub fn move_pane_to_new_tab(
+        &self,
+        pane_id: PaneId,
+        window_id: Option<WindowId>,
+        workspace: Option<String>,
+    ) -> anyhow::Result<(Rc<Tab>, WindowId)> {
+        let (_domain_id, src_window_id, src_tab_id) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", pane_id))?;
+        let src_tab = self
+            .get_tab(src_tab_id)
+            .ok_or_else(|| anyhow!("invalid tab id {}", src_tab_id))?;
+
+        // The new tab takes the size of the tabs in the target window
+        let size = match window_id {
+            Some(window_id) => {
+                let window = self
+                    .get_window(window_id)
+                    .ok_or_else(|| anyhow!("no such window {}", window_id))?;
+                window
+                    .get_active()
+                    .map(|tab| tab.get_size())
+                    .unwrap_or_else(|| src_tab.get_size())
+            }
+            None => src_tab.get_size(),
+        };
+
+        // Remove the pane before creating a window for it, so that
+        // we don't leave an empty window behind if this fails
+        let pane = src_tab
+            .remove_pane(pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found in tab {}", pane_id, src_tab_id))?;
+
+        let window_builder;
+        let window_id = match window_id {
+            Some(window_id) => window_id,
+            None => {
+                let workspace = workspace.or_else(|| {
+                    self.get_window(src_window_id)
+                        .map(|w| w.get_workspace().to_string())
+                });
+                window_builder = self.new_empty_window(workspace);
+                *window_builder
+            }
+        };
+
+        let tab = Rc::new(Tab::new(&size));
+        tab.assign_pane(&pane);
+        if let Err(err) = pane.resize(size) {
+            log::error!("failed to resize pane {}: {:#}", pane_id, err);
+        }
+        self.add_tab_no_panes(&tab);
+        self.add_tab_to_window(&tab, window_id)?;
+
+        if let Some(mut window) = self.get_window_mut(window_id) {
+            if let Some(idx) = window.idx_by_id(tab.tab_id()) {
+                window.save_and_then_set_active(idx);
+            }
+        }
+
+        self.notify(MuxNotification::WindowInvalidated(src_window_id));
+        self.prune_dead_windows();
+        Ok((tab, window_id))
+    }
+
     pub fn window_containing_tab(&self, tab_id: TabId) -> Option<WindowId> {
         for w in self.windows.borrow().values() {
             for t in w.iter() {
@@ -914,29 +1115,11 @@ impl Mux {
         command_dir: Option<String>,
         pane: Option<Rc<dyn Pane>>,
     ) -> Option<String> {
-        command_dir.or_else(|| {
-            match pane {
-                Some(pane) => pane
-                    .get_current_working_dir()
-                    .and_then(|url| {
-                        percent_decode_str(url.path())
-                            .decode_utf8()
-                            .ok()
-                            .map(|path| path.into_owned())
-                    })
-                    .map(|path| {
-                        // On Windows the file URI can produce a path like:
-                        // `/C:\Users` which is valid in a file URI, but the leading slash
-                        // is not liked by the windows file APIs, so we strip it off here.
-                        let bytes = path.as_bytes();
-                        if bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
-                            path[1..].to_owned()
-                        } else {
-                            path
-                        }
-                    }),
-                None => None,
-            }
+        command_dir.or_else(|| match pane {
+            Some(pane) => pane
+                .get_current_working_dir()
+                .and_then(|url| cwd_from_url(&url)),
+            None => None,
         })
     }
 
@@ -1054,6 +1237,26 @@ impl Mux {
     }
 }
 
+/// Converts the file URL advised by a pane for its cwd into a
+/// path that can be passed as the cwd of a spawned command
+pub(crate) fn cwd_from_url(url: &Url) -> Option<String> {
+    percent_decode_str(url.path())
+        .decode_utf8()
+        .ok()
+        .map(|path| path.into_owned())
+        .map(|path| {
+            // On Windows the file URI can produce a path like:
+            // `/C:\Users` which is valid in a file URI, but the leading slash
+            // is not liked by the windows file APIs, so we strip it off here.
+            let bytes = path.as_bytes();
+            if bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+                path[1..].to_owned()
+            } else {
+                path
+            }
+        })
+}
+
 pub struct IdentityHolder {
     prior: Option<Arc<ClientId>>,
 }
@@ -1119,3 +1322,111 @@ impl wezterm_term::DownloadHandler for MuxDownloader {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pane::alloc_pane_id;
+    use crate::tab::test::FakePane;
+    use wezterm_term::{Terminal, TerminalSize};
+
+    fn size(cols: u16, rows: u16) -> PtySize {
+        PtySize {
+            rows,
+            cols,
+            pixel_width: cols * 8,
+            pixel_height: rows * 16,
+        }
+    }
+
+    /// Adds a tab to the window, with its panes split horizontally
+    fn add_fake_tab(mux: &Mux, window_id: WindowId, size: PtySize, panes: usize) -> Rc<Tab> {
+        let tab = Rc::new(Tab::new(&size));
+        for idx in 0..panes {
+            let pane = FakePane::new(alloc_pane_id(), size);
+            if idx == 0 {
+                tab.assign_pane(&pane);
+            } else {
+                tab.split_and_insert(idx - 1, SplitDirection::Horizontal, Rc::clone(&pane))
+                    .unwrap();
+            }
+            mux.add_pane(&pane).unwrap();
+        }
+        mux.add_tab_no_panes(&tab);
+        mux.add_tab_to_window(&tab, window_id).unwrap();
+        tab
+    }
+
+    #[test]
+    fn move_pane_to_new_tab() {
+        let _executor = promise::spawn::SimpleExecutor::new();
+        let mux = Rc::new(Mux::new(None));
+        Mux::set_mux(&mux);
+
+        let src_window = *mux.new_empty_window(None);
+        let src_tab = add_fake_tab(&mux, src_window, size(80, 24), 2);
+        let pane_id = src_tab.iter_panes()[1].pane.pane_id();
+        let dest_window = *mux.new_empty_window(None);
+        add_fake_tab(&mux, dest_window, size(100, 30), 1);
+
+        // Moving to a window that doesn't exist leaves the pane alone
+        assert!(mux
+            .move_pane_to_new_tab(pane_id, Some(WindowId::max_value()), None)
+            .is_err());
+        assert_eq!(src_tab.iter_panes().len(), 2);
+        assert_eq!(mux.iter_windows().len(), 2);
+
+        // The pane is sized to fit the window that it is moved into
+        let (tab, window_id) = mux
+            .move_pane_to_new_tab(pane_id, Some(dest_window), None)
+            .unwrap();
+        assert_eq!(window_id, dest_window);
+        assert_eq!(tab.get_size(), size(100, 30));
+        assert_eq!(
+            FakePane::size_of(&mux.get_pane(pane_id).unwrap()),
+            size(100, 30)
+        );
+        assert_eq!(src_tab.iter_panes().len(), 1);
+        assert_eq!(mux.get_window(dest_window).unwrap().len(), 2);
+
+        // A new window gets a tab of the same size as the source tab
+        let (tab, window_id) = mux.move_pane_to_new_tab(pane_id, None, None).unwrap();
+        assert_eq!(mux.iter_windows().len(), 3);
+        assert!(window_id != src_window && window_id != dest_window);
+        assert_eq!(tab.get_size(), size(100, 30));
+        assert_eq!(mux.get_window(dest_window).unwrap().len(), 1);
+
+        Mux::shutdown();
+    }
+
+    #[test]
+    fn releasing_held_output_ends_synchronized_update() {
+        config::use_test_configuration();
+        let mut terminal = Terminal::new(
+            TerminalSize {
+                physical_rows: 24,
+                physical_cols: 80,
+                pixel_width: 0,
+                pixel_height: 0,
+            },
+            Arc::new(config::TermConfig::new()),
+            "WezTerm",
+            "test",
+            Box::new(Vec::new()),
+        );
+
+        let mut parser = termwiz::escape::parser::Parser::new();
+        for begin in [&b"\x1b[?2026h"[..], &b"\x1bP=1s\x1b\\"[..]] {
+            let actions = parser.parse_as_vec(begin);
+            assert!(is_begin_synchronized_update(&actions[0]));
+            terminal.perform_actions(actions);
+            assert!(terminal.is_synchronized_output_active());
+
+            // This is what the timeout and overflow paths apply
+            let end = end_synchronized_update();
+            assert!(is_end_synchronized_update(&end));
+            terminal.perform_actions(vec![end]);
+            assert!(!terminal.is_synchronized_output_active());
+        }
+    }
+}
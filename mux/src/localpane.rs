@@ -349,6 +349,10 @@ impl Pane for LocalPane {
         }
     }
 
+    fn is_synchronized_output_active(&self) -> bool {
+        self.terminal.borrow().is_synchronized_output_active()
+    }
+
     fn get_current_working_dir(&self) -> Option<Url> {
         self.terminal
             .borrow()
@@ -373,6 +377,28 @@ impl Pane for LocalPane {
         None
     }
 
+    fn get_foreground_process_argv(&self) -> Option<Vec<String>> {
+        let root_pid = match &*self.process.borrow() {
+            ProcessState::Running { pid: Some(pid), .. } => *pid,
+            _ => return None,
+        };
+
+        #[cfg(unix)]
+        let foreground = self
+            .pty
+            .borrow()
+            .process_group_leader()
+            .and_then(|pid| LocalProcessInfo::with_root_pid(pid as u32));
+
+        #[cfg(windows)]
+        let foreground = self.divine_foreground_process();
+
+        match foreground {
+            Some(info) if info.pid != root_pid && !info.argv.is_empty() => Some(info.argv),
+            _ => None,
+        }
+    }
+
     fn can_close_without_prompting(&self, _reason: CloseReason) -> bool {
         if let Some(info) = self.divine_process_list(true) {
             log::trace!(
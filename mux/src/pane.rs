@@ -376,6 +376,12 @@ pub trait Pane: Downcast {
     fn is_mouse_grabbed(&self) -> bool;
     fn is_alt_screen_active(&self) -> bool;
 
+    /// Returns true if the application in the pane has enabled
+    /// synchronized output and its output is being held
+    fn is_synchronized_output_active(&self) -> bool {
+        false
+    }
+
     fn set_clipboard(&self, _clipboard: &Arc<dyn Clipboard>) {}
     fn set_download_handler(&self, _handler: &Arc<dyn DownloadHandler>) {}
     fn set_config(&self, _config: Arc<dyn TerminalConfiguration>) {}
@@ -388,6 +394,13 @@ pub trait Pane: Downcast {
         None
     }
 
+    /// Returns the command line of the foreground process, if that is
+    /// something other than the process that was spawned into the pane;
+    /// for example, an editor that was launched from the shell.
+    fn get_foreground_process_argv(&self) -> Option<Vec<String>> {
+        None
+    }
+
     fn trickle_paste(&self, text: String) -> anyhow::Result<()> {
         if text.len() <= PASTE_CHUNK_SIZE {
             // Send it all now
@@ -0,0 +1,313 @@
+//! Transferring files to and from the remote host of a pane that
+//! belongs to an ssh domain
+use crate::domain::Domain;
+use crate::pane::{Pane, PaneId};
+use crate::ssh::RemoteSshDomain;
+use crate::Mux;
+use anyhow::{anyhow, Context};
+use smol::io::{AsyncReadExt, AsyncWriteExt};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use wezterm_ssh::{Metadata, Sftp, Utf8Path, Utf8PathBuf};
+
+/// The size of each read and write made during a transfer
+const CHUNK_SIZE: usize = 32 * 1024;
+
+/// Describes how far along a transfer is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub transferred: u64,
+    /// The size of the file, if known
+    pub total: Option<u64>,
+}
+
+impl TransferProgress {
+    pub fn percent(&self) -> Option<u64> {
+        match self.total {
+            Some(0) => Some(100),
+            Some(total) => Some(self.transferred.min(total) * 100 / total),
+            None => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TransferProgress {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.total, self.percent()) {
+            (Some(total), Some(percent)) => write!(
+                fmt,
+                "{} of {} ({}%)",
+                format_size(self.transferred),
+                format_size(total),
+                percent
+            ),
+            _ => write!(fmt, "{}", format_size(self.transferred)),
+        }
+    }
+}
+
+/// Formats a number of bytes in a human friendly way
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.;
+    let mut unit = 0;
+    while value >= 1024. && unit + 1 < UNITS.len() {
+        value /= 1024.;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// The sftp handle for the ssh session of a pane, along with the
+/// current working directory of that pane on the remote host.
+/// This is resolved on the main thread, but can then be sent to
+/// another thread to carry out transfers without blocking the mux.
+#[derive(Clone, Debug)]
+pub struct PaneSftp {
+    pub sftp: Sftp,
+    pub cwd: Option<Utf8PathBuf>,
+}
+
+impl PaneSftp {
+    /// Must be called on the main thread
+    pub fn for_pane(pane_id: PaneId) -> anyhow::Result<Self> {
+        let mux = Mux::get().ok_or_else(|| anyhow!("must be called on the main thread"))?;
+        let pane = mux
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+        let domain = mux
+            .get_domain(pane.domain_id())
+            .ok_or_else(|| anyhow!("pane {} has no domain", pane_id))?;
+        let ssh_domain = domain
+            .downcast_ref::<RemoteSshDomain>()
+            .ok_or_else(|| anyhow!("pane {} does not belong to an ssh domain", pane_id))?;
+        let sftp = ssh_domain
+            .sftp()
+            .ok_or_else(|| anyhow!("ssh domain {} is not connected", ssh_domain.domain_name()))?;
+        Ok(Self {
+            sftp,
+            cwd: remote_cwd(&pane),
+        })
+    }
+
+    /// Resolves `path` relative to the remote cwd of the pane, or
+    /// relative to the home directory on the remote host if the cwd
+    /// is not known.  An empty path resolves to that directory.
+    pub async fn resolve_remote_path(&self, path: &str) -> anyhow::Result<Utf8PathBuf> {
+        let path = Utf8Path::new(path);
+        if path.is_absolute() {
+            return Ok(path.to_path_buf());
+        }
+        let base = match &self.cwd {
+            Some(cwd) => cwd.to_path_buf(),
+            None => self
+                .sftp
+                .canonicalize(".")
+                .await
+                .context("resolving remote home directory")?,
+        };
+        if path.as_str().is_empty() || path.as_str() == "." {
+            Ok(base)
+        } else {
+            Ok(base.join(path))
+        }
+    }
+
+    /// Downloads `remote`, which is resolved relative to the remote
+    /// cwd of the pane, to `local`.
+    /// Returns the remote and local paths that were used.
+    pub async fn download<F: FnMut(TransferProgress)>(
+        &self,
+        remote: &str,
+        local: &Path,
+        progress: F,
+    ) -> anyhow::Result<(Utf8PathBuf, PathBuf)> {
+        let remote = self.resolve_remote_path(remote).await?;
+        let local = download(&self.sftp, &remote, local, progress).await?;
+        Ok((remote, local))
+    }
+
+    /// Uploads `local` to `remote`, which is resolved relative to the
+    /// remote cwd of the pane.
+    /// Returns the remote path that was written.
+    pub async fn upload<F: FnMut(TransferProgress)>(
+        &self,
+        local: &Path,
+        remote: &str,
+        progress: F,
+    ) -> anyhow::Result<Utf8PathBuf> {
+        let remote = self.resolve_remote_path(remote).await?;
+        upload(&self.sftp, local, &remote, progress).await
+    }
+}
+
+/// Returns the current working directory of the pane on the remote
+/// host, as advised by the shell via OSC 7
+pub fn remote_cwd(pane: &Rc<dyn Pane>) -> Option<Utf8PathBuf> {
+    let url = pane.get_current_working_dir()?;
+    if url.scheme() != "file" {
+        return None;
+    }
+    let path = percent_encoding::percent_decode_str(url.path())
+        .decode_utf8()
+        .ok()?;
+    Some(Utf8PathBuf::from(path.as_ref()))
+}
+
+/// Copies `remote` to `local`.  If `local` is a directory, the
+/// file is placed inside it using the same name as the remote file.
+/// `progress` is called as data is transferred.
+/// Returns the local path to which the file was written.
+pub async fn download<F: FnMut(TransferProgress)>(
+    sftp: &Sftp,
+    remote: &Utf8Path,
+    local: &Path,
+    mut progress: F,
+) -> anyhow::Result<PathBuf> {
+    let metadata: Metadata = sftp
+        .metadata(remote.to_path_buf())
+        .await
+        .with_context(|| format!("reading metadata for {}", remote))?;
+    if metadata.is_dir() {
+        anyhow::bail!("{} is a directory", remote);
+    }
+
+    let local = if local.is_dir() {
+        local.join(
+            remote
+                .file_name()
+                .ok_or_else(|| anyhow!("{} has no file name", remote))?,
+        )
+    } else {
+        local.to_path_buf()
+    };
+
+    let mut remote_file = sftp
+        .open(remote.to_path_buf())
+        .await
+        .with_context(|| format!("opening {}", remote))?;
+    let mut local_file =
+        std::fs::File::create(&local).with_context(|| format!("creating {}", local.display()))?;
+
+    let mut status = TransferProgress {
+        transferred: 0,
+        total: metadata.size,
+    };
+    progress(status);
+
+    let result: anyhow::Result<()> = async {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            let len = remote_file.read(&mut buf).await?;
+            if len == 0 {
+                break;
+            }
+            local_file.write_all(&buf[0..len])?;
+            status.transferred += len as u64;
+            progress(status);
+        }
+        Ok(())
+    }
+    .await;
+
+    if let Err(err) = result {
+        drop(local_file);
+        std::fs::remove_file(&local).ok();
+        return Err(err.context(format!("downloading {}", remote)));
+    }
+
+    Ok(local)
+}
+
+/// Copies `local` to `remote`.  If `remote` is a directory, the
+/// file is placed inside it using the same name as the local file.
+/// `progress` is called as data is transferred.
+/// Returns the remote path to which the file was written.
+pub async fn upload<F: FnMut(TransferProgress)>(
+    sftp: &Sftp,
+    local: &Path,
+    remote: &Utf8Path,
+    mut progress: F,
+) -> anyhow::Result<Utf8PathBuf> {
+    let mut local_file =
+        std::fs::File::open(local).with_context(|| format!("opening {}", local.display()))?;
+    let local_metadata = local_file.metadata()?;
+    if local_metadata.is_dir() {
+        anyhow::bail!("{} is a directory", local.display());
+    }
+
+    let remote_is_dir = sftp
+        .metadata(remote.to_path_buf())
+        .await
+        .map(|meta| meta.is_dir())
+        .unwrap_or(false);
+    let remote = if remote_is_dir {
+        let name = local
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("{} has no UTF-8 file name", local.display()))?;
+        remote.join(name)
+    } else {
+        remote.to_path_buf()
+    };
+
+    let mut remote_file = sftp
+        .create(remote.clone())
+        .await
+        .with_context(|| format!("creating {}", remote))?;
+
+    let mut status = TransferProgress {
+        transferred: 0,
+        total: Some(local_metadata.len()),
+    };
+    progress(status);
+
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let len = local_file.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        remote_file
+            .write_all(&buf[0..len])
+            .await
+            .with_context(|| format!("uploading to {}", remote))?;
+        status.transferred += len as u64;
+        progress(status);
+    }
+    remote_file.close().await?;
+
+    Ok(remote)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn progress() {
+        let status = TransferProgress {
+            transferred: 512,
+            total: Some(3 * 1024 * 1024),
+        };
+        assert_eq!(status.percent(), Some(0));
+        assert_eq!(status.to_string(), "512 B of 3.0 MiB (0%)");
+
+        let status = TransferProgress {
+            transferred: 1536,
+            total: None,
+        };
+        assert_eq!(status.percent(), None);
+        assert_eq!(status.to_string(), "1.5 KiB");
+
+        let status = TransferProgress {
+            transferred: 0,
+            total: Some(0),
+        };
+        assert_eq!(status.percent(), Some(100));
+    }
+}
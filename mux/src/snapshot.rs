@@ -0,0 +1,751 @@
+//! Saving the windows, tabs and panes of the mux to disk so that
+//! they can be recreated after the mux server is restarted
+use crate::domain::{Domain, DomainState};
+use crate::pane::{Pane, PaneId};
+use crate::tab::{PaneEntry, PaneNode, SplitDirectionAndSize, Tab, TabId};
+use crate::window::WindowId;
+use crate::{cwd_from_url, Mux};
+use anyhow::{anyhow, Context};
+use portable_pty::PtySize;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+use termwiz::escape::parser::Parser;
+
+/// Bump this when making incompatible changes to the snapshot types
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub version: u32,
+    pub windows: Vec<WindowSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowSnapshot {
+    pub workspace: String,
+    #[serde(default)]
+    pub title: String,
+    pub active_tab: usize,
+    pub tabs: Vec<TabSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TabSnapshot {
+    pub size: PtySize,
+    #[serde(default)]
+    pub title: String,
+    pub root: PaneTreeSnapshot,
+}
+
+/// Mirrors the bintree of a `Tab`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PaneTreeSnapshot {
+    Split {
+        left: Box<PaneTreeSnapshot>,
+        right: Box<PaneTreeSnapshot>,
+        node: SplitDirectionAndSize,
+    },
+    Leaf(PaneSnapshot),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaneSnapshot {
+    pub domain: String,
+    pub title: String,
+    pub size: PtySize,
+    pub working_dir: Option<String>,
+    /// The command line of the process that was in the foreground,
+    /// if it wasn't the process that was spawned into the pane
+    pub foreground_command: Option<Vec<String>>,
+    pub is_active: bool,
+    pub is_zoomed: bool,
+    /// The text of the most recent lines of output
+    #[serde(default)]
+    pub scrollback: Option<String>,
+}
+
+impl SessionSnapshot {
+    /// Captures the current state of the mux.
+    /// Panes that belong to domains that cannot spawn new panes,
+    /// such as overlays, are omitted.
+    pub fn capture(mux: &Mux, scrollback_lines: usize) -> Self {
+        let mut windows = vec![];
+        for window_id in mux.iter_windows() {
+            let (workspace, title, active_tab, tabs) = match mux.get_window(window_id) {
+                Some(window) => (
+                    window.get_workspace().to_string(),
+                    window.get_title().to_string(),
+                    window.get_active_idx(),
+                    window.iter().map(Rc::clone).collect::<Vec<_>>(),
+                ),
+                None => continue,
+            };
+
+            let tabs: Vec<TabSnapshot> = tabs
+                .iter()
+                .filter_map(|tab| TabSnapshot::capture(mux, tab, scrollback_lines))
+                .collect();
+            if tabs.is_empty() {
+                continue;
+            }
+
+            windows.push(WindowSnapshot {
+                workspace,
+                title,
+                active_tab: active_tab.min(tabs.len() - 1),
+                tabs,
+            });
+        }
+
+        Self {
+            version: SNAPSHOT_VERSION,
+            windows,
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let snapshot: Self =
+            serde_json::from_slice(&data).with_context(|| format!("parsing {}", path.display()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            anyhow::bail!(
+                "{} has version {} but version {} is required",
+                path.display(),
+                snapshot.version,
+                SNAPSHOT_VERSION
+            );
+        }
+        Ok(snapshot)
+    }
+
+    /// Writes the snapshot to `path`.  The data is written to a temporary
+    /// file that then replaces `path`, so that a crash part way through
+    /// doesn't leave a truncated snapshot behind.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let dir = path
+            .parent()
+            .ok_or_else(|| anyhow!("{} has no parent dir", path.display()))?;
+        config::create_user_owned_dirs(dir)?;
+
+        let temp_path = path.with_extension("tmp");
+        let mut file = std::fs::File::create(&temp_path)
+            .with_context(|| format!("creating {}", temp_path.display()))?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.flush()?;
+        drop(file);
+
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("replacing {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn num_panes(&self) -> usize {
+        self.windows
+            .iter()
+            .flat_map(|window| window.tabs.iter())
+            .map(|tab| tab.root.leaves().len())
+            .sum()
+    }
+
+    /// Recreates the windows, tabs and panes described by the snapshot,
+    /// returning the ids of the new windows.
+    /// Must be called on the main thread.
+    pub async fn restore(self, restore_foreground_commands: bool) -> anyhow::Result<Vec<WindowId>> {
+        let mux = Mux::get().ok_or_else(|| anyhow!("must be called on the main thread"))?;
+        let mut window_ids = vec![];
+
+        for window in self.windows {
+            let window_id = mux.new_empty_window(Some(window.workspace.clone()));
+            window_ids.push(*window_id);
+
+            if let Err(err) = window
+                .restore(&mux, *window_id, restore_foreground_commands)
+                .await
+            {
+                // Don't leave a partially restored session behind.
+                // Dropping the builder announces the window first, so
+                // that its removal is seen after its creation.
+                drop(window_id);
+                for window_id in window_ids {
+                    mux.kill_window(window_id);
+                }
+                return Err(err);
+            }
+        }
+
+        Ok(window_ids)
+    }
+}
+
+impl WindowSnapshot {
+    async fn restore(
+        self,
+        mux: &Rc<Mux>,
+        window_id: WindowId,
+        restore_foreground_commands: bool,
+    ) -> anyhow::Result<()> {
+        for tab in self.tabs {
+            tab.restore(mux, window_id, &self.workspace, restore_foreground_commands)
+                .await?;
+        }
+
+        if let Some(mut win) = mux.get_window_mut(window_id) {
+            win.set_title(&self.title);
+            if self.active_tab < win.len() {
+                win.set_active_without_saving(self.active_tab);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TabSnapshot {
+    fn capture(mux: &Mux, tab: &Rc<Tab>, scrollback_lines: usize) -> Option<Self> {
+        let root = PaneTreeSnapshot::capture(mux, tab.codec_pane_tree(), scrollback_lines)?;
+        Some(Self {
+            size: tab.get_size(),
+            title: tab.get_title(),
+            root,
+        })
+    }
+
+    async fn restore(
+        self,
+        mux: &Rc<Mux>,
+        window_id: WindowId,
+        workspace: &str,
+        restore_foreground_commands: bool,
+    ) -> anyhow::Result<()> {
+        let mut pane_ids = vec![];
+        for pane in self.root.leaves() {
+            match pane.spawn(mux, restore_foreground_commands).await {
+                Ok(pane) => pane_ids.push(pane.pane_id()),
+                Err(err) => {
+                    for pane_id in pane_ids {
+                        mux.remove_pane(pane_id);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        let tab = Rc::new(Tab::new(&self.size));
+        tab.set_title(&self.title);
+        let root = self.root.into_pane_node(
+            &mut pane_ids.into_iter(),
+            tab.tab_id(),
+            window_id,
+            workspace,
+        );
+
+        mux.add_tab_no_panes(&tab);
+        tab.sync_with_pane_tree(self.size, root, |entry| {
+            mux.get_pane(entry.pane_id)
+                .expect("restored pane to have been added to the mux")
+        });
+        if let Err(err) = mux.add_tab_to_window(&tab, window_id) {
+            mux.remove_tab(tab.tab_id());
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+impl PaneTreeSnapshot {
+    fn capture(mux: &Mux, node: PaneNode, scrollback_lines: usize) -> Option<Self> {
+        match node {
+            PaneNode::Empty => None,
+            PaneNode::Leaf(entry) => {
+                PaneSnapshot::capture(mux, entry, scrollback_lines).map(Self::Leaf)
+            }
+            PaneNode::Split { left, right, node } => {
+                let left = Self::capture(mux, *left, scrollback_lines);
+                let right = Self::capture(mux, *right, scrollback_lines);
+                match (left, right) {
+                    (Some(left), Some(right)) => Some(Self::Split {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        node,
+                    }),
+                    // If one side was omitted, the other takes its space
+                    (Some(only), None) | (None, Some(only)) => Some(only),
+                    (None, None) => None,
+                }
+            }
+        }
+    }
+
+    /// Returns the panes in the same order as `into_pane_node`
+    /// consumes their ids
+    pub fn leaves(&self) -> Vec<&PaneSnapshot> {
+        match self {
+            Self::Leaf(pane) => vec![pane],
+            Self::Split { left, right, .. } => {
+                let mut leaves = left.leaves();
+                leaves.append(&mut right.leaves());
+                leaves
+            }
+        }
+    }
+
+    /// Converts the snapshot into the form accepted by
+    /// `Tab::sync_with_pane_tree`, taking the id of the pane
+    /// that was spawned for each leaf from `pane_ids`
+    fn into_pane_node(
+        self,
+        pane_ids: &mut impl Iterator<Item = PaneId>,
+        tab_id: TabId,
+        window_id: WindowId,
+        workspace: &str,
+    ) -> PaneNode {
+        match self {
+            Self::Leaf(pane) => match pane_ids.next() {
+                Some(pane_id) => PaneNode::Leaf(PaneEntry {
+                    window_id,
+                    tab_id,
+                    pane_id,
+                    title: pane.title,
+                    size: pane.size,
+                    working_dir: None,
+                    is_active_pane: pane.is_active,
+                    is_zoomed_pane: pane.is_zoomed,
+                    workspace: workspace.to_string(),
+                }),
+                None => PaneNode::Empty,
+            },
+            Self::Split { left, right, node } => PaneNode::Split {
+                left: Box::new(left.into_pane_node(pane_ids, tab_id, window_id, workspace)),
+                right: Box::new(right.into_pane_node(pane_ids, tab_id, window_id, workspace)),
+                node,
+            },
+        }
+    }
+}
+
+impl PaneSnapshot {
+    fn capture(mux: &Mux, entry: PaneEntry, scrollback_lines: usize) -> Option<Self> {
+        let pane = mux.get_pane(entry.pane_id)?;
+        let domain = mux.get_domain(pane.domain_id())?;
+        if !domain.spawnable() {
+            return None;
+        }
+
+        Some(Self {
+            domain: domain.domain_name().to_string(),
+            title: entry.title,
+            size: entry.size,
+            working_dir: entry.working_dir.and_then(|url| cwd_from_url(&url.url)),
+            foreground_command: pane.get_foreground_process_argv(),
+            is_active: entry.is_active_pane,
+            is_zoomed: entry.is_zoomed_pane,
+            scrollback: if scrollback_lines > 0 {
+                Some(scrollback_text(&pane, scrollback_lines))
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Spawns the default program for the domain in the saved cwd,
+    /// falling back to the default domain if the saved domain is
+    /// no longer available
+    async fn spawn(
+        &self,
+        mux: &Rc<Mux>,
+        restore_foreground_command: bool,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        let domain = match mux.get_domain_by_name(&self.domain) {
+            Some(domain) if domain.spawnable() && domain.state() == DomainState::Attached => domain,
+            _ => {
+                log::warn!(
+                    "domain {} is not available; restoring pane into the default domain",
+                    self.domain
+                );
+                mux.default_domain()
+            }
+        };
+
+        let pane = domain
+            .spawn_pane(self.size, None, self.working_dir.clone())
+            .await
+            .with_context(|| format!("spawning pane in domain {}", domain.domain_name()))?;
+        if let Err(err) = self.restore_state(mux, &pane, restore_foreground_command) {
+            mux.remove_pane(pane.pane_id());
+            return Err(err);
+        }
+
+        Ok(pane)
+    }
+
+    /// Adds the newly spawned pane to the mux and restores its
+    /// scrollback and foreground command
+    fn restore_state(
+        &self,
+        mux: &Rc<Mux>,
+        pane: &Rc<dyn Pane>,
+        restore_foreground_command: bool,
+    ) -> anyhow::Result<()> {
+        mux.add_pane(pane)?;
+
+        if let Some(scrollback) = &self.scrollback {
+            let mut parser = Parser::new();
+            let actions = parser.parse_as_vec(format!("{}\r\n", scrollback).as_bytes());
+            pane.perform_actions(actions);
+        }
+
+        if restore_foreground_command {
+            if let Some(argv) = &self.foreground_command {
+                write!(pane.writer(), "{}\r", shell_words::join(argv))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the text of the last `nlines` lines of the pane,
+/// without any trailing blank lines
+fn scrollback_text(pane: &Rc<dyn Pane>, nlines: usize) -> String {
+    let dims = pane.get_dimensions();
+    let bottom_row = dims.physical_top + dims.viewport_rows as isize;
+    let top_row = bottom_row.saturating_sub(nlines as isize);
+    let (_first_row, lines) = pane.get_lines(top_row..bottom_row);
+
+    let mut text: Vec<String> = lines
+        .iter()
+        .map(|line| line.as_str().trim_end().to_string())
+        .collect();
+    while text.last().map(|line| line.is_empty()).unwrap_or(false) {
+        text.pop();
+    }
+    text.join("\r\n")
+}
+
+/// Captures the state of the mux and saves it to `path`.
+/// Must be called on the main thread.
+pub fn save_session(path: &Path, scrollback_lines: usize) -> anyhow::Result<SessionSnapshot> {
+    let mux = Mux::get().ok_or_else(|| anyhow!("must be called on the main thread"))?;
+    let snapshot = SessionSnapshot::capture(&mux, scrollback_lines);
+    snapshot.save(path)?;
+    Ok(snapshot)
+}
+
+/// Restores the session saved in `path`, returning the ids of the
+/// windows that were created.
+/// Must be called on the main thread.
+pub async fn restore_session(
+    path: &Path,
+    restore_foreground_commands: bool,
+) -> anyhow::Result<Vec<WindowId>> {
+    let snapshot = SessionSnapshot::load(path)?;
+    log::info!(
+        "restoring {} windows with {} panes from {}",
+        snapshot.windows.len(),
+        snapshot.num_panes(),
+        path.display()
+    );
+    snapshot.restore(restore_foreground_commands).await
+}
+
+/// Restores the session saved in `path` when the mux server starts,
+/// returning the ids of the windows that were created.
+/// If the session cannot be restored, the file is renamed to
+/// `<path>.failed` so that the periodic save doesn't replace a session
+/// that the user may want to recover.  An error is returned only when
+/// the file couldn't be moved aside, in which case the session must not
+/// be saved over it.
+/// Must be called on the main thread.
+pub async fn restore_startup_session(
+    path: &Path,
+    restore_foreground_commands: bool,
+) -> anyhow::Result<Vec<WindowId>> {
+    match restore_session(path, restore_foreground_commands).await {
+        Ok(window_ids) => Ok(window_ids),
+        Err(err) => {
+            let mut failed_path = path.as_os_str().to_owned();
+            failed_path.push(".failed");
+            let failed_path = PathBuf::from(failed_path);
+            std::fs::rename(path, &failed_path).with_context(|| {
+                format!(
+                    "failed to restore mux session: {:#}; failed to move {} aside",
+                    err,
+                    path.display()
+                )
+            })?;
+            log::error!(
+                "failed to restore mux session: {:#}; it has been moved to {}",
+                err,
+                failed_path.display()
+            );
+            Ok(vec![])
+        }
+    }
+}
+
+/// Saves the session every `save_interval_seconds` for as long
+/// as the mux is running.
+/// Must be called on the main thread.
+pub fn schedule_periodic_save() {
+    promise::spawn::spawn(async move {
+        loop {
+            let options = config::configuration().mux_session.clone();
+            if !options.persist {
+                break;
+            }
+            smol::Timer::after(Duration::from_secs(options.save_interval_seconds.max(1))).await;
+            if Mux::get().is_none() {
+                break;
+            }
+            if let Err(err) = save_session(&options.session_file(), options.scrollback_lines) {
+                log::error!("failed to save mux session: {:#}", err);
+            }
+        }
+    })
+    .detach();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::DomainId;
+    use crate::pane::alloc_pane_id;
+    use crate::tab::test::FakePane;
+    use crate::tab::SplitDirection;
+    use async_trait::async_trait;
+    use portable_pty::CommandBuilder;
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    /// A domain whose spawns fail once `remaining` reaches zero
+    struct FlakyDomain {
+        id: DomainId,
+        remaining: Cell<usize>,
+    }
+
+    #[async_trait(?Send)]
+    impl Domain for FlakyDomain {
+        async fn spawn_pane(
+            &self,
+            size: PtySize,
+            _command: Option<CommandBuilder>,
+            _command_dir: Option<String>,
+        ) -> anyhow::Result<Rc<dyn Pane>> {
+            match self.remaining.get() {
+                0 => anyhow::bail!("out of panes"),
+                n => {
+                    self.remaining.set(n - 1);
+                    Ok(FakePane::new(alloc_pane_id(), size))
+                }
+            }
+        }
+
+        fn domain_id(&self) -> DomainId {
+            self.id
+        }
+
+        fn domain_name(&self) -> &str {
+            "flaky"
+        }
+
+        async fn attach(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn detach(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn state(&self) -> DomainState {
+            DomainState::Attached
+        }
+    }
+
+    fn size(cols: u16, rows: u16) -> PtySize {
+        PtySize {
+            rows,
+            cols,
+            pixel_width: cols * 8,
+            pixel_height: rows * 16,
+        }
+    }
+
+    fn leaf(title: &str, cols: u16, rows: u16, is_active: bool) -> PaneTreeSnapshot {
+        PaneTreeSnapshot::Leaf(PaneSnapshot {
+            domain: "local".to_string(),
+            title: title.to_string(),
+            size: size(cols, rows),
+            working_dir: Some("/tmp".to_string()),
+            foreground_command: None,
+            is_active,
+            is_zoomed: false,
+            scrollback: None,
+        })
+    }
+
+    #[test]
+    fn pane_tree_round_trip() {
+        let root = PaneTreeSnapshot::Split {
+            left: Box::new(leaf("left", 40, 24, false)),
+            right: Box::new(PaneTreeSnapshot::Split {
+                left: Box::new(leaf("top", 39, 12, true)),
+                right: Box::new(leaf("bottom", 39, 11, false)),
+                node: SplitDirectionAndSize {
+                    direction: SplitDirection::Vertical,
+                    first: size(39, 12),
+                    second: size(39, 11),
+                },
+            }),
+            node: SplitDirectionAndSize {
+                direction: SplitDirection::Horizontal,
+                first: size(40, 24),
+                second: size(39, 24),
+            },
+        };
+
+        let titles: Vec<&str> = root
+            .leaves()
+            .iter()
+            .map(|pane| pane.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["left", "top", "bottom"]);
+
+        let snapshot = SessionSnapshot {
+            version: SNAPSHOT_VERSION,
+            windows: vec![WindowSnapshot {
+                workspace: "default".to_string(),
+                title: "main".to_string(),
+                active_tab: 0,
+                tabs: vec![TabSnapshot {
+                    size: size(80, 24),
+                    title: String::new(),
+                    root: root.clone(),
+                }],
+            }],
+        };
+        assert_eq!(snapshot.num_panes(), 3);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: SessionSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, snapshot);
+
+        let node = root.into_pane_node(&mut vec![10, 11, 12].into_iter(), 1, 2, "default");
+        match node {
+            PaneNode::Split { left, right, .. } => {
+                match *left {
+                    PaneNode::Leaf(entry) => {
+                        assert_eq!(entry.pane_id, 10);
+                        assert_eq!(entry.title, "left");
+                        assert_eq!(entry.tab_id, 1);
+                        assert_eq!(entry.window_id, 2);
+                    }
+                    _ => panic!("expected a leaf"),
+                }
+                match *right {
+                    PaneNode::Split { left, right, .. } => {
+                        assert!(
+                            matches!(*left, PaneNode::Leaf(ref entry) if entry.pane_id == 11 && entry.is_active_pane)
+                        );
+                        assert!(matches!(*right, PaneNode::Leaf(ref entry) if entry.pane_id == 12));
+                    }
+                    _ => panic!("expected a split"),
+                }
+            }
+            _ => panic!("expected a split"),
+        }
+    }
+
+    #[test]
+    fn failed_restore_removes_what_was_spawned() {
+        let _executor = promise::spawn::SimpleExecutor::new();
+        let domain: Arc<dyn Domain> = Arc::new(FlakyDomain {
+            id: crate::domain::alloc_domain_id(),
+            remaining: Cell::new(2),
+        });
+        let mux = Rc::new(Mux::new(Some(domain)));
+        Mux::set_mux(&mux);
+
+        let tab = |root| TabSnapshot {
+            size: size(80, 24),
+            title: String::new(),
+            root,
+        };
+        let split = PaneTreeSnapshot::Split {
+            left: Box::new(leaf("left", 40, 24, true)),
+            right: Box::new(leaf("right", 39, 24, false)),
+            node: SplitDirectionAndSize {
+                direction: SplitDirection::Horizontal,
+                first: size(40, 24),
+                second: size(39, 24),
+            },
+        };
+        // The first window is restored, but the third pane of the
+        // second window cannot be spawned
+        let snapshot = SessionSnapshot {
+            version: SNAPSHOT_VERSION,
+            windows: vec![
+                WindowSnapshot {
+                    workspace: "default".to_string(),
+                    title: String::new(),
+                    active_tab: 0,
+                    tabs: vec![tab(leaf("first", 80, 24, true))],
+                },
+                WindowSnapshot {
+                    workspace: "default".to_string(),
+                    title: String::new(),
+                    active_tab: 0,
+                    tabs: vec![tab(split)],
+                },
+            ],
+        };
+
+        assert!(smol::block_on(snapshot.restore(false)).is_err());
+        assert!(mux.iter_windows().is_empty());
+        assert!(mux.iter_panes().is_empty());
+        Mux::shutdown();
+    }
+
+    #[test]
+    fn failed_startup_restore_keeps_the_session_file() {
+        let _executor = promise::spawn::SimpleExecutor::new();
+        let domain: Arc<dyn Domain> = Arc::new(FlakyDomain {
+            id: crate::domain::alloc_domain_id(),
+            remaining: Cell::new(0),
+        });
+        let mux = Rc::new(Mux::new(Some(domain)));
+        Mux::set_mux(&mux);
+
+        let snapshot = SessionSnapshot {
+            version: SNAPSHOT_VERSION,
+            windows: vec![WindowSnapshot {
+                workspace: "default".to_string(),
+                title: String::new(),
+                active_tab: 0,
+                tabs: vec![TabSnapshot {
+                    size: size(80, 24),
+                    title: String::new(),
+                    root: leaf("only", 80, 24, true),
+                }],
+            }],
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        snapshot.save(&path).unwrap();
+        let saved = std::fs::read(&path).unwrap();
+
+        let window_ids = smol::block_on(restore_startup_session(&path, false)).unwrap();
+        assert!(window_ids.is_empty());
+        assert!(mux.iter_windows().is_empty());
+
+        // The session is moved aside rather than being left to be
+        // replaced by the periodic save
+        assert!(!path.exists());
+        let failed = std::fs::read(dir.path().join("session.json.failed")).unwrap();
+        assert_eq!(failed, saved);
+        assert_eq!(
+            SessionSnapshot::load(&dir.path().join("session.json.failed")).unwrap(),
+            snapshot
+        );
+        Mux::shutdown();
+    }
+}
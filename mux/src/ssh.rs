@@ -23,7 +23,7 @@ use termwiz::lineedit::*;
 use termwiz::render::terminfo::TerminfoRenderer;
 use termwiz::surface::Change;
 use termwiz::terminal::{ScreenSize, Terminal, TerminalWaker};
-use wezterm_ssh::{ConfigMap, Session, SessionEvent, SshChildProcess, SshPty};
+use wezterm_ssh::{ConfigMap, Session, SessionEvent, Sftp, SshChildProcess, SshPty};
 
 #[derive(Default)]
 struct PasswordPromptHost {
@@ -139,8 +139,8 @@ pub struct RemoteSshDomain {
 }
 
 pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap> {
-    let mut ssh_config = wezterm_ssh::Config::new();
-    ssh_config.add_default_config_files();
+    let mut config = wezterm_ssh::Config::new();
+    config.add_default_config_files();
 
     let (remote_host_name, port) = {
         let parts: Vec<&str> = ssh_dom.remote_address.split(':').collect();
@@ -152,7 +152,7 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
         }
     };
 
-    let mut ssh_config = ssh_config.for_host(&remote_host_name);
+    let mut ssh_config = config.for_host(&remote_host_name);
     ssh_config.insert(
         "wezterm_ssh_backend".to_string(),
         match ssh_dom
@@ -167,6 +167,11 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
     for (k, v) in &ssh_dom.ssh_option {
         ssh_config.insert(k.to_string(), v.to_string());
     }
+    if ssh_dom.ssh_option.contains_key("proxyjump") {
+        // Use the jump hosts named by the override instead of any
+        // that were resolved from the config files
+        config.resolve_proxy_jump(&mut ssh_config);
+    }
 
     if let Some(username) = &ssh_dom.username {
         ssh_config.insert("user".to_string(), username.to_string());
@@ -177,6 +182,22 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
     if ssh_dom.no_agent_auth {
         ssh_config.insert("identitiesonly".to_string(), "yes".to_string());
     }
+    for (key, forwards) in [
+        ("localforward", &ssh_dom.local_forward),
+        ("remoteforward", &ssh_dom.remote_forward),
+        ("dynamicforward", &ssh_dom.dynamic_forward),
+    ] {
+        if forwards.is_empty() {
+            continue;
+        }
+        let mut combined: Vec<&str> = ssh_config
+            .get(key)
+            .map(|existing| existing.split(',').collect())
+            .unwrap_or_default();
+        combined.extend(forwards.iter().map(|s| s.as_str()));
+        let combined = combined.join(",");
+        ssh_config.insert(key.to_string(), combined);
+    }
     Ok(ssh_config)
 }
 
@@ -195,6 +216,12 @@ impl RemoteSshDomain {
         ssh_domain_to_ssh_config(&self.dom)
     }
 
+    /// Returns an sftp handle for the ssh session, if the
+    /// session has been established
+    pub fn sftp(&self) -> Option<Sftp> {
+        self.session.borrow().as_ref().map(|session| session.sftp())
+    }
+
     fn build_command(
         &self,
         pane_id: PaneId,
@@ -25,6 +25,7 @@ pub struct Tab {
     size: RefCell<PtySize>,
     active: RefCell<usize>,
     zoomed: RefCell<Option<Rc<dyn Pane>>>,
+    title: RefCell<String>,
 }
 
 #[derive(Clone)]
@@ -415,9 +416,22 @@ impl Tab {
             size: RefCell::new(*size),
             active: RefCell::new(0),
             zoomed: RefCell::new(None),
+            title: RefCell::new(String::new()),
         }
     }
 
+    /// Returns the title that was explicitly assigned to this tab,
+    /// which is empty if none has been set
+    pub fn get_title(&self) -> String {
+        self.title.borrow().clone()
+    }
+
+    /// Assigns the title of the tab.  An empty title reverts to
+    /// using the title of the active pane.
+    pub fn set_title(&self, title: &str) {
+        *self.title.borrow_mut() = title.to_string();
+    }
+
     /// Called by the multiplexer client when building a local tab to
     /// mirror a remote tab.  The supplied `root` is the information
     /// about our counterpart in the the remote server.
@@ -1176,7 +1190,39 @@ impl Tab {
         self.remove_pane_if(|_, pane| pane.domain_id() == domain)
     }
 
+    /// Removes the specified pane from the tab without killing it,
+    /// returning it so that it can be placed into a different tab.
+    pub fn remove_pane(&self, pane_id: PaneId) -> Option<Rc<dyn Pane>> {
+        self.remove_pane_if_impl(|_, pane| pane.pane_id() == pane_id)
+            .into_iter()
+            .next()
+    }
+
     fn remove_pane_if<F>(&self, f: F) -> bool
+    where
+        F: Fn(usize, &Rc<dyn Pane>) -> bool,
+    {
+        let dead_panes: Vec<PaneId> = self
+            .remove_pane_if_impl(f)
+            .iter()
+            .map(|pane| pane.pane_id())
+            .collect();
+
+        if !dead_panes.is_empty() {
+            promise::spawn::spawn_into_main_thread(async move {
+                let mux = Mux::get().unwrap();
+                for pane_id in dead_panes.into_iter() {
+                    mux.remove_pane(pane_id);
+                }
+            })
+            .detach();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn remove_pane_if_impl<F>(&self, f: F) -> Vec<Rc<dyn Pane>>
     where
         F: Fn(usize, &Rc<dyn Pane>) -> bool,
     {
@@ -1217,7 +1263,7 @@ impl Tab {
                         let parent;
                         match cursor.unsplit_leaf() {
                             Ok((c, dead, p)) => {
-                                dead_panes.push(dead.pane_id());
+                                dead_panes.push(dead);
                                 parent = p.unwrap();
                                 cursor = c;
                             }
@@ -1225,7 +1271,7 @@ impl Tab {
                                 // We might be the root, for example
                                 if c.is_top() && c.is_leaf() {
                                     root.replace(Tree::Empty);
-                                    dead_panes.push(pane.pane_id());
+                                    dead_panes.push(pane);
                                 } else {
                                     root.replace(c.tree());
                                 }
@@ -1267,18 +1313,7 @@ impl Tab {
             *self.active.borrow_mut() = active_idx;
         }
 
-        if !dead_panes.is_empty() {
-            promise::spawn::spawn_into_main_thread(async move {
-                let mux = Mux::get().unwrap();
-                for pane_id in dead_panes.into_iter() {
-                    mux.remove_pane(pane_id);
-                }
-            })
-            .detach();
-            true
-        } else {
-            false
-        }
+        dead_panes
     }
 
     pub fn can_close_without_prompting(&self, reason: CloseReason) -> bool {
@@ -1599,7 +1634,7 @@ impl Into<String> for SerdeUrl {
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::*;
     use crate::renderable::*;
     use rangeset::RangeSet;
@@ -1609,18 +1644,30 @@ mod test {
     use wezterm_term::color::ColorPalette;
     use wezterm_term::{KeyCode, KeyModifiers, Line, MouseEvent, StableRowIndex};
 
-    struct FakePane {
+    /// A pane with no process or content, for testing the layout of
+    /// tabs and the mux
+    pub(crate) struct FakePane {
         id: PaneId,
         size: RefCell<PtySize>,
     }
 
     impl FakePane {
-        fn new(id: PaneId, size: PtySize) -> Rc<dyn Pane> {
+        pub(crate) fn new(id: PaneId, size: PtySize) -> Rc<dyn Pane> {
             Rc::new(Self {
                 id,
                 size: RefCell::new(size),
             })
         }
+
+        /// Returns the size that `pane`, which must be a FakePane,
+        /// was most recently resized to
+        pub(crate) fn size_of(pane: &Rc<dyn Pane>) -> PtySize {
+            *pane
+                .downcast_ref::<Self>()
+                .expect("pane to be a FakePane")
+                .size
+                .borrow()
+        }
     }
 
     impl Pane for FakePane {
@@ -1,7 +1,10 @@
 use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState};
 use crate::pane::{Pane, PaneId};
-use crate::tab::TabId;
-use crate::tmux_commands::{ListAllPanes, TmuxCommand};
+use crate::tab::{SplitDirection, Tab, TabId};
+use crate::tmux_commands::{
+    DetachClient, ListAllPanes, NewWindow, Resize, ResizePane, SplitWindow, TmuxCommand,
+};
+use crate::window::WindowId;
 use crate::{Mux, MuxWindowBuilder};
 use async_trait::async_trait;
 use filedescriptor::FileDescriptor;
@@ -48,6 +51,8 @@ pub(crate) struct TmuxTab {
     pub tab_id: TabId, // local tab ID
     pub tmux_window_id: TmuxWindowId,
     pub panes: HashSet<TmuxPaneId>, // tmux panes within tmux window
+    /// The size of the tmux window, as most recently reported by tmux
+    pub size: PtySize,
 }
 
 pub(crate) type TmuxCmdQueue = VecDeque<Box<dyn TmuxCommand>>;
@@ -60,6 +65,7 @@ pub(crate) struct TmuxDomainState {
     pub gui_tabs: RefCell<Vec<TmuxTab>>,
     pub remote_panes: RefCell<HashMap<TmuxPaneId, RefTmuxRemotePane>>,
     pub tmux_session: RefCell<Option<TmuxSessionId>>,
+    detached: RefCell<bool>,
 }
 
 pub struct TmuxDomain {
@@ -102,14 +108,38 @@ impl TmuxDomainState {
                         log::error!("Tmux pane {} havn't been attached", pane);
                     }
                 }
-                Event::WindowAdd { window: _ } => {
+                Event::WindowAdd { window } => {
                     self.create_gui_window();
+                    if self.tab_for_window(*window).is_none() {
+                        // Learn the layout of the new window
+                        self.cmd_queue
+                            .lock()
+                            .unwrap()
+                            .push_back(Box::new(ListAllPanes));
+                    }
+                }
+                Event::WindowClose { window } => {
+                    self.close_window(*window);
+                }
+                Event::WindowRenamed { window, name } => {
+                    self.set_window_title(*window, name);
+                }
+                Event::WindowPaneChanged { window, pane } => {
+                    self.set_active_pane(*window, *pane);
+                }
+                Event::LayoutChange { window, layout, .. } => {
+                    if self.tab_for_window(*window).is_some() {
+                        if let Err(err) = self.sync_window_layout(*window, layout) {
+                            log::error!("Failed to sync tmux window layout: {:#}", err);
+                        }
+                    }
                 }
                 Event::SessionChanged { session, name: _ } => {
                     *self.tmux_session.borrow_mut() = Some(*session);
                     log::info!("tmux session changed:{}", session);
                 }
                 Event::Exit { reason: _ } => {
+                    *self.detached.borrow_mut() = true;
                     let mut pane_map = self.remote_panes.borrow_mut();
                     for (_, v) in pane_map.iter_mut() {
                         let remote_pane = v.lock().unwrap();
@@ -162,6 +192,59 @@ impl TmuxDomainState {
         .detach();
     }
 
+    /// schedule telling tmux that the pane `pane_id`, which is mirrored
+    /// by `local_pane_id`, was resized to `size`.  If the tab that
+    /// contains it is still the size of the tmux window then a split
+    /// was moved, and the pane is resized to match.  Otherwise the tab
+    /// was resized, and the tmux client is resized to match; tmux then
+    /// lays out the panes itself.  The tab cannot be inspected while it
+    /// is resizing its panes, so this is deferred to the main thread.
+    pub fn schedule_resize(
+        domain_id: DomainId,
+        local_pane_id: PaneId,
+        pane_id: TmuxPaneId,
+        size: PtySize,
+    ) {
+        promise::spawn::spawn_into_main_thread(async move {
+            let mux = Mux::get().expect("to be called on main thread");
+            let tab = match mux
+                .resolve_pane_id(local_pane_id)
+                .and_then(|(_, _, tab_id)| mux.get_tab(tab_id))
+            {
+                Some(tab) => tab,
+                None => return,
+            };
+            let tab_size = tab.get_size();
+            if let Some(domain) = mux.get_domain(domain_id) {
+                if let Some(tmux_domain) = domain.downcast_ref::<TmuxDomain>() {
+                    let window_size = tmux_domain
+                        .inner
+                        .gui_tabs
+                        .borrow()
+                        .iter()
+                        .find(|t| t.tab_id == tab.tab_id())
+                        .map(|t| (t.size.cols, t.size.rows));
+                    let cmd: Box<dyn TmuxCommand> =
+                        if window_size == Some((tab_size.cols, tab_size.rows)) {
+                            Box::new(ResizePane { pane_id, size })
+                        } else {
+                            Box::new(Resize { size: tab_size })
+                        };
+                    {
+                        let mut cmd_queue = tmux_domain.inner.cmd_queue.lock().unwrap();
+                        if cmd_queue.back().map(|cmd| cmd.get_command()) == Some(cmd.get_command())
+                        {
+                            return;
+                        }
+                        cmd_queue.push_back(cmd);
+                    }
+                    tmux_domain.send_next_command();
+                }
+            }
+        })
+        .detach();
+    }
+
     /// create a standalone window for tmux tabs
     pub fn create_gui_window(&self) {
         if self.gui_window.borrow().is_none() {
@@ -192,6 +275,7 @@ impl TmuxDomain {
             gui_tabs: RefCell::new(Vec::default()),
             remote_panes: RefCell::new(HashMap::default()),
             tmux_session: RefCell::new(None),
+            detached: RefCell::new(false),
         });
 
         Self { inner }
@@ -200,17 +284,97 @@ impl TmuxDomain {
     fn send_next_command(&self) {
         self.inner.send_next_command();
     }
+
+    /// Queues a command that reports a newly created pane, and waits
+    /// for the local pane that mirrors it
+    async fn spawn_with<F>(&self, make_command: F) -> anyhow::Result<Rc<dyn Pane>>
+    where
+        F: FnOnce(flume::Sender<anyhow::Result<PaneId>>) -> Box<dyn TmuxCommand>,
+    {
+        if *self.inner.detached.borrow() {
+            anyhow::bail!("tmux domain is detached");
+        }
+        let (tx, rx) = flume::bounded(1);
+        self.inner
+            .cmd_queue
+            .lock()
+            .unwrap()
+            .push_back(make_command(tx));
+        TmuxDomainState::schedule_send_next_command(self.inner.domain_id);
+
+        let pane_id = rx.recv_async().await??;
+        let mux = Mux::get().expect("to be called on main thread");
+        mux.get_pane(pane_id)
+            .ok_or_else(|| anyhow::anyhow!("tmux pane {} was not added to the mux", pane_id))
+    }
+}
+
+/// Returns the command line to pass to tmux, if any
+fn tmux_command_line(command: Option<CommandBuilder>) -> anyhow::Result<Option<String>> {
+    match command {
+        Some(cmd) if !cmd.is_default_prog() => Ok(Some(cmd.as_unix_command_line()?)),
+        _ => Ok(None),
+    }
 }
 
 #[async_trait(?Send)]
 impl Domain for TmuxDomain {
+    async fn spawn(
+        &self,
+        size: PtySize,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        _window: WindowId,
+    ) -> anyhow::Result<Rc<Tab>> {
+        // The tab is created in the tmux window as part of mirroring
+        // the layout of the new tmux window
+        let pane = self.spawn_pane(size, command, command_dir).await?;
+        let mux = Mux::get().expect("to be called on main thread");
+        mux.resolve_pane_id(pane.pane_id())
+            .and_then(|(_, _, tab_id)| mux.get_tab(tab_id))
+            .ok_or_else(|| anyhow::anyhow!("tmux window has no tab"))
+    }
+
+    async fn split_pane(
+        &self,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        _tab: TabId,
+        pane_id: PaneId,
+        direction: SplitDirection,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        let pane = match self.inner.remote_pane_id(pane_id) {
+            Some(pane) => pane,
+            None => anyhow::bail!("pane {} is not a tmux pane", pane_id),
+        };
+        let command = tmux_command_line(command)?;
+        self.spawn_with(|result| {
+            Box::new(SplitWindow {
+                pane,
+                direction,
+                command,
+                command_dir,
+                result,
+            })
+        })
+        .await
+    }
+
     async fn spawn_pane(
         &self,
         _size: PtySize,
-        _command: Option<CommandBuilder>,
-        _command_dir: Option<String>,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
     ) -> anyhow::Result<Rc<dyn Pane>> {
-        anyhow::bail!("Spawn_pane not yet implemented for TmuxDomain");
+        let command = tmux_command_line(command)?;
+        self.spawn_with(|result| {
+            Box::new(NewWindow {
+                command,
+                command_dir,
+                result,
+            })
+        })
+        .await
     }
 
     fn domain_id(&self) -> DomainId {
@@ -226,10 +390,25 @@ impl Domain for TmuxDomain {
     }
 
     fn detach(&self) -> anyhow::Result<()> {
-        anyhow::bail!("detach not implemented for TmuxDomain");
+        if *self.inner.detached.borrow() {
+            return Ok(());
+        }
+        // tmux acknowledges with %exit, which releases our panes
+        self.inner
+            .cmd_queue
+            .lock()
+            .unwrap()
+            .push_back(Box::new(DetachClient));
+        TmuxDomainState::schedule_send_next_command(self.inner.domain_id);
+        *self.inner.detached.borrow_mut() = true;
+        Ok(())
     }
 
     fn state(&self) -> DomainState {
-        DomainState::Attached
+        if *self.inner.detached.borrow() {
+            DomainState::Detached
+        } else {
+            DomainState::Attached
+        }
     }
 }
@@ -1,7 +1,7 @@
 use crate::domain::DomainId;
 use crate::localpane::LocalPane;
-use crate::pane::alloc_pane_id;
-use crate::tab::{Tab, TabId};
+use crate::pane::{alloc_pane_id, PaneId};
+use crate::tab::{PaneEntry, PaneNode, SplitDirection, SplitDirectionAndSize, Tab, TabId};
 use crate::tmux::{TmuxDomain, TmuxDomainState, TmuxRemotePane, TmuxTab};
 use crate::tmux_pty::{TmuxChild, TmuxPty};
 use crate::{Mux, Pane};
@@ -19,6 +19,12 @@ pub(crate) trait TmuxCommand: Send + Debug {
     fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()>;
 }
 
+/// The format used when asking tmux to describe its panes.
+/// `window_name` is last because it may contain spaces.
+const PANE_FORMAT: &str = "#{session_id} #{window_id} #{pane_id} \
+    #{pane_index} #{cursor_x} #{cursor_y} #{pane_width} #{pane_height} \
+    #{pane_left} #{pane_top} #{window_layout} #{window_name}";
+
 #[derive(Debug)]
 pub(crate) struct PaneItem {
     session_id: TmuxSessionId,
@@ -31,155 +37,477 @@ pub(crate) struct PaneItem {
     pane_height: u64,
     pane_left: u64,
     pane_top: u64,
+    window_layout: String,
+    window_name: String,
+}
+
+/// Parses the output of a command that used `PANE_FORMAT`
+fn parse_pane_items(output: &str) -> anyhow::Result<Vec<PaneItem>> {
+    let mut items = vec![];
+
+    for line in output.split('\n') {
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.splitn(12, ' ');
+        let session_id = fields.next().ok_or_else(|| anyhow!("missing session_id"))?;
+        let window_id = fields.next().ok_or_else(|| anyhow!("missing window_id"))?;
+        let pane_id = fields.next().ok_or_else(|| anyhow!("missing pane_id"))?;
+        let _pane_index = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_index"))?
+            .parse()?;
+        let cursor_x = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing cursor_x"))?
+            .parse()?;
+        let cursor_y = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing cursor_y"))?
+            .parse()?;
+        let pane_width = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_width"))?
+            .parse()?;
+        let pane_height = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_height"))?
+            .parse()?;
+        let pane_left = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_left"))?
+            .parse()?;
+        let pane_top = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_top"))?
+            .parse()?;
+        let window_layout = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing window_layout"))?
+            .to_owned();
+        let window_name = fields.next().unwrap_or("").to_owned();
+
+        // These ids all have various sigils such as `$`, `%`, `@`,
+        // so skip those prior to parsing them
+        let session_id = session_id[1..].parse()?;
+        let window_id = window_id[1..].parse()?;
+        let pane_id = pane_id[1..].parse()?;
+
+        items.push(PaneItem {
+            session_id,
+            window_id,
+            pane_id,
+            _pane_index,
+            cursor_x,
+            cursor_y,
+            pane_width,
+            pane_height,
+            pane_left,
+            pane_top,
+            window_layout,
+            window_name,
+        });
+    }
+
+    Ok(items)
+}
+
+fn cell_size(width: u64, height: u64) -> PtySize {
+    PtySize {
+        rows: height as u16,
+        cols: width as u16,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Converts a tmux layout cell into the binary split tree used by `Tab`.
+/// `make_entry` is called for each pane in the layout.
+fn layout_to_pane_node<F>(cell: &LayoutCell, make_entry: &mut F) -> anyhow::Result<PaneNode>
+where
+    F: FnMut(TmuxPaneId, &LayoutCell) -> anyhow::Result<PaneEntry>,
+{
+    match &cell.content {
+        LayoutContent::Pane(pane_id) => Ok(PaneNode::Leaf(make_entry(*pane_id, cell)?)),
+        LayoutContent::LeftRight(cells) => split_cells(
+            SplitDirection::Horizontal,
+            cells,
+            cell.width,
+            cell.height,
+            make_entry,
+        ),
+        LayoutContent::TopBottom(cells) => split_cells(
+            SplitDirection::Vertical,
+            cells,
+            cell.width,
+            cell.height,
+            make_entry,
+        ),
+    }
+}
+
+/// tmux cells can hold any number of children, whereas a `Tab` split
+/// has exactly two, so the children are folded into a chain of splits
+/// between the first child and the remainder.
+fn split_cells<F>(
+    direction: SplitDirection,
+    cells: &[LayoutCell],
+    width: u64,
+    height: u64,
+    make_entry: &mut F,
+) -> anyhow::Result<PaneNode>
+where
+    F: FnMut(TmuxPaneId, &LayoutCell) -> anyhow::Result<PaneEntry>,
+{
+    let (first, rest) = match cells.split_first() {
+        Some(split) => split,
+        None => return Ok(PaneNode::Empty),
+    };
+    if rest.is_empty() {
+        return layout_to_pane_node(first, make_entry);
+    }
+
+    // Each split is separated by a single cell wide border
+    let (rest_width, rest_height) = match direction {
+        SplitDirection::Horizontal => (width.saturating_sub(first.width + 1), height),
+        SplitDirection::Vertical => (width, height.saturating_sub(first.height + 1)),
+    };
+
+    Ok(PaneNode::Split {
+        left: Box::new(layout_to_pane_node(first, make_entry)?),
+        right: Box::new(split_cells(
+            direction,
+            rest,
+            rest_width,
+            rest_height,
+            make_entry,
+        )?),
+        node: SplitDirectionAndSize {
+            direction,
+            first: cell_size(first.width, first.height),
+            second: cell_size(rest_width, rest_height),
+        },
+    })
 }
 
 impl TmuxDomainState {
-    /// check if a PaneItem received from ListAllPanes has been attached
-    fn check_pane_attached(&self, target: &PaneItem) -> bool {
-        let pane_list = self.gui_tabs.borrow();
-        let local_tab = match pane_list
+    /// Returns the local tab that mirrors the specified tmux window
+    pub(crate) fn tab_for_window(&self, window_id: TmuxWindowId) -> Option<TabId> {
+        self.gui_tabs
+            .borrow()
             .iter()
-            .find(|&x| x.tmux_window_id == target.window_id)
+            .find(|t| t.tmux_window_id == window_id)
+            .map(|t| t.tab_id)
+    }
+
+    /// Returns the local pane that mirrors the specified tmux pane
+    pub(crate) fn local_pane_id(&self, pane_id: TmuxPaneId) -> Option<PaneId> {
+        self.remote_panes
+            .borrow()
+            .get(&pane_id)
+            .map(|p| p.lock().unwrap().local_pane_id)
+    }
+
+    /// Returns the tmux pane that is mirrored by the specified local pane
+    pub(crate) fn remote_pane_id(&self, local_pane_id: PaneId) -> Option<TmuxPaneId> {
+        self.remote_panes
+            .borrow()
+            .values()
+            .map(|p| p.lock().unwrap())
+            .find(|p| p.local_pane_id == local_pane_id)
+            .map(|p| p.pane_id)
+    }
+
+    /// Creates a local pane to mirror a remote tmux pane, and schedules
+    /// fetching its current content
+    fn create_pane(
+        &self,
+        window_id: TmuxWindowId,
+        pane_id: TmuxPaneId,
+        cell: &LayoutCell,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        let local_pane_id = alloc_pane_id();
+        let (output_read, output_write) = filedescriptor::socketpair()?;
+        let active_lock = Arc::new((Mutex::new(false), Condvar::new()));
+
+        let ref_pane = Arc::new(Mutex::new(TmuxRemotePane {
+            local_pane_id,
+            output_write,
+            active_lock: active_lock.clone(),
+            session_id: self.tmux_session.borrow().unwrap_or(0),
+            window_id,
+            pane_id,
+            cursor_x: 0,
+            cursor_y: 0,
+            pane_width: cell.width,
+            pane_height: cell.height,
+            pane_left: cell.x,
+            pane_top: cell.y,
+        }));
+
         {
-            Some(x) => x,
+            let mut pane_map = self.remote_panes.borrow_mut();
+            pane_map.insert(pane_id, ref_pane.clone());
+        }
+
+        let pane_pty = TmuxPty {
+            domain_id: self.domain_id,
+            reader: output_read,
+            cmd_queue: self.cmd_queue.clone(),
+            master_pane: ref_pane,
+        };
+        let writer = pane_pty.try_clone_writer()?;
+        let mux = Mux::get().expect("should be called at main thread");
+        let size = cell_size(cell.width, cell.height);
+
+        let child = TmuxChild {
+            domain_id: self.domain_id,
+            pane_id,
+            cmd_queue: self.cmd_queue.clone(),
+            active_lock,
+        };
+
+        let terminal = wezterm_term::Terminal::new(
+            crate::pty_size_to_terminal_size(size),
+            std::sync::Arc::new(config::TermConfig::new()),
+            "WezTerm",
+            config::wezterm_version(),
+            Box::new(writer),
+        );
+
+        let local_pane: Rc<dyn Pane> = Rc::new(LocalPane::new(
+            local_pane_id,
+            terminal,
+            Box::new(child),
+            Box::new(pane_pty),
+            self.domain_id,
+        ));
+        mux.add_pane(&local_pane)?;
+
+        self.cmd_queue
+            .lock()
+            .unwrap()
+            .push_back(Box::new(CapturePane(pane_id)));
+        TmuxDomainState::schedule_send_next_command(self.domain_id);
+
+        log::info!("new pane attached");
+        Ok(local_pane)
+    }
+
+    /// Forgets about a remote pane and allows its local counterpart
+    /// to be reaped
+    fn release_pane(&self, pane_id: TmuxPaneId) -> Option<PaneId> {
+        let ref_pane = self.remote_panes.borrow_mut().remove(&pane_id)?;
+        let remote_pane = ref_pane.lock().unwrap();
+        let (lock, condvar) = &*remote_pane.active_lock;
+        let mut released = lock.lock().unwrap();
+        *released = true;
+        condvar.notify_all();
+        Some(remote_pane.local_pane_id)
+    }
+
+    /// Updates the local tab that mirrors a tmux window so that its
+    /// split tree matches the layout reported by tmux, creating the
+    /// tab and any panes that are not yet known, and removing panes
+    /// that are no longer present.
+    pub(crate) fn sync_window_layout(
+        &self,
+        window_id: TmuxWindowId,
+        layout: &WindowLayout,
+    ) -> anyhow::Result<()> {
+        let mux = Mux::get().expect("should be called at main thread");
+        self.create_gui_window();
+        let gui_window_id = match self.gui_window.borrow().as_ref() {
+            Some(x) => **x,
             None => {
-                return false;
+                anyhow::bail!("No tmux gui created");
             }
         };
-        match local_tab.panes.get(&target.pane_id) {
-            Some(_) => {
-                return true;
+
+        let size = cell_size(layout.width, layout.height);
+        let (tab, is_new_tab) = match self
+            .tab_for_window(window_id)
+            .and_then(|tab_id| mux.get_tab(tab_id))
+        {
+            Some(tab) => (tab, false),
+            None => (Rc::new(Tab::new(&size)), true),
+        };
+        let tab_id = tab.tab_id();
+        let active_pane_id = tab.get_active_pane().map(|p| p.pane_id());
+
+        let mut pane_ids = HashSet::new();
+        let mut resized_panes = vec![];
+        let root = layout_to_pane_node(&layout.root, &mut |pane_id, cell| {
+            pane_ids.insert(pane_id);
+            let local_pane_id = match self.remote_panes.borrow().get(&pane_id) {
+                Some(ref_pane) => {
+                    let mut remote_pane = ref_pane.lock().unwrap();
+                    remote_pane.pane_width = cell.width;
+                    remote_pane.pane_height = cell.height;
+                    remote_pane.pane_left = cell.x;
+                    remote_pane.pane_top = cell.y;
+                    Some(remote_pane.local_pane_id)
+                }
+                None => None,
+            };
+            let size = cell_size(cell.width, cell.height);
+            let local_pane = match local_pane_id.and_then(|id| mux.get_pane(id)) {
+                Some(pane) => {
+                    resized_panes.push((Rc::clone(&pane), size));
+                    pane
+                }
+                None => {
+                    // The local pane may have been closed while tmux
+                    // is still catching up with killing it
+                    self.release_pane(pane_id);
+                    self.create_pane(window_id, pane_id, cell)?
+                }
+            };
+            let local_pane_id = local_pane.pane_id();
+            Ok(PaneEntry {
+                window_id: gui_window_id,
+                tab_id,
+                pane_id: local_pane_id,
+                title: String::new(),
+                size,
+                working_dir: None,
+                is_active_pane: Some(local_pane_id) == active_pane_id,
+                is_zoomed_pane: false,
+                workspace: mux.active_workspace(),
+            })
+        })?;
+
+        tab.sync_with_pane_tree(size, root, |entry| {
+            mux.get_pane(entry.pane_id)
+                .expect("tmux pane to have been added to the mux")
+        });
+
+        // The tab adopts the new size without resizing its panes,
+        // so apply the sizes that tmux picked for existing panes
+        for (pane, size) in resized_panes {
+            let dims = pane.get_dimensions();
+            if dims.cols != size.cols as usize || dims.viewport_rows != size.rows as usize {
+                pane.resize(size).ok();
             }
-            None => {
-                return false;
+        }
+
+        let removed_panes: Vec<TmuxPaneId> = {
+            let mut gui_tabs = self.gui_tabs.borrow_mut();
+            match gui_tabs.iter_mut().find(|t| t.tmux_window_id == window_id) {
+                Some(tmux_tab) => {
+                    let removed = tmux_tab.panes.difference(&pane_ids).cloned().collect();
+                    tmux_tab.tab_id = tab_id;
+                    tmux_tab.panes = pane_ids;
+                    tmux_tab.size = size;
+                    removed
+                }
+                None => {
+                    gui_tabs.push(TmuxTab {
+                        tab_id,
+                        tmux_window_id: window_id,
+                        panes: pane_ids,
+                        size,
+                    });
+                    vec![]
+                }
+            }
+        };
+        for pane_id in removed_panes {
+            if let Some(local_pane_id) = self.release_pane(pane_id) {
+                mux.remove_pane(local_pane_id);
             }
         }
+
+        if is_new_tab {
+            mux.add_tab_no_panes(&tab);
+            mux.add_tab_to_window(&tab, gui_window_id)?;
+            if let Some(gui_window) = self.gui_window.borrow_mut().as_mut() {
+                gui_window.notify();
+            }
+        }
+
+        Ok(())
     }
 
-    /// after we create a tab for a remote pane, save its ID into the
-    /// TmuxPane-TmuxPane tree, so we can ref it later.
-    fn add_attached_pane(&self, target: &PaneItem, tab_id: &TabId) -> anyhow::Result<()> {
-        let mut pane_list = self.gui_tabs.borrow_mut();
-        let local_tab = match pane_list
-            .iter_mut()
-            .find(|x| x.tmux_window_id == target.window_id)
-        {
-            Some(x) => x,
-            None => {
-                pane_list.push(TmuxTab {
-                    tab_id: *tab_id,
-                    tmux_window_id: target.window_id,
-                    panes: HashSet::new(),
-                });
-                pane_list.last_mut().unwrap()
+    /// Removes the local tab that mirrors a tmux window that was closed
+    pub(crate) fn close_window(&self, window_id: TmuxWindowId) {
+        let tmux_tab = {
+            let mut gui_tabs = self.gui_tabs.borrow_mut();
+            match gui_tabs.iter().position(|t| t.tmux_window_id == window_id) {
+                Some(idx) => gui_tabs.remove(idx),
+                None => return,
             }
         };
-        match local_tab.panes.get(&target.pane_id) {
-            Some(_) => {
-                anyhow::bail!("Tmux pane already attached");
-            }
-            None => {
-                local_tab.panes.insert(target.pane_id);
-                return Ok(());
+        for pane_id in tmux_tab.panes.iter() {
+            self.release_pane(*pane_id);
+        }
+        let mux = Mux::get().expect("should be called at main thread");
+        mux.remove_tab(tmux_tab.tab_id);
+    }
+
+    /// Propagates the name of a tmux window to the panes of its local tab
+    pub(crate) fn set_window_title(&self, window_id: TmuxWindowId, name: &str) {
+        let name: String = name.chars().filter(|c| !c.is_control()).collect();
+        let gui_tabs = self.gui_tabs.borrow();
+        let tmux_tab = match gui_tabs.iter().find(|t| t.tmux_window_id == window_id) {
+            Some(t) => t,
+            None => return,
+        };
+        let pane_map = self.remote_panes.borrow();
+        for pane_id in tmux_tab.panes.iter() {
+            if let Some(ref_pane) = pane_map.get(pane_id) {
+                let mut remote_pane = ref_pane.lock().unwrap();
+                if let Err(err) = write!(remote_pane.output_write, "\x1b]2;{}\x1b\\", name) {
+                    log::error!("Failed to set title of tmux pane {}: {:#}", pane_id, err);
+                }
             }
         }
     }
 
+    /// Makes the local counterpart of a tmux pane the active pane in its tab
+    pub(crate) fn set_active_pane(&self, window_id: TmuxWindowId, pane_id: TmuxPaneId) {
+        let mux = Mux::get().expect("should be called at main thread");
+        let tab = match self
+            .tab_for_window(window_id)
+            .and_then(|tab_id| mux.get_tab(tab_id))
+        {
+            Some(tab) => tab,
+            None => return,
+        };
+        if let Some(pane) = self
+            .local_pane_id(pane_id)
+            .and_then(|local_pane_id| mux.get_pane(local_pane_id))
+        {
+            tab.set_active_pane(&pane);
+        }
+    }
+
     fn sync_pane_state(&self, panes: &[PaneItem]) -> anyhow::Result<()> {
-        // TODO:
-        // 1) iter over current session panes
-        // 2) create pane if not exist
-        // 3) fetch scroll buffer if new created
-        // 4) update pane state if exist
         let current_session = self.tmux_session.borrow().unwrap_or(0);
+        let mut synced_windows = HashSet::new();
         for pane in panes.iter() {
-            if pane.session_id != current_session || self.check_pane_attached(&pane) {
+            if pane.session_id != current_session {
                 continue;
             }
 
-            let local_pane_id = alloc_pane_id();
-            let (output_read, output_write) = filedescriptor::socketpair()?;
-            let active_lock = Arc::new((Mutex::new(false), Condvar::new()));
-
-            let ref_pane = Arc::new(Mutex::new(TmuxRemotePane {
-                local_pane_id,
-                output_write,
-                active_lock: active_lock.clone(),
-                session_id: pane.session_id,
-                window_id: pane.window_id,
-                pane_id: pane.pane_id,
-                cursor_x: pane.cursor_x,
-                cursor_y: pane.cursor_y,
-                pane_width: pane.pane_width,
-                pane_height: pane.pane_height,
-                pane_left: pane.pane_left,
-                pane_top: pane.pane_top,
-            }));
-
-            {
-                let mut pane_map = self.remote_panes.borrow_mut();
-                pane_map.insert(pane.pane_id, ref_pane.clone());
-            }
-
-            let pane_pty = TmuxPty {
-                domain_id: self.domain_id,
-                reader: output_read,
-                cmd_queue: self.cmd_queue.clone(),
-                master_pane: ref_pane,
-            };
-            let writer = pane_pty.try_clone_writer()?;
-            let mux = Mux::get().expect("should be called at main thread");
-            let size = PtySize {
-                rows: pane.pane_height as u16,
-                cols: pane.pane_width as u16,
-                pixel_width: 0,
-                pixel_height: 0,
-            };
-
-            let child = TmuxChild {
-                active_lock: active_lock.clone(),
-            };
-
-            let terminal = wezterm_term::Terminal::new(
-                crate::pty_size_to_terminal_size(size),
-                std::sync::Arc::new(config::TermConfig::new()),
-                "WezTerm",
-                config::wezterm_version(),
-                Box::new(writer),
-            );
-
-            let local_pane: Rc<dyn Pane> = Rc::new(LocalPane::new(
-                local_pane_id,
-                terminal,
-                Box::new(child),
-                Box::new(pane_pty),
-                self.domain_id,
-            ));
-
-            let tab = Rc::new(Tab::new(&size));
-            tab.assign_pane(&local_pane);
-
-            self.create_gui_window();
-            let mut gui_window = self.gui_window.borrow_mut();
-            let gui_window_id = match gui_window.as_mut() {
-                Some(x) => x,
-                None => {
-                    anyhow::bail!("No tmux gui created");
+            if synced_windows.insert(pane.window_id) {
+                let is_new_window = self.tab_for_window(pane.window_id).is_none();
+                let layout = parse_layout(&pane.window_layout)?;
+                self.sync_window_layout(pane.window_id, &layout)?;
+                if is_new_window {
+                    self.set_window_title(pane.window_id, &pane.window_name);
                 }
-            };
-
-            mux.add_tab_and_active_pane(&tab)?;
-            mux.add_tab_to_window(&tab, **gui_window_id)?;
-            gui_window_id.notify();
-
-            self.cmd_queue
-                .lock()
-                .unwrap()
-                .push_back(Box::new(CapturePane(pane.pane_id)));
-            TmuxDomainState::schedule_send_next_command(self.domain_id);
+            }
 
-            self.add_attached_pane(&pane, &tab.tab_id())?;
-            log::info!("new pane attached");
+            if let Some(ref_pane) = self.remote_panes.borrow().get(&pane.pane_id) {
+                let mut remote_pane = ref_pane.lock().unwrap();
+                remote_pane.cursor_x = pane.cursor_x;
+                remote_pane.cursor_y = pane.cursor_y;
+                remote_pane.pane_width = pane.pane_width;
+                remote_pane.pane_height = pane.pane_height;
+                remote_pane.pane_left = pane.pane_left;
+                remote_pane.pane_top = pane.pane_top;
+            }
         }
         Ok(())
     }
@@ -189,71 +517,11 @@ impl TmuxDomainState {
 pub(crate) struct ListAllPanes;
 impl TmuxCommand for ListAllPanes {
     fn get_command(&self) -> String {
-        "list-panes -aF '#{session_id} #{window_id} #{pane_id} \
-            #{pane_index} #{cursor_x} #{cursor_y} #{pane_width} #{pane_height} \
-            #{pane_left} #{pane_top}'\n"
-            .to_owned()
+        format!("list-panes -aF '{}'\n", PANE_FORMAT)
     }
 
     fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
-        let mut items = vec![];
-
-        for line in result.output.split('\n') {
-            if line.is_empty() {
-                continue;
-            }
-            let mut fields = line.split(' ');
-            let session_id = fields.next().ok_or_else(|| anyhow!("missing session_id"))?;
-            let window_id = fields.next().ok_or_else(|| anyhow!("missing window_id"))?;
-            let pane_id = fields.next().ok_or_else(|| anyhow!("missing pane_id"))?;
-            let _pane_index = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_index"))?
-                .parse()?;
-            let cursor_x = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing cursor_x"))?
-                .parse()?;
-            let cursor_y = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing cursor_y"))?
-                .parse()?;
-            let pane_width = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_width"))?
-                .parse()?;
-            let pane_height = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_height"))?
-                .parse()?;
-            let pane_left = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_left"))?
-                .parse()?;
-            let pane_top = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_top"))?
-                .parse()?;
-
-            // These ids all have various sigils such as `$`, `%`, `@`,
-            // so skip those prior to parsing them
-            let session_id = session_id[1..].parse()?;
-            let window_id = window_id[1..].parse()?;
-            let pane_id = pane_id[1..].parse()?;
-
-            items.push(PaneItem {
-                session_id,
-                window_id,
-                pane_id,
-                _pane_index,
-                cursor_x,
-                cursor_y,
-                pane_width,
-                pane_height,
-                pane_left,
-                pane_top,
-            });
-        }
+        let items = parse_pane_items(&result.output)?;
 
         log::info!("panes in domain_id {}: {:?}", domain_id, items);
         let mux = Mux::get().expect("to be called on main thread");
@@ -288,6 +556,34 @@ impl TmuxCommand for Resize {
     }
 }
 
+/// Resizes a pane after a split in the tab that mirrors its window moved
+#[derive(Debug)]
+pub(crate) struct ResizePane {
+    pub pane_id: TmuxPaneId,
+    pub size: PtySize,
+}
+
+impl TmuxCommand for ResizePane {
+    fn get_command(&self) -> String {
+        format!(
+            "resize-pane -t %{} -x {} -y {}\n",
+            self.pane_id, self.size.cols, self.size.rows
+        )
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            log::error!(
+                "Error resizing pane: domain_id={} pane_id={} result={:?}",
+                domain_id,
+                self.pane_id,
+                result
+            );
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct CapturePane(TmuxPaneId);
 impl TmuxCommand for CapturePane {
@@ -340,3 +636,228 @@ impl TmuxCommand for SendKeys {
         Ok(())
     }
 }
+
+/// The format used by commands that create panes, so that the
+/// new pane can be mirrored as soon as the command completes
+const NEW_PANE_FORMAT: &str = "#{window_id} #{pane_id} #{window_layout}";
+
+/// Appends the optional working directory and command arguments
+/// shared by `new-window` and `split-window`
+fn append_spawn_args(cmd: &mut String, command: &Option<String>, command_dir: &Option<String>) {
+    if let Some(dir) = command_dir {
+        write!(cmd, " -c {}", shell_words::quote(dir)).expect("unable to write dir");
+    }
+    if let Some(command) = command {
+        write!(cmd, " {}", shell_words::quote(command)).expect("unable to write command");
+    }
+}
+
+/// Mirrors the pane reported by a command that used `NEW_PANE_FORMAT`,
+/// returning the id of its local counterpart
+fn attach_new_pane(domain_id: DomainId, result: &Guarded) -> anyhow::Result<PaneId> {
+    if result.error {
+        anyhow::bail!("{}", result.output.trim());
+    }
+    let line = result
+        .output
+        .lines()
+        .next()
+        .ok_or_else(|| anyhow!("missing new pane"))?;
+    let mut fields = line.split(' ');
+    let window_id = fields.next().ok_or_else(|| anyhow!("missing window_id"))?;
+    let pane_id = fields.next().ok_or_else(|| anyhow!("missing pane_id"))?;
+    let layout = fields
+        .next()
+        .ok_or_else(|| anyhow!("missing window_layout"))?;
+
+    let window_id: TmuxWindowId = window_id[1..].parse()?;
+    let pane_id: TmuxPaneId = pane_id[1..].parse()?;
+    let layout = parse_layout(layout)?;
+
+    let mux = Mux::get().expect("to be called on main thread");
+    let domain = match mux.get_domain(domain_id) {
+        Some(d) => d,
+        None => anyhow::bail!("Tmux domain lost"),
+    };
+    let tmux_domain = match domain.downcast_ref::<TmuxDomain>() {
+        Some(t) => t,
+        None => anyhow::bail!("Tmux domain lost"),
+    };
+    tmux_domain.inner.sync_window_layout(window_id, &layout)?;
+    tmux_domain
+        .inner
+        .local_pane_id(pane_id)
+        .ok_or_else(|| anyhow!("Tmux pane {} was not attached", pane_id))
+}
+
+#[derive(Debug)]
+pub(crate) struct NewWindow {
+    pub command: Option<String>,
+    pub command_dir: Option<String>,
+    pub result: flume::Sender<anyhow::Result<PaneId>>,
+}
+impl TmuxCommand for NewWindow {
+    fn get_command(&self) -> String {
+        let mut cmd = format!("new-window -P -F '{}'", NEW_PANE_FORMAT);
+        append_spawn_args(&mut cmd, &self.command, &self.command_dir);
+        cmd.push('\n');
+        cmd
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        self.result.send(attach_new_pane(domain_id, result)).ok();
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SplitWindow {
+    pub pane: TmuxPaneId,
+    pub direction: SplitDirection,
+    pub command: Option<String>,
+    pub command_dir: Option<String>,
+    pub result: flume::Sender<anyhow::Result<PaneId>>,
+}
+impl TmuxCommand for SplitWindow {
+    fn get_command(&self) -> String {
+        let direction = match self.direction {
+            SplitDirection::Horizontal => "-h",
+            SplitDirection::Vertical => "-v",
+        };
+        let mut cmd = format!(
+            "split-window {} -t %{} -P -F '{}'",
+            direction, self.pane, NEW_PANE_FORMAT
+        );
+        append_spawn_args(&mut cmd, &self.command, &self.command_dir);
+        cmd.push('\n');
+        cmd
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        self.result.send(attach_new_pane(domain_id, result)).ok();
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct KillPane(pub TmuxPaneId);
+impl TmuxCommand for KillPane {
+    fn get_command(&self) -> String {
+        format!("kill-pane -t %{}\n", self.0)
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            log::error!(
+                "Error killing pane %{}: domain_id={} result={:?}",
+                self.0,
+                domain_id,
+                result
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct DetachClient;
+impl TmuxCommand for DetachClient {
+    fn get_command(&self) -> String {
+        "detach-client\n".to_owned()
+    }
+
+    fn process_result(&self, _domain_id: DomainId, _result: &Guarded) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Converts `layout`, returning a compact description of the
+    /// resulting tree: splits are shown as `H` (side by side) or `V`
+    /// (stacked) along with the sizes of their two halves
+    fn convert(layout: &str) -> anyhow::Result<String> {
+        let layout = parse_layout(layout)?;
+        let root = layout_to_pane_node(&layout.root, &mut |pane_id, cell| {
+            Ok(PaneEntry {
+                window_id: 0,
+                tab_id: 0,
+                pane_id: pane_id as PaneId,
+                title: String::new(),
+                size: cell_size(cell.width, cell.height),
+                working_dir: None,
+                is_active_pane: false,
+                is_zoomed_pane: false,
+                workspace: String::new(),
+            })
+        })?;
+        Ok(describe(&root))
+    }
+
+    fn describe(node: &PaneNode) -> String {
+        let size = |s: &PtySize| format!("{}x{}", s.cols, s.rows);
+        match node {
+            PaneNode::Empty => "empty".to_string(),
+            PaneNode::Leaf(entry) => format!("%{}:{}", entry.pane_id, size(&entry.size)),
+            PaneNode::Split { left, right, node } => format!(
+                "{}[{}|{}]({}, {})",
+                match node.direction {
+                    SplitDirection::Horizontal => 'H',
+                    SplitDirection::Vertical => 'V',
+                },
+                size(&node.first),
+                size(&node.second),
+                describe(left),
+                describe(right)
+            ),
+        }
+    }
+
+    #[test]
+    fn single_pane() {
+        assert_eq!(convert("b25d,80x24,0,0,5").unwrap(), "%5:80x24");
+    }
+
+    #[test]
+    fn nested_splits() {
+        assert_eq!(
+            convert("c4ab,160x40,0,0{80x40,0,0,1,79x40,81,0[79x20,81,0,2,79x19,81,21,3]}").unwrap(),
+            "H[80x40|79x40](%1:80x40, V[79x20|79x19](%2:79x20, %3:79x19))"
+        );
+    }
+
+    #[test]
+    fn more_than_two_children() {
+        // The children are folded into a chain of splits, each of which
+        // accounts for the border between the first child and the rest
+        assert_eq!(
+            convert("abcd,160x40,0,0{50x40,0,0,1,50x40,51,0,2,58x40,102,0,3}").unwrap(),
+            "H[50x40|109x40](%1:50x40, H[50x40|58x40](%2:50x40, %3:58x40))"
+        );
+        assert_eq!(
+            convert("abcd,80x24,0,0[80x7,0,0,1,80x7,0,8,2,80x8,0,16,3]").unwrap(),
+            "V[80x7|80x16](%1:80x7, V[80x7|80x8](%2:80x7, %3:80x8))"
+        );
+    }
+
+    #[test]
+    fn pane_errors_are_propagated() {
+        let layout = parse_layout("c4ab,160x40,0,0{80x40,0,0,1,79x40,81,0,2}").unwrap();
+        let err = layout_to_pane_node(&layout.root, &mut |pane_id, _cell| {
+            anyhow::bail!("no pane %{}", pane_id)
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "no pane %1");
+    }
+
+    #[test]
+    fn resize_pane_command() {
+        let cmd = ResizePane {
+            pane_id: 3,
+            size: cell_size(80, 24),
+        };
+        assert_eq!(cmd.get_command(), "resize-pane -t %3 -x 80 -y 24\n");
+    }
+}
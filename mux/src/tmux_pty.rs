@@ -1,10 +1,11 @@
 use crate::tmux::{RefTmuxRemotePane, TmuxCmdQueue, TmuxDomainState};
-use crate::tmux_commands::{Resize, SendKeys};
+use crate::tmux_commands::{KillPane, SendKeys};
 use crate::DomainId;
 use filedescriptor::FileDescriptor;
 use portable_pty::{Child, ChildKiller, ExitStatus, MasterPty};
 use std::io::{Read, Write};
 use std::sync::{Arc, Condvar, Mutex};
+use termwiz::tmux_cc::TmuxPaneId;
 
 /// A local tmux pane(tab) based on a tmux pty
 #[derive(Debug)]
@@ -65,12 +66,20 @@ impl Write for TmuxPty {
 
 #[derive(Clone, Debug)]
 pub(crate) struct TmuxChild {
+    pub domain_id: DomainId,
+    pub pane_id: TmuxPaneId,
+    pub cmd_queue: Arc<Mutex<TmuxCmdQueue>>,
     pub active_lock: Arc<(Mutex<bool>, Condvar)>,
 }
 
 impl Child for TmuxChild {
     fn try_wait(&mut self) -> std::io::Result<Option<portable_pty::ExitStatus>> {
-        todo!()
+        let (lock, _) = &*self.active_lock;
+        if *lock.lock().unwrap() {
+            Ok(Some(ExitStatus::with_exit_code(0)))
+        } else {
+            Ok(None)
+        }
     }
 
     fn wait(&mut self) -> std::io::Result<portable_pty::ExitStatus> {
@@ -93,14 +102,42 @@ impl Child for TmuxChild {
 }
 
 #[derive(Clone, Debug)]
-struct TmuxChildKiller {}
+struct TmuxChildKiller {
+    domain_id: DomainId,
+    pane_id: TmuxPaneId,
+    cmd_queue: Arc<Mutex<TmuxCmdQueue>>,
+    active_lock: Arc<(Mutex<bool>, Condvar)>,
+}
+
+/// Asks tmux to kill the remote pane.  The local pane is released
+/// once tmux reports that the pane has gone away, so there is nothing
+/// to do if that has already happened.
+fn kill_remote_pane(
+    domain_id: DomainId,
+    pane_id: TmuxPaneId,
+    cmd_queue: &Mutex<TmuxCmdQueue>,
+    active_lock: &(Mutex<bool>, Condvar),
+) {
+    let (lock, _) = active_lock;
+    if *lock.lock().unwrap() {
+        return;
+    }
+    cmd_queue
+        .lock()
+        .unwrap()
+        .push_back(Box::new(KillPane(pane_id)));
+    TmuxDomainState::schedule_send_next_command(domain_id);
+}
 
 impl ChildKiller for TmuxChildKiller {
     fn kill(&mut self) -> std::io::Result<()> {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "TmuxChildKiller: kill not implemented!",
-        ))
+        kill_remote_pane(
+            self.domain_id,
+            self.pane_id,
+            &self.cmd_queue,
+            &self.active_lock,
+        );
+        Ok(())
     }
 
     fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
@@ -110,22 +147,38 @@ impl ChildKiller for TmuxChildKiller {
 
 impl ChildKiller for TmuxChild {
     fn kill(&mut self) -> std::io::Result<()> {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "TmuxPty: kill not implemented!",
-        ))
+        kill_remote_pane(
+            self.domain_id,
+            self.pane_id,
+            &self.cmd_queue,
+            &self.active_lock,
+        );
+        Ok(())
     }
 
     fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
-        Box::new(TmuxChildKiller {})
+        Box::new(TmuxChildKiller {
+            domain_id: self.domain_id,
+            pane_id: self.pane_id,
+            cmd_queue: self.cmd_queue.clone(),
+            active_lock: self.active_lock.clone(),
+        })
     }
 }
 
 impl MasterPty for TmuxPty {
     fn resize(&self, size: portable_pty::PtySize) -> Result<(), anyhow::Error> {
-        let mut cmd_queue = self.cmd_queue.lock().unwrap();
-        cmd_queue.push_back(Box::new(Resize { size }));
-        TmuxDomainState::schedule_send_next_command(self.domain_id);
+        let (local_pane_id, pane_id) = {
+            let mut pane = self.master_pane.lock().unwrap();
+            if (pane.pane_width, pane.pane_height) == (size.cols as u64, size.rows as u64) {
+                // Already the size that tmux gave it
+                return Ok(());
+            }
+            pane.pane_width = size.cols as u64;
+            pane.pane_height = size.rows as u64;
+            (pane.local_pane_id, pane.pane_id)
+        };
+        TmuxDomainState::schedule_resize(self.domain_id, local_pane_id, pane_id, size);
         Ok(())
     }
 
@@ -11,6 +11,7 @@ pub struct Window {
     active: usize,
     last_active: Option<TabId>,
     workspace: String,
+    title: String,
 }
 
 impl Window {
@@ -25,9 +26,26 @@ impl Window {
                     .expect("Window::new to be called on mux thread")
                     .active_workspace()
             }),
+            title: String::new(),
         }
     }
 
+    /// Returns the title that was explicitly assigned to this window,
+    /// which is empty if none has been set
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    /// Assigns the title of the window.  An empty title reverts to
+    /// using the title of the active tab.
+    pub fn set_title(&mut self, title: &str) {
+        if title == self.title {
+            return;
+        }
+        self.title = title.to_string();
+        self.invalidate();
+    }
+
     pub fn get_workspace(&self) -> &str {
         &self.workspace
     }
@@ -154,31 +154,48 @@ pub trait SlavePty {
 }
 
 /// Represents the exit status of a child process.
-/// This is rather anemic in the current version of this crate,
-/// holding only an indicator of success or failure.
+/// The exit code is retained so that it can be reported, for example
+/// by `wezterm record`; the representation is private, so this is
+/// compatible with earlier versions that only tracked success.
 #[derive(Debug, Clone)]
 pub struct ExitStatus {
-    successful: bool,
+    code: u32,
 }
 
 impl ExitStatus {
     /// Construct an ExitStatus from a process return code
     pub fn with_exit_code(code: u32) -> Self {
-        Self {
-            successful: code == 0,
-        }
+        Self { code }
     }
 
     pub fn success(&self) -> bool {
-        self.successful
+        self.code == 0
+    }
+
+    /// Returns the exit code of the process.
+    /// A process that was terminated by a signal on unix
+    /// systems has an exit code of 128 plus the signal number,
+    /// following the convention used by shells.
+    pub fn exit_code(&self) -> u32 {
+        self.code
     }
 }
 
 impl From<std::process::ExitStatus> for ExitStatus {
     fn from(status: std::process::ExitStatus) -> ExitStatus {
-        ExitStatus {
-            successful: status.success(),
+        if let Some(code) = status.code() {
+            return ExitStatus::with_exit_code(code as u32);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return ExitStatus::with_exit_code(128 + signal as u32);
+            }
         }
+
+        ExitStatus::with_exit_code(1)
     }
 }
 
@@ -337,3 +354,30 @@ pub fn native_pty_system() -> Box<dyn PtySystem> {
 pub type NativePtySystem = unix::UnixPtySystem;
 #[cfg(windows)]
 pub type NativePtySystem = win::conpty::ConPtySystem;
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    #[test]
+    fn exit_status() {
+        let status = ExitStatus::with_exit_code(0);
+        assert!(status.success());
+        assert_eq!(status.exit_code(), 0);
+
+        let status = ExitStatus::with_exit_code(3);
+        assert!(!status.success());
+        assert_eq!(status.exit_code(), 3);
+
+        // The raw wait status encodes the exit code in the second byte
+        let status: ExitStatus = std::process::ExitStatus::from_raw(2 << 8).into();
+        assert!(!status.success());
+        assert_eq!(status.exit_code(), 2);
+
+        // and a terminating signal in the low bits
+        let status: ExitStatus = std::process::ExitStatus::from_raw(libc::SIGKILL).into();
+        assert!(!status.success());
+        assert_eq!(status.exit_code(), 128 + libc::SIGKILL as u32);
+    }
+}
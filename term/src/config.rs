@@ -174,6 +174,13 @@ pub trait TerminalConfiguration: std::fmt::Debug {
         false
     }
 
+    /// Return true if applications may negotiate the kitty keyboard
+    /// protocol.
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+    fn enable_kitty_keyboard(&self) -> bool {
+        false
+    }
+
     /// The default unicode version to assume.
     /// This affects how the width of certain sequences is interpreted.
     /// At the time of writing, we default to 9 even though the current
@@ -0,0 +1,445 @@
+//! Exports lines from the terminal, retaining the colors, attributes
+//! and hyperlinks of their cells, as HTML, standalone SVG or text
+//! with ANSI escape sequences.
+use crate::color::ColorPalette;
+use crate::{CellAttributes, Intensity, Line, Underline};
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+use termwiz::color::{ColorAttribute, ColorSpec, RgbColor};
+use termwiz::escape::csi::{Sgr, CSI};
+use termwiz::escape::osc::OperatingSystemCommand;
+use termwiz::hyperlink::Hyperlink;
+
+/// The font size used for SVG output, in pixels
+const SVG_FONT_SIZE: f32 = 14.;
+/// The width of a cell in SVG output, in pixels
+const SVG_CELL_WIDTH: f32 = SVG_FONT_SIZE * 0.6;
+/// The height of a line in SVG output, in pixels
+const SVG_LINE_HEIGHT: f32 = SVG_FONT_SIZE * 1.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub enum ExportFormat {
+    /// Plain text, without any attributes
+    Text,
+    /// An HTML document
+    Html,
+    /// A standalone SVG image
+    Svg,
+    /// Text with ANSI escape sequences for the attributes
+    Ansi,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "html" => Ok(Self::Html),
+            "svg" => Ok(Self::Svg),
+            "ansi" => Ok(Self::Ansi),
+            _ => Err(format!(
+                "invalid format {:?}; expected one of text, html, svg or ansi",
+                s
+            )),
+        }
+    }
+}
+
+/// A sequence of cells from a line that share the same attributes
+struct Run<'a> {
+    attrs: &'a CellAttributes,
+    text: String,
+    col: usize,
+    width: usize,
+}
+
+/// Returns true if the cell would render identically to a cell that
+/// was never written to, so that it can be omitted from the end of
+/// the line.
+fn is_blank(text: &str, attrs: &CellAttributes) -> bool {
+    text == " "
+        && attrs.background() == ColorAttribute::Default
+        && !attrs.reverse()
+        && attrs.underline() == Underline::None
+        && !attrs.strikethrough()
+        && !attrs.overline()
+}
+
+/// Splits the visible cells of a line into runs with the same
+/// attributes, omitting any blank cells from the end of the line
+fn line_runs(line: &Line) -> Vec<Run<'_>> {
+    let cells: Vec<_> = line.visible_cells().collect();
+    let len = cells
+        .iter()
+        .rposition(|(_, cell)| !is_blank(cell.str(), cell.attrs()))
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+
+    let mut runs: Vec<Run> = vec![];
+    for (col, cell) in &cells[0..len] {
+        match runs.last_mut() {
+            Some(run) if run.attrs == cell.attrs() => {
+                run.text.push_str(cell.str());
+                run.width += cell.width();
+            }
+            _ => runs.push(Run {
+                attrs: cell.attrs(),
+                text: cell.str().to_string(),
+                col: *col,
+                width: cell.width(),
+            }),
+        }
+    }
+    runs
+}
+
+/// Resolves the foreground and background colors of a run,
+/// taking reverse video, half intensity and invisibility into account
+fn resolve_colors(attrs: &CellAttributes, palette: &ColorPalette) -> (RgbColor, RgbColor) {
+    let mut fg = palette.resolve_fg(attrs.foreground());
+    let mut bg = palette.resolve_bg(attrs.background());
+    if attrs.reverse() {
+        std::mem::swap(&mut fg, &mut bg);
+    }
+    if attrs.intensity() == Intensity::Half {
+        fg = blend(fg, bg);
+    }
+    if attrs.invisible() {
+        fg = bg;
+    }
+    (fg, bg)
+}
+
+/// Returns the color halfway between a and b
+fn blend(a: RgbColor, b: RgbColor) -> RgbColor {
+    let (ar, ag, ab) = a.to_tuple_rgb8();
+    let (br, bg, bb) = b.to_tuple_rgb8();
+    let mid = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+    RgbColor::new_8bpc(mid(ar, br), mid(ag, bg), mid(ab, bb))
+}
+
+fn has_default_background(attrs: &CellAttributes) -> bool {
+    attrs.background() == ColorAttribute::Default && !attrs.reverse()
+}
+
+/// Returns the value for a CSS/SVG `text-decoration`, if any
+fn text_decoration(attrs: &CellAttributes) -> Option<String> {
+    let mut lines = vec![];
+    if attrs.underline() != Underline::None {
+        lines.push("underline");
+    }
+    if attrs.overline() {
+        lines.push("overline");
+    }
+    if attrs.strikethrough() {
+        lines.push("line-through");
+    }
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
+
+/// URI schemes that are safe to emit as links in HTML and SVG output;
+/// others, such as `javascript:`, are exported as plain text
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto", "file"];
+
+/// Returns the uri of the hyperlink if it is safe to emit it as a link
+fn safe_link_uri(link: &Hyperlink) -> Option<&str> {
+    let uri = link.uri();
+    let (scheme, _) = uri.split_once(':')?;
+    if LINK_SCHEMES
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    {
+        Some(uri)
+    } else {
+        None
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Exports `lines` in the specified format, resolving colors
+/// using `palette`
+pub fn export_lines(lines: &[Line], palette: &ColorPalette, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Text => export_text(lines),
+        ExportFormat::Html => export_html(lines, palette),
+        ExportFormat::Svg => export_svg(lines, palette),
+        ExportFormat::Ansi => export_ansi(lines),
+    }
+}
+
+fn export_text(lines: &[Line]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        let line: String = line_runs(line).into_iter().map(|run| run.text).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+fn export_html(lines: &[Line], palette: &ColorPalette) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n</head>\n");
+    write!(
+        html,
+        "<body style=\"margin:0\">\n<pre style=\"margin:0;padding:0.5em;\
+         font-family:monospace;color:{};background-color:{}\">",
+        palette.foreground.to_rgb_string(),
+        palette.background.to_rgb_string()
+    )
+    .ok();
+
+    for line in lines {
+        for run in line_runs(line) {
+            let attrs = run.attrs;
+            let (fg, bg) = resolve_colors(attrs, palette);
+
+            let mut style = vec![];
+            if fg != palette.foreground {
+                style.push(format!("color:{}", fg.to_rgb_string()));
+            }
+            if !has_default_background(attrs) {
+                style.push(format!("background-color:{}", bg.to_rgb_string()));
+            }
+            if attrs.intensity() == Intensity::Bold {
+                style.push("font-weight:bold".to_string());
+            }
+            if attrs.italic() {
+                style.push("font-style:italic".to_string());
+            }
+            if let Some(decoration) = text_decoration(attrs) {
+                style.push(format!("text-decoration-line:{}", decoration));
+                match attrs.underline() {
+                    Underline::Double => style.push("text-decoration-style:double".to_string()),
+                    Underline::Curly => style.push("text-decoration-style:wavy".to_string()),
+                    Underline::Dotted => style.push("text-decoration-style:dotted".to_string()),
+                    Underline::Dashed => style.push("text-decoration-style:dashed".to_string()),
+                    Underline::Single | Underline::None => {}
+                }
+                if attrs.underline_color() != ColorAttribute::Default {
+                    style.push(format!(
+                        "text-decoration-color:{}",
+                        palette.resolve_fg(attrs.underline_color()).to_rgb_string()
+                    ));
+                }
+            }
+
+            let mut text = escape_xml(&run.text);
+            if !style.is_empty() {
+                text = format!("<span style=\"{}\">{}</span>", style.join(";"), text);
+            }
+            if let Some(uri) = attrs.hyperlink().and_then(|link| safe_link_uri(link)) {
+                text = format!(
+                    "<a href=\"{}\" style=\"color:inherit\">{}</a>",
+                    escape_xml(uri),
+                    text
+                );
+            }
+            html.push_str(&text);
+        }
+        html.push('\n');
+    }
+
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
+
+fn export_svg(lines: &[Line], palette: &ColorPalette) -> String {
+    let line_runs: Vec<Vec<Run>> = lines.iter().map(line_runs).collect();
+    let cols = line_runs
+        .iter()
+        .filter_map(|runs| runs.last().map(|run| run.col + run.width))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let width = cols as f32 * SVG_CELL_WIDTH;
+    let height = lines.len().max(1) as f32 * SVG_LINE_HEIGHT;
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.1}\" height=\"{h:.1}\" \
+         viewBox=\"0 0 {w:.1} {h:.1}\" font-family=\"monospace\" font-size=\"{size}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{bg}\"/>\n",
+        w = width,
+        h = height,
+        size = SVG_FONT_SIZE,
+        bg = palette.background.to_rgb_string()
+    )
+    .ok();
+
+    for (row, runs) in line_runs.iter().enumerate() {
+        let top = row as f32 * SVG_LINE_HEIGHT;
+        let baseline = top + SVG_FONT_SIZE;
+
+        for run in runs {
+            let (fg, bg) = resolve_colors(run.attrs, palette);
+            let x = run.col as f32 * SVG_CELL_WIDTH;
+            let run_width = run.width as f32 * SVG_CELL_WIDTH;
+
+            if !has_default_background(run.attrs) {
+                writeln!(
+                    svg,
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>",
+                    x,
+                    top,
+                    run_width,
+                    SVG_LINE_HEIGHT,
+                    bg.to_rgb_string()
+                )
+                .ok();
+            }
+
+            if run.text.trim().is_empty() && text_decoration(run.attrs).is_none() {
+                continue;
+            }
+
+            let mut attributes = format!(
+                "x=\"{:.1}\" y=\"{:.1}\" textLength=\"{:.1}\" lengthAdjust=\"spacingAndGlyphs\" \
+                 fill=\"{}\"",
+                x,
+                baseline,
+                run_width,
+                fg.to_rgb_string()
+            );
+            if run.attrs.intensity() == Intensity::Bold {
+                attributes.push_str(" font-weight=\"bold\"");
+            }
+            if run.attrs.italic() {
+                attributes.push_str(" font-style=\"italic\"");
+            }
+            if let Some(decoration) = text_decoration(run.attrs) {
+                write!(attributes, " text-decoration=\"{}\"", decoration).ok();
+            }
+
+            let mut text = format!(
+                "<text {} xml:space=\"preserve\">{}</text>",
+                attributes,
+                escape_xml(&run.text)
+            );
+            if let Some(uri) = run.attrs.hyperlink().and_then(|link| safe_link_uri(link)) {
+                text = format!("<a href=\"{}\">{}</a>", escape_xml(uri), text);
+            }
+            svg.push_str(&text);
+            svg.push('\n');
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn color_spec(color: ColorAttribute) -> ColorSpec {
+    match color {
+        ColorAttribute::Default => ColorSpec::Default,
+        ColorAttribute::PaletteIndex(idx) => ColorSpec::PaletteIndex(idx),
+        ColorAttribute::TrueColorWithPaletteFallback(color, _)
+        | ColorAttribute::TrueColorWithDefaultFallback(color) => ColorSpec::TrueColor(color),
+    }
+}
+
+/// Returns the SGR sequence that sets all of `attrs`, starting
+/// from the default attributes
+fn sgr_for_attrs(attrs: &CellAttributes) -> String {
+    let mut sgr = vec![];
+    if attrs.intensity() != Intensity::Normal {
+        sgr.push(Sgr::Intensity(attrs.intensity()));
+    }
+    if attrs.underline() != Underline::None {
+        sgr.push(Sgr::Underline(attrs.underline()));
+    }
+    if attrs.underline_color() != ColorAttribute::Default {
+        sgr.push(Sgr::UnderlineColor(color_spec(attrs.underline_color())));
+    }
+    if attrs.blink() != termwiz::cell::Blink::None {
+        sgr.push(Sgr::Blink(attrs.blink()));
+    }
+    if attrs.italic() {
+        sgr.push(Sgr::Italic(true));
+    }
+    if attrs.reverse() {
+        sgr.push(Sgr::Inverse(true));
+    }
+    if attrs.invisible() {
+        sgr.push(Sgr::Invisible(true));
+    }
+    if attrs.strikethrough() {
+        sgr.push(Sgr::StrikeThrough(true));
+    }
+    if attrs.overline() {
+        sgr.push(Sgr::Overline(true));
+    }
+    if attrs.foreground() != ColorAttribute::Default {
+        sgr.push(Sgr::Foreground(color_spec(attrs.foreground())));
+    }
+    if attrs.background() != ColorAttribute::Default {
+        sgr.push(Sgr::Background(color_spec(attrs.background())));
+    }
+
+    let mut result = String::new();
+    for sgr in sgr {
+        write!(result, "{}", CSI::Sgr(sgr)).ok();
+    }
+    result
+}
+
+fn export_ansi(lines: &[Line]) -> String {
+    let mut ansi = String::new();
+
+    for line in lines {
+        let mut current_sgr = String::new();
+        let mut current_link: Option<&Arc<Hyperlink>> = None;
+
+        for run in line_runs(line) {
+            if run.attrs.hyperlink() != current_link {
+                current_link = run.attrs.hyperlink();
+                write!(
+                    ansi,
+                    "{}",
+                    OperatingSystemCommand::SetHyperlink(current_link.map(|l| (**l).clone()))
+                )
+                .ok();
+            }
+
+            let sgr = sgr_for_attrs(run.attrs);
+            if sgr != current_sgr {
+                write!(ansi, "{}{}", CSI::Sgr(Sgr::Reset), sgr).ok();
+                current_sgr = sgr;
+            }
+
+            ansi.push_str(&run.text);
+        }
+
+        if current_link.is_some() {
+            write!(ansi, "{}", OperatingSystemCommand::SetHyperlink(None)).ok();
+        }
+        if !current_sgr.is_empty() {
+            write!(ansi, "{}", CSI::Sgr(Sgr::Reset)).ok();
+        }
+        ansi.push('\n');
+    }
+    ansi
+}
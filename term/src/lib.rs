@@ -127,6 +127,7 @@ pub struct SemanticZone {
 }
 
 pub mod color;
+pub mod export;
 
 #[cfg(test)]
 mod test;
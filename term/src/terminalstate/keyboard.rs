@@ -1,10 +1,23 @@
 use crate::input::*;
 use crate::TerminalState;
-use termwiz::input::{KeyCodeEncodeModes, KeyboardEncoding};
+use termwiz::escape::csi::{Keyboard, KittyKeyboardMode};
+use termwiz::escape::CSI;
+use termwiz::input::{KeyCodeEncodeModes, KeyEventKind, KeyboardEncoding, KittyKeyboardFlags};
+
+/// The kitty keyboard protocol recommends bounding the size of
+/// the flags stack to avoid denial of service; when it is full,
+/// the oldest entries are evicted.
+const MAX_KITTY_KEYBOARD_STACK: usize = 128;
 
 impl TerminalState {
     fn effective_keyboard_encoding(&self) -> KeyboardEncoding {
         match self.keyboard_encoding {
+            KeyboardEncoding::Xterm
+                if self.config.enable_kitty_keyboard()
+                    && !self.screen.kitty_keyboard_flags().is_empty() =>
+            {
+                KeyboardEncoding::Kitty(self.screen.kitty_keyboard_flags())
+            }
             KeyboardEncoding::Xterm if self.config.enable_csi_u_key_encoding() => {
                 KeyboardEncoding::CsiU
             }
@@ -12,6 +25,79 @@ impl TerminalState {
         }
     }
 
+    pub(crate) fn perform_csi_keyboard(&mut self, keyboard: Keyboard) {
+        if !self.config.enable_kitty_keyboard() {
+            // Don't respond to the query so that the application
+            // falls back to the legacy encoding
+            log::trace!("ignoring {:?} as kitty keyboard is disabled", keyboard);
+            return;
+        }
+        match keyboard {
+            Keyboard::QueryKittySupport => {
+                let flags = self.screen.kitty_keyboard_flags();
+                write!(
+                    self.writer,
+                    "{}",
+                    CSI::Keyboard(Keyboard::ReportKittyState(flags))
+                )
+                .ok();
+                self.writer.flush().ok();
+            }
+            Keyboard::PushKittyState(flags) => {
+                let stack = self.screen.kitty_keyboard_stack();
+                if stack.len() >= MAX_KITTY_KEYBOARD_STACK {
+                    stack.remove(0);
+                }
+                stack.push(flags);
+            }
+            Keyboard::PopKittyState(n) => {
+                let stack = self.screen.kitty_keyboard_stack();
+                let new_len = stack.len().saturating_sub(n as usize);
+                stack.truncate(new_len);
+            }
+            Keyboard::SetKittyState { flags, mode } => {
+                let current = self.screen.kitty_keyboard_flags();
+                let flags = match mode {
+                    KittyKeyboardMode::AssignAll => flags,
+                    KittyKeyboardMode::SetSpecified => current | flags,
+                    KittyKeyboardMode::ClearSpecified => current - flags,
+                };
+                let stack = self.screen.kitty_keyboard_stack();
+                match stack.last_mut() {
+                    Some(top) => *top = flags,
+                    None => stack.push(flags),
+                }
+            }
+            Keyboard::ReportKittyState(_) => {
+                log::warn!("unhandled {:?}", keyboard);
+            }
+        }
+    }
+
+    /// Determines whether a key event is a press, a repeat of a key
+    /// that is already held down, or a release, keeping track of which
+    /// keys are held.  Returns None for the release of a key whose press
+    /// we never saw (eg: it was consumed by a key assignment).
+    fn classify_key_event(&mut self, key: KeyCode, is_down: bool) -> Option<KeyEventKind> {
+        let key = match key {
+            KeyCode::Char(c) if c.is_ascii_uppercase() => KeyCode::Char(c.to_ascii_lowercase()),
+            key => key,
+        };
+        let idx = self.keys_down.iter().position(|&k| k == key);
+        match (is_down, idx) {
+            (true, Some(_)) => Some(KeyEventKind::Repeat),
+            (true, None) => {
+                self.keys_down.push(key);
+                Some(KeyEventKind::Press)
+            }
+            (false, Some(idx)) => {
+                self.keys_down.remove(idx);
+                Some(KeyEventKind::Release)
+            }
+            (false, None) => None,
+        }
+    }
+
     /// Processes a key event generated by the gui/render layer
     /// that is embedding the Terminal.  This method translates the
     /// keycode into a sequence of bytes to send to the slave end
@@ -23,16 +109,21 @@ impl TerminalState {
         is_down: bool,
     ) -> anyhow::Result<()> {
         let encoding = self.effective_keyboard_encoding();
+        let kind = self.classify_key_event(key, is_down);
+
+        let modes = KeyCodeEncodeModes {
+            encoding,
+            newline_mode: self.newline_mode,
+            application_cursor_keys: self.application_cursor_keys,
+        };
 
-        let to_send = key.encode_up_down(
-            mods,
-            KeyCodeEncodeModes {
-                encoding,
-                newline_mode: self.newline_mode,
-                application_cursor_keys: self.application_cursor_keys,
-            },
-            is_down,
-        )?;
+        let to_send = match (encoding, kind) {
+            (KeyboardEncoding::Kitty(flags), Some(kind)) => {
+                key.encode_kitty(mods, modes, flags, kind)?
+            }
+            (KeyboardEncoding::Kitty(_), None) => String::new(),
+            _ => key.encode_up_down(mods, modes, is_down)?,
+        };
 
         if to_send.is_empty() {
             return Ok(());
@@ -57,4 +148,10 @@ impl TerminalState {
     pub fn key_down(&mut self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
         self.key_up_down(key, mods, true)
     }
+
+    /// Returns the kitty keyboard protocol flags that are in effect
+    /// for the active screen.
+    pub fn get_kitty_keyboard_flags(&self) -> KittyKeyboardFlags {
+        self.screen.kitty_keyboard_flags()
+    }
 }
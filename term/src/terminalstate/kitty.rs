@@ -286,6 +286,17 @@ impl TerminalState {
                     log::error!("Error {:#} while handling KittyImage::ComposeFrame", err);
                 }
             }
+            KittyImage::ControlAnimation {
+                animation,
+                verbosity,
+            } => {
+                // Animations are played using the durations of their frames
+                log::debug!(
+                    "unhandled KittyImage::ControlAnimation {:?} {:?}",
+                    animation,
+                    verbosity
+                );
+            }
         };
 
         Ok(())
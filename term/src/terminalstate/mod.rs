@@ -10,15 +10,16 @@ use std::collections::HashMap;
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
 use terminfo::{Database, Value};
-use termwiz::cell::UnicodeVersion;
+use termwiz::cell::{Blink, Intensity, Underline, UnicodeVersion};
+use termwiz::color::{ColorAttribute, ColorSpec};
 use termwiz::escape::csi::{
-    Cursor, CursorStyle, DecPrivateMode, DecPrivateModeCode, Device, Edit, EraseInDisplay,
-    EraseInLine, Mode, Sgr, TabulationClear, TerminalMode, TerminalModeCode, Window, XtSmGraphics,
-    XtSmGraphicsAction, XtSmGraphicsItem, XtSmGraphicsStatus,
+    AttributeChangeExtent, Cursor, CursorStyle, DecPrivateMode, DecPrivateModeCode, Device, Edit,
+    EraseInDisplay, EraseInLine, Mode, Sgr, TabulationClear, TerminalMode, TerminalModeCode,
+    Window, XtSmGraphics, XtSmGraphicsAction, XtSmGraphicsItem, XtSmGraphicsStatus,
 };
 use termwiz::escape::{OneBased, OperatingSystemCommand, CSI};
 use termwiz::image::ImageData;
-use termwiz::input::KeyboardEncoding;
+use termwiz::input::{KeyboardEncoding, KittyKeyboardFlags};
 use termwiz::surface::{CursorShape, CursorVisibility, SequenceNo};
 use url::Url;
 use wezterm_bidi::ParagraphDirectionHint;
@@ -29,6 +30,7 @@ mod keyboard;
 mod kitty;
 mod mouse;
 pub(crate) mod performer;
+mod rectangle;
 mod sixel;
 use crate::terminalstate::image::*;
 use crate::terminalstate::kitty::*;
@@ -123,15 +125,26 @@ impl TabStop {
     }
 }
 
+/// The mode states reported by DECRPM
+/// <https://vt100.net/docs/vt510-rm/DECRPM.html>
+#[derive(Debug, Clone, Copy)]
+enum DecRqmStatus {
+    NotRecognized = 0,
+    Set = 1,
+    Reset = 2,
+    PermanentlySet = 3,
+    PermanentlyReset = 4,
+}
+
 #[derive(Debug, Clone)]
 struct SavedCursor {
     position: CursorPosition,
     wrap_next: bool,
+    /// Also carries the DECSCA selective erase protection state
     pen: CellAttributes,
     dec_origin_mode: bool,
     g0_charset: CharSet,
     g1_charset: CharSet,
-    // TODO: selective_erase when supported
 }
 
 struct ScreenOrAlt {
@@ -143,6 +156,10 @@ struct ScreenOrAlt {
     alt_screen_is_active: bool,
     saved_cursor: Option<SavedCursor>,
     alt_saved_cursor: Option<SavedCursor>,
+    /// The kitty keyboard protocol maintains separate
+    /// stacks of flags for the primary and alternate screens
+    kitty_keyboard_stack: Vec<KittyKeyboardFlags>,
+    alt_kitty_keyboard_stack: Vec<KittyKeyboardFlags>,
 }
 
 impl Deref for ScreenOrAlt {
@@ -191,6 +208,8 @@ impl ScreenOrAlt {
             alt_screen_is_active: false,
             saved_cursor: None,
             alt_saved_cursor: None,
+            kitty_keyboard_stack: vec![],
+            alt_kitty_keyboard_stack: vec![],
         }
     }
 
@@ -246,6 +265,28 @@ impl ScreenOrAlt {
             &mut self.saved_cursor
         }
     }
+
+    pub fn kitty_keyboard_stack(&mut self) -> &mut Vec<KittyKeyboardFlags> {
+        if self.alt_screen_is_active {
+            &mut self.alt_kitty_keyboard_stack
+        } else {
+            &mut self.kitty_keyboard_stack
+        }
+    }
+
+    pub fn kitty_keyboard_flags(&self) -> KittyKeyboardFlags {
+        let stack = if self.alt_screen_is_active {
+            &self.alt_kitty_keyboard_stack
+        } else {
+            &self.kitty_keyboard_stack
+        };
+        stack.last().copied().unwrap_or(KittyKeyboardFlags::NONE)
+    }
+
+    pub fn clear_kitty_keyboard_stacks(&mut self) {
+        self.kitty_keyboard_stack.clear();
+        self.alt_kitty_keyboard_stack.clear();
+    }
 }
 
 /// Manages the state for the terminal
@@ -289,6 +330,9 @@ pub struct TerminalState {
     left_and_right_margins: Range<usize>,
     left_and_right_margin_mode: bool,
 
+    /// DECSACE; controls how DECCARA and DECRARA apply
+    attribute_change_extent: AttributeChangeExtent,
+
     /// When set, modifies the sequence of bytes sent for keys
     /// designated as cursor keys.  This includes various navigation
     /// keys.  The code in key_down() is responsible for interpreting this.
@@ -324,8 +368,15 @@ pub struct TerminalState {
     current_mouse_buttons: Vec<MouseButton>,
     last_mouse_move: Option<MouseEvent>,
     cursor_visible: bool,
+    /// Synchronized output (DECSET 2026 or BSU/ESU) is active.
+    /// The output itself is held back by wezterm's mux; we track
+    /// it here so that DECRQM can report the correct state.
+    synchronized_output: bool,
 
     keyboard_encoding: KeyboardEncoding,
+    /// The keys that are currently held down, used to distinguish
+    /// key repeat events for the kitty keyboard protocol
+    keys_down: Vec<KeyCode>,
     /// Support for US, UK, and DEC Special Graphics
     g0_charset: CharSet,
     g1_charset: CharSet,
@@ -507,6 +558,7 @@ impl TerminalState {
             top_and_bottom_margins: 0..size.physical_rows as VisibleRowIndex,
             left_and_right_margins: 0..size.physical_cols,
             left_and_right_margin_mode: false,
+            attribute_change_extent: AttributeChangeExtent::Stream,
             wrap_next: false,
             clear_semantic_attribute_on_newline: false,
             // We default auto wrap to true even though the default for
@@ -526,6 +578,8 @@ impl TerminalState {
             focus_tracking: false,
             mouse_encoding: MouseEncoding::X10,
             keyboard_encoding: KeyboardEncoding::Xterm,
+            keys_down: vec![],
+            synchronized_output: false,
             sixel_scrolls_right: false,
             any_event_mouse: false,
             button_event_mouse: false,
@@ -717,6 +771,11 @@ impl TerminalState {
         self.bracketed_paste
     }
 
+    /// Returns true if the application has enabled synchronized output
+    pub fn is_synchronized_output_active(&self) -> bool {
+        self.synchronized_output
+    }
+
     /// Advise the terminal about a change in its focus state
     pub fn focus_changed(&mut self, focused: bool) {
         if !focused {
@@ -742,6 +801,9 @@ impl TerminalState {
         self.focused = focused;
         if !focused {
             self.lost_focus_seqno = self.seqno;
+            // We won't see the key up events for keys that are
+            // released while we don't have focus
+            self.keys_down.clear();
         }
     }
 
@@ -1169,28 +1231,44 @@ impl TerminalState {
         match dev {
             Device::DeviceAttributes(a) => log::warn!("unhandled: {:?}", a),
             Device::SoftReset => {
-                // TODO: see https://vt100.net/docs/vt510-rm/DECSTR.html
-                self.pen = CellAttributes::default();
+                // https://vt100.net/docs/vt510-rm/DECSTR.html
+                // DECTCEM: the cursor is visible
+                self.cursor_visible = true;
+                // IRM: replace mode
                 self.insert = false;
+                // DECOM: absolute positioning
                 self.dec_origin_mode = false;
-                // Note that xterm deviates from the documented DECSTR
+                // DECAWM: Note that xterm deviates from the documented DECSTR
                 // setting for dec_auto_wrap, so we do too
                 self.dec_auto_wrap = true;
+                // DECNKM and DECCKM: numeric keypad and normal cursor keys
                 self.application_cursor_keys = false;
                 self.application_keypad = false;
+                // DECSTBM and DECSLRM: the margins are the whole screen
                 self.top_and_bottom_margins = 0..self.screen().physical_rows as i64;
                 self.left_and_right_margins = 0..self.screen().physical_cols;
                 self.left_and_right_margin_mode = false;
+                // G0-G1 and GL: the default character sets
+                self.g0_charset = CharSet::Ascii;
+                self.g1_charset = CharSet::DecLineDrawing;
+                self.shift_out = false;
+                // SGR and DECSCA: normal rendition, unprotected
+                self.pen = CellAttributes::default();
+                self.attribute_change_extent = AttributeChangeExtent::Stream;
+                // DECSC: the saved cursor state is reset to home
                 self.screen.activate_alt_screen(self.seqno);
                 self.screen.saved_cursor().take();
                 self.screen.activate_primary_screen(self.seqno);
                 self.screen.saved_cursor().take();
                 self.kitty_remove_all_placements(true);
+                // The kitty keyboard flags revert to the legacy encoding
+                self.screen.clear_kitty_keyboard_stacks();
 
                 self.reverse_wraparound_mode = false;
                 self.reverse_video_mode = false;
                 self.bidi_enabled.take();
                 self.bidi_hint.take();
+                self.synchronized_output = false;
             }
             Device::RequestPrimaryDeviceAttributes => {
                 let mut ident = "\x1b[?65".to_string(); // Vt500
@@ -1198,6 +1276,7 @@ impl TerminalState {
                 ident.push_str(";6"); // Selective erase
                 ident.push_str(";18"); // windowing extensions
                 ident.push_str(";22"); // ANSI color, vt525
+                ident.push_str(";28"); // rectangular editing
                 ident.push('c');
 
                 self.writer.write(ident.as_bytes()).ok();
@@ -1275,16 +1354,39 @@ impl TerminalState {
         }
     }
 
-    fn decqrm_response(&mut self, mode: Mode, mut recognized: bool, enabled: bool) {
+    fn decqrm_response(&mut self, mode: Mode, recognized: bool, enabled: bool) {
+        let status = if recognized {
+            if enabled {
+                DecRqmStatus::Set
+            } else {
+                DecRqmStatus::Reset
+            }
+        } else {
+            DecRqmStatus::NotRecognized
+        };
+        self.decqrm_status_response(mode, status);
+    }
+
+    /// Responds to a DECRQM for a mode that can't be changed
+    fn decqrm_permanent_response(&mut self, mode: Mode, enabled: bool) {
+        let status = if enabled {
+            DecRqmStatus::PermanentlySet
+        } else {
+            DecRqmStatus::PermanentlyReset
+        };
+        self.decqrm_status_response(mode, status);
+    }
+
+    fn decqrm_status_response(&mut self, mode: Mode, mut status: DecRqmStatus) {
         let (is_dec, number) = match &mode {
             Mode::QueryDecPrivateMode(DecPrivateMode::Code(code)) => (true, code.to_u16().unwrap()),
             Mode::QueryDecPrivateMode(DecPrivateMode::Unspecified(code)) => {
-                recognized = false;
+                status = DecRqmStatus::NotRecognized;
                 (true, *code)
             }
             Mode::QueryMode(TerminalMode::Code(code)) => (false, code.to_u16().unwrap()),
             Mode::QueryMode(TerminalMode::Unspecified(code)) => {
-                recognized = false;
+                status = DecRqmStatus::NotRecognized;
                 (false, *code)
             }
             _ => unreachable!(),
@@ -1292,18 +1394,9 @@ impl TerminalState {
 
         let prefix = if is_dec { "?" } else { "" };
 
-        let status = if recognized {
-            if enabled {
-                1 // set
-            } else {
-                2 // reset
-            }
-        } else {
-            0
-        };
-
-        log::trace!("{:?} -> recognized={} status={}", mode, recognized, status);
-        write!(self.writer, "\x1b[{}{};{}$y", prefix, number, status).ok();
+        log::trace!("{:?} -> status={:?}", mode, status);
+        write!(self.writer, "\x1b[{}{};{}$y", prefix, number, status as u8).ok();
+        self.writer.flush().ok();
     }
 
     fn perform_csi_mode(&mut self, mode: Mode) {
@@ -1324,6 +1417,9 @@ impl TerminalState {
             | Mode::ResetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::AutoRepeat)) => {
                 // We leave key repeat to the GUI layer prefs
             }
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::AutoRepeat)) => {
+                self.decqrm_permanent_response(mode, true);
+            }
 
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::Win32InputMode)) => {
                 self.keyboard_encoding = KeyboardEncoding::Win32;
@@ -1386,6 +1482,11 @@ impl TerminalState {
                 self.dec_restore_cursor();
             }
 
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::SaveCursor)) => {
+                // This is an action rather than a state
+                self.decqrm_response(mode, true, false);
+            }
+
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::AutoWrap)) => {
                 self.dec_auto_wrap = true;
             }
@@ -1435,25 +1536,27 @@ impl TerminalState {
             Mode::SetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::SynchronizedOutput,
             )) => {
-                // This is handled in wezterm's mux
+                // The output is held in wezterm's mux
+                self.synchronized_output = true;
             }
             Mode::ResetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::SynchronizedOutput,
             )) => {
-                // This is handled in wezterm's mux
+                self.synchronized_output = false;
             }
             Mode::QueryDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::SynchronizedOutput,
             )) => {
-                // This is handled in wezterm's mux; if we get here, then it isn't enabled,
-                // so we always report false
-                self.decqrm_response(mode, true, false);
+                self.decqrm_response(mode, true, self.synchronized_output);
             }
 
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::SmoothScroll))
             | Mode::ResetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::SmoothScroll)) => {
                 // We always output at our "best" rate
             }
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::SmoothScroll)) => {
+                self.decqrm_permanent_response(mode, false);
+            }
 
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::ReverseVideo)) => {
                 // Turn on reverse video for all of the lines on the
@@ -1467,6 +1570,10 @@ impl TerminalState {
                 self.reverse_video_mode = false;
             }
 
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::ReverseVideo)) => {
+                self.decqrm_response(mode, true, self.reverse_video_mode);
+            }
+
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::Select132Columns))
             | Mode::ResetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::Select132Columns,
@@ -1562,6 +1669,19 @@ impl TerminalState {
                 }
             }
 
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::OptEnableAlternateScreen,
+            ))
+            | Mode::QueryDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::EnableAlternateScreen,
+            ))
+            | Mode::QueryDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::ClearAndEnableAlternateScreen,
+            )) => {
+                let alt = self.screen.is_alt_screen_active();
+                self.decqrm_response(mode, true, alt);
+            }
+
             Mode::SetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::ApplicationCursorKeys,
             )) => {
@@ -1617,6 +1737,18 @@ impl TerminalState {
             Mode::ResetMode(TerminalMode::Code(TerminalModeCode::ShowCursor)) => {
                 self.cursor_visible = false;
             }
+            Mode::QueryMode(TerminalMode::Code(TerminalModeCode::ShowCursor)) => {
+                self.decqrm_response(mode, true, self.cursor_visible);
+            }
+
+            Mode::QueryMode(TerminalMode::Code(TerminalModeCode::KeyboardAction)) => {
+                // The keyboard is never locked
+                self.decqrm_permanent_response(mode, false);
+            }
+            Mode::QueryMode(TerminalMode::Code(TerminalModeCode::SendReceive)) => {
+                // We never locally echo the input
+                self.decqrm_permanent_response(mode, true);
+            }
 
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::MouseTracking)) => {
                 self.mouse_tracking = true;
@@ -1636,6 +1768,11 @@ impl TerminalState {
             | Mode::ResetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::HighlightMouseTracking,
             )) => {}
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::HighlightMouseTracking,
+            )) => {
+                self.decqrm_permanent_response(mode, false);
+            }
 
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::ButtonEventMouse)) => {
                 self.button_event_mouse = true;
@@ -2100,6 +2237,32 @@ impl TerminalState {
                 self.cursor.x = x;
                 self.cursor.y = y;
             }
+            Edit::SelectiveEraseInDisplay(erase) => self.selective_erase_in_display(erase),
+            Edit::SelectiveEraseInLine(erase) => self.selective_erase_in_line(erase),
+            Edit::SelectCharacterProtection(protect) => {
+                self.pen.set_protected(protect);
+            }
+            Edit::CopyRectangularArea {
+                src,
+                src_page: _,
+                dest_top,
+                dest_left,
+                dest_page: _,
+            } => self.copy_rectangular_area(src, dest_top, dest_left),
+            Edit::FillRectangularArea { ch, area } => self.fill_rectangular_area(ch, area),
+            Edit::EraseRectangularArea(area) => self.erase_rectangular_area(area),
+            Edit::SelectiveEraseRectangularArea(area) => {
+                self.selective_erase_rectangular_area(area)
+            }
+            Edit::ChangeAttributesInRectangularArea { area, attributes } => {
+                self.change_attributes_in_rectangular_area(area, &attributes, false)
+            }
+            Edit::ReverseAttributesInRectangularArea { area, attributes } => {
+                self.change_attributes_in_rectangular_area(area, &attributes, true)
+            }
+            Edit::SelectAttributeChangeExtent(extent) => {
+                self.attribute_change_extent = extent;
+            }
         }
     }
 
@@ -2434,9 +2597,12 @@ impl TerminalState {
             Sgr::Reset => {
                 let link = self.pen.hyperlink().map(Arc::clone);
                 let semantic_type = self.pen.semantic_type();
+                // DECSCA protection is not an SGR attribute
+                let protected = self.pen.protected();
                 self.pen = CellAttributes::default();
                 self.pen.set_hyperlink(link);
                 self.pen.set_semantic_type(semantic_type);
+                self.pen.set_protected(protected);
             }
             Sgr::Intensity(intensity) => {
                 self.pen.set_intensity(intensity);
@@ -2475,6 +2641,76 @@ impl TerminalState {
         }
     }
 
+    /// Returns the SGR parameters that describe the current pen,
+    /// as reported by DECRQSS
+    fn pen_sgr_report(&self) -> String {
+        fn color_spec(color: ColorAttribute) -> ColorSpec {
+            match color {
+                ColorAttribute::TrueColorWithPaletteFallback(color, _)
+                | ColorAttribute::TrueColorWithDefaultFallback(color) => {
+                    ColorSpec::TrueColor(color)
+                }
+                ColorAttribute::PaletteIndex(idx) => ColorSpec::PaletteIndex(idx),
+                ColorAttribute::Default => ColorSpec::Default,
+            }
+        }
+
+        let pen = &self.pen;
+        let mut sgrs = vec![Sgr::Reset];
+        if pen.intensity() != Intensity::Normal {
+            sgrs.push(Sgr::Intensity(pen.intensity()));
+        }
+        if pen.italic() {
+            sgrs.push(Sgr::Italic(true));
+        }
+        if pen.underline() != Underline::None {
+            sgrs.push(Sgr::Underline(pen.underline()));
+        }
+        if pen.blink() != Blink::None {
+            sgrs.push(Sgr::Blink(pen.blink()));
+        }
+        if pen.reverse() {
+            sgrs.push(Sgr::Inverse(true));
+        }
+        if pen.invisible() {
+            sgrs.push(Sgr::Invisible(true));
+        }
+        if pen.strikethrough() {
+            sgrs.push(Sgr::StrikeThrough(true));
+        }
+        if pen.overline() {
+            sgrs.push(Sgr::Overline(true));
+        }
+        if pen.foreground() != ColorAttribute::Default {
+            sgrs.push(Sgr::Foreground(color_spec(pen.foreground())));
+        }
+        if pen.background() != ColorAttribute::Default {
+            sgrs.push(Sgr::Background(color_spec(pen.background())));
+        }
+        if pen.underline_color() != ColorAttribute::Default {
+            sgrs.push(Sgr::UnderlineColor(color_spec(pen.underline_color())));
+        }
+
+        sgrs.iter()
+            .map(|sgr| sgr.to_string().trim_end_matches('m').to_string())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    /// Returns the DECSCUSR parameter for the current cursor shape
+    fn cursor_style_report(&self) -> u8 {
+        let style = match self.cursor.shape {
+            CursorShape::Default => CursorStyle::Default,
+            CursorShape::BlinkingBlock => CursorStyle::BlinkingBlock,
+            CursorShape::SteadyBlock => CursorStyle::SteadyBlock,
+            CursorShape::BlinkingUnderline => CursorStyle::BlinkingUnderline,
+            CursorShape::SteadyUnderline => CursorStyle::SteadyUnderline,
+            CursorShape::BlinkingBar => CursorStyle::BlinkingBar,
+            CursorShape::SteadyBar => CursorStyle::SteadyBar,
+        };
+        style as u8
+    }
+
     /// Computes the set of `SemanticZone`s for the current terminal screen.
     /// Semantic zones are contiguous runs of cells that have the same
     /// `SemanticType` (Prompt, Input, Output).
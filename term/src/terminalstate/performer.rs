@@ -8,7 +8,7 @@ use num_traits::FromPrimitive;
 use std::fmt::Write;
 use std::ops::{Deref, DerefMut};
 use termwiz::cell::{grapheme_column_width, Cell, CellAttributes, SemanticType, UnicodeVersion};
-use termwiz::escape::csi::{CharacterPath, EraseInDisplay};
+use termwiz::escape::csi::{AttributeChangeExtent, CharacterPath, EraseInDisplay};
 use termwiz::escape::osc::{
     ChangeColorPair, ColorOrQuery, FinalTermSemanticPrompt, ITermProprietary,
     ITermUnicodeVersionOp, Selection,
@@ -258,6 +258,33 @@ impl<'a> Performer<'a> {
                                 .ok();
                                 self.writer.flush().ok();
                             }
+                            &[b'm'] => {
+                                // SGR - the current graphic rendition
+                                let sgr = self.pen_sgr_report();
+                                write!(self.writer, "{}1$r{}m{}", DCS, sgr, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b' ', b'q'] => {
+                                // DECSCUSR - cursor style
+                                let style = self.cursor_style_report();
+                                write!(self.writer, "{}1$r{} q{}", DCS, style, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b'"', b'q'] => {
+                                // DECSCA - character protection attribute
+                                let protected = if self.pen.protected() { 1 } else { 0 };
+                                write!(self.writer, "{}1$r{}\"q{}", DCS, protected, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b'*', b'x'] => {
+                                // DECSACE - attribute change extent
+                                let extent = match self.attribute_change_extent {
+                                    AttributeChangeExtent::Stream => 1,
+                                    AttributeChangeExtent::Rectangle => 2,
+                                };
+                                write!(self.writer, "{}1$r{}*x{}", DCS, extent, ST).ok();
+                                self.writer.flush().ok();
+                            }
                             _ => {
                                 log::warn!("unhandled DECRQSS {:?}", s);
                                 // Reply that the request is invalid
@@ -266,6 +293,16 @@ impl<'a> Performer<'a> {
                             }
                         }
                     }
+                    (b's', &[b'=']) => {
+                        // BSU/ESU - Begin/End Synchronized Update.
+                        // The output is held in wezterm's mux; this is
+                        // equivalent to DECSET/DECRST 2026
+                        match s.params.as_slice() {
+                            &[1] => self.synchronized_output = true,
+                            &[2] => self.synchronized_output = false,
+                            _ => log::warn!("unhandled {:?}", s),
+                        }
+                    }
                     _ => log::warn!("unhandled {:?}", s),
                 }
             }
@@ -413,6 +450,7 @@ impl<'a> Performer<'a> {
             CSI::Device(dev) => self.state.perform_device(*dev),
             CSI::Mouse(mouse) => error!("mouse report sent by app? {:?}", mouse),
             CSI::Window(window) => self.state.perform_csi_window(window),
+            CSI::Keyboard(keyboard) => self.state.perform_csi_keyboard(keyboard),
             CSI::SelectCharacterPath(CharacterPath::ImplementationDefault, _) => {
                 self.state.bidi_hint.take();
             }
@@ -544,6 +582,8 @@ impl<'a> Performer<'a> {
                 self.focus_tracking = false;
                 self.mouse_encoding = MouseEncoding::X10;
                 self.keyboard_encoding = KeyboardEncoding::Xterm;
+                self.screen.clear_kitty_keyboard_stacks();
+                self.synchronized_output = false;
                 self.sixel_scrolls_right = false;
                 self.any_event_mouse = false;
                 self.button_event_mouse = false;
@@ -0,0 +1,351 @@
+//! The DEC rectangular area operations and selective erase
+use crate::terminalstate::TerminalState;
+use crate::VisibleRowIndex;
+use std::ops::Range;
+use termwiz::cell::{Blink, Cell, CellAttributes, Intensity, Underline};
+use termwiz::escape::csi::{
+    AttributeChangeExtent, EraseInDisplay, EraseInLine, RectangularArea, Sgr,
+};
+use termwiz::escape::OneBased;
+
+impl TerminalState {
+    /// Returns the row and column ranges of the screen that are covered
+    /// by `area`.  The coordinates are relative to the scroll margins
+    /// when origin mode is enabled, and are clipped to the screen (or
+    /// margins).  Returns None if the area is empty.
+    fn resolve_rectangular_area(
+        &mut self,
+        area: &RectangularArea,
+    ) -> Option<(Range<VisibleRowIndex>, Range<usize>)> {
+        let (rows, cols) = self.rectangular_area_coords(area);
+        if rows.is_empty() || cols.is_empty() {
+            None
+        } else {
+            Some((rows, cols))
+        }
+    }
+
+    /// Like resolve_rectangular_area, but doesn't check whether the
+    /// area is empty.  The top is clamped to the bottom and the left
+    /// to the right edge of the bounds; the left may still be to the
+    /// right of `right`, which is meaningful for DECSACE stream mode.
+    fn rectangular_area_coords(
+        &mut self,
+        area: &RectangularArea,
+    ) -> (Range<VisibleRowIndex>, Range<usize>) {
+        let (rows, cols) = self.rectangular_area_bounds();
+
+        let top = rows.start + area.top.as_zero_based() as VisibleRowIndex;
+        let bottom = area
+            .bottom
+            .map(|b| rows.start + b.as_one_based() as VisibleRowIndex)
+            .unwrap_or(rows.end)
+            .min(rows.end);
+        let left = cols
+            .start
+            .saturating_add(area.left.as_zero_based() as usize);
+        let right = area
+            .right
+            .map(|r| cols.start.saturating_add(r.as_one_based() as usize))
+            .unwrap_or(cols.end)
+            .min(cols.end);
+
+        (top.min(bottom)..bottom, left.min(cols.end)..right)
+    }
+
+    /// The region that rectangular area coordinates are relative to
+    fn rectangular_area_bounds(&mut self) -> (Range<VisibleRowIndex>, Range<usize>) {
+        if self.dec_origin_mode {
+            (
+                self.top_and_bottom_margins.clone(),
+                self.left_and_right_margins.clone(),
+            )
+        } else {
+            let screen = self.screen();
+            (
+                0..screen.physical_rows as VisibleRowIndex,
+                0..screen.physical_cols,
+            )
+        }
+    }
+
+    /// Replaces the text of the unprotected cells in the specified
+    /// region with spaces, preserving their attributes
+    fn selective_erase(&mut self, rows: Range<VisibleRowIndex>, cols: Range<usize>) {
+        let seqno = self.seqno;
+        let screen = self.screen_mut();
+        for y in rows {
+            let line_idx = screen.phys_row(y);
+            let line = screen.line_mut(line_idx);
+            for x in cols.clone() {
+                let attrs = match line.cells().get(x) {
+                    Some(cell) if !cell.attrs().protected() => cell.attrs().clone(),
+                    _ => continue,
+                };
+                line.set_cell(x, Cell::new(' ', attrs), seqno);
+            }
+        }
+    }
+
+    /// DECSED
+    pub(crate) fn selective_erase_in_display(&mut self, erase: EraseInDisplay) {
+        let cy = self.cursor.y;
+        let rows = self.screen().physical_rows as VisibleRowIndex;
+        let cols = self.screen().physical_cols;
+        match erase {
+            EraseInDisplay::EraseToEndOfDisplay => {
+                self.selective_erase_in_line(EraseInLine::EraseToEndOfLine);
+                self.selective_erase(cy + 1..rows, 0..cols);
+            }
+            EraseInDisplay::EraseToStartOfDisplay => {
+                self.selective_erase_in_line(EraseInLine::EraseToStartOfLine);
+                self.selective_erase(0..cy, 0..cols);
+            }
+            EraseInDisplay::EraseDisplay => self.selective_erase(0..rows, 0..cols),
+            EraseInDisplay::EraseScrollback => {
+                self.screen_mut().erase_scrollback();
+            }
+        }
+    }
+
+    /// DECSEL
+    pub(crate) fn selective_erase_in_line(&mut self, erase: EraseInLine) {
+        let cx = self.cursor.x;
+        let cy = self.cursor.y;
+        let cols = self.screen().physical_cols;
+        let range = match erase {
+            EraseInLine::EraseToEndOfLine => cx..cols,
+            EraseInLine::EraseToStartOfLine => 0..(cx + 1).min(cols),
+            EraseInLine::EraseLine => 0..cols,
+        };
+        self.selective_erase(cy..cy + 1, range);
+    }
+
+    /// DECSERA
+    pub(crate) fn selective_erase_rectangular_area(&mut self, area: RectangularArea) {
+        if let Some((rows, cols)) = self.resolve_rectangular_area(&area) {
+            self.selective_erase(rows, cols);
+        }
+    }
+
+    /// DECERA; erases all of the cells in the area, regardless
+    /// of their protection
+    pub(crate) fn erase_rectangular_area(&mut self, area: RectangularArea) {
+        let blank = Cell::blank_with_attrs(self.pen.clone_sgr_only());
+        self.fill_area(area, &blank);
+    }
+
+    /// DECFRA; fills the area with `ch` using the current pen
+    pub(crate) fn fill_rectangular_area(&mut self, ch: char, area: RectangularArea) {
+        if ch.is_control() {
+            return;
+        }
+        let cell = Cell::new(ch, self.pen.clone());
+        if cell.width() != 1 {
+            return;
+        }
+        self.fill_area(area, &cell);
+    }
+
+    fn fill_area(&mut self, area: RectangularArea, cell: &Cell) {
+        let seqno = self.seqno;
+        if let Some((rows, cols)) = self.resolve_rectangular_area(&area) {
+            let screen = self.screen_mut();
+            for y in rows {
+                for x in cols.clone() {
+                    screen.set_cell(x, y, cell, seqno);
+                }
+            }
+        }
+    }
+
+    /// DECCRA; copies the cells in `src` so that its top left corner
+    /// is at `dest_top`, `dest_left`.  The portion of the copy that
+    /// falls outside of the screen (or margins) is clipped.
+    /// We only have a single page, so the page numbers are ignored.
+    pub(crate) fn copy_rectangular_area(
+        &mut self,
+        src: RectangularArea,
+        dest_top: OneBased,
+        dest_left: OneBased,
+    ) {
+        let seqno = self.seqno;
+        let (src_rows, src_cols) = match self.resolve_rectangular_area(&src) {
+            Some(area) => area,
+            None => return,
+        };
+        let (bounds_rows, bounds_cols) = self.rectangular_area_bounds();
+        let dest_top = bounds_rows.start + dest_top.as_zero_based() as VisibleRowIndex;
+        let dest_left = bounds_cols
+            .start
+            .saturating_add(dest_left.as_zero_based() as usize);
+
+        // Take a copy of the source first, as the source and
+        // destination areas may overlap
+        let screen = self.screen_mut();
+        let copied: Vec<Vec<Cell>> = src_rows
+            .map(|y| {
+                src_cols
+                    .clone()
+                    .map(|x| screen.get_cell(x, y).cloned().unwrap_or_else(Cell::blank))
+                    .collect()
+            })
+            .collect();
+
+        for (dy, row) in copied.into_iter().enumerate() {
+            let y = dest_top + dy as VisibleRowIndex;
+            if y >= bounds_rows.end {
+                break;
+            }
+            for (dx, cell) in row.into_iter().enumerate() {
+                let x = dest_left.saturating_add(dx);
+                if x >= bounds_cols.end {
+                    break;
+                }
+                screen.set_cell(x, y, &cell, seqno);
+            }
+        }
+    }
+
+    /// DECCARA and DECRARA.
+    /// The set of attributes that can be changed are those from the
+    /// DEC specification (bold, underline, blink and reverse), and,
+    /// as an xterm extension, the invisible attribute and colors.
+    pub(crate) fn change_attributes_in_rectangular_area(
+        &mut self,
+        area: RectangularArea,
+        attributes: &[Sgr],
+        reverse: bool,
+    ) {
+        let seqno = self.seqno;
+        let stream = self.attribute_change_extent == AttributeChangeExtent::Stream;
+        // In stream mode the start column may be to the right of
+        // the end column when the area spans multiple lines
+        let (rows, cols) = self.rectangular_area_coords(&area);
+        if rows.is_empty() || (cols.is_empty() && !(stream && rows.end - rows.start > 1)) {
+            return;
+        }
+        let (_, bounds_cols) = self.rectangular_area_bounds();
+        let first_row = rows.start;
+        let last_row = rows.end - 1;
+
+        let screen = self.screen_mut();
+        for y in rows {
+            // In stream mode the change runs from the start position
+            // to the end position, wrapping at the margins
+            let cols = if stream {
+                let start = if y == first_row {
+                    cols.start
+                } else {
+                    bounds_cols.start
+                };
+                let end = if y == last_row {
+                    cols.end
+                } else {
+                    bounds_cols.end
+                };
+                start.min(end)..end
+            } else {
+                cols.start.min(cols.end)..cols.end
+            };
+
+            let line_idx = screen.phys_row(y);
+            let line = screen.line_mut(line_idx);
+            if line.cells().len() < cols.end {
+                line.resize(cols.end, seqno);
+            }
+            for cell in &mut line.cells_mut_for_attr_changes_only()[cols] {
+                let attrs = cell.attrs_mut();
+                for sgr in attributes {
+                    if reverse {
+                        reverse_attribute(attrs, sgr);
+                    } else {
+                        change_attribute(attrs, sgr);
+                    }
+                }
+            }
+            line.update_last_change_seqno(seqno);
+        }
+    }
+}
+
+fn change_attribute(attrs: &mut CellAttributes, sgr: &Sgr) {
+    match sgr {
+        Sgr::Reset => {
+            attrs
+                .set_intensity(Intensity::Normal)
+                .set_underline(Underline::None)
+                .set_blink(Blink::None)
+                .set_reverse(false)
+                .set_invisible(false);
+        }
+        Sgr::Intensity(intensity) => {
+            attrs.set_intensity(*intensity);
+        }
+        Sgr::Underline(underline) => {
+            attrs.set_underline(*underline);
+        }
+        Sgr::Blink(blink) => {
+            attrs.set_blink(*blink);
+        }
+        Sgr::Inverse(inverse) => {
+            attrs.set_reverse(*inverse);
+        }
+        Sgr::Invisible(invis) => {
+            attrs.set_invisible(*invis);
+        }
+        Sgr::Foreground(col) => {
+            attrs.set_foreground(*col);
+        }
+        Sgr::Background(col) => {
+            attrs.set_background(*col);
+        }
+        _ => {}
+    }
+}
+
+fn reverse_attribute(attrs: &mut CellAttributes, sgr: &Sgr) {
+    let toggle_intensity = |attrs: &mut CellAttributes| {
+        let intensity = match attrs.intensity() {
+            Intensity::Bold => Intensity::Normal,
+            _ => Intensity::Bold,
+        };
+        attrs.set_intensity(intensity);
+    };
+    let toggle_underline = |attrs: &mut CellAttributes| {
+        let underline = match attrs.underline() {
+            Underline::None => Underline::Single,
+            _ => Underline::None,
+        };
+        attrs.set_underline(underline);
+    };
+    let toggle_blink = |attrs: &mut CellAttributes| {
+        let blink = match attrs.blink() {
+            Blink::None => Blink::Slow,
+            _ => Blink::None,
+        };
+        attrs.set_blink(blink);
+    };
+    let toggle_reverse = |attrs: &mut CellAttributes| {
+        let reverse = !attrs.reverse();
+        attrs.set_reverse(reverse);
+    };
+
+    match sgr {
+        Sgr::Reset => {
+            toggle_intensity(attrs);
+            toggle_underline(attrs);
+            toggle_blink(attrs);
+            toggle_reverse(attrs);
+        }
+        Sgr::Intensity(Intensity::Bold) => toggle_intensity(attrs),
+        Sgr::Underline(Underline::Single) => toggle_underline(attrs),
+        Sgr::Blink(Blink::Slow) => toggle_blink(attrs),
+        Sgr::Inverse(true) => toggle_reverse(attrs),
+        Sgr::Invisible(true) => {
+            let invis = !attrs.invisible();
+            attrs.set_invisible(invis);
+        }
+        _ => {}
+    }
+}
@@ -1,5 +1,7 @@
 use super::*;
+use pretty_assertions::assert_eq;
 use termwiz::color::AnsiColor;
+use termwiz::input::KittyKeyboardFlags;
 
 /// In this issue, the `CSI 2 P` sequence incorrectly removed two
 /// cells from the line, leaving them effectively blank, when those
@@ -222,3 +224,256 @@ fn test_ed_erase_scrollback() {
     term.print("b");
     assert_all_contents(&term, file!(), line!(), &["111", "222", "ab "]);
 }
+
+#[test]
+fn test_synchronized_output() {
+    let mut term = TestTerm::new(3, 3, 0);
+    assert!(!term.is_synchronized_output_active());
+    term.set_mode("?2026", true);
+    assert!(term.is_synchronized_output_active());
+    term.set_mode("?2026", false);
+    assert!(!term.is_synchronized_output_active());
+
+    // BSU/ESU DCS form
+    term.print("\x1bP=1s\x1b\\");
+    assert!(term.is_synchronized_output_active());
+    term.print("\x1bP=2s\x1b\\");
+    assert!(!term.is_synchronized_output_active());
+
+    term.set_mode("?2026", true);
+    term.soft_reset();
+    assert!(!term.is_synchronized_output_active());
+}
+
+#[test]
+fn test_decfra_decera() {
+    let mut term = TestTerm::new(4, 5, 0);
+    term.print("\x1b[88;2;2;3;4$x");
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["     ", " XXX ", " XXX ", "     "],
+    );
+
+    term.print("\x1b[3;3;3;3$z");
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["     ", " XXX ", " X X ", "     "],
+    );
+
+    // The bottom and right edges default to the edges of the screen
+    term.print("\x1b[35;3;4$x");
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["     ", " XXX ", " X ##", "   ##"],
+    );
+
+    // Origin mode makes the coordinates relative to the margins
+    term.print("\x1b[2;3r\x1b[?6h\x1b[1;1;1;2$z");
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["     ", "  XX ", " X ##", "   ##"],
+    );
+}
+
+#[test]
+fn test_deccra() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("ab\r\ncd");
+    term.print("\x1b[1;1;2;2;1;2;3;1$v");
+    assert_visible_contents(&term, file!(), line!(), &["ab  ", "cdab", "  cd"]);
+
+    // Overlapping areas are copied from the original content,
+    // and the destination is clipped to the screen
+    term.print("\x1b[2;1;3;4;1;1;2;1$v");
+    assert_visible_contents(&term, file!(), line!(), &["acda", "c  c", "  cd"]);
+}
+
+#[test]
+fn test_deccara_decrara() {
+    let mut term = TestTerm::new(2, 4, 0);
+    term.print("abcd\r\nefgh");
+
+    // DECSACE rectangle extent
+    term.print("\x1b[2*x\x1b[1;2;2;3;1$r");
+    let bold = |x: usize, y: VisibleRowIndex| {
+        term.screen().get_cell(x, y).unwrap().attrs().intensity() == Intensity::Bold
+    };
+    assert_eq!(
+        [bold(0, 0), bold(1, 0), bold(2, 0), bold(3, 0)],
+        [false, true, true, false]
+    );
+    assert_eq!(
+        [bold(0, 1), bold(1, 1), bold(2, 1), bold(3, 1)],
+        [false, true, true, false]
+    );
+
+    // Reversing toggles the attributes
+    term.print("\x1b[1;1;2;2;1$t");
+    let bold = |x: usize, y: VisibleRowIndex| {
+        term.screen().get_cell(x, y).unwrap().attrs().intensity() == Intensity::Bold
+    };
+    assert_eq!(
+        [bold(0, 0), bold(1, 0), bold(2, 0), bold(3, 0)],
+        [true, false, true, false]
+    );
+
+    // DECSACE stream extent wraps at the end of the line
+    term.print("\x1b[1*x\x1b[1;3;2;1;4$r");
+    let underline = |x: usize, y: VisibleRowIndex| {
+        term.screen().get_cell(x, y).unwrap().attrs().underline() == Underline::Single
+    };
+    assert_eq!(
+        [
+            underline(0, 0),
+            underline(1, 0),
+            underline(2, 0),
+            underline(3, 0)
+        ],
+        [false, false, true, true]
+    );
+    assert_eq!(
+        [
+            underline(0, 1),
+            underline(1, 1),
+            underline(2, 1),
+            underline(3, 1)
+        ],
+        [true, false, false, false]
+    );
+    assert_visible_contents(&term, file!(), line!(), &["abcd", "efgh"]);
+}
+
+#[test]
+fn test_deccara_decrara_out_of_range() {
+    let mut term = TestTerm::new(3, 10, 0);
+    let bold_rows = |term: &TestTerm| -> Vec<bool> {
+        (0..3)
+            .map(|y| {
+                (0..10).any(|x| {
+                    term.screen().get_cell(x, y).unwrap().attrs().intensity() == Intensity::Bold
+                })
+            })
+            .collect()
+    };
+
+    // In stream mode a start beyond the right edge begins on the next line
+    term.print("\x1b[1;20;3;30;1$r");
+    assert_eq!(bold_rows(&term), vec![false, true, true]);
+    term.print("\x1b[1;20;3;30;1$t");
+    assert_eq!(bold_rows(&term), vec![false, false, false]);
+
+    // A top beyond the bottom is empty
+    term.print("\x1b[5;1;3;10;1$r\x1b[5;1;3;10;1$t");
+    assert_eq!(bold_rows(&term), vec![false, false, false]);
+
+    // In rectangle mode the area is empty
+    term.print("\x1b[2*x\x1b[1;20;3;30;1$r\x1b[1;20;3;30;1$t");
+    assert_eq!(bold_rows(&term), vec![false, false, false]);
+}
+
+#[test]
+fn test_selective_erase() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("ab\x1b[1\"qcd\x1b[0\"q\r\nefgh\r\nij\x1b[1\"qkl");
+
+    // DECSEL
+    term.cup(0, 0);
+    term.print("\x1b[?2K");
+    assert_visible_contents(&term, file!(), line!(), &["  cd", "efgh", "ijkl"]);
+
+    // DECSERA
+    term.print("\x1b[2;2;3;4${");
+    assert_visible_contents(&term, file!(), line!(), &["  cd", "e   ", "i kl"]);
+
+    // DECSED
+    term.print("\x1b[?2J");
+    assert_visible_contents(&term, file!(), line!(), &["  cd", "    ", "  kl"]);
+
+    // Protection is preserved by SGR reset and DECSC/DECRC,
+    // and regular ED erases protected cells
+    term.print("\x1b[0m\x1b7\x1b[0\"q\x1b8\x1b[1;1Hz\x1b[?K");
+    assert_visible_contents(&term, file!(), line!(), &["z cd", "    ", "  kl"]);
+    term.print("\x1b[2J");
+    assert_visible_contents(&term, file!(), line!(), &["    ", "    ", "    "]);
+}
+
+#[test]
+fn test_kitty_keyboard_stack() {
+    let mut term = TestTerm::new(3, 4, 0);
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+
+    term.print("\x1b[>1u\x1b[>3u\x1b[?u");
+    term.assert_response("\x1b[?3u");
+
+    // CSI = flags ; mode u modifies the top of the stack
+    term.print("\x1b[=16;2u");
+    assert_eq!(term.get_kitty_keyboard_flags().bits(), 19);
+    term.print("\x1b[=2;3u");
+    assert_eq!(term.get_kitty_keyboard_flags().bits(), 17);
+    term.print("\x1b[=8u");
+    assert_eq!(term.get_kitty_keyboard_flags().bits(), 8);
+
+    term.print("\x1b[<u\x1b[?u");
+    term.assert_response("\x1b[?1u");
+
+    // Popping more entries than the stack holds empties it
+    term.print("\x1b[<5u\x1b[?u");
+    term.assert_response("\x1b[?0u");
+
+    // Setting flags with an empty stack pushes an entry
+    term.print("\x1b[=4u");
+    assert_eq!(term.get_kitty_keyboard_flags().bits(), 4);
+    term.print("\x1b[<u");
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+}
+
+#[test]
+fn test_kitty_keyboard_alt_screen() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("\x1b[>1u");
+
+    // The alternate screen has its own, initially empty, stack
+    term.print("\x1b[?1049h");
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+    term.print("\x1b[>8u\x1b[>2u");
+    assert_eq!(term.get_kitty_keyboard_flags().bits(), 2);
+
+    term.print("\x1b[?1049l");
+    assert_eq!(term.get_kitty_keyboard_flags().bits(), 1);
+    term.print("\x1b[<u");
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+
+    // and it is preserved while the primary screen is active
+    term.print("\x1b[?1049h");
+    assert_eq!(term.get_kitty_keyboard_flags().bits(), 2);
+    term.print("\x1b[<u");
+    assert_eq!(term.get_kitty_keyboard_flags().bits(), 8);
+}
+
+#[test]
+fn test_kitty_keyboard_reset() {
+    let mut term = TestTerm::new(3, 4, 0);
+
+    // RIS clears the stacks of both screens
+    term.print("\x1b[>1u\x1b[?1049h\x1b[>2u\x1bc");
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+    term.print("\x1b[?1049h");
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+    term.print("\x1b[?1049l");
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+
+    // and so does DECSTR
+    term.print("\x1b[>1u\x1b[?1049h\x1b[>2u");
+    term.soft_reset();
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+    term.print("\x1b[?1049h");
+    assert_eq!(term.get_kitty_keyboard_flags(), KittyKeyboardFlags::NONE);
+}
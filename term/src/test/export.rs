@@ -0,0 +1,107 @@
+//! Testing exporting lines as text, HTML, SVG and ANSI
+
+use super::*;
+use crate::export::{export_lines, ExportFormat};
+use pretty_assertions::assert_eq;
+
+fn export(term: &TestTerm, format: ExportFormat) -> String {
+    export_lines(
+        &term.screen().visible_lines(),
+        &ColorPalette::default(),
+        format,
+    )
+}
+
+#[test]
+fn export_text() {
+    let mut term = TestTerm::new(3, 10, 0);
+    term.print("hello\r\n\x1b[1mworld\x1b[0m");
+    assert_eq!(export(&term, ExportFormat::Text), "hello\nworld\n\n");
+}
+
+#[test]
+fn export_ansi() {
+    let mut term = TestTerm::new(2, 10, 0);
+    term.print("a\x1b[1;31mb\x1b[0mc\x1b[44m \x1b[0m");
+    assert_eq!(
+        export(&term, ExportFormat::Ansi),
+        "a\x1b[0m\x1b[1m\x1b[31mb\x1b[0mc\x1b[0m\x1b[44m \x1b[0m\n\n"
+    );
+}
+
+#[test]
+fn export_ansi_hyperlink() {
+    let mut term = TestTerm::new(1, 10, 0);
+    let link = Arc::new(Hyperlink::new("http://example.com"));
+    term.hyperlink(&link);
+    term.print("ab");
+    term.hyperlink_off();
+    term.print("c");
+    assert_eq!(
+        export(&term, ExportFormat::Ansi),
+        "\x1b]8;;http://example.com\x1b\\ab\x1b]8;;\x1b\\c\n"
+    );
+}
+
+#[test]
+fn export_html() {
+    let mut term = TestTerm::new(1, 10, 0);
+    let link = Arc::new(Hyperlink::new("http://example.com/?a&b"));
+    term.print("<\x1b[1;32mok\x1b[0m ");
+    term.hyperlink(&link);
+    term.print("x");
+    term.hyperlink_off();
+
+    let palette = ColorPalette::default();
+    let html = export(&term, ExportFormat::Html);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("&lt;"));
+    assert!(html.contains(&format!(
+        "<span style=\"color:{};font-weight:bold\">ok</span>",
+        palette.colors.0[2].to_rgb_string()
+    )));
+    assert!(html
+        .contains("<a href=\"http://example.com/?a&amp;b\" style=\"color:inherit\">x</a>\n</pre>"));
+}
+
+#[test]
+fn export_svg() {
+    let mut term = TestTerm::new(2, 10, 0);
+    term.print("ab\x1b[7mc\x1b[0m");
+
+    let palette = ColorPalette::default();
+    let svg = export(&term, ExportFormat::Svg);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"25.2\""));
+    // The reversed cell has a background rect in the foreground color
+    assert!(svg.contains(&format!(
+        "<rect x=\"16.8\" y=\"0.0\" width=\"8.4\" height=\"16.8\" fill=\"{}\"/>",
+        palette.foreground.to_rgb_string()
+    )));
+    assert!(svg.contains(">ab</text>"));
+    assert!(svg.ends_with("</svg>\n"));
+}
+
+#[test]
+fn export_unsafe_hyperlink() {
+    let mut term = TestTerm::new(1, 10, 0);
+    let link = Arc::new(Hyperlink::new("javascript:alert(1)"));
+    term.hyperlink(&link);
+    term.print("x");
+    term.hyperlink_off();
+    let link = Arc::new(Hyperlink::new("MAILTO:a@example.com"));
+    term.hyperlink(&link);
+    term.print("y");
+    term.hyperlink_off();
+
+    for format in [ExportFormat::Html, ExportFormat::Svg] {
+        let exported = export(&term, format);
+        assert!(!exported.contains("javascript"));
+        assert!(exported.contains("<a href=\"MAILTO:a@example.com\""));
+    }
+}
+
+#[test]
+fn export_format_from_str() {
+    assert_eq!("HTML".parse::<ExportFormat>(), Ok(ExportFormat::Html));
+    assert!("pdf".parse::<ExportFormat>().is_err());
+}
@@ -0,0 +1,38 @@
+//! Tests for the kitty graphics protocol
+use super::*;
+use pretty_assertions::assert_eq;
+use std::time::Duration;
+use termwiz::image::ImageDataType;
+
+/// A single opaque red pixel, as base64 encoded RGBA
+const RED_PIXEL: &str = "/wAA/w==";
+
+#[test]
+fn test_kitty_frame_gap() {
+    let mut term = TestTerm::new(3, 10, 0);
+    term.print(format!(
+        "\x1b_Ga=T,f=32,s=1,v=1,i=1,q=2;{}\x1b\\",
+        RED_PIXEL
+    ));
+    // Add a second frame that is shown for 100ms
+    term.print(format!(
+        "\x1b_Ga=f,f=32,s=1,v=1,i=1,z=100,q=2;{}\x1b\\",
+        RED_PIXEL
+    ));
+
+    let images = term
+        .screen()
+        .get_cell(0, 0)
+        .and_then(|cell| cell.attrs().images())
+        .expect("the image to be placed at the cursor");
+    let data = images[0].image_data().data();
+    match &*data {
+        ImageDataType::AnimRgba8 { durations, .. } => {
+            assert_eq!(
+                durations,
+                &vec![Duration::from_millis(0), Duration::from_millis(100)]
+            );
+        }
+        wat => panic!("expected an animation, got {:?}", wat),
+    }
+}
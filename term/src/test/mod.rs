@@ -6,11 +6,16 @@ mod c0;
 use bitflags::bitflags;
 mod c1;
 mod csi;
+mod export;
+mod kitty;
+mod reports;
 // mod selection; FIXME: port to render layer
 use crate::color::ColorPalette;
 use pretty_assertions::assert_eq;
 use std::cell::RefCell;
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
+use std::time::Duration;
 use termwiz::escape::csi::{Edit, EraseInDisplay, EraseInLine};
 use termwiz::escape::{OneBased, OperatingSystemCommand, CSI};
 use termwiz::surface::{CursorShape, CursorVisibility, SequenceNo, SEQ_ZERO};
@@ -39,8 +44,25 @@ impl Clipboard for LocalClip {
     }
 }
 
+/// Captures the data that the terminal sends back to the application
+struct ChannelWriter {
+    sender: Sender<Vec<u8>>,
+}
+
+impl std::io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.sender.send(buf.to_vec()).ok();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 struct TestTerm {
     term: Terminal,
+    responses: Receiver<Vec<u8>>,
 }
 
 #[derive(Debug)]
@@ -55,6 +77,14 @@ impl TerminalConfiguration for TestTermConfig {
     fn color_palette(&self) -> ColorPalette {
         ColorPalette::default()
     }
+
+    fn enable_kitty_graphics(&self) -> bool {
+        true
+    }
+
+    fn enable_kitty_keyboard(&self) -> bool {
+        true
+    }
 }
 
 impl TestTerm {
@@ -64,6 +94,7 @@ impl TestTerm {
             .filter_level(log::LevelFilter::Trace)
             .try_init();
 
+        let (sender, responses) = channel();
         let mut term = Terminal::new(
             TerminalSize {
                 physical_rows: height,
@@ -74,12 +105,12 @@ impl TestTerm {
             Arc::new(TestTermConfig { scrollback }),
             "WezTerm",
             "O_o",
-            Box::new(Vec::new()),
+            Box::new(ChannelWriter { sender }),
         );
         let clip: Arc<dyn Clipboard> = Arc::new(LocalClip::new());
         term.set_clipboard(&clip);
 
-        let mut term = Self { term };
+        let mut term = Self { term, responses };
 
         term.set_auto_wrap(true);
 
@@ -144,6 +175,19 @@ impl TestTerm {
         self.print("\x1b]8;;\x1b\\");
     }
 
+    /// Asserts that the terminal sent `expected` back to the application
+    /// in response to the sequences that were printed
+    fn assert_response(&self, expected: &str) {
+        let mut response = vec![];
+        while response.len() < expected.len() {
+            match self.responses.recv_timeout(Duration::from_secs(5)) {
+                Ok(data) => response.extend_from_slice(&data),
+                Err(_) => break,
+            }
+        }
+        assert_eq!(String::from_utf8_lossy(&response), expected);
+    }
+
     fn soft_reset(&mut self) {
         self.print(CSI);
         self.print("!p");
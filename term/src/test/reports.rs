@@ -0,0 +1,230 @@
+//! Conformance tests for the status and mode reports (DECRQSS and
+//! DECRQM) and for the state that is reset by DECSTR
+use super::*;
+use num_traits::FromPrimitive;
+use pretty_assertions::assert_eq;
+use termwiz::escape::csi::{DecPrivateModeCode, TerminalModeCode};
+
+fn decrqss(term: &mut TestTerm, setting: &str) {
+    term.print(format!("{}$q{}{}", DCS, setting, ST));
+}
+
+fn decrqm(term: &mut TestTerm, mode: &str) {
+    term.print(format!("{}{}$p", CSI, mode));
+}
+
+/// Queries the mode and returns the DECRPM response
+fn mode_report(term: &mut TestTerm, mode: &str) -> String {
+    decrqm(term, mode);
+    let mut response = vec![];
+    while !response.ends_with(b"$y") {
+        let data = term
+            .responses
+            .recv_timeout(Duration::from_secs(5))
+            .expect("a DECRPM response");
+        response.extend_from_slice(&data);
+    }
+    String::from_utf8(response).unwrap()
+}
+
+#[test]
+fn test_decrqss_sgr() {
+    let mut term = TestTerm::new(3, 10, 0);
+    decrqss(&mut term, "m");
+    term.assert_response("\x1bP1$r0m\x1b\\");
+
+    term.print("\x1b[1;3;4;7;31;48;5;100m");
+    decrqss(&mut term, "m");
+    term.assert_response("\x1bP1$r0;1;3;4;7;31;48:5:100m\x1b\\");
+
+    term.print("\x1b[0;2;4:3;38:2::255:128:0m");
+    decrqss(&mut term, "m");
+    term.assert_response("\x1bP1$r0;2;4:3;38:2::255:128:0m\x1b\\");
+}
+
+#[test]
+fn test_decrqss_cursor_style() {
+    let mut term = TestTerm::new(3, 10, 0);
+    decrqss(&mut term, " q");
+    term.assert_response("\x1bP1$r0 q\x1b\\");
+
+    term.print("\x1b[4 q");
+    decrqss(&mut term, " q");
+    term.assert_response("\x1bP1$r4 q\x1b\\");
+}
+
+#[test]
+fn test_decrqss_margins() {
+    let mut term = TestTerm::new(5, 10, 0);
+    decrqss(&mut term, "r");
+    term.assert_response("\x1bP1$r1;5r\x1b\\");
+
+    term.print("\x1b[2;4r");
+    decrqss(&mut term, "r");
+    term.assert_response("\x1bP1$r2;4r\x1b\\");
+
+    term.print("\x1b[?69h\x1b[3;7s");
+    decrqss(&mut term, "s");
+    term.assert_response("\x1bP1$r3;7s\x1b\\");
+}
+
+#[test]
+fn test_decrqss_protection_and_extent() {
+    let mut term = TestTerm::new(3, 10, 0);
+    decrqss(&mut term, "\"q");
+    term.assert_response("\x1bP1$r0\"q\x1b\\");
+
+    term.print("\x1b[1\"q");
+    decrqss(&mut term, "\"q");
+    term.assert_response("\x1bP1$r1\"q\x1b\\");
+
+    decrqss(&mut term, "*x");
+    term.assert_response("\x1bP1$r1*x\x1b\\");
+    term.print("\x1b[2*x");
+    decrqss(&mut term, "*x");
+    term.assert_response("\x1bP1$r2*x\x1b\\");
+}
+
+#[test]
+fn test_decrqss_conformance_and_invalid() {
+    let mut term = TestTerm::new(3, 10, 0);
+    decrqss(&mut term, "\"p");
+    term.assert_response("\x1bP1$r65;1\"p\x1b\\");
+
+    decrqss(&mut term, "z");
+    term.assert_response("\x1bP0$r\x1b\\");
+}
+
+#[test]
+fn test_decrqm() {
+    let mut term = TestTerm::new(3, 10, 0);
+
+    // DECAWM is enabled by TestTerm
+    decrqm(&mut term, "?7");
+    term.assert_response("\x1b[?7;1$y");
+    term.set_auto_wrap(false);
+    decrqm(&mut term, "?7");
+    term.assert_response("\x1b[?7;2$y");
+
+    decrqm(&mut term, "?1049");
+    term.assert_response("\x1b[?1049;2$y");
+    term.set_mode("?1049", true);
+    decrqm(&mut term, "?1049");
+    term.assert_response("\x1b[?1049;1$y");
+    decrqm(&mut term, "?47");
+    term.assert_response("\x1b[?47;1$y");
+
+    decrqm(&mut term, "?5");
+    term.assert_response("\x1b[?5;2$y");
+    term.set_mode("?5", true);
+    decrqm(&mut term, "?5");
+    term.assert_response("\x1b[?5;1$y");
+
+    // Permanently set and reset modes
+    decrqm(&mut term, "?8");
+    term.assert_response("\x1b[?8;3$y");
+    decrqm(&mut term, "?4");
+    term.assert_response("\x1b[?4;4$y");
+    decrqm(&mut term, "2");
+    term.assert_response("\x1b[2;4$y");
+    decrqm(&mut term, "12");
+    term.assert_response("\x1b[12;3$y");
+
+    decrqm(&mut term, "4");
+    term.assert_response("\x1b[4;2$y");
+    term.set_mode("4", true);
+    decrqm(&mut term, "4");
+    term.assert_response("\x1b[4;1$y");
+
+    // Unknown modes are reported as not recognized
+    decrqm(&mut term, "?9999");
+    term.assert_response("\x1b[?9999;0$y");
+    decrqm(&mut term, "9999");
+    term.assert_response("\x1b[9999;0$y");
+}
+
+/// Every mode that we know about should be reported as recognized
+#[test]
+fn test_decrqm_recognizes_all_modes() {
+    let mut term = TestTerm::new(3, 10, 0);
+
+    let dec_modes = [
+        1, 2, 3, 4, 5, 6, 7, 8, 12, 25, 45, 47, 69, 80, 1000, 1001, 1002, 1003, 1004, 1006, 1016,
+        1047, 1048, 1049, 1070, 2004, 2026, 8452, 9001,
+    ];
+    for mode in &dec_modes {
+        // Sanity check that the list is kept up to date
+        assert!(DecPrivateModeCode::from_u16(*mode).is_some(), "{}", mode);
+        let response = mode_report(&mut term, &format!("?{}", mode));
+        assert!(
+            !response.ends_with(";0$y"),
+            "mode ?{} was not recognized: {:?}",
+            mode,
+            response
+        );
+    }
+
+    let ansi_modes = [2, 4, 8, 12, 20, 25];
+    for mode in &ansi_modes {
+        assert!(TerminalModeCode::from_u16(*mode).is_some(), "{}", mode);
+        let response = mode_report(&mut term, &mode.to_string());
+        assert!(
+            !response.ends_with(";0$y"),
+            "mode {} was not recognized: {:?}",
+            mode,
+            response
+        );
+    }
+}
+
+#[test]
+fn test_decstr() {
+    let mut term = TestTerm::new(5, 10, 0);
+
+    term.set_mode("4", true); // IRM
+    term.set_mode("?1", true); // DECCKM
+    term.set_mode("?25", false); // DECTCEM
+    term.set_mode("?69", true); // DECLRMM
+    term.print("\x1b[2;4r\x1b[2;5s");
+    term.set_mode("?6", true); // DECOM
+    term.print("\x1b[1;31m\x1b[1\"q\x1b[2*x");
+    // Designate DEC line drawing into G0
+    term.print("\x1b(0");
+    term.print("\x1b7");
+
+    term.soft_reset();
+
+    decrqm(&mut term, "4");
+    term.assert_response("\x1b[4;2$y");
+    decrqm(&mut term, "?1");
+    term.assert_response("\x1b[?1;2$y");
+    decrqm(&mut term, "?25");
+    term.assert_response("\x1b[?25;1$y");
+    decrqm(&mut term, "?6");
+    term.assert_response("\x1b[?6;2$y");
+    decrqm(&mut term, "?69");
+    term.assert_response("\x1b[?69;2$y");
+    // xterm leaves autowrap enabled
+    decrqm(&mut term, "?7");
+    term.assert_response("\x1b[?7;1$y");
+
+    decrqss(&mut term, "r");
+    term.assert_response("\x1bP1$r1;5r\x1b\\");
+    decrqss(&mut term, "s");
+    term.assert_response("\x1bP1$r1;10s\x1b\\");
+    decrqss(&mut term, "m");
+    term.assert_response("\x1bP1$r0m\x1b\\");
+    decrqss(&mut term, "\"q");
+    term.assert_response("\x1bP1$r0\"q\x1b\\");
+    decrqss(&mut term, "*x");
+    term.assert_response("\x1bP1$r1*x\x1b\\");
+
+    // The character set is back to ASCII
+    term.cup(0, 4);
+    term.print("q");
+    assert_eq!(term.screen().get_cell(0, 4).unwrap().str(), "q");
+
+    // The saved cursor was reset, so restoring it homes the cursor
+    term.print("\x1b8");
+    term.assert_cursor_pos(0, 0, None, None);
+}
@@ -27,18 +27,26 @@ impl LineEditorHost for Host {
         &mut self.history
     }
 
+    /// A trailing backslash continues the input on the next line
+    fn is_line_complete(&self, line: &str) -> bool {
+        !line.ends_with('\\')
+    }
+
     /// Demo of the completion API for words starting with "h" or "he"
     fn complete(&self, line: &str, cursor_position: usize) -> Vec<CompletionCandidate> {
         let mut candidates = vec![];
         if let Some((range, word)) = word_at_cursor(line, cursor_position) {
-            let words = &["hello", "help", "he-man"];
+            let words = &[
+                ("hello", "a greeting"),
+                ("help", "a request for assistance"),
+                ("he-man", "a cartoon character"),
+            ];
 
-            for w in words {
+            for (w, description) in words {
                 if w.starts_with(word) {
-                    candidates.push(CompletionCandidate {
-                        range: range.clone(),
-                        text: w.to_string(),
-                    });
+                    candidates.push(
+                        CompletionCandidate::new(range.clone(), *w).with_description(*description),
+                    );
                 }
             }
         }
@@ -94,6 +102,9 @@ fn main() -> termwiz::Result<()> {
     println!("Type `exit` to quit this example, or start a word with `h` and press Tab.");
     let mut terminal = line_editor_terminal()?;
     let mut editor = LineEditor::new(&mut terminal);
+    if std::env::args().any(|arg| arg == "--vi") {
+        editor.set_edit_mode(EditMode::Vi);
+    }
 
     let mut host = Host::default();
     loop {
@@ -0,0 +1,194 @@
+//! This example shows the stock widgets.  Use Tab to move between
+//! them, type a name and press Enter to add it to the list, switch
+//! tabs with the arrow keys and press Escape to quit.
+#[cfg(feature = "widgets")]
+mod inner {
+    use termwiz::caps::Capabilities;
+    use termwiz::input::*;
+    use termwiz::surface::Change;
+    use termwiz::terminal::buffered::BufferedTerminal;
+    use termwiz::terminal::{new_terminal, Terminal};
+    use termwiz::widgets::layout::ChildOrientation;
+    use termwiz::widgets::*;
+    use termwiz::Error;
+
+    const MAX_NAMES: usize = 10;
+
+    /// Stacks the other widgets vertically
+    struct MainScreen {}
+
+    impl Widget for MainScreen {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn get_size_constraints(&self) -> layout::Constraints {
+            let mut c = layout::Constraints::default();
+            c.child_orientation = ChildOrientation::Vertical;
+            c
+        }
+    }
+
+    /// Adds the widget that shows the content of the active tab
+    fn add_tab_content(
+        ui: &mut Ui,
+        root: WidgetId,
+        tab: usize,
+        names: &[String],
+        log: &str,
+    ) -> WidgetId {
+        match tab {
+            0 => ui.add_child(root, List::new(names.to_vec())),
+            1 => ui.add_child(
+                root,
+                Table::new(
+                    vec![
+                        Column {
+                            name: "Widget".to_string(),
+                            alignment: Alignment::Left,
+                        },
+                        Column {
+                            name: "Focusable".to_string(),
+                            alignment: Alignment::Center,
+                        },
+                    ],
+                    [
+                        ("TextInput", "yes"),
+                        ("List", "yes"),
+                        ("Table", "yes"),
+                        ("ScrollView", "yes"),
+                        ("TabStrip", "yes"),
+                        ("Modal", "yes"),
+                        ("ProgressBar", "no"),
+                    ]
+                    .iter()
+                    .map(|(name, focusable)| vec![name.to_string(), focusable.to_string()])
+                    .collect(),
+                ),
+            ),
+            _ => {
+                let mut view = ScrollView::new(log);
+                view.scroll_to(usize::MAX);
+                ui.add_child(root, view)
+            }
+        }
+    }
+
+    pub fn run() -> Result<(), Error> {
+        let caps = Capabilities::new_from_env()?;
+        let mut buf = BufferedTerminal::new(new_terminal(caps)?)?;
+        buf.terminal().set_raw_mode()?;
+
+        let mut names: Vec<String> = vec![];
+        let mut log = String::from("Started");
+
+        let mut ui = Ui::new();
+        let root = ui.set_root(MainScreen {});
+        let tabs = ui.add_child(
+            root,
+            TabStrip::new(vec![
+                "Names".to_string(),
+                "Widgets".to_string(),
+                "Log".to_string(),
+            ]),
+        );
+        let input = ui.add_child(root, {
+            let mut input = TextInput::new();
+            input.set_placeholder("Type a name and press Enter");
+            input
+        });
+        let progress = ui.add_child(root, ProgressBar::new());
+        let mut active_tab = 0;
+        let mut content = add_tab_content(&mut ui, root, active_tab, &names, &log);
+        let mut modal = None;
+        ui.set_focus(input);
+
+        loop {
+            ui.process_event_queue()?;
+
+            // Replace the content when a different tab is activated
+            let tab = ui.widget::<TabStrip>(tabs).map_or(0, |t| t.active());
+            if tab != active_tab {
+                active_tab = tab;
+                ui.remove(content);
+                content = add_tab_content(&mut ui, root, active_tab, &names, &log);
+            }
+
+            if let Some(id) = modal {
+                match ui.widget::<Modal>(id).and_then(|m| m.result()) {
+                    Some(0) => break,
+                    Some(_) => {
+                        ui.remove(id);
+                        modal = None;
+                    }
+                    None => {}
+                }
+            }
+
+            if ui.render_to_screen(&mut buf)? {
+                continue;
+            }
+            buf.flush()?;
+
+            match buf.terminal().poll_input(None) {
+                Ok(Some(InputEvent::Resized { rows, cols })) => {
+                    buf.add_change(Change::ClearScreen(Default::default()));
+                    buf.resize(cols, rows);
+                }
+                Ok(Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                }))) if modal.is_none() => {
+                    modal = Some(ui.add_overlay(Modal::new(
+                        "Quit",
+                        "Do you really want to quit the widget gallery?",
+                        vec!["Quit".to_string(), "Cancel".to_string()],
+                    )));
+                }
+                Ok(Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    modifiers: Modifiers::NONE,
+                }))) if ui.focused() == Some(input) && names.len() < MAX_NAMES => {
+                    let name = match ui.widget_mut::<TextInput>(input) {
+                        Some(text) => {
+                            let name = text.text().to_string();
+                            text.set_text("");
+                            name
+                        }
+                        None => continue,
+                    };
+                    if name.is_empty() {
+                        continue;
+                    }
+                    log.push_str(&format!("\nAdded {}", name));
+                    names.push(name);
+                    if let Some(list) = ui.widget_mut::<List>(content) {
+                        list.set_items(names.clone());
+                    }
+                    if let Some(bar) = ui.widget_mut::<ProgressBar>(progress) {
+                        bar.set_fraction(names.len() as f64 / MAX_NAMES as f64);
+                        bar.set_label(&format!("{}/{} names", names.len(), MAX_NAMES));
+                    }
+                }
+                Ok(Some(input)) => ui.queue_event(WidgetEvent::Input(input)),
+                Ok(None) => {}
+                Err(e) => {
+                    print!("{:?}\r\n", e);
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "widgets"))]
+mod inner {
+    pub fn run() -> termwiz::Result<()> {
+        println!("recompile with --features widgets");
+        Ok(())
+    }
+}
+
+fn main() -> termwiz::Result<()> {
+    inner::run()
+}
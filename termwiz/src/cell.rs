@@ -64,6 +64,7 @@ impl std::fmt::Debug for CellAttributes {
             .field("wrapped", &self.wrapped())
             .field("overline", &self.overline())
             .field("semantic_type", &self.semantic_type())
+            .field("protected", &self.protected())
             .field("foreground", &self.foreground)
             .field("background", &self.background)
             .field("fat", &self.fat)
@@ -248,6 +249,8 @@ impl CellAttributes {
     bitfield!(wrapped, set_wrapped, 11);
     bitfield!(overline, set_overline, 12);
     bitfield!(semantic_type, set_semantic_type, SemanticType, 0b11, 13);
+    // Set by DECSCA; protected cells are skipped by the selective erase functions
+    bitfield!(protected, set_protected, 15);
 
     pub const fn blank() -> Self {
         Self {
@@ -456,6 +459,8 @@ impl CellAttributes {
         // be deterministically tagged as Output so that we have an
         // easier time in get_semantic_zones.
         res.set_semantic_type(SemanticType::default());
+        // Blank cells are never protected from selective erase
+        res.set_protected(false);
         res.set_underline_color(self.underline_color());
         res
     }
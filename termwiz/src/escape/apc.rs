@@ -797,7 +797,7 @@ impl KittyImageFrame {
                 None | Some(0) => None,
                 n => n,
             },
-            duration_ms: match geti(keys, "Z") {
+            duration_ms: match geti(keys, "z") {
                 None | Some(0) => None,
                 n => n,
             },
@@ -815,7 +815,7 @@ impl KittyImageFrame {
         set(keys, "y", &self.y);
         set(keys, "c", &self.base_frame);
         set(keys, "r", &self.frame_number);
-        set(keys, "Z", &self.duration_ms);
+        set(keys, "z", &self.duration_ms);
         match &self.composition_mode {
             KittyFrameCompositionMode::AlphaBlending => {}
             KittyFrameCompositionMode::Overwrite => {
@@ -826,6 +826,94 @@ impl KittyImageFrame {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyAnimationState {
+    /// s=1
+    Stopped,
+    /// Run the animation, but wait for new frames to be
+    /// transmitted when the last frame is reached.
+    /// s=2
+    Loading,
+    /// Run the animation, looping when the last frame is reached.
+    /// s=3
+    Running,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyImageAnimation {
+    /// i=...
+    pub image_id: Option<u32>,
+    /// I=...
+    pub image_number: Option<u32>,
+
+    /// s=...
+    pub state: Option<KittyAnimationState>,
+
+    /// 1-based number of the frame whose gap should be changed.
+    /// r=...
+    pub frame_number: Option<u32>,
+
+    /// The new gap in milliseconds for frame_number.
+    /// Negative values cause the frame to be skipped.
+    /// z=...
+    pub duration_ms: Option<i32>,
+
+    /// 1-based number of the frame that should be made current.
+    /// c=...
+    pub current_frame: Option<u32>,
+
+    /// The number of loops to play.  1 loops forever,
+    /// any other value plays the animation that many times less one.
+    /// v=...
+    pub loops: Option<u32>,
+}
+
+impl KittyImageAnimation {
+    fn from_keys(keys: &BTreeMap<&str, &str>) -> Option<Self> {
+        Some(Self {
+            image_id: geti(keys, "i"),
+            image_number: geti(keys, "I"),
+            state: match geti(keys, "s") {
+                None | Some(0) => None,
+                Some(1) => Some(KittyAnimationState::Stopped),
+                Some(2) => Some(KittyAnimationState::Loading),
+                Some(3) => Some(KittyAnimationState::Running),
+                _ => return None,
+            },
+            frame_number: match geti(keys, "r") {
+                None | Some(0) => None,
+                n => n,
+            },
+            duration_ms: geti(keys, "z"),
+            current_frame: match geti(keys, "c") {
+                None | Some(0) => None,
+                n => n,
+            },
+            loops: match geti(keys, "v") {
+                None | Some(0) => None,
+                n => n,
+            },
+        })
+    }
+
+    fn to_keys(&self, keys: &mut BTreeMap<&'static str, String>) {
+        set(keys, "i", &self.image_id);
+        set(keys, "I", &self.image_number);
+        if let Some(state) = &self.state {
+            let s = match state {
+                KittyAnimationState::Stopped => "1",
+                KittyAnimationState::Loading => "2",
+                KittyAnimationState::Running => "3",
+            };
+            keys.insert("s", s.to_string());
+        }
+        set(keys, "r", &self.frame_number);
+        set(keys, "z", &self.duration_ms);
+        set(keys, "c", &self.current_frame);
+        set(keys, "v", &self.loops);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum KittyImage {
     /// a='t'
@@ -864,6 +952,11 @@ pub enum KittyImage {
         frame: KittyImageFrameCompose,
         verbosity: KittyImageVerbosity,
     },
+    /// a='a'
+    ControlAnimation {
+        animation: KittyImageAnimation,
+        verbosity: KittyImageVerbosity,
+    },
 }
 
 impl KittyImage {
@@ -876,6 +969,7 @@ impl KittyImage {
             Self::Delete { verbosity, .. } => *verbosity,
             Self::TransmitFrame { verbosity, .. } => *verbosity,
             Self::ComposeFrame { verbosity, .. } => *verbosity,
+            Self::ControlAnimation { verbosity, .. } => *verbosity,
         }
     }
 
@@ -929,6 +1023,10 @@ impl KittyImage {
                 frame: KittyImageFrameCompose::from_keys(&keys)?,
                 verbosity,
             }),
+            "a" => Some(Self::ControlAnimation {
+                animation: KittyImageAnimation::from_keys(&keys)?,
+                verbosity,
+            }),
             _ => None,
         }
     }
@@ -952,7 +1050,7 @@ impl KittyImage {
                 verbosity,
                 placement,
             } => {
-                keys.insert("a", "Q".to_string());
+                keys.insert("a", "T".to_string());
                 verbosity.to_keys(keys);
                 placement.to_keys(keys);
                 transmit.to_keys(keys);
@@ -993,6 +1091,14 @@ impl KittyImage {
                 frame.to_keys(keys);
                 verbosity.to_keys(keys);
             }
+            Self::ControlAnimation {
+                animation,
+                verbosity,
+            } => {
+                keys.insert("a", "a".to_string());
+                animation.to_keys(keys);
+                verbosity.to_keys(keys);
+            }
         }
     }
 }
@@ -1087,4 +1193,38 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn kitty_frame_gap() {
+        // The gap before the next frame is z; Z is the z-index of a placement
+        let frame = KittyImage::parse_apc("Ga=f,i=3,z=100,q=2;AAAA".as_bytes()).unwrap();
+        match &frame {
+            KittyImage::TransmitFrame { frame, .. } => {
+                assert_eq!(frame.duration_ms, Some(100));
+            }
+            wat => panic!("expected TransmitFrame, got {:?}", wat),
+        }
+        assert!(frame.to_string().contains(",z=100"));
+    }
+
+    #[test]
+    fn kitty_animation() {
+        let control = KittyImage::parse_apc("Ga=a,i=3,s=3,v=1,q=2".as_bytes()).unwrap();
+        assert_eq!(
+            control,
+            KittyImage::ControlAnimation {
+                animation: KittyImageAnimation {
+                    image_id: Some(3),
+                    image_number: None,
+                    state: Some(KittyAnimationState::Running),
+                    frame_number: None,
+                    duration_ms: None,
+                    current_frame: None,
+                    loops: Some(1),
+                },
+                verbosity: KittyImageVerbosity::Quiet,
+            }
+        );
+        assert_eq!(control.to_string(), "\x1b_Ga=a,i=3,q=2,s=3,v=1");
+    }
 }
@@ -1,7 +1,7 @@
 use super::OneBased;
 use crate::cell::{Blink, Intensity, Underline};
 use crate::color::{AnsiColor, ColorSpec, RgbColor};
-use crate::input::{Modifiers, MouseButtons};
+use crate::input::{KittyKeyboardFlags, Modifiers, MouseButtons};
 use num_derive::*;
 use num_traits::{FromPrimitive, ToPrimitive};
 use std::fmt::{Display, Error as FmtError, Formatter};
@@ -27,6 +27,8 @@ pub enum CSI {
 
     Window(Window),
 
+    Keyboard(Keyboard),
+
     /// ECMA-48 SCP
     SelectCharacterPath(CharacterPath, i64),
 
@@ -82,6 +84,7 @@ impl Display for CSI {
             CSI::Mouse(mouse) => mouse.fmt(f)?,
             CSI::Device(dev) => dev.fmt(f)?,
             CSI::Window(window) => window.fmt(f)?,
+            CSI::Keyboard(k) => k.fmt(f)?,
             CSI::SelectCharacterPath(path, n) => {
                 let a = match path {
                     CharacterPath::ImplementationDefault => 0,
@@ -630,6 +633,51 @@ impl XtermKeyModifierResource {
     }
 }
 
+/// Controls how the flags passed to `Keyboard::SetKittyState`
+/// are combined with the current set of flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum KittyKeyboardMode {
+    AssignAll = 1,
+    SetSpecified = 2,
+    ClearSpecified = 3,
+}
+
+/// Sequences used to negotiate the kitty keyboard protocol.
+/// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/#progressive-enhancement>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Keyboard {
+    /// `CSI = flags ; mode u`
+    SetKittyState {
+        flags: KittyKeyboardFlags,
+        mode: KittyKeyboardMode,
+    },
+    /// `CSI > flags u`
+    PushKittyState(KittyKeyboardFlags),
+    /// `CSI < number u`
+    PopKittyState(u32),
+    /// `CSI ? u`
+    QueryKittySupport,
+    /// `CSI ? flags u`; the response to `QueryKittySupport`
+    ReportKittyState(KittyKeyboardFlags),
+}
+
+impl Display for Keyboard {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        match self {
+            Self::SetKittyState { flags, mode } => write!(
+                f,
+                "={};{}u",
+                flags.bits(),
+                mode.to_u8().ok_or_else(|| FmtError)?
+            ),
+            Self::PushKittyState(flags) => write!(f, ">{}u", flags.bits()),
+            Self::PopKittyState(n) => write!(f, "<{}u", n),
+            Self::QueryKittySupport => write!(f, "?u"),
+            Self::ReportKittyState(flags) => write!(f, "?{}u", flags.bits()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Mode {
     SetDecPrivateMode(DecPrivateMode),
@@ -1053,6 +1101,101 @@ pub enum Edit {
 
     /// REP - Repeat the preceding character n times
     Repeat(u32),
+
+    /// DECSED - Selective Erase in Display.
+    /// Like ED, but cells that were written with DECSCA protection
+    /// enabled are left untouched.
+    SelectiveEraseInDisplay(EraseInDisplay),
+
+    /// DECSEL - Selective Erase in Line.
+    /// Like EL, but cells that were written with DECSCA protection
+    /// enabled are left untouched.
+    SelectiveEraseInLine(EraseInLine),
+
+    /// DECSCA - Select Character Protection Attribute.
+    /// When true, subsequently printed characters are protected from
+    /// the selective erase operations.
+    /// <https://vt100.net/docs/vt510-rm/DECSCA.html>
+    SelectCharacterProtection(bool),
+
+    /// DECCRA - Copy Rectangular Area.
+    /// <https://vt100.net/docs/vt510-rm/DECCRA.html>
+    CopyRectangularArea {
+        src: RectangularArea,
+        src_page: OneBased,
+        dest_top: OneBased,
+        dest_left: OneBased,
+        dest_page: OneBased,
+    },
+
+    /// DECFRA - Fill Rectangular Area with the specified character,
+    /// using the current graphic rendition.
+    /// <https://vt100.net/docs/vt510-rm/DECFRA.html>
+    FillRectangularArea { ch: char, area: RectangularArea },
+
+    /// DECERA - Erase Rectangular Area.
+    /// <https://vt100.net/docs/vt510-rm/DECERA.html>
+    EraseRectangularArea(RectangularArea),
+
+    /// DECSERA - Selective Erase Rectangular Area.
+    /// Erases the unprotected cells in the area.
+    /// <https://vt100.net/docs/vt510-rm/DECSERA.html>
+    SelectiveEraseRectangularArea(RectangularArea),
+
+    /// DECCARA - Change Attributes in Rectangular Area.
+    /// Applies the SGR attributes to the cells in the area.
+    /// <https://vt100.net/docs/vt510-rm/DECCARA.html>
+    ChangeAttributesInRectangularArea {
+        area: RectangularArea,
+        attributes: Vec<Sgr>,
+    },
+
+    /// DECRARA - Reverse Attributes in Rectangular Area.
+    /// Toggles the SGR attributes in the cells in the area.
+    /// <https://vt100.net/docs/vt510-rm/DECRARA.html>
+    ReverseAttributesInRectangularArea {
+        area: RectangularArea,
+        attributes: Vec<Sgr>,
+    },
+
+    /// DECSACE - Select Attribute Change Extent.
+    /// Controls whether DECCARA and DECRARA apply to a rectangle
+    /// or to the stream of characters between the start and end positions.
+    /// <https://vt100.net/docs/vt510-rm/DECSACE.html>
+    SelectAttributeChangeExtent(AttributeChangeExtent),
+}
+
+/// Describes the rectangle passed to the DEC rectangular area operations.
+/// The coordinates are relative to the origin mode; when `bottom` or
+/// `right` are `None` they extend to the last line or column of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RectangularArea {
+    pub top: OneBased,
+    pub left: OneBased,
+    pub bottom: Option<OneBased>,
+    pub right: Option<OneBased>,
+}
+
+impl Display for RectangularArea {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(
+            f,
+            "{};{};{};{}",
+            self.top,
+            self.left,
+            self.bottom.map(|b| b.to_string()).unwrap_or_default(),
+            self.right.map(|r| r.to_string()).unwrap_or_default()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeChangeExtent {
+    /// The attributes change from the start position to the end
+    /// position, wrapping at the end of each line
+    Stream,
+    /// The attributes change only within the rectangle
+    Rectangle,
 }
 
 trait EncodeCSIParam {
@@ -1103,11 +1246,64 @@ impl Display for Edit {
             Edit::ScrollUp(n) => n.write_csi(f, "S")?,
             Edit::EraseInDisplay(n) => n.write_csi(f, "J")?,
             Edit::Repeat(n) => n.write_csi(f, "b")?,
+            Edit::SelectiveEraseInDisplay(n) => {
+                write!(f, "?")?;
+                n.write_csi(f, "J")?
+            }
+            Edit::SelectiveEraseInLine(n) => {
+                write!(f, "?")?;
+                n.write_csi(f, "K")?
+            }
+            Edit::SelectCharacterProtection(protect) => {
+                write!(f, "{}\"q", if *protect { 1 } else { 0 })?
+            }
+            Edit::CopyRectangularArea {
+                src,
+                src_page,
+                dest_top,
+                dest_left,
+                dest_page,
+            } => write!(
+                f,
+                "{};{};{};{};{}$v",
+                src, src_page, dest_top, dest_left, dest_page
+            )?,
+            Edit::FillRectangularArea { ch, area } => write!(f, "{};{}$x", *ch as u32, area)?,
+            Edit::EraseRectangularArea(area) => write!(f, "{}$z", area)?,
+            Edit::SelectiveEraseRectangularArea(area) => write!(f, "{}${{", area)?,
+            Edit::ChangeAttributesInRectangularArea { area, attributes } => {
+                write!(f, "{}", area)?;
+                write_sgr_params(f, attributes)?;
+                write!(f, "$r")?
+            }
+            Edit::ReverseAttributesInRectangularArea { area, attributes } => {
+                write!(f, "{}", area)?;
+                write_sgr_params(f, attributes)?;
+                write!(f, "$t")?
+            }
+            Edit::SelectAttributeChangeExtent(extent) => write!(
+                f,
+                "{}*x",
+                match extent {
+                    AttributeChangeExtent::Stream => 1,
+                    AttributeChangeExtent::Rectangle => 2,
+                }
+            )?,
         }
         Ok(())
     }
 }
 
+/// Writes a list of SGR attributes as `;` separated parameters,
+/// as used by DECCARA and DECRARA
+fn write_sgr_params(f: &mut Formatter, attributes: &[Sgr]) -> Result<(), FmtError> {
+    for sgr in attributes {
+        let sgr = sgr.to_string();
+        write!(f, ";{}", sgr.trim_end_matches('m'))?;
+    }
+    Ok(())
+}
+
 impl Display for Cursor {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
         match self {
@@ -1606,6 +1802,20 @@ impl<'a> CSIParser<'a> {
             ('k', [.., CsiParam::P(b' ')]) => self.select_character_path(params),
             ('q', [.., CsiParam::P(b' ')]) => self.cursor_style(params),
             ('y', [.., CsiParam::P(b'*')]) => self.checksum_area(params),
+            ('x', [.., CsiParam::P(b'*')]) => self.decsace(params),
+            ('q', [.., CsiParam::P(b'"')]) => self.decsca(params),
+            ('v', [.., CsiParam::P(b'$')]) => self.deccra(params),
+            ('x', [.., CsiParam::P(b'$')]) => self.decfra(params),
+            ('z', [.., CsiParam::P(b'$')]) => self
+                .rectangular_area(params)
+                .map(|area| CSI::Edit(Edit::EraseRectangularArea(area))),
+            ('{', [.., CsiParam::P(b'$')]) => self
+                .rectangular_area(params)
+                .map(|area| CSI::Edit(Edit::SelectiveEraseRectangularArea(area))),
+            ('r', [.., CsiParam::P(b'$')]) => self.change_attributes_in_area(params, false),
+            ('t', [.., CsiParam::P(b'$')]) => self.change_attributes_in_area(params, true),
+            ('J', [CsiParam::P(b'?'), ..]) => self.selective_erase(params),
+            ('K', [CsiParam::P(b'?'), ..]) => self.selective_erase(params),
 
             ('c', [CsiParam::P(b'='), ..]) => self
                 .req_tertiary_device_attributes(params)
@@ -1644,6 +1854,11 @@ impl<'a> CSIParser<'a> {
                 .map(|mode| CSI::Mode(Mode::SaveDecPrivateMode(mode))),
             ('m', [CsiParam::P(b'>'), ..]) => self.xterm_key_modifier(params),
 
+            ('u', [CsiParam::P(b'='), ..])
+            | ('u', [CsiParam::P(b'>'), ..])
+            | ('u', [CsiParam::P(b'<'), ..])
+            | ('u', [CsiParam::P(b'?'), ..]) => self.kitty_keyboard(params).map(CSI::Keyboard),
+
             ('p', [CsiParam::P(b'!')]) => Ok(CSI::Device(Box::new(Device::SoftReset))),
 
             _ => match self.control {
@@ -1789,6 +2004,135 @@ impl<'a> CSIParser<'a> {
         }))
     }
 
+    fn decsace(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let extent = match params {
+            [CsiParam::P(b'*')]
+            | [CsiParam::Integer(0), CsiParam::P(b'*')]
+            | [CsiParam::Integer(1), CsiParam::P(b'*')] => AttributeChangeExtent::Stream,
+            [CsiParam::Integer(2), CsiParam::P(b'*')] => AttributeChangeExtent::Rectangle,
+            _ => return Err(()),
+        };
+        Ok(CSI::Edit(Edit::SelectAttributeChangeExtent(extent)))
+    }
+
+    fn decsca(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let protect = match params {
+            [CsiParam::P(b'"')]
+            | [CsiParam::Integer(0), CsiParam::P(b'"')]
+            | [CsiParam::Integer(2), CsiParam::P(b'"')] => false,
+            [CsiParam::Integer(1), CsiParam::P(b'"')] => true,
+            _ => return Err(()),
+        };
+        Ok(CSI::Edit(Edit::SelectCharacterProtection(protect)))
+    }
+
+    fn selective_erase(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = &params[1..];
+        Ok(CSI::Edit(match self.control {
+            'J' => Edit::SelectiveEraseInDisplay(ParseParams::parse_params(params)?),
+            _ => Edit::SelectiveEraseInLine(ParseParams::parse_params(params)?),
+        }))
+    }
+
+    /// Parses the `Pt;Pl;Pb;Pr` rectangle that starts at `idx` in
+    /// the cracked parameters.  A 0 or missing bottom or right
+    /// edge selects the last line or column.
+    fn parse_area(params: &Cracked, idx: usize) -> Result<RectangularArea, ()> {
+        fn edge(p: Option<&CsiParam>) -> Result<Option<OneBased>, ()> {
+            match p {
+                None | Some(CsiParam::Integer(0)) => Ok(None),
+                Some(p) => OneBased::from_esc_param(p).map(Some),
+            }
+        }
+        Ok(RectangularArea {
+            top: OneBased::from_optional_esc_param(params.get(idx))?,
+            left: OneBased::from_optional_esc_param(params.get(idx + 1))?,
+            bottom: edge(params.get(idx + 2))?,
+            right: edge(params.get(idx + 3))?,
+        })
+    }
+
+    fn rectangular_area(&mut self, params: &'a [CsiParam]) -> Result<RectangularArea, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+        if params.len() > 4 {
+            return Err(());
+        }
+        Self::parse_area(&params, 0)
+    }
+
+    fn deccra(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+        if params.len() > 8 {
+            return Err(());
+        }
+        Ok(CSI::Edit(Edit::CopyRectangularArea {
+            src: Self::parse_area(&params, 0)?,
+            src_page: OneBased::from_optional_esc_param(params.get(4))?,
+            dest_top: OneBased::from_optional_esc_param(params.get(5))?,
+            dest_left: OneBased::from_optional_esc_param(params.get(6))?,
+            dest_page: OneBased::from_optional_esc_param(params.get(7))?,
+        }))
+    }
+
+    fn decfra(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+        if params.len() > 5 {
+            return Err(());
+        }
+        let ch = params
+            .int(0)?
+            .to_u32()
+            .and_then(std::char::from_u32)
+            .ok_or(())?;
+        Ok(CSI::Edit(Edit::FillRectangularArea {
+            ch,
+            area: Self::parse_area(&params, 1)?,
+        }))
+    }
+
+    fn change_attributes_in_area(
+        &mut self,
+        params: &'a [CsiParam],
+        reverse: bool,
+    ) -> Result<CSI, ()> {
+        let params = &params[..params.len() - 1];
+
+        // The first four parameters describe the area; the remainder
+        // are SGR attributes, which may include `:` separated colors.
+        let mut area_len = params.len();
+        let mut separators = 0;
+        for (idx, p) in params.iter().enumerate() {
+            if let CsiParam::P(b';') = p {
+                separators += 1;
+                if separators == 4 {
+                    area_len = idx;
+                    break;
+                }
+            }
+        }
+        let (area, attributes) = params.split_at(area_len);
+        let area = Self::parse_area(&Cracked::parse(area)?, 0)?;
+
+        let attributes = if attributes.len() > 1 {
+            let mut sgrs = vec![];
+            for csi in CSI::parse(&attributes[1..], false, 'm') {
+                match csi {
+                    CSI::Sgr(sgr) => sgrs.push(sgr),
+                    _ => return Err(()),
+                }
+            }
+            sgrs
+        } else {
+            vec![Sgr::Reset]
+        };
+
+        Ok(CSI::Edit(if reverse {
+            Edit::ReverseAttributesInRectangularArea { area, attributes }
+        } else {
+            Edit::ChangeAttributesInRectangularArea { area, attributes }
+        }))
+    }
+
     fn dsr(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
         match params {
             [CsiParam::Integer(5)] => {
@@ -1866,6 +2210,35 @@ impl<'a> CSIParser<'a> {
         }
     }
 
+    fn kitty_keyboard(&mut self, params: &'a [CsiParam]) -> Result<Keyboard, ()> {
+        fn flags(p: &CsiParam) -> Result<KittyKeyboardFlags, ()> {
+            let bits = p.as_integer().ok_or(())?;
+            KittyKeyboardFlags::from_bits(u16::from_i64(bits).ok_or(())?).ok_or(())
+        }
+
+        match params {
+            [CsiParam::P(b'?')] => Ok(Keyboard::QueryKittySupport),
+            [CsiParam::P(b'?'), p] => Ok(Keyboard::ReportKittyState(flags(p)?)),
+            [CsiParam::P(b'>')] => Ok(Keyboard::PushKittyState(KittyKeyboardFlags::NONE)),
+            [CsiParam::P(b'>'), p] => Ok(Keyboard::PushKittyState(flags(p)?)),
+            [CsiParam::P(b'<')] => Ok(Keyboard::PopKittyState(1)),
+            [CsiParam::P(b'<'), CsiParam::Integer(n)] => {
+                Ok(Keyboard::PopKittyState(u32::from_i64(*n).ok_or(())?))
+            }
+            [CsiParam::P(b'='), p] => Ok(Keyboard::SetKittyState {
+                flags: flags(p)?,
+                mode: KittyKeyboardMode::AssignAll,
+            }),
+            [CsiParam::P(b'='), p, CsiParam::P(b';'), CsiParam::Integer(mode)] => {
+                Ok(Keyboard::SetKittyState {
+                    flags: flags(p)?,
+                    mode: FromPrimitive::from_i64(*mode).ok_or(())?,
+                })
+            }
+            _ => Err(()),
+        }
+    }
+
     fn decslrm(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
         match params {
             [] => {
@@ -2703,6 +3076,135 @@ mod test {
         );
     }
 
+    fn parse_int(control: char, params: &[i64], intermediate: u8, expected: &str) -> Vec<CSI> {
+        let mut cparams = vec![];
+        for &p in params {
+            if !cparams.is_empty() {
+                cparams.push(CsiParam::P(b';'));
+            }
+            cparams.push(CsiParam::Integer(p));
+        }
+        cparams.push(CsiParam::P(intermediate));
+        let res = CSI::parse(&cparams, false, control).collect();
+        println!("parsed -> {:#?}", res);
+        assert_eq!(encode(&res), expected);
+        res
+    }
+
+    #[test]
+    fn rectangular_area() {
+        let area = RectangularArea {
+            top: OneBased::new(2),
+            left: OneBased::new(3),
+            bottom: Some(OneBased::new(4)),
+            right: Some(OneBased::new(5)),
+        };
+
+        assert_eq!(
+            parse_int('x', &[65, 2, 3, 4, 5], b'$', "\x1b[65;2;3;4;5$x"),
+            vec![CSI::Edit(Edit::FillRectangularArea { ch: 'A', area })]
+        );
+        assert_eq!(
+            parse_int('z', &[2, 3, 4, 5], b'$', "\x1b[2;3;4;5$z"),
+            vec![CSI::Edit(Edit::EraseRectangularArea(area))]
+        );
+        assert_eq!(
+            parse_int('{', &[2, 3, 4, 5], b'$', "\x1b[2;3;4;5${"),
+            vec![CSI::Edit(Edit::SelectiveEraseRectangularArea(area))]
+        );
+        assert_eq!(
+            parse_int('z', &[], b'$', "\x1b[1;1;;$z"),
+            vec![CSI::Edit(Edit::EraseRectangularArea(RectangularArea {
+                top: OneBased::new(1),
+                left: OneBased::new(1),
+                bottom: None,
+                right: None,
+            }))]
+        );
+        assert_eq!(
+            parse_int(
+                'v',
+                &[2, 3, 4, 5, 1, 7, 8, 1],
+                b'$',
+                "\x1b[2;3;4;5;1;7;8;1$v"
+            ),
+            vec![CSI::Edit(Edit::CopyRectangularArea {
+                src: area,
+                src_page: OneBased::new(1),
+                dest_top: OneBased::new(7),
+                dest_left: OneBased::new(8),
+                dest_page: OneBased::new(1),
+            })]
+        );
+        assert_eq!(
+            parse_int('r', &[2, 3, 4, 5, 1, 4], b'$', "\x1b[2;3;4;5;1;4$r"),
+            vec![CSI::Edit(Edit::ChangeAttributesInRectangularArea {
+                area,
+                attributes: vec![
+                    Sgr::Intensity(Intensity::Bold),
+                    Sgr::Underline(Underline::Single)
+                ],
+            })]
+        );
+        assert_eq!(
+            parse_int('t', &[2, 3, 4, 5, 7], b'$', "\x1b[2;3;4;5;7$t"),
+            vec![CSI::Edit(Edit::ReverseAttributesInRectangularArea {
+                area,
+                attributes: vec![Sgr::Inverse(true)],
+            })]
+        );
+        assert_eq!(
+            parse_int('t', &[2, 3, 4, 5], b'$', "\x1b[2;3;4;5;0$t"),
+            vec![CSI::Edit(Edit::ReverseAttributesInRectangularArea {
+                area,
+                attributes: vec![Sgr::Reset],
+            })]
+        );
+        assert_eq!(
+            parse_int('x', &[2], b'*', "\x1b[2*x"),
+            vec![CSI::Edit(Edit::SelectAttributeChangeExtent(
+                AttributeChangeExtent::Rectangle
+            ))]
+        );
+        assert_eq!(
+            parse_int('x', &[], b'*', "\x1b[1*x"),
+            vec![CSI::Edit(Edit::SelectAttributeChangeExtent(
+                AttributeChangeExtent::Stream
+            ))]
+        );
+    }
+
+    #[test]
+    fn selective_erase() {
+        assert_eq!(
+            parse_int('q', &[1], b'"', "\x1b[1\"q"),
+            vec![CSI::Edit(Edit::SelectCharacterProtection(true))]
+        );
+        assert_eq!(
+            parse_int('q', &[], b'"', "\x1b[0\"q"),
+            vec![CSI::Edit(Edit::SelectCharacterProtection(false))]
+        );
+
+        let res: Vec<_> =
+            CSI::parse(&[CsiParam::P(b'?'), CsiParam::Integer(2)], false, 'J').collect();
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::SelectiveEraseInDisplay(
+                EraseInDisplay::EraseDisplay
+            ))]
+        );
+        assert_eq!(encode(&res), "\x1b[?2J");
+
+        let res: Vec<_> = CSI::parse(&[CsiParam::P(b'?')], false, 'K').collect();
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::SelectiveEraseInLine(
+                EraseInLine::EraseToEndOfLine
+            ))]
+        );
+        assert_eq!(encode(&res), "\x1b[?K");
+    }
+
     #[test]
     fn window() {
         assert_eq!(
@@ -2815,6 +3317,62 @@ mod test {
         assert_eq!(res, vec![CSI::Device(Box::new(Device::SoftReset))],);
     }
 
+    #[test]
+    fn kitty_keyboard() {
+        let res: Vec<_> = CSI::parse(&[CsiParam::P(b'?')], false, 'u').collect();
+        assert_eq!(res, vec![CSI::Keyboard(Keyboard::QueryKittySupport)]);
+        assert_eq!(encode(&res), "\x1b[?u");
+
+        let res: Vec<_> =
+            CSI::parse(&[CsiParam::P(b'?'), CsiParam::Integer(3)], false, 'u').collect();
+        assert_eq!(
+            res,
+            vec![CSI::Keyboard(Keyboard::ReportKittyState(
+                KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KittyKeyboardFlags::REPORT_EVENT_TYPES
+            ))]
+        );
+        assert_eq!(encode(&res), "\x1b[?3u");
+
+        let res: Vec<_> =
+            CSI::parse(&[CsiParam::P(b'>'), CsiParam::Integer(1)], false, 'u').collect();
+        assert_eq!(
+            res,
+            vec![CSI::Keyboard(Keyboard::PushKittyState(
+                KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+            ))]
+        );
+        assert_eq!(encode(&res), "\x1b[>1u");
+
+        let res: Vec<_> = CSI::parse(&[CsiParam::P(b'<')], false, 'u').collect();
+        assert_eq!(res, vec![CSI::Keyboard(Keyboard::PopKittyState(1))]);
+        assert_eq!(encode(&res), "\x1b[<1u");
+
+        let res: Vec<_> = CSI::parse(
+            &[
+                CsiParam::P(b'='),
+                CsiParam::Integer(8),
+                CsiParam::P(b';'),
+                CsiParam::Integer(2),
+            ],
+            false,
+            'u',
+        )
+        .collect();
+        assert_eq!(
+            res,
+            vec![CSI::Keyboard(Keyboard::SetKittyState {
+                flags: KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES,
+                mode: KittyKeyboardMode::SetSpecified,
+            })]
+        );
+        assert_eq!(encode(&res), "\x1b[=8;2u");
+
+        // Plain CSI u is still SCORC
+        let res: Vec<_> = CSI::parse(&[], false, 'u').collect();
+        assert_eq!(res, vec![CSI::Cursor(Cursor::RestoreCursor)]);
+    }
+
     #[test]
     fn device_attr() {
         let res: Vec<_> = CSI::parse(
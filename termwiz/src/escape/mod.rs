@@ -9,7 +9,6 @@
 use crate::tmux_cc::Event;
 use num_derive::*;
 use std::fmt::{Display, Error as FmtError, Formatter, Write as FmtWrite};
-use wezterm_color_types::LinearRgba;
 
 pub mod apc;
 pub mod csi;
@@ -119,13 +118,25 @@ impl std::fmt::Debug for ShortDeviceControl {
 impl Display for ShortDeviceControl {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
         write!(f, "\x1bP")?;
+        // Private markers such as `=` precede the parameters
+        for b in self
+            .intermediates
+            .iter()
+            .filter(|b| (0x3c..=0x3f).contains(*b))
+        {
+            f.write_char(*b as char)?;
+        }
         for (idx, p) in self.params.iter().enumerate() {
             if idx > 0 {
                 write!(f, ";")?;
             }
             write!(f, "{}", p)?;
         }
-        for b in &self.intermediates {
+        for b in self
+            .intermediates
+            .iter()
+            .filter(|b| !(0x3c..=0x3f).contains(*b))
+        {
             f.write_char(*b as char)?;
         }
         f.write_char(self.byte as char)?;
@@ -353,7 +364,7 @@ pub enum SixelData {
     Repeat { repeat_count: u32, data: SixelValue },
 
     /// Set the specified color map entry to the specified
-    /// linear RGB color value
+    /// RGB color value
     DefineColorMapRGB {
         color_number: u16,
         rgb: crate::color::RgbColor,
@@ -389,14 +400,15 @@ impl Display for SixelData {
                 write!(f, "!{}{}", repeat_count, (data + 0x3f) as char)
             }
             Self::DefineColorMapRGB { color_number, rgb } => {
-                let LinearRgba(r, g, b, _) = rgb.to_linear_tuple_rgba();
+                let (r, g, b) = rgb.to_tuple_rgb8();
+                let percent = |c: u8| (c as u32 * 100 + 127) / 255;
                 write!(
                     f,
                     "#{};2;{};{};{}",
                     color_number,
-                    (r * 100.) as u8,
-                    (g * 100.) as u8,
-                    (b * 100.0) as u8
+                    percent(r),
+                    percent(g),
+                    percent(b)
                 )
             }
             Self::DefineColorMapHSL {
@@ -191,6 +191,9 @@ fn is_short_dcs(intermediates: &[u8], byte: u8) -> bool {
     if intermediates == &[b'$'] && byte == b'q' {
         // DECRQSS
         true
+    } else if intermediates == b"=" && byte == b's' {
+        // Begin/End Synchronized Update
+        true
     } else {
         false
     }
@@ -569,6 +572,24 @@ mod test {
         );
     }
 
+    #[test]
+    fn synchronized_update_dcs() {
+        let mut p = Parser::new();
+        let actions = p.parse_as_vec(b"\x1bP=1s\x1b\\");
+        assert_eq!(
+            actions[0],
+            Action::DeviceControl(DeviceControlMode::ShortDeviceControl(Box::new(
+                ShortDeviceControl {
+                    params: vec![1],
+                    intermediates: vec![b'='],
+                    byte: b's',
+                    data: vec![],
+                }
+            )))
+        );
+        assert_eq!(actions[0].to_string(), "\x1bP=1s\x1b\\");
+    }
+
     #[test]
     fn bidi_modes() {
         assert_eq!(
@@ -22,6 +22,33 @@ pub enum KeyboardEncoding {
     CsiU,
     /// <https://github.com/microsoft/terminal/blob/main/doc/specs/%234999%20-%20Improved%20keyboard%20handling%20in%20Conpty.md>
     Win32,
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+    Kitty(KittyKeyboardFlags),
+}
+
+bitflags! {
+    /// The progressive enhancement flags that an application can
+    /// request when using the kitty keyboard protocol.
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/#progressive-enhancement>
+    #[derive(Default)]
+    pub struct KittyKeyboardFlags: u16 {
+        const NONE = 0;
+        const DISAMBIGUATE_ESCAPE_CODES = 1;
+        const REPORT_EVENT_TYPES = 2;
+        const REPORT_ALTERNATE_KEYS = 4;
+        const REPORT_ALL_KEYS_AS_ESCAPE_CODES = 8;
+        const REPORT_ASSOCIATED_TEXT = 16;
+    }
+}
+
+/// Describes the kind of key event that is being encoded.
+/// Only the kitty keyboard protocol is able to represent
+/// repeat and release events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEventKind {
+    Press,
+    Repeat,
+    Release,
 }
 
 /// Specifies terminal modes/configuration that can influence how a KeyCode
@@ -260,6 +287,15 @@ impl KeyCode {
         modes: KeyCodeEncodeModes,
         is_down: bool,
     ) -> Result<String> {
+        if let KeyboardEncoding::Kitty(flags) = modes.encoding {
+            let kind = if is_down {
+                KeyEventKind::Press
+            } else {
+                KeyEventKind::Release
+            };
+            return self.encode_kitty(mods, modes, flags, kind);
+        }
+
         if !is_down {
             return Ok(String::new());
         }
@@ -272,6 +308,10 @@ impl KeyCode {
     pub fn encode(&self, mods: Modifiers, modes: KeyCodeEncodeModes) -> Result<String> {
         use KeyCode::*;
 
+        if let KeyboardEncoding::Kitty(flags) = modes.encoding {
+            return self.encode_kitty(mods, modes, flags, KeyEventKind::Press);
+        }
+
         let key = self.normalize_shift_to_upper_case(mods);
         // Normalize the modifier state for Char's that are uppercase; remove
         // the SHIFT modifier so that reduce ambiguity below
@@ -503,6 +543,256 @@ impl KeyCode {
 
         Ok(buf)
     }
+
+    /// Returns the kitty keyboard protocol key number and CSI final
+    /// character for keys that don't map to a unicode codepoint.
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/#functional-key-definitions>
+    fn kitty_functional_key(self) -> Option<(u32, char)> {
+        use KeyCode::*;
+        Some(match self {
+            Escape => (27, 'u'),
+            Enter => (13, 'u'),
+            Tab => (9, 'u'),
+            Backspace => (127, 'u'),
+            Insert => (2, '~'),
+            Delete => (3, '~'),
+            LeftArrow | ApplicationLeftArrow => (1, 'D'),
+            RightArrow | ApplicationRightArrow => (1, 'C'),
+            UpArrow | ApplicationUpArrow => (1, 'A'),
+            DownArrow | ApplicationDownArrow => (1, 'B'),
+            PageUp => (5, '~'),
+            PageDown => (6, '~'),
+            Home => (1, 'H'),
+            End => (1, 'F'),
+            CapsLock => (57358, 'u'),
+            ScrollLock => (57359, 'u'),
+            NumLock => (57360, 'u'),
+            PrintScreen => (57361, 'u'),
+            Pause => (57362, 'u'),
+            Applications => (57363, 'u'),
+            Function(1) => (1, 'P'),
+            Function(2) => (1, 'Q'),
+            Function(3) => (13, '~'),
+            Function(4) => (1, 'S'),
+            Function(5) => (15, '~'),
+            Function(6) => (17, '~'),
+            Function(7) => (18, '~'),
+            Function(8) => (19, '~'),
+            Function(9) => (20, '~'),
+            Function(10) => (21, '~'),
+            Function(11) => (23, '~'),
+            Function(12) => (24, '~'),
+            Function(n) if (13..=35).contains(&n) => (57376 + u32::from(n - 13), 'u'),
+            Numpad0 => (57399, 'u'),
+            Numpad1 => (57400, 'u'),
+            Numpad2 => (57401, 'u'),
+            Numpad3 => (57402, 'u'),
+            Numpad4 => (57403, 'u'),
+            Numpad5 => (57404, 'u'),
+            Numpad6 => (57405, 'u'),
+            Numpad7 => (57406, 'u'),
+            Numpad8 => (57407, 'u'),
+            Numpad9 => (57408, 'u'),
+            Decimal => (57409, 'u'),
+            Divide => (57410, 'u'),
+            Multiply => (57411, 'u'),
+            Subtract => (57412, 'u'),
+            Add => (57413, 'u'),
+            Separator => (57416, 'u'),
+            MediaPlayPause => (57430, 'u'),
+            MediaStop => (57432, 'u'),
+            MediaNextTrack => (57435, 'u'),
+            MediaPrevTrack => (57436, 'u'),
+            VolumeDown => (57438, 'u'),
+            VolumeUp => (57439, 'u'),
+            VolumeMute => (57440, 'u'),
+            Shift | LeftShift => (57441, 'u'),
+            Control | LeftControl => (57442, 'u'),
+            Alt | LeftAlt | Menu | LeftMenu => (57443, 'u'),
+            Super | LeftWindows => (57444, 'u'),
+            Hyper => (57445, 'u'),
+            Meta => (57446, 'u'),
+            RightShift => (57447, 'u'),
+            RightControl => (57448, 'u'),
+            RightAlt | RightMenu => (57449, 'u'),
+            RightWindows => (57450, 'u'),
+            _ => return None,
+        })
+    }
+
+    /// Returns the byte sequence that represents this KeyCode and
+    /// Modifier combination using the kitty keyboard protocol with
+    /// the specified progressive enhancement `flags`.
+    /// Keys that the protocol leaves unchanged are encoded using
+    /// the legacy xterm compatible encoding.
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+    pub fn encode_kitty(
+        &self,
+        mods: Modifiers,
+        modes: KeyCodeEncodeModes,
+        flags: KittyKeyboardFlags,
+        kind: KeyEventKind,
+    ) -> Result<String> {
+        use KeyCode::*;
+
+        let report_events = flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES);
+        let report_all = flags.contains(KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES);
+        let disambiguate =
+            report_all || flags.contains(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES);
+
+        let kind = match kind {
+            KeyEventKind::Release if !report_events => return Ok(String::new()),
+            KeyEventKind::Repeat if !report_events => KeyEventKind::Press,
+            kind => kind,
+        };
+        let is_press = kind != KeyEventKind::Release;
+
+        let legacy_modes = KeyCodeEncodeModes {
+            encoding: KeyboardEncoding::Xterm,
+            ..modes
+        };
+
+        if is_press && !disambiguate {
+            return self.encode(mods, legacy_modes);
+        }
+
+        let key = match self.normalize_shift_to_upper_case(mods) {
+            Char('\x7f') => Delete,
+            Char('\x08') => Backspace,
+            Char('\r') | Char('\n') => Enter,
+            Char('\t') => Tab,
+            Char('\x1b') => Escape,
+            key => key,
+        };
+
+        // Modifiers other than SHIFT prevent a key from generating text
+        let text_mods = mods & (Modifiers::ALT | Modifiers::CTRL | Modifiers::SUPER);
+        let mut buf = String::new();
+
+        match key {
+            Char(c) => {
+                if is_press && text_mods.is_empty() && !report_all {
+                    return self.encode(mods, legacy_modes);
+                }
+
+                let mut lower = c.to_lowercase();
+                let base = match (lower.next(), lower.next()) {
+                    (Some(base), None) => base,
+                    _ => c,
+                };
+                let shifted = if flags.contains(KittyKeyboardFlags::REPORT_ALTERNATE_KEYS)
+                    && mods.contains(Modifiers::SHIFT)
+                    && base != c
+                {
+                    Some(c)
+                } else {
+                    None
+                };
+                let text = if is_press
+                    && text_mods.is_empty()
+                    && flags.contains(KittyKeyboardFlags::REPORT_ASSOCIATED_TEXT)
+                {
+                    Some(c)
+                } else {
+                    None
+                };
+
+                kitty_csi_encode(&mut buf, base as u32, shifted, mods, kind, text, 'u')?;
+            }
+
+            Enter | Tab | Backspace if mods.is_empty() && !report_all => {
+                if is_press {
+                    return self.encode(mods, legacy_modes);
+                }
+            }
+
+            Home
+            | End
+            | UpArrow
+            | DownArrow
+            | RightArrow
+            | LeftArrow
+            | ApplicationUpArrow
+            | ApplicationDownArrow
+            | ApplicationRightArrow
+            | ApplicationLeftArrow
+            | PageUp
+            | PageDown
+            | Insert
+            | Delete
+            | Function(1..=12)
+                if is_press && mods.is_empty() && !report_all =>
+            {
+                return self.encode(mods, legacy_modes);
+            }
+
+            Control | LeftControl | RightControl | Alt | LeftAlt | RightAlt | Menu | LeftMenu
+            | RightMenu | Super | Hyper | Shift | LeftShift | RightShift | Meta | LeftWindows
+            | RightWindows | NumLock | ScrollLock | CapsLock
+                if !report_all => {}
+
+            _ => match key.kitty_functional_key() {
+                Some((number, final_byte)) => {
+                    kitty_csi_encode(&mut buf, number, None, mods, kind, None, final_byte)?;
+                }
+                None => {
+                    if is_press {
+                        return self.encode(mods, legacy_modes);
+                    }
+                }
+            },
+        }
+
+        Ok(buf)
+    }
+}
+
+/// Emits a kitty keyboard protocol CSI sequence of the form
+/// `CSI number:shifted ; modifiers:event ; text final`,
+/// omitting trailing fields that hold default values.
+fn kitty_csi_encode(
+    buf: &mut String,
+    number: u32,
+    shifted: Option<char>,
+    mods: Modifiers,
+    kind: KeyEventKind,
+    text: Option<char>,
+    final_byte: char,
+) -> Result<()> {
+    let mut key_field = number.to_string();
+    if let Some(shifted) = shifted {
+        write!(key_field, ":{}", shifted as u32)?;
+    }
+
+    let encoded_mods = 1 + encode_kitty_modifiers(mods);
+    let mut mods_field = String::new();
+    if encoded_mods != 1 || kind != KeyEventKind::Press {
+        write!(mods_field, "{}", encoded_mods)?;
+        match kind {
+            KeyEventKind::Press => {}
+            KeyEventKind::Repeat => mods_field.push_str(":2"),
+            KeyEventKind::Release => mods_field.push_str(":3"),
+        }
+    }
+
+    buf.push_str(CSI);
+    if let Some(text) = text {
+        write!(buf, "{};{};{}", key_field, mods_field, text as u32)?;
+    } else if !mods_field.is_empty() {
+        write!(buf, "{};{}", key_field, mods_field)?;
+    } else if final_byte == 'u' || final_byte == '~' || number != 1 || shifted.is_some() {
+        buf.push_str(&key_field);
+    }
+    buf.push(final_byte);
+    Ok(())
+}
+
+fn encode_kitty_modifiers(mods: Modifiers) -> u8 {
+    let mut number = encode_modifiers(mods);
+    if mods.contains(Modifiers::SUPER) {
+        number |= 8;
+    }
+    number
 }
 
 fn encode_modifiers(mods: Modifiers) -> u8 {
@@ -1593,4 +1883,106 @@ mod test {
             "\x1bOP".to_string()
         );
     }
+
+    #[test]
+    fn encode_kitty() {
+        let mode = KeyCodeEncodeModes {
+            encoding: KeyboardEncoding::Kitty(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES),
+            newline_mode: false,
+            application_cursor_keys: false,
+        };
+
+        let press = |key: KeyCode, mods: Modifiers| key.encode_up_down(mods, mode, true).unwrap();
+        let release =
+            |key: KeyCode, mods: Modifiers| key.encode_up_down(mods, mode, false).unwrap();
+
+        assert_eq!(press(KeyCode::Char('a'), Modifiers::NONE), "a");
+        assert_eq!(press(KeyCode::Char('a'), Modifiers::SHIFT), "A");
+        assert_eq!(press(KeyCode::Char('i'), Modifiers::CTRL), "\x1b[105;5u");
+        assert_eq!(press(KeyCode::Tab, Modifiers::NONE), "\t");
+        assert_eq!(press(KeyCode::Tab, Modifiers::SHIFT), "\x1b[9;2u");
+        assert_eq!(press(KeyCode::Escape, Modifiers::NONE), "\x1b[27u");
+        assert_eq!(press(KeyCode::Enter, Modifiers::NONE), "\r");
+        assert_eq!(
+            press(KeyCode::Char('a'), Modifiers::CTRL | Modifiers::SHIFT),
+            "\x1b[97;6u"
+        );
+        assert_eq!(press(KeyCode::Char('x'), Modifiers::SUPER), "\x1b[120;9u");
+        assert_eq!(press(KeyCode::UpArrow, Modifiers::NONE), "\x1b[A");
+        assert_eq!(press(KeyCode::UpArrow, Modifiers::CTRL), "\x1b[1;5A");
+        assert_eq!(press(KeyCode::Function(3), Modifiers::NONE), "\x1bOR");
+        assert_eq!(press(KeyCode::Function(3), Modifiers::ALT), "\x1b[13;3~");
+        assert_eq!(press(KeyCode::Numpad5, Modifiers::NONE), "\x1b[57404u");
+        assert_eq!(press(KeyCode::LeftShift, Modifiers::SHIFT), "");
+        assert_eq!(release(KeyCode::Char('a'), Modifiers::NONE), "");
+
+        let mode = KeyCodeEncodeModes {
+            encoding: KeyboardEncoding::Kitty(
+                KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KittyKeyboardFlags::REPORT_EVENT_TYPES,
+            ),
+            ..mode
+        };
+        let flags = match mode.encoding {
+            KeyboardEncoding::Kitty(flags) => flags,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            KeyCode::Char('a')
+                .encode_kitty(Modifiers::NONE, mode, flags, KeyEventKind::Repeat)
+                .unwrap(),
+            "a"
+        );
+        assert_eq!(
+            KeyCode::Char('a').encode_up_down(Modifiers::NONE, mode, false).unwrap(),
+            "\x1b[97;1:3u"
+        );
+        assert_eq!(
+            KeyCode::Char('i')
+                .encode_kitty(Modifiers::CTRL, mode, flags, KeyEventKind::Repeat)
+                .unwrap(),
+            "\x1b[105;5:2u"
+        );
+        assert_eq!(
+            KeyCode::LeftArrow.encode_up_down(Modifiers::NONE, mode, false).unwrap(),
+            "\x1b[1;1:3D"
+        );
+        assert_eq!(
+            KeyCode::Enter.encode_up_down(Modifiers::NONE, mode, false).unwrap(),
+            ""
+        );
+
+        let flags = KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+            | KittyKeyboardFlags::REPORT_ALTERNATE_KEYS
+            | KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+            | KittyKeyboardFlags::REPORT_ASSOCIATED_TEXT;
+        let mode = KeyCodeEncodeModes {
+            encoding: KeyboardEncoding::Kitty(flags),
+            ..mode
+        };
+        assert_eq!(
+            KeyCode::Char('a').encode(Modifiers::NONE, mode).unwrap(),
+            "\x1b[97;;97u"
+        );
+        assert_eq!(
+            KeyCode::Char('a').encode(Modifiers::SHIFT, mode).unwrap(),
+            "\x1b[97:65;2;65u"
+        );
+        assert_eq!(
+            KeyCode::Char('a').encode(Modifiers::CTRL, mode).unwrap(),
+            "\x1b[97;5u"
+        );
+        assert_eq!(
+            KeyCode::Enter.encode(Modifiers::NONE, mode).unwrap(),
+            "\x1b[13u"
+        );
+        assert_eq!(
+            KeyCode::LeftShift.encode(Modifiers::SHIFT, mode).unwrap(),
+            "\x1b[57441;2u"
+        );
+        assert_eq!(
+            KeyCode::UpArrow.encode(Modifiers::NONE, mode).unwrap(),
+            "\x1b[A"
+        );
+    }
 }
@@ -1,6 +1,6 @@
 pub type RepeatCount = usize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Movement {
     BackwardChar(RepeatCount),
     BackwardWord(RepeatCount),
@@ -9,9 +9,103 @@ pub enum Movement {
     StartOfLine,
     EndOfLine,
     None,
+    /// Move to the last character of the whitespace delimited word,
+    /// skipping to the next word if already there (vi `E`)
+    ForwardWordEnd(RepeatCount),
+    /// Move to the start of the next word, where runs of word
+    /// characters and runs of punctuation are distinct words (vi `w`)
+    ForwardViWord(RepeatCount),
+    /// Move to the start of the previous word, where runs of word
+    /// characters and runs of punctuation are distinct words (vi `b`)
+    BackwardViWord(RepeatCount),
+    /// Move to the last character of the word, where runs of word
+    /// characters and runs of punctuation are distinct words (vi `e`)
+    ForwardViWordEnd(RepeatCount),
+    /// Move to the first non-blank character of the line (vi `^`)
+    FirstNonBlank,
+    /// Move onto the Nth occurrence of the character to the right
+    /// of the cursor within the current line (vi `f`)
+    ForwardFind(RepeatCount, char),
+    /// Move to just before the Nth occurrence of the character to the
+    /// right of the cursor within the current line (vi `t`)
+    ForwardTill(RepeatCount, char),
+    /// Move onto the Nth occurrence of the character to the left
+    /// of the cursor within the current line (vi `F`)
+    BackwardFind(RepeatCount, char),
+    /// Move to just after the Nth occurrence of the character to the
+    /// left of the cursor within the current line (vi `T`)
+    BackwardTill(RepeatCount, char),
+    /// Move to the same column in a prior line of a multi-line buffer
+    PreviousLine(RepeatCount),
+    /// Move to the same column in a subsequent line of a multi-line buffer
+    NextLine(RepeatCount),
 }
 
-#[derive(Debug, Clone)]
+impl Movement {
+    /// Returns true if the character at the destination of the movement
+    /// is included in the text that is affected when the movement is
+    /// used as the extent of a `Kill` or `Copy`.
+    /// This is the case for the vi motions that land on the last
+    /// character of their target, such as `e` and `f`.
+    pub fn is_inclusive(&self) -> bool {
+        matches!(
+            self,
+            Movement::ForwardWordEnd(_)
+                | Movement::ForwardViWordEnd(_)
+                | Movement::ForwardFind(..)
+                | Movement::ForwardTill(..)
+        )
+    }
+}
+
+/// A region of text that is defined in terms of its content around
+/// the cursor, rather than as a movement from the cursor.
+/// These are used to implement the vi text objects such as `iw` and `a"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObject {
+    /// The word under the cursor, where runs of word characters and
+    /// runs of punctuation are distinct words.  If `around` is true,
+    /// the trailing (or, failing that, leading) whitespace is included.
+    Word { around: bool },
+    /// The whitespace delimited word under the cursor
+    BigWord { around: bool },
+    /// The text between a pair of `quote` characters on the current line.
+    /// If `around` is true, the quotes themselves are included.
+    Quoted { quote: char, around: bool },
+    /// The text between the innermost pair of `open` and `close`
+    /// characters that enclose the cursor.  If `around` is true,
+    /// the brackets themselves are included.
+    Bracketed {
+        open: char,
+        close: char,
+        around: bool,
+    },
+    /// The current line of a multi-line buffer, including its
+    /// line break
+    Line,
+}
+
+/// Changes the selected candidate in the completion menu.
+/// The candidates are laid out in rows, from left to right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionMovement {
+    /// Select the next candidate, wrapping around to the first
+    Next,
+    /// Select the previous candidate, wrapping around to the last
+    Previous,
+    /// Select the candidate in the prior row of the same column
+    Up,
+    /// Select the candidate in the next row of the same column
+    Down,
+    Left,
+    Right,
+    /// Select the candidate one page further on in the menu
+    NextPage,
+    /// Select the candidate one page back in the menu
+    PreviousPage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Action {
     AcceptLine,
     Cancel,
@@ -28,4 +122,31 @@ pub enum Action {
     NoAction,
     HistoryIncSearchBackwards,
     HistoryIncSearchForwards,
+    /// Revert the most recent change to the line
+    Undo,
+    /// Re-apply the most recently undone change
+    Redo,
+    /// Insert the most recently killed text at the cursor
+    Yank(RepeatCount),
+    /// Immediately after a `Yank` or `YankPop`, replace the yanked
+    /// text with the prior entry from the kill ring
+    YankPop,
+    /// Save the text covered by the movement to the kill ring
+    /// without removing it from the line
+    Copy(Movement),
+    /// Remove the text covered by the text object and save it to
+    /// the kill ring
+    KillTextObject(TextObject),
+    /// Save the text covered by the text object to the kill ring
+    CopyTextObject(TextObject),
+    /// Replace the characters under and to the right of the cursor
+    /// with the specified character
+    ReplaceChar(RepeatCount, char),
+    /// Change the selected candidate in the completion menu
+    SelectCompletion(CompletionMovement),
+    /// Keep the selected candidate and close the completion menu
+    AcceptCompletion,
+    /// Restore the line to its state prior to completion and
+    /// close the completion menu
+    CancelCompletion,
 }
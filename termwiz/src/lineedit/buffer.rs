@@ -0,0 +1,450 @@
+//! The line buffer that is shared by the `LineEditor` and the
+//! `TextInput` widget, and the logic for moving around within it
+use super::{char_class, CharClass, Movement, RepeatCount, TextObject};
+use std::ops::Range;
+use unicode_segmentation::GraphemeCursor;
+
+/// Holds the text being edited and the position of the cursor
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LineBuffer {
+    pub(crate) line: String,
+    /// byte index into the UTF-8 string data of the insertion
+    /// point.  This is NOT the number of graphemes!
+    pub(crate) cursor: usize,
+}
+
+impl LineBuffer {
+    /// Inserts a character at the cursor, and moves the cursor
+    /// past the grapheme that contains it
+    pub(crate) fn insert_char(&mut self, c: char) {
+        self.line.insert(self.cursor, c);
+        let mut cursor = GraphemeCursor::new(self.cursor, self.line.len(), false);
+        if let Ok(Some(pos)) = cursor.next_boundary(&self.line, 0) {
+            self.cursor = pos;
+        }
+    }
+
+    /// Inserts text at the cursor, and moves the cursor past it
+    pub(crate) fn insert_text(&mut self, text: &str) {
+        self.line.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Compute the cursor position after applying movement
+    pub(crate) fn eval_movement(&self, movement: Movement) -> usize {
+        match movement {
+            Movement::BackwardChar(rep) => {
+                let mut position = self.cursor;
+                for _ in 0..rep {
+                    let mut cursor = GraphemeCursor::new(position, self.line.len(), false);
+                    if let Ok(Some(pos)) = cursor.prev_boundary(&self.line, 0) {
+                        position = pos;
+                    } else {
+                        break;
+                    }
+                }
+                position
+            }
+            Movement::BackwardWord(rep) => {
+                let char_indices: Vec<(usize, char)> = self.line.char_indices().collect();
+                if char_indices.is_empty() {
+                    return self.cursor;
+                }
+                let mut char_position = char_indices
+                    .iter()
+                    .position(|(idx, _)| *idx == self.cursor)
+                    .unwrap_or(char_indices.len() - 1);
+
+                for _ in 0..rep {
+                    if char_position == 0 {
+                        break;
+                    }
+
+                    let mut found = None;
+                    for prev in (0..char_position - 1).rev() {
+                        if char_indices[prev].1.is_whitespace() {
+                            found = Some(prev + 1);
+                            break;
+                        }
+                    }
+
+                    char_position = found.unwrap_or(0);
+                }
+                char_indices[char_position].0
+            }
+            Movement::ForwardWord(rep) => {
+                let char_indices: Vec<(usize, char)> = self.line.char_indices().collect();
+                if char_indices.is_empty() {
+                    return self.cursor;
+                }
+                let mut char_position = char_indices
+                    .iter()
+                    .position(|(idx, _)| *idx == self.cursor)
+                    .unwrap_or_else(|| char_indices.len());
+
+                for _ in 0..rep {
+                    // Skip any non-whitespace characters
+                    while char_position < char_indices.len()
+                        && !char_indices[char_position].1.is_whitespace()
+                    {
+                        char_position += 1;
+                    }
+
+                    // Skip any whitespace characters
+                    while char_position < char_indices.len()
+                        && char_indices[char_position].1.is_whitespace()
+                    {
+                        char_position += 1;
+                    }
+
+                    // We are now on the start of the next word
+                }
+                char_indices
+                    .get(char_position)
+                    .map(|(i, _)| *i)
+                    .unwrap_or_else(|| self.line.len())
+            }
+            Movement::ForwardChar(rep) => {
+                let mut position = self.cursor;
+                for _ in 0..rep {
+                    let mut cursor = GraphemeCursor::new(position, self.line.len(), false);
+                    if let Ok(Some(pos)) = cursor.next_boundary(&self.line, 0) {
+                        position = pos;
+                    } else {
+                        break;
+                    }
+                }
+                position
+            }
+            Movement::StartOfLine => self.line_bounds(self.cursor).start,
+            Movement::EndOfLine => self.line_bounds(self.cursor).end,
+            Movement::None => self.cursor,
+            Movement::FirstNonBlank => {
+                let bounds = self.line_bounds(self.cursor);
+                let line = &self.line[bounds.clone()];
+                bounds.start + line.len() - line.trim_start().len()
+            }
+            Movement::ForwardViWord(rep) => self.forward_word_start(rep, false),
+            Movement::BackwardViWord(rep) => self.backward_word_start(rep, false),
+            Movement::ForwardViWordEnd(rep) => self.forward_word_end(rep, false),
+            Movement::ForwardWordEnd(rep) => self.forward_word_end(rep, true),
+            Movement::ForwardFind(rep, c) | Movement::ForwardTill(rep, c) => {
+                let end = self.line_bounds(self.cursor).end;
+                let mut found = self.line[self.cursor..end]
+                    .char_indices()
+                    .skip(1)
+                    .filter(|(_, x)| *x == c)
+                    .map(|(idx, _)| self.cursor + idx);
+                match found.nth(rep.saturating_sub(1)) {
+                    Some(pos) if matches!(movement, Movement::ForwardTill(..)) => {
+                        self.prev_char_boundary(pos)
+                    }
+                    Some(pos) => pos,
+                    None => self.cursor,
+                }
+            }
+            Movement::BackwardFind(rep, c) | Movement::BackwardTill(rep, c) => {
+                let start = self.line_bounds(self.cursor).start;
+                let mut found = self.line[start..self.cursor]
+                    .char_indices()
+                    .rev()
+                    .filter(|(_, x)| *x == c)
+                    .map(|(idx, x)| (start + idx, x));
+                match found.nth(rep.saturating_sub(1)) {
+                    Some((pos, x)) if matches!(movement, Movement::BackwardTill(..)) => {
+                        pos + x.len_utf8()
+                    }
+                    Some((pos, _)) => pos,
+                    None => self.cursor,
+                }
+            }
+            Movement::PreviousLine(rep) => self.vertical_movement(-(rep as isize)),
+            Movement::NextLine(rep) => self.vertical_movement(rep as isize),
+        }
+    }
+
+    /// Returns the byte range of the line containing `pos` within a
+    /// multi-line buffer, excluding its line break
+    pub(crate) fn line_bounds(&self, pos: usize) -> Range<usize> {
+        let start = self.line[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let end = self.line[pos..]
+            .find('\n')
+            .map(|i| pos + i)
+            .unwrap_or_else(|| self.line.len());
+        start..end
+    }
+
+    fn prev_char_boundary(&self, pos: usize) -> usize {
+        self.line[..pos]
+            .char_indices()
+            .next_back()
+            .map(|(idx, _)| idx)
+            .unwrap_or(0)
+    }
+
+    fn next_char_boundary(&self, pos: usize) -> usize {
+        self.line[pos..]
+            .chars()
+            .next()
+            .map(|c| pos + c.len_utf8())
+            .unwrap_or(pos)
+    }
+
+    /// Move by `delta` lines in a multi-line buffer, preserving the
+    /// column as far as possible
+    fn vertical_movement(&self, delta: isize) -> usize {
+        let bounds = self.line_bounds(self.cursor);
+        let column = self.line[bounds.start..self.cursor].chars().count();
+
+        let mut target = bounds;
+        for _ in 0..delta.abs() {
+            if delta < 0 {
+                if target.start == 0 {
+                    break;
+                }
+                target = self.line_bounds(target.start - 1);
+            } else {
+                if target.end == self.line.len() {
+                    break;
+                }
+                target = self.line_bounds(target.end + 1);
+            }
+        }
+
+        self.line[target.clone()]
+            .char_indices()
+            .nth(column)
+            .map(|(idx, _)| target.start + idx)
+            .unwrap_or(target.end)
+    }
+
+    /// Returns the number of lines in the buffer before, and after,
+    /// the line containing the cursor
+    pub(crate) fn lines_around_cursor(&self) -> (usize, usize) {
+        let before = self.line[..self.cursor].matches('\n').count();
+        let after = self.line[self.cursor..].matches('\n').count();
+        (before, after)
+    }
+
+    fn forward_word_start(&self, rep: RepeatCount, big_word: bool) -> usize {
+        let chars: Vec<(usize, char)> = self.line[self.cursor..].char_indices().collect();
+        let mut idx = 0;
+        for _ in 0..rep {
+            if idx >= chars.len() {
+                break;
+            }
+            let class = char_class(chars[idx].1, big_word);
+            // Skip the remainder of the current word
+            if class != CharClass::Whitespace {
+                while idx < chars.len() && char_class(chars[idx].1, big_word) == class {
+                    idx += 1;
+                }
+            }
+            // Skip any whitespace leading up to the next word
+            while idx < chars.len() && char_class(chars[idx].1, big_word) == CharClass::Whitespace {
+                idx += 1;
+            }
+        }
+        chars
+            .get(idx)
+            .map(|(pos, _)| self.cursor + pos)
+            .unwrap_or_else(|| self.line.len())
+    }
+
+    fn backward_word_start(&self, rep: RepeatCount, big_word: bool) -> usize {
+        let chars: Vec<(usize, char)> = self.line[..self.cursor].char_indices().collect();
+        let mut idx = chars.len();
+        for _ in 0..rep {
+            // Skip any whitespace preceding the cursor
+            while idx > 0 && char_class(chars[idx - 1].1, big_word) == CharClass::Whitespace {
+                idx -= 1;
+            }
+            if idx == 0 {
+                break;
+            }
+            // Move to the start of the word
+            let class = char_class(chars[idx - 1].1, big_word);
+            while idx > 0 && char_class(chars[idx - 1].1, big_word) == class {
+                idx -= 1;
+            }
+        }
+        chars.get(idx).map(|(pos, _)| *pos).unwrap_or(self.cursor)
+    }
+
+    /// Returns the position of the last character of the word,
+    /// moving on to the next word if the cursor is already there
+    fn forward_word_end(&self, rep: RepeatCount, big_word: bool) -> usize {
+        let chars: Vec<(usize, char)> = self.line[self.cursor..].char_indices().collect();
+        let mut idx = 0;
+        for _ in 0..rep {
+            let mut next = idx + 1;
+            // Skip whitespace leading up to the next word
+            while next < chars.len() && char_class(chars[next].1, big_word) == CharClass::Whitespace
+            {
+                next += 1;
+            }
+            if next >= chars.len() {
+                break;
+            }
+            let class = char_class(chars[next].1, big_word);
+            while next + 1 < chars.len() && char_class(chars[next + 1].1, big_word) == class {
+                next += 1;
+            }
+            idx = next;
+        }
+        chars
+            .get(idx)
+            .map(|(pos, _)| self.cursor + pos)
+            .unwrap_or(self.cursor)
+    }
+
+    /// Returns the range of text that is covered by `movement`,
+    /// taking into account whether the movement is inclusive
+    pub(crate) fn movement_range(&self, movement: Movement) -> Range<usize> {
+        if let Movement::ForwardTill(rep, c) = movement {
+            // This covers the character before the target, which may
+            // be the character under the cursor
+            return self.cursor..self.eval_movement(Movement::ForwardFind(rep, c));
+        }
+        let pos = self.eval_movement(movement);
+        if pos < self.cursor {
+            pos..self.cursor
+        } else if pos > self.cursor && movement.is_inclusive() {
+            let mut cursor = GraphemeCursor::new(pos, self.line.len(), false);
+            let end = match cursor.next_boundary(&self.line, 0) {
+                Ok(Some(end)) => end,
+                _ => self.line.len(),
+            };
+            self.cursor..end
+        } else {
+            self.cursor..pos
+        }
+    }
+
+    /// Returns the range of text covered by the text object, if any
+    pub(crate) fn eval_text_object(&self, object: TextObject) -> Option<Range<usize>> {
+        match object {
+            TextObject::Word { around } => self.word_object(around, false),
+            TextObject::BigWord { around } => self.word_object(around, true),
+            TextObject::Quoted { quote, around } => {
+                let bounds = self.line_bounds(self.cursor);
+                let quotes: Vec<usize> = self.line[bounds.clone()]
+                    .char_indices()
+                    .filter(|(_, c)| *c == quote)
+                    .map(|(idx, _)| bounds.start + idx)
+                    .collect();
+                // Quotes are paired up from the start of the line; use
+                // the pair around the cursor, or failing that, the
+                // first pair after it
+                let (open, close) = quotes
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .find(|(_, close)| *close >= self.cursor)?;
+                if around {
+                    Some(open..close + quote.len_utf8())
+                } else {
+                    Some(open + quote.len_utf8()..close)
+                }
+            }
+            TextObject::Bracketed {
+                open,
+                close,
+                around,
+            } => {
+                // Find the unbalanced opening bracket at or before the cursor
+                let mut depth = 0;
+                let mut start = None;
+                let before = &self.line[..self.next_char_boundary(self.cursor)];
+                for (idx, c) in before.char_indices().rev() {
+                    if c == close && idx != self.cursor {
+                        depth += 1;
+                    } else if c == open {
+                        if depth == 0 {
+                            start = Some(idx);
+                            break;
+                        }
+                        depth -= 1;
+                    }
+                }
+                let start = start?;
+
+                // and its matching closing bracket
+                let mut depth = 0;
+                let mut end = None;
+                for (idx, c) in self.line[start + open.len_utf8()..].char_indices() {
+                    let idx = start + open.len_utf8() + idx;
+                    if c == open {
+                        depth += 1;
+                    } else if c == close {
+                        if depth == 0 {
+                            end = Some(idx);
+                            break;
+                        }
+                        depth -= 1;
+                    }
+                }
+                let end = end?;
+
+                if around {
+                    Some(start..end + close.len_utf8())
+                } else {
+                    Some(start + open.len_utf8()..end)
+                }
+            }
+            TextObject::Line => {
+                let bounds = self.line_bounds(self.cursor);
+                if bounds.end < self.line.len() {
+                    Some(bounds.start..bounds.end + 1)
+                } else if bounds.start > 0 {
+                    Some(bounds.start - 1..bounds.end)
+                } else {
+                    Some(bounds)
+                }
+            }
+        }
+    }
+
+    fn word_object(&self, around: bool, big_word: bool) -> Option<Range<usize>> {
+        let bounds = self.line_bounds(self.cursor);
+        let class_at = |pos: usize| {
+            self.line[pos..]
+                .chars()
+                .next()
+                .map(|c| char_class(c, big_word))
+        };
+        let class = class_at(self.cursor).filter(|_| self.cursor < bounds.end)?;
+
+        // Expand to cover the run of characters of the same class
+        let mut start = self.cursor;
+        while start > bounds.start {
+            let prev = self.prev_char_boundary(start);
+            if class_at(prev) != Some(class) {
+                break;
+            }
+            start = prev;
+        }
+        let mut end = self.cursor;
+        while end < bounds.end && class_at(end) == Some(class) {
+            end = self.next_char_boundary(end);
+        }
+
+        if around && class != CharClass::Whitespace {
+            // Include the whitespace that follows the word, or if
+            // there is none, the whitespace that precedes it
+            let mut trailing = end;
+            while trailing < bounds.end && class_at(trailing) == Some(CharClass::Whitespace) {
+                trailing = self.next_char_boundary(trailing);
+            }
+            if trailing > end {
+                end = trailing;
+            } else {
+                while start > bounds.start
+                    && class_at(self.prev_char_boundary(start)) == Some(CharClass::Whitespace)
+                {
+                    start = self.prev_char_boundary(start);
+                }
+            }
+        }
+        Some(start..end)
+    }
+}
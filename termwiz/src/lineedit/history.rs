@@ -1,5 +1,10 @@
+use crate::Result;
 use std::borrow::Cow;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Represents a position within the history.
 /// Smaller numbers are assumed to be before larger numbers,
@@ -36,7 +41,14 @@ pub struct SearchResult<'a> {
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum SearchStyle {
+    /// The pattern may appear anywhere in the line
     Substring,
+    /// The line must start with the pattern
+    Prefix,
+    /// The characters of the pattern must appear in the line in the
+    /// same order, but not necessarily next to each other.
+    /// The comparison is case insensitive.
+    Fuzzy,
 }
 
 impl SearchStyle {
@@ -45,10 +57,43 @@ impl SearchStyle {
     pub fn match_against(&self, pattern: &str, line: &str) -> Option<usize> {
         match self {
             Self::Substring => line.find(pattern),
+            Self::Prefix => {
+                if line.starts_with(pattern) {
+                    Some(0)
+                } else {
+                    None
+                }
+            }
+            Self::Fuzzy => fuzzy_match(pattern, line),
         }
     }
 }
 
+fn fuzzy_match(pattern: &str, line: &str) -> Option<usize> {
+    fn fold(c: char) -> char {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+
+    let mut pattern = pattern.chars().map(fold).peekable();
+    let mut start = None;
+    for (idx, c) in line.char_indices() {
+        match pattern.peek() {
+            None => break,
+            Some(&wanted) if wanted == fold(c) => {
+                start.get_or_insert(idx);
+                pattern.next();
+            }
+            Some(_) => {}
+        }
+    }
+
+    if pattern.peek().is_none() {
+        Some(start.unwrap_or(0))
+    } else {
+        None
+    }
+}
+
 /// Encodes the direction the search should take, relative to the
 /// current HistoryIndex.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -112,26 +157,477 @@ impl History for BasicHistory {
         direction: SearchDirection,
         pattern: &str,
     ) -> Option<SearchResult> {
-        let mut idx = idx;
-
-        loop {
-            let line = match self.entries.get(idx) {
-                Some(line) => line,
-                None => return None,
-            };
-
-            if let Some(cursor) = style.match_against(pattern, line) {
-                return Some(SearchResult {
-                    line: Cow::Borrowed(line.as_str()),
-                    idx,
-                    cursor,
-                });
+        search_entries(
+            |idx| self.entries.get(idx).map(String::as_str),
+            idx,
+            style,
+            direction,
+            pattern,
+        )
+    }
+}
+
+/// Searches the entries returned by `get`, starting at `idx` and
+/// continuing in the specified direction until either a match is
+/// found or `get` returns None
+fn search_entries<'a, F>(
+    get: F,
+    mut idx: HistoryIndex,
+    style: SearchStyle,
+    direction: SearchDirection,
+    pattern: &str,
+) -> Option<SearchResult<'a>>
+where
+    F: Fn(HistoryIndex) -> Option<&'a str>,
+{
+    loop {
+        let line = get(idx)?;
+
+        if let Some(cursor) = style.match_against(pattern, line) {
+            return Some(SearchResult {
+                line: Cow::Borrowed(line),
+                idx,
+                cursor,
+            });
+        }
+
+        idx = direction.next(idx)?;
+    }
+}
+
+/// An entry in a `FileHistory`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HistoryEntry {
+    pub line: String,
+    /// The time at which the entry was added, if known
+    pub timestamp: Option<SystemTime>,
+}
+
+/// The number of entries retained by a `FileHistory` unless
+/// otherwise specified via `FileHistory::set_max_entries`
+const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+/// A history implementation that persists its entries to a file, so
+/// that they are retained across runs of the application.
+///
+/// Each entry that is added is appended to the file while holding
+/// an exclusive lock on it, so multiple sessions can safely share the
+/// same file.  Adding a line that is already present in the history
+/// moves it to the end rather than creating a duplicate, and only the
+/// most recent `max_entries` entries are retained.  The file is
+/// compacted when it grows to hold more than twice that number of
+/// entries.
+///
+/// The entries from other sessions are picked up when the history is
+/// created, or when `reload` is called.
+///
+/// Each entry is stored on its own line of the file, prefixed by the
+/// number of seconds since the unix epoch at which it was added, and
+/// a tab.  Backslashes and line breaks within the entry are escaped.
+/// Lines without a timestamp are loaded verbatim as entries with no
+/// timestamp, so a file containing one plain entry per line can be
+/// imported.
+///
+/// ```no_run
+/// use termwiz::lineedit::{FileHistory, History};
+///
+/// let mut history = FileHistory::new("/tmp/my-repl-history")?;
+/// history.add("hello");
+/// # Ok::<(), termwiz::Error>(())
+/// ```
+pub struct FileHistory {
+    path: PathBuf,
+    entries: VecDeque<HistoryEntry>,
+    max_entries: usize,
+}
+
+impl FileHistory {
+    /// Creates a history that is backed by the file at `path`, and
+    /// loads its entries.  It is not an error for the file to not
+    /// exist yet; it will be created when the first entry is added.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let mut history = Self {
+            path: path.into(),
+            entries: VecDeque::new(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+        };
+        history.reload()?;
+        Ok(history)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sets the maximum number of entries to retain; older entries
+    /// are discarded.
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries.max(1);
+        while self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Replaces the entries with those from the file, which includes
+    /// any that were added by other sessions since it was loaded
+    pub fn reload(&mut self) -> Result<()> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                self.entries.clear();
+                return Ok(());
             }
+            Err(err) => return Err(err.into()),
+        };
+        lock_file(&file, false)?;
+        let entries = read_entries(&mut file)?;
+        self.entries = dedup_entries(entries, self.max_entries);
+        Ok(())
+    }
+
+    /// Returns the entry, including its timestamp, for an index
+    pub fn entry(&self, idx: HistoryIndex) -> Option<&HistoryEntry> {
+        self.entries.get(idx)
+    }
+
+    /// Returns an iterator over the entries, from oldest to newest
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
 
-            idx = match direction.next(idx) {
-                None => return None,
-                Some(idx) => idx,
-            };
+    /// Appends the entry to the file, compacting the file if it
+    /// has grown too large
+    fn persist(&self, entry: &HistoryEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
         }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)?;
+        lock_file(&file, true)?;
+
+        let mut entries = read_entries(&mut file)?;
+        if entries.len() < self.max_entries * 2 {
+            file.seek(SeekFrom::End(0))?;
+            file.write_all(format_entry(entry).as_bytes())?;
+        } else {
+            entries.push(entry.clone());
+            let mut data = String::new();
+            for entry in dedup_entries(entries, self.max_entries) {
+                data.push_str(&format_entry(&entry));
+            }
+            file.seek(SeekFrom::Start(0))?;
+            file.set_len(0)?;
+            file.write_all(data.as_bytes())?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+impl History for FileHistory {
+    fn get(&self, idx: HistoryIndex) -> Option<Cow<str>> {
+        self.entries
+            .get(idx)
+            .map(|entry| Cow::Borrowed(entry.line.as_str()))
+    }
+
+    fn last(&self) -> Option<HistoryIndex> {
+        if self.entries.is_empty() {
+            None
+        } else {
+            Some(self.entries.len() - 1)
+        }
+    }
+
+    fn add(&mut self, line: &str) {
+        if let Some(idx) = self.entries.iter().position(|entry| entry.line == line) {
+            self.entries.remove(idx);
+        }
+        let entry = HistoryEntry {
+            line: line.to_owned(),
+            timestamp: Some(SystemTime::now()),
+        };
+        if let Err(err) = self.persist(&entry) {
+            log::error!(
+                "failed to save history to {}: {:#}",
+                self.path.display(),
+                err
+            );
+        }
+        self.entries.push_back(entry);
+        while self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+        }
+    }
+
+    fn search(
+        &self,
+        idx: HistoryIndex,
+        style: SearchStyle,
+        direction: SearchDirection,
+        pattern: &str,
+    ) -> Option<SearchResult> {
+        search_entries(
+            |idx| self.entries.get(idx).map(|entry| entry.line.as_str()),
+            idx,
+            style,
+            direction,
+            pattern,
+        )
+    }
+}
+
+/// Removes all but the most recent occurrence of each line, and
+/// then all but the most recent `max_entries` entries
+fn dedup_entries(entries: Vec<HistoryEntry>, max_entries: usize) -> VecDeque<HistoryEntry> {
+    let mut seen = HashSet::new();
+    let mut result = VecDeque::new();
+    for entry in entries.into_iter().rev() {
+        if result.len() == max_entries {
+            break;
+        }
+        if seen.insert(entry.line.clone()) {
+            result.push_front(entry);
+        }
+    }
+    result
+}
+
+fn read_entries(file: &mut File) -> Result<Vec<HistoryEntry>> {
+    let mut data = String::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_string(&mut data)?;
+    Ok(data.lines().map(parse_entry).collect())
+}
+
+fn parse_entry(record: &str) -> HistoryEntry {
+    // Only timestamped records are escaped; other lines are plain
+    // entries imported from elsewhere
+    let (secs, line) = match record.split_once('\t') {
+        Some((secs, line)) => match secs.parse::<u64>() {
+            Ok(secs) => (secs, line),
+            Err(_) => return plain_entry(record),
+        },
+        None => return plain_entry(record),
+    };
+
+    let mut unescaped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some(c) => unescaped.push(c),
+                None => unescaped.push('\\'),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    HistoryEntry {
+        line: unescaped,
+        timestamp: Some(UNIX_EPOCH + Duration::from_secs(secs)),
+    }
+}
+
+fn plain_entry(line: &str) -> HistoryEntry {
+    HistoryEntry {
+        line: line.to_string(),
+        timestamp: None,
+    }
+}
+
+fn format_entry(entry: &HistoryEntry) -> String {
+    let secs = entry
+        .timestamp
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|t| t.as_secs());
+    // An entry without a timestamp is written verbatim so that it is
+    // read back in the same way, unless it has line breaks that
+    // require escaping, and thus a timestamp
+    if secs.is_none() && !entry.line.contains(['\n', '\r']) {
+        return format!("{}\n", entry.line);
+    }
+
+    let mut record = format!("{}\t", secs.unwrap_or(0));
+    for c in entry.line.chars() {
+        match c {
+            '\\' => record.push_str("\\\\"),
+            '\n' => record.push_str("\\n"),
+            '\r' => record.push_str("\\r"),
+            c => record.push(c),
+        }
+    }
+    record.push('\n');
+    record
+}
+
+/// Locks the file, blocking until the lock is available.
+/// The lock is released when the file is closed.
+#[cfg(unix)]
+fn lock_file(file: &File, exclusive: bool) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let operation = if exclusive {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_SH
+    };
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// Locks the file, blocking until the lock is available.
+/// The lock is released when the file is closed.
+#[cfg(windows)]
+fn lock_file(file: &File, exclusive: bool) -> std::io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use winapi::um::fileapi::LockFileEx;
+    use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, OVERLAPPED};
+    let flags = if exclusive {
+        LOCKFILE_EXCLUSIVE_LOCK
+    } else {
+        0
+    };
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    let res = unsafe { LockFileEx(file.as_raw_handle() as _, flags, 0, !0, !0, &mut overlapped) };
+    if res == 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns a path for a history file that is removed when dropped
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "termwiz-history-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = std::fs::remove_file(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn lines(history: &FileHistory) -> Vec<&str> {
+        history.entries().map(|e| e.line.as_str()).collect()
+    }
+
+    #[test]
+    fn search_styles() {
+        assert_eq!(SearchStyle::Substring.match_against("lo", "hello"), Some(3));
+        assert_eq!(SearchStyle::Prefix.match_against("he", "hello"), Some(0));
+        assert_eq!(SearchStyle::Prefix.match_against("lo", "hello"), None);
+        assert_eq!(
+            SearchStyle::Fuzzy.match_against("gco", "git commit"),
+            Some(0)
+        );
+        assert_eq!(
+            SearchStyle::Fuzzy.match_against("CMT", "git commit"),
+            Some(4)
+        );
+        assert_eq!(SearchStyle::Fuzzy.match_against("ctg", "git commit"), None);
+        assert_eq!(SearchStyle::Fuzzy.match_against("", "git commit"), Some(0));
+    }
+
+    #[test]
+    fn persists_and_dedups() {
+        let path = TempPath::new("persist");
+        let mut history = FileHistory::new(&path.0).unwrap();
+        assert_eq!(history.last(), None);
+        history.add("one");
+        history.add("two\nlines \\ here");
+        history.add("three");
+        history.add("one");
+        assert_eq!(lines(&history), vec!["two\nlines \\ here", "three", "one"]);
+
+        let loaded = FileHistory::new(&path.0).unwrap();
+        assert_eq!(lines(&loaded), lines(&history));
+        assert!(loaded.entry(0).unwrap().timestamp.is_some());
+
+        let result = loaded
+            .search(2, SearchStyle::Prefix, SearchDirection::Backwards, "th")
+            .unwrap();
+        assert_eq!(result.idx, 1);
+        assert_eq!(result.line, "three");
+    }
+
+    #[test]
+    fn shared_between_sessions() {
+        let path = TempPath::new("shared");
+        let mut first = FileHistory::new(&path.0).unwrap();
+        let mut second = FileHistory::new(&path.0).unwrap();
+        first.add("a");
+        second.add("b");
+        first.add("c");
+        assert_eq!(lines(&first), vec!["a", "c"]);
+
+        first.reload().unwrap();
+        assert_eq!(lines(&first), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn caps_and_compacts() {
+        let path = TempPath::new("cap");
+        let mut history = FileHistory::new(&path.0).unwrap();
+        history.set_max_entries(3);
+        for i in 0..10 {
+            history.add(&i.to_string());
+        }
+        assert_eq!(lines(&history), vec!["7", "8", "9"]);
+
+        let data = std::fs::read_to_string(&path.0).unwrap();
+        assert!(data.lines().count() <= 6, "{:?}", data);
+
+        let mut loaded = FileHistory::new(&path.0).unwrap();
+        loaded.set_max_entries(3);
+        assert_eq!(lines(&loaded), vec!["7", "8", "9"]);
+    }
+
+    #[test]
+    fn imports_plain_lines() {
+        let path = TempPath::new("plain");
+        std::fs::write(&path.0, "ls -l\ngrep 'a\\.b' file\n1650000000\tpwd\n").unwrap();
+        let history = FileHistory::new(&path.0).unwrap();
+        assert_eq!(lines(&history), vec!["ls -l", "grep 'a\\.b' file", "pwd"]);
+        assert_eq!(history.entry(0).unwrap().timestamp, None);
+        assert_eq!(
+            history.entry(2).unwrap().timestamp,
+            Some(UNIX_EPOCH + Duration::from_secs(1650000000))
+        );
+
+        // Plain entries are written back verbatim
+        assert_eq!(
+            format_entry(history.entry(1).unwrap()),
+            "grep 'a\\.b' file\n"
+        );
     }
 }
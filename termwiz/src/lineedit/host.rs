@@ -49,6 +49,15 @@ pub trait LineEditorHost {
         vec![OutputElement::Text(prompt.to_owned())]
     }
 
+    /// Return the rendered form of the prompt that is shown at the start
+    /// of the second and subsequent lines of a multi-line buffer.
+    /// The default implementation returns blank space of the same width
+    /// as the prompt, so that the lines of the buffer are aligned.
+    fn render_continuation_prompt(&self, prompt: &str) -> Vec<OutputElement> {
+        let width = crate::cell::unicode_column_width(prompt, None);
+        vec![OutputElement::Text(" ".repeat(width))]
+    }
+
     /// Given a reference to the current line being edited, render a preview
     /// of its outcome. The preview is cleared when the input is accepted,
     /// or canceled.
@@ -71,6 +80,16 @@ pub trait LineEditorHost {
         (vec![OutputElement::Text(line.to_owned())], cursor_x_pos)
     }
 
+    /// Called when the user attempts to accept the line.
+    /// Return `false` to indicate that the input is incomplete, for
+    /// example, because it has unbalanced brackets or an unterminated
+    /// string, in which case a line break is inserted at the cursor
+    /// and editing continues, allowing the input to span multiple lines.
+    /// The default implementation considers every line to be complete.
+    fn is_line_complete(&self, _line: &str) -> bool {
+        true
+    }
+
     /// Returns the history implementation
     fn history(&mut self) -> &mut dyn History;
 
@@ -103,11 +122,36 @@ pub trait LineEditorHost {
 /// completion of "he" in that context, then the corresponding CompletionCandidate
 /// would have its range set to [4..6] (the "he" slice range) and its text
 /// set to "hello".
+/// When there are multiple candidates, they are listed in a menu below
+/// the line; if any of them have a `description`, the candidates are
+/// listed one per row with their descriptions alongside.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct CompletionCandidate {
     /// The section of the input line to be replaced
     pub range: std::ops::Range<usize>,
     /// The replacement text
     pub text: String,
+    /// An optional explanation of the candidate to show in the
+    /// completion menu
+    pub description: Option<String>,
+}
+
+impl CompletionCandidate {
+    /// Create a candidate that replaces `range` with `text`
+    pub fn new(range: std::ops::Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+            description: None,
+        }
+    }
+
+    /// Set the description shown alongside the candidate in the menu
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
 }
 
 /// A concrete implementation of `LineEditorHost` that uses the default behaviors.
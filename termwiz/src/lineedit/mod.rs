@@ -34,23 +34,105 @@
 //! Ctrl-K        | Delete from cursor to end of line
 //! Ctrl-L        | Move the cursor to the top left, clear screen and repaint
 //! Ctrl-R        | Incremental history search mode
+//! Tab           | Complete the word at the cursor
 //! Ctrl-W        | Delete word leading up to cursor
+//! Ctrl-Y        | Insert the most recently deleted text
+//! Ctrl-_        | Undo the most recent change
 //! Alt-b, Alt-Left | Move the cursor backwards one word
 //! Alt-f, Alt-Right | Move the cursor forwards one word
+//! Alt-y         | Replace the text inserted by Ctrl-Y with the prior deleted text
+//! Alt-Enter     | Insert a line break
+//!
+//! Text that is deleted by commands other than the single character
+//! deletions is saved to a kill ring, from which it can be recalled
+//! by Ctrl-Y and Alt-y.
+//!
+//! ## Multi-line Input
+//!
+//! When the line is accepted, `LineEditorHost::is_line_complete` is
+//! consulted; if it returns `false` a line break is inserted instead,
+//! and editing continues on the next line.  The Up and Down keys move
+//! between the lines of the buffer before moving through the history.
+//!
+//! ## Completion
+//!
+//! Pressing Tab calls `LineEditorHost::complete`.  A sole candidate is
+//! inserted immediately.  Otherwise, the text that is common to all of
+//! the candidates is inserted and they are listed in a menu below the
+//! line, which is filtered as you continue to type.
+//! While the menu is shown, the following keys are supported:
+//!
+//! Keystroke     | Action
+//! ---------     | ------
+//! Tab, Shift-Tab | Select the next or previous candidate
+//! Up, Down, Left, Right | Move the selection within the menu
+//! PageUp, PageDown | Move the selection by a page
+//! Enter         | Keep the selected candidate and close the menu
+//! Escape        | Restore the line and close the menu
+//!
+//! ## History
+//!
+//! The history is provided by `LineEditorHost::history`.  `BasicHistory`
+//! holds the entries in memory, while `FileHistory` also persists them
+//! to a file that can be shared by concurrent sessions.
+//! `LineEditor::set_search_style` selects whether Ctrl-R matches
+//! entries by substring, prefix or fuzzy matching.
+//!
+//! ## Vi Mode
+//!
+//! Calling `LineEditor::set_edit_mode(EditMode::Vi)` enables a modal
+//! keymap that emulates vi.  Editing starts in insert mode, in which
+//! the keys above apply, and Escape switches to normal mode, in which
+//! the following commands are supported.  Most of them accept a count.
+//!
+//! Keystroke     | Action
+//! ---------     | ------
+//! h, l, w, b, e, W, B, E | Move by character or word
+//! 0, ^, $       | Move to the start, first non-blank or end of the line
+//! f, F, t, T, ;, , | Move to, or up to, a character on the line
+//! j, k          | Move between lines, then through the history
+//! i, a, I, A, o, O | Enter insert mode
+//! d, c, y       | Delete, change or copy the text covered by a motion or text object
+//! dd, cc, yy    | Delete, change or copy the line
+//! D, C, Y, x, X, s, S | Shorthands for the common deletions and changes
+//! iw, aw, iW, aW | Text objects for words
+//! i", a", i', a', i`, a` | Text objects for quoted strings
+//! i(, a(, i[, a[, i{, a{, i<, a< | Text objects for bracketed text
+//! p, P          | Insert the most recently deleted or copied text
+//! r             | Replace characters under the cursor
+//! u, Ctrl-R     | Undo and redo
+//! .             | Repeat the most recent change
+//! v             | Visual mode; motions extend the selection, which d, c and y act on
+//! /, ?          | Incremental history search
 use crate::caps::{Capabilities, ProbeHints};
+use crate::cell::{unicode_column_width, AttributeChange, Intensity};
 use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
 use crate::surface::change::ChangeSequence;
-use crate::surface::{Change, Position};
+use crate::surface::{Change, CursorShape, Position};
 use crate::terminal::{new_terminal, Terminal};
 use crate::{bail, ensure, Result};
-use unicode_segmentation::GraphemeCursor;
+use std::collections::VecDeque;
+use std::ops::Range;
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
 mod actions;
+mod buffer;
 mod history;
 mod host;
-pub use actions::{Action, Movement, RepeatCount};
+mod vi;
+pub use actions::{Action, CompletionMovement, Movement, RepeatCount, TextObject};
+pub(crate) use buffer::LineBuffer;
 pub use history::*;
 pub use host::*;
+use vi::ViKeymap;
+pub use vi::ViMode;
+
+/// The maximum number of entries retained in the kill ring
+const KILL_RING_SIZE: usize = 32;
+
+/// The maximum number of rows of candidates shown in the
+/// completion menu; further candidates are shown on other pages
+const MAX_COMPLETION_ROWS: usize = 10;
 
 /// The `LineEditor` struct provides line editing facilities similar
 /// to those in the unix shell.
@@ -71,10 +153,7 @@ pub use host::*;
 pub struct LineEditor<'term> {
     terminal: &'term mut dyn Terminal,
     prompt: String,
-    line: String,
-    /// byte index into the UTF-8 string data of the insertion
-    /// point.  This is NOT the number of graphemes!
-    cursor: usize,
+    buffer: LineBuffer,
 
     history_pos: Option<usize>,
     bottom_line: Option<String>,
@@ -85,6 +164,60 @@ pub struct LineEditor<'term> {
     move_to_editor_end: Option<Change>,
 
     state: EditorState,
+    search_style: SearchStyle,
+
+    edit_mode: EditMode,
+    vi: ViKeymap,
+
+    /// Recently killed text; the most recent entry is at the front
+    kill_ring: VecDeque<String>,
+    /// Whether the most recent action was a kill, in which case
+    /// a subsequent kill is appended to the same kill ring entry
+    killing: bool,
+    yank: Option<YankState>,
+
+    undo_stack: Vec<(String, usize)>,
+    redo_stack: Vec<(String, usize)>,
+    /// Whether the next change should be merged into the most recent
+    /// undo step, so that eg: typing a word can be undone in one step
+    undo_coalesce: bool,
+}
+
+/// Selects the keymap used by the `LineEditor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditMode {
+    /// Emacs style key bindings, similar to readline.  This is the default.
+    Emacs,
+    /// Modal vi style key bindings
+    Vi,
+}
+
+/// Tracks the text inserted by the most recent Yank, so that YankPop
+/// can replace it
+struct YankState {
+    range: Range<usize>,
+    kill_ring_index: usize,
+}
+
+/// Classifies characters for the purposes of word movement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+/// Returns the class of `c`.  If `big_word` is true, punctuation is
+/// considered to be part of a word, so that words are delimited only
+/// by whitespace.
+pub(crate) fn char_class(c: char, big_word: bool) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if big_word || c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -103,22 +236,82 @@ enum EditorState {
 
 struct CompletionState {
     candidates: Vec<CompletionCandidate>,
-    index: usize,
+    /// The selected candidate, if any.  The menu just lists
+    /// the candidates until one is selected.
+    index: Option<usize>,
     original_line: String,
     original_cursor: usize,
+    /// The layout of the menu as of the most recent render
+    columns: usize,
+    page_rows: usize,
 }
 
 impl CompletionState {
-    fn next(&mut self) {
-        self.index += 1;
-        if self.index >= self.candidates.len() {
-            self.index = 0;
+    fn new(candidates: Vec<CompletionCandidate>, line: &str, cursor: usize) -> Self {
+        Self {
+            candidates,
+            index: None,
+            original_line: line.to_string(),
+            original_cursor: cursor,
+            columns: 1,
+            page_rows: MAX_COMPLETION_ROWS,
         }
     }
 
+    fn select(&mut self, movement: CompletionMovement) {
+        let len = self.candidates.len();
+        if len == 0 {
+            return;
+        }
+        let columns = self.columns.max(1);
+        let page = columns * self.page_rows.max(1);
+        self.index = Some(match (self.index, movement) {
+            (
+                None,
+                CompletionMovement::Previous
+                | CompletionMovement::Up
+                | CompletionMovement::Left
+                | CompletionMovement::PreviousPage,
+            ) => len - 1,
+            (None, _) => 0,
+            (Some(idx), CompletionMovement::Next | CompletionMovement::Right) => (idx + 1) % len,
+            (Some(idx), CompletionMovement::Previous | CompletionMovement::Left) => {
+                (idx + len - 1) % len
+            }
+            (Some(idx), CompletionMovement::Down) => {
+                if idx + columns < len {
+                    idx + columns
+                } else {
+                    // Wrap around to the top of the column
+                    idx % columns
+                }
+            }
+            (Some(idx), CompletionMovement::Up) => {
+                if idx >= columns {
+                    idx - columns
+                } else {
+                    // Wrap around to the bottom of the column, which
+                    // may be in the penultimate row if the last row
+                    // is incomplete
+                    let last_row = (len - 1) / columns * columns;
+                    if last_row + idx < len {
+                        last_row + idx
+                    } else {
+                        last_row.saturating_sub(columns) + idx
+                    }
+                }
+            }
+            (Some(idx), CompletionMovement::NextPage) => (idx + page).min(len - 1),
+            (Some(idx), CompletionMovement::PreviousPage) => idx.saturating_sub(page),
+        });
+    }
+
     fn current(&self) -> (usize, String) {
+        let candidate = match self.index {
+            Some(idx) => &self.candidates[idx],
+            None => return (self.original_cursor, self.original_line.clone()),
+        };
         let mut line = self.original_line.clone();
-        let candidate = &self.candidates[self.index];
         line.replace_range(candidate.range.clone(), &candidate.text);
 
         // To figure the new cursor position do a little math:
@@ -130,6 +323,115 @@ impl CompletionState {
 
         (new_cursor, line)
     }
+
+    /// Returns the text that all of the candidates start with, if
+    /// they all replace the same range of the line and that text
+    /// would extend what is already there
+    fn common_prefix(&self) -> Option<String> {
+        let first = self.candidates.first()?;
+        let mut prefix = first.text.as_str();
+        for candidate in &self.candidates[1..] {
+            if candidate.range != first.range {
+                return None;
+            }
+            let len = prefix
+                .char_indices()
+                .zip(candidate.text.chars())
+                .find(|((_, a), b)| a != b)
+                .map(|((idx, _), _)| idx)
+                .unwrap_or_else(|| prefix.len().min(candidate.text.len()));
+            prefix = &prefix[..len];
+        }
+        let existing = self.original_line.get(first.range.clone())?;
+        if prefix.len() >= existing.len() && prefix != existing {
+            Some(prefix.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Renders the menu below the line, and records the layout so
+    /// that the selection can be moved by row and by page
+    fn render(&mut self, changes: &mut ChangeSequence, screen_cols: usize, max_rows: usize) {
+        // Avoid writing to the last column, so that the terminal
+        // won't wrap onto the next line
+        let usable_width = screen_cols.saturating_sub(1).max(1);
+        let text_width = self
+            .candidates
+            .iter()
+            .map(|c| unicode_column_width(&c.text, None))
+            .max()
+            .unwrap_or(0);
+        let has_descriptions = self.candidates.iter().any(|c| c.description.is_some());
+
+        let (column_width, text_width) = if has_descriptions {
+            (usable_width, text_width.min(usable_width / 2))
+        } else {
+            let text_width = text_width.min(usable_width);
+            (text_width + 2, text_width)
+        };
+        self.columns = (usable_width / column_width).max(1);
+        let len = self.candidates.len();
+        let total_rows = self.candidates.chunks(self.columns).len();
+        self.page_rows = total_rows.min(max_rows).max(1);
+
+        let page_len = self.columns * self.page_rows;
+        let first = self.index.unwrap_or(0) / page_len * page_len;
+        let last = (first + page_len).min(len);
+
+        for (row_idx, row) in self.candidates[first..last]
+            .chunks(self.columns)
+            .enumerate()
+        {
+            changes.add("\r\n");
+            for (col_idx, candidate) in row.iter().enumerate() {
+                let idx = first + row_idx * self.columns + col_idx;
+                changes.add(Change::AllAttributes(Default::default()));
+                if self.index == Some(idx) {
+                    changes.add(AttributeChange::Reverse(true));
+                }
+                let (text, width) = truncate_to_width(&candidate.text, text_width);
+                changes.add(text);
+                changes.add(" ".repeat(text_width - width));
+
+                if has_descriptions {
+                    if let Some(description) = &candidate.description {
+                        let (description, _) = truncate_to_width(
+                            description,
+                            usable_width.saturating_sub(text_width + 2),
+                        );
+                        changes.add("  ");
+                        changes.add(AttributeChange::Intensity(Intensity::Half));
+                        changes.add(description);
+                    }
+                } else if col_idx + 1 < row.len() {
+                    changes.add(Change::AllAttributes(Default::default()));
+                    changes.add("  ");
+                }
+            }
+        }
+        changes.add(Change::AllAttributes(Default::default()));
+
+        if total_rows > self.page_rows {
+            changes.add(format!("\r\n{}-{} of {}", first + 1, last, len));
+        }
+    }
+}
+
+/// Returns the longest prefix of text that fits in width columns,
+/// along with its width
+pub(crate) fn truncate_to_width(text: &str, width: usize) -> (String, usize) {
+    let mut result = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let grapheme_width = unicode_column_width(grapheme, None);
+        if used + grapheme_width > width {
+            break;
+        }
+        used += grapheme_width;
+        result.push_str(grapheme);
+    }
+    (result, used)
 }
 
 impl<'term> LineEditor<'term> {
@@ -155,14 +457,47 @@ impl<'term> LineEditor<'term> {
         Self {
             terminal,
             prompt: "> ".to_owned(),
-            line: String::new(),
-            cursor: 0,
+            buffer: LineBuffer::default(),
             history_pos: None,
             bottom_line: None,
             completion: None,
             move_to_editor_start: None,
             move_to_editor_end: None,
             state: EditorState::Inactive,
+            search_style: SearchStyle::Substring,
+            edit_mode: EditMode::Emacs,
+            vi: ViKeymap::new(),
+            kill_ring: VecDeque::new(),
+            killing: false,
+            yank: None,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            undo_coalesce: false,
+        }
+    }
+
+    /// Selects the keymap to use for subsequent editing
+    pub fn set_edit_mode(&mut self, mode: EditMode) {
+        self.edit_mode = mode;
+        self.vi.reset();
+    }
+
+    pub fn edit_mode(&self) -> EditMode {
+        self.edit_mode
+    }
+
+    /// Selects how the incremental history search matches the
+    /// pattern against the history entries
+    pub fn set_search_style(&mut self, style: SearchStyle) {
+        self.search_style = style;
+    }
+
+    /// Returns the current vi mode, or `None` if the vi keymap
+    /// is not in use
+    pub fn vi_mode(&self) -> Option<ViMode> {
+        match self.edit_mode {
+            EditMode::Vi => Some(self.vi.mode()),
+            EditMode::Emacs => None,
         }
     }
 
@@ -186,12 +521,13 @@ impl<'term> LineEditor<'term> {
                 cursor,
                 ..
             } => (matching_line, *cursor),
-            _ => (&self.line, self.cursor),
+            _ => (&self.buffer.line, self.buffer.cursor),
         };
 
-        let cursor_position_after_printing_prompt = changes.current_cursor_position();
-
-        let (elements, cursor_x_pos) = host.highlight_line(line_to_display, cursor);
+        let selection = match (&self.state, self.vi_mode()) {
+            (EditorState::Editing, Some(ViMode::Visual)) => self.selection(),
+            _ => None,
+        };
 
         // Calculate what the cursor position would be after printing X columns
         // of text from the specified location.
@@ -210,24 +546,64 @@ impl<'term> LineEditor<'term> {
 
             (col, row)
         }
-        let cursor_position = compute_cursor_after_printing_x_columns(
-            cursor_position_after_printing_prompt.0,
-            cursor_position_after_printing_prompt.1,
-            cursor_x_pos,
-            screen_size.cols,
-        );
 
-        for ele in elements {
-            changes.add(ele);
-        }
+        // Each line of a multi-line buffer is highlighted separately,
+        // and the continuation prompt is rendered at the start of the
+        // second and subsequent lines
+        let mut cursor_position = changes.current_cursor_position();
+        let mut segment_start = 0;
+        for (idx, segment) in line_to_display.split('\n').enumerate() {
+            if idx > 0 {
+                changes.add("\r\n");
+                changes.add(Change::AllAttributes(Default::default()));
+                for ele in host.render_continuation_prompt(&self.prompt) {
+                    changes.add(ele);
+                }
+                changes.add(Change::AllAttributes(Default::default()));
+            }
+            let segment_end = segment_start + segment.len();
+            let has_cursor = cursor >= segment_start && cursor <= segment_end;
+
+            let cursor_position_after_printing_prompt = changes.current_cursor_position();
+            let (elements, cursor_x_pos) = host.highlight_line(
+                segment,
+                if has_cursor {
+                    cursor - segment_start
+                } else {
+                    0
+                },
+            );
+
+            if has_cursor {
+                cursor_position = compute_cursor_after_printing_x_columns(
+                    cursor_position_after_printing_prompt.0,
+                    cursor_position_after_printing_prompt.1,
+                    cursor_x_pos,
+                    screen_size.cols,
+                );
+            }
+
+            let elements = match &selection {
+                Some(selection) => highlight_selection(
+                    elements,
+                    selection.start.saturating_sub(segment_start)
+                        ..selection.end.saturating_sub(segment_start),
+                ),
+                None => elements,
+            };
+            for ele in elements {
+                changes.add(ele);
+            }
 
-        let cursor_after_line_render = changes.current_cursor_position();
-        if cursor_after_line_render.0 == screen_size.cols {
-            // If the cursor position remains in the first column
-            // then the renderer may still consider itself to be on
-            // the prior line; force out an additional character to force
-            // it to apply wrapping/flush.
-            changes.add(" ");
+            let cursor_after_line_render = changes.current_cursor_position();
+            if cursor_after_line_render.0 == screen_size.cols {
+                // If the cursor position remains in the first column
+                // then the renderer may still consider itself to be on
+                // the prior line; force out an additional character to force
+                // it to apply wrapping/flush.
+                changes.add(" ");
+            }
+            segment_start = segment_end + 1;
         }
 
         if let EditorState::Editing = &self.state {
@@ -243,6 +619,15 @@ impl<'term> LineEditor<'term> {
             }
         }
 
+        if let Some(state) = self.completion.as_mut() {
+            // Leave room for the input area, and the paging status line
+            let max_rows = screen_size
+                .rows
+                .saturating_sub(changes.render_height() + 2)
+                .clamp(1, MAX_COMPLETION_ROWS);
+            state.render(&mut changes, screen_size.cols, max_rows);
+        }
+
         if let EditorState::Searching {
             style, direction, ..
         } = &self.state
@@ -251,6 +636,10 @@ impl<'term> LineEditor<'term> {
             let label = match (style, direction) {
                 (SearchStyle::Substring, SearchDirection::Backwards) => "bck-i-search",
                 (SearchStyle::Substring, SearchDirection::Forwards) => "fwd-i-search",
+                (SearchStyle::Prefix, SearchDirection::Backwards) => "bck-prefix-search",
+                (SearchStyle::Prefix, SearchDirection::Forwards) => "fwd-prefix-search",
+                (SearchStyle::Fuzzy, SearchDirection::Backwards) => "bck-fuzzy-search",
+                (SearchStyle::Fuzzy, SearchDirection::Forwards) => "fwd-fuzzy-search",
             };
             // Do not be affected by attributes set by previous lines.
             changes.add(Change::AllAttributes(Default::default()));
@@ -258,7 +647,7 @@ impl<'term> LineEditor<'term> {
             // the text in the line editing area, but since the input
             // is drawn here, we render an `_` to indicate where the input
             // position really is.
-            changes.add(format!("\r\n{}: {}_", label, self.line));
+            changes.add(format!("\r\n{}: {}_", label, self.buffer.line));
         }
 
         // Add some debugging status at the bottom
@@ -274,6 +663,14 @@ impl<'term> LineEditor<'term> {
 
         changes.move_to(cursor_position);
 
+        // Reflect the vi mode in the cursor shape
+        if let Some(mode) = self.vi_mode() {
+            changes.add(Change::CursorShape(match mode {
+                ViMode::Insert => CursorShape::BlinkingBar,
+                ViMode::Normal | ViMode::Visual => CursorShape::BlinkingBlock,
+            }));
+        }
+
         let mut changes = changes.consume();
         if let Some(start) = self.move_to_editor_start.take() {
             changes.insert(0, start);
@@ -321,21 +718,69 @@ impl<'term> LineEditor<'term> {
             self.terminal
                 .render(&[move_end, Change::ClearToEndOfScreen(Default::default())])?;
         }
+        if self.edit_mode == EditMode::Vi {
+            self.terminal
+                .render(&[Change::CursorShape(CursorShape::Default)])?;
+        }
 
         self.terminal.flush()?;
         self.terminal.set_cooked_mode()?;
         res
     }
 
-    fn resolve_action(
+    fn resolve_actions(
         &mut self,
         event: &InputEvent,
         host: &mut dyn LineEditorHost,
-    ) -> Option<Action> {
+    ) -> Option<Vec<Action>> {
         if let Some(action) = host.resolve_action(event, self) {
-            return Some(action);
+            return Some(vec![action]);
         }
 
+        if let Some(action) = self.resolve_completion_action(event) {
+            return Some(vec![action]);
+        }
+
+        let action = Self::resolve_emacs_action(event);
+        match (self.edit_mode, &self.state) {
+            // The search pattern is always edited using the emacs keymap
+            (EditMode::Emacs, _) | (EditMode::Vi, EditorState::Searching { .. }) => {
+                action.map(|action| vec![action])
+            }
+            (EditMode::Vi, _) => {
+                self.vi
+                    .resolve(event, &self.buffer.line, self.buffer.cursor, action)
+            }
+        }
+    }
+
+    /// Resolves the keys that navigate the completion menu while
+    /// it is being shown
+    fn resolve_completion_action(&self, event: &InputEvent) -> Option<Action> {
+        let state = self.completion.as_ref()?;
+        let key = match event {
+            InputEvent::Key(key) => key,
+            _ => return None,
+        };
+        let movement = match (&key.key, key.modifiers) {
+            (KeyCode::Tab, Modifiers::SHIFT) => CompletionMovement::Previous,
+            (KeyCode::UpArrow, Modifiers::NONE) => CompletionMovement::Up,
+            (KeyCode::DownArrow, Modifiers::NONE) => CompletionMovement::Down,
+            (KeyCode::LeftArrow, Modifiers::NONE) => CompletionMovement::Left,
+            (KeyCode::RightArrow, Modifiers::NONE) => CompletionMovement::Right,
+            (KeyCode::PageUp, Modifiers::NONE) => CompletionMovement::PreviousPage,
+            (KeyCode::PageDown, Modifiers::NONE) => CompletionMovement::NextPage,
+            (KeyCode::Escape, Modifiers::NONE) => return Some(Action::CancelCompletion),
+            // Without a selection, Enter accepts the line as usual
+            (KeyCode::Enter, Modifiers::NONE) if state.index.is_some() => {
+                return Some(Action::AcceptCompletion)
+            }
+            _ => return None,
+        };
+        Some(Action::SelectCompletion(movement))
+    }
+
+    pub(crate) fn resolve_emacs_action(event: &InputEvent) -> Option<Action> {
         match event {
             InputEvent::Key(KeyEvent {
                 key: KeyCode::Char('C'),
@@ -352,6 +797,11 @@ impl<'term> LineEditor<'term> {
                 modifiers: Modifiers::CTRL,
             }) => Some(Action::EndOfFile),
 
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Enter,
+                modifiers: Modifiers::ALT,
+            }) => Some(Action::InsertChar(1, '\n')),
+
             InputEvent::Key(KeyEvent {
                 key: KeyCode::Char('J'),
                 modifiers: Modifiers::CTRL,
@@ -478,6 +928,24 @@ impl<'term> LineEditor<'term> {
                 key: KeyCode::ApplicationRightArrow,
                 modifiers: Modifiers::NONE,
             }) => Some(Action::Move(Movement::ForwardChar(1))),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('Y'),
+                modifiers: Modifiers::CTRL,
+            }) => Some(Action::Yank(1)),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('y'),
+                modifiers: Modifiers::ALT,
+            }) => Some(Action::YankPop),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('_'),
+                modifiers: Modifiers::CTRL,
+            })
+            // Ctrl-_ is sent as 0x1f by most terminals
+            | InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('\x1f'),
+                modifiers: Modifiers::NONE,
+            }) => Some(Action::Undo),
+
             InputEvent::Key(KeyEvent {
                 key: KeyCode::Char(c),
                 modifiers: Modifiers::SHIFT,
@@ -512,123 +980,100 @@ impl<'term> LineEditor<'term> {
         }
     }
 
-    /// Compute the cursor position after applying movement
-    fn eval_movement(&self, movement: Movement) -> usize {
-        match movement {
-            Movement::BackwardChar(rep) => {
-                let mut position = self.cursor;
-                for _ in 0..rep {
-                    let mut cursor = GraphemeCursor::new(position, self.line.len(), false);
-                    if let Ok(Some(pos)) = cursor.prev_boundary(&self.line, 0) {
-                        position = pos;
-                    } else {
-                        break;
-                    }
-                }
-                position
-            }
-            Movement::BackwardWord(rep) => {
-                let char_indices: Vec<(usize, char)> = self.line.char_indices().collect();
-                if char_indices.is_empty() {
-                    return self.cursor;
-                }
-                let mut char_position = char_indices
-                    .iter()
-                    .position(|(idx, _)| *idx == self.cursor)
-                    .unwrap_or(char_indices.len() - 1);
-
-                for _ in 0..rep {
-                    if char_position == 0 {
-                        break;
-                    }
+    /// Returns the range of the vi visual mode selection, which
+    /// includes the character under the cursor
+    fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.vi.visual_anchor()?.min(self.buffer.line.len());
+        let (start, end) = if anchor <= self.buffer.cursor {
+            (anchor, self.buffer.cursor)
+        } else {
+            (self.buffer.cursor, anchor)
+        };
+        let mut cursor = GraphemeCursor::new(end, self.buffer.line.len(), false);
+        let end = match cursor.next_boundary(&self.buffer.line, 0) {
+            Ok(Some(end)) => end,
+            _ => self.buffer.line.len(),
+        };
+        Some(start..end)
+    }
 
-                    let mut found = None;
-                    for prev in (0..char_position - 1).rev() {
-                        if char_indices[prev].1.is_whitespace() {
-                            found = Some(prev + 1);
-                            break;
-                        }
-                    }
+    /// Removes the text covered by `kill_movement`.  If `move_movement`
+    /// is `None`, the cursor is left at the start of the removed text.
+    fn kill_text(&mut self, kill_movement: Movement, move_movement: Option<Movement>) {
+        self.clear_completion();
+        let range = self.buffer.movement_range(kill_movement);
+        let new_cursor = match move_movement {
+            Some(movement) => self.buffer.eval_movement(movement),
+            None => range.start,
+        };
 
-                    char_position = found.unwrap_or(0);
-                }
-                char_indices[char_position].0
-            }
-            Movement::ForwardWord(rep) => {
-                let char_indices: Vec<(usize, char)> = self.line.char_indices().collect();
-                if char_indices.is_empty() {
-                    return self.cursor;
-                }
-                let mut char_position = char_indices
-                    .iter()
-                    .position(|(idx, _)| *idx == self.cursor)
-                    .unwrap_or_else(|| char_indices.len());
+        // Deleting single characters is too fine grained to be worth
+        // remembering, and would push more useful text out of the ring
+        if !matches!(
+            kill_movement,
+            Movement::BackwardChar(_) | Movement::ForwardChar(_)
+        ) {
+            let backwards = range.end == self.buffer.cursor;
+            let text = self.buffer.line[range.clone()].to_string();
+            self.save_killed_text(&text, backwards);
+            self.killing = true;
+        } else {
+            self.killing = false;
+        }
 
-                for _ in 0..rep {
-                    // Skip any non-whitespace characters
-                    while char_position < char_indices.len()
-                        && !char_indices[char_position].1.is_whitespace()
-                    {
-                        char_position += 1;
-                    }
+        self.buffer.line.replace_range(range, "");
 
-                    // Skip any whitespace characters
-                    while char_position < char_indices.len()
-                        && char_indices[char_position].1.is_whitespace()
-                    {
-                        char_position += 1;
-                    }
+        // Clamp to the line length, otherwise a kill to end of line
+        // command will leave the cursor way off beyond the end of
+        // the line.
+        self.buffer.cursor = new_cursor.min(self.buffer.line.len());
+    }
 
-                    // We are now on the start of the next word
-                }
-                char_indices
-                    .get(char_position)
-                    .map(|(i, _)| *i)
-                    .unwrap_or_else(|| self.line.len())
-            }
-            Movement::ForwardChar(rep) => {
-                let mut position = self.cursor;
-                for _ in 0..rep {
-                    let mut cursor = GraphemeCursor::new(position, self.line.len(), false);
-                    if let Ok(Some(pos)) = cursor.next_boundary(&self.line, 0) {
-                        position = pos;
-                    } else {
-                        break;
-                    }
-                }
-                position
-            }
-            Movement::StartOfLine => 0,
-            Movement::EndOfLine => {
-                let mut cursor =
-                    GraphemeCursor::new(self.line.len().saturating_sub(1), self.line.len(), false);
-                if let Ok(Some(pos)) = cursor.next_boundary(&self.line, 0) {
-                    pos
+    /// Saves text to the kill ring.  In the emacs keymap, consecutive
+    /// kills are accumulated into a single entry, so that eg: repeated
+    /// Ctrl-W followed by Ctrl-Y restores all of the killed words.
+    fn save_killed_text(&mut self, text: &str, backwards: bool) {
+        if text.is_empty() {
+            return;
+        }
+        if self.killing && self.edit_mode == EditMode::Emacs {
+            if let Some(front) = self.kill_ring.front_mut() {
+                if backwards {
+                    front.insert_str(0, text);
                 } else {
-                    self.cursor
+                    front.push_str(text);
                 }
+                return;
             }
-            Movement::None => self.cursor,
         }
+        self.kill_ring.push_front(text.to_string());
+        self.kill_ring.truncate(KILL_RING_SIZE);
     }
 
-    fn kill_text(&mut self, kill_movement: Movement, move_movement: Movement) {
+    fn kill_range(&mut self, range: Range<usize>) {
         self.clear_completion();
-        let kill_pos = self.eval_movement(kill_movement);
-        let new_cursor = self.eval_movement(move_movement);
+        let text = self.buffer.line[range.clone()].to_string();
+        self.save_killed_text(&text, false);
+        self.buffer.line.replace_range(range.clone(), "");
+        self.buffer.cursor = range.start;
+    }
 
-        let (lower, upper) = if kill_pos < self.cursor {
-            (kill_pos, self.cursor)
+    /// Undoes the most recent change if `undo` is true, or re-applies
+    /// the most recently undone change if it is false
+    fn undo(&mut self, undo: bool) {
+        let (from, to) = if undo {
+            (&mut self.undo_stack, &mut self.redo_stack)
         } else {
-            (self.cursor, kill_pos)
+            (&mut self.redo_stack, &mut self.undo_stack)
         };
-
-        self.line.replace_range(lower..upper, "");
-
-        // Clamp to the line length, otherwise a kill to end of line
-        // command will leave the cursor way off beyond the end of
-        // the line.
-        self.cursor = new_cursor.min(self.line.len());
+        if let Some((line, cursor)) = from.pop() {
+            to.push((
+                std::mem::replace(&mut self.buffer.line, line),
+                self.buffer.cursor,
+            ));
+            self.buffer.cursor = cursor.min(self.buffer.line.len());
+        }
+        self.undo_coalesce = false;
     }
 
     fn clear_completion(&mut self) {
@@ -642,8 +1087,8 @@ impl<'term> LineEditor<'term> {
             ..
         } = &self.state
         {
-            self.line = matching_line.to_string();
-            self.cursor = *cursor;
+            self.buffer.line = matching_line.to_string();
+            self.buffer.cursor = *cursor;
             self.state = EditorState::Editing;
         }
     }
@@ -653,7 +1098,7 @@ impl<'term> LineEditor<'term> {
     /// a custom editor operation on the line buffer contents.
     /// The cursor position is the byte index into the line UTF-8 bytes.
     pub fn get_line_and_cursor(&mut self) -> (&str, usize) {
-        (&self.line, self.cursor)
+        (&self.buffer.line, self.buffer.cursor)
     }
 
     /// Sets the current line and cursor position.
@@ -668,8 +1113,8 @@ impl<'term> LineEditor<'term> {
             cursor,
             line.len()
         );
-        self.line = line.to_string();
-        self.cursor = cursor;
+        self.buffer.line = line.to_string();
+        self.buffer.cursor = cursor;
     }
 
     /// Call this after changing modifying the line buffer.
@@ -698,9 +1143,9 @@ impl<'term> LineEditor<'term> {
             let last_matching_line;
             let last_cursor;
 
-            if let Some(result) = host
-                .history()
-                .search(history_pos, *style, *direction, &self.line)
+            if let Some(result) =
+                host.history()
+                    .search(history_pos, *style, *direction, &self.buffer.line)
             {
                 self.history_pos.replace(result.idx);
                 last_matching_line = result.line.to_string();
@@ -732,8 +1177,8 @@ impl<'term> LineEditor<'term> {
         } else {
             // Not yet searching, so we start a new search
             // with an empty pattern
-            self.line.clear();
-            self.cursor = 0;
+            self.buffer.line.clear();
+            self.buffer.cursor = 0;
             self.history_pos.take();
         }
 
@@ -754,7 +1199,7 @@ impl<'term> LineEditor<'term> {
 
         let search_result = host
             .history()
-            .search(history_pos, style, direction, &self.line);
+            .search(history_pos, style, direction, &self.buffer.line);
 
         let last_matching_line;
         let last_cursor;
@@ -788,23 +1233,83 @@ impl<'term> LineEditor<'term> {
     /// You don't normally need to call this unless you are defining
     /// custom key mapping or custom actions in your embedding application.
     pub fn apply_action(&mut self, host: &mut dyn LineEditorHost, action: Action) -> Result<()> {
+        self.apply_actions(host, vec![action])
+    }
+
+    /// Applies a sequence of actions that together form a single command,
+    /// such as a vi operator and its motion, so that the command can be
+    /// undone in a single step
+    fn apply_actions(&mut self, host: &mut dyn LineEditorHost, actions: Vec<Action>) -> Result<()> {
+        let record_undo = !matches!(self.state, EditorState::Searching { .. })
+            && !actions
+                .iter()
+                .any(|action| matches!(action, Action::Undo | Action::Redo));
+        let before = (self.buffer.line.clone(), self.buffer.cursor);
+        let only_inserts = !actions.is_empty()
+            && actions
+                .iter()
+                .all(|action| matches!(action, Action::InsertChar(..)));
+
+        for action in actions {
+            self.apply_single_action(host, action)?;
+        }
+
+        if record_undo {
+            let changed = self.buffer.line != before.0;
+            let coalesce = match self.vi_mode() {
+                // The whole of an insert session is undone at once
+                Some(mode) => mode == ViMode::Insert,
+                None => only_inserts,
+            };
+            if changed && !(self.undo_coalesce && coalesce) {
+                self.undo_stack.push(before);
+                self.redo_stack.clear();
+            }
+            if changed || !coalesce {
+                self.undo_coalesce = changed && coalesce;
+            }
+        }
+
+        // In vi normal mode the cursor rests on a character rather than
+        // after the end of the line
+        if let (EditorState::Editing, Some(ViMode::Normal)) = (&self.state, self.vi_mode()) {
+            let bounds = self.buffer.line_bounds(self.buffer.cursor);
+            if self.buffer.cursor == bounds.end && self.buffer.cursor > bounds.start {
+                self.buffer.cursor = self.buffer.eval_movement(Movement::BackwardChar(1));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_single_action(&mut self, host: &mut dyn LineEditorHost, action: Action) -> Result<()> {
+        // These track state that is only relevant to the action
+        // that immediately follows a kill or a yank
+        let yank = self.yank.take();
+        self.killing = match action {
+            Action::Kill(_) | Action::KillAndMove(..) => std::mem::take(&mut self.killing),
+            _ => false,
+        };
+
+        // Editing the word that is being completed filters the menu
+        let refilter_completion = self.completion.is_some()
+            && matches!(
+                action,
+                Action::InsertChar(..)
+                    | Action::InsertText(..)
+                    | Action::Kill(Movement::BackwardChar(_))
+                    | Action::Kill(Movement::ForwardChar(_))
+            );
+
         // When searching, reinterpret history next/prev as repeated
         // search actions in the appropriate direction
         let action = match (action, &self.state) {
-            (
-                Action::HistoryPrevious,
-                EditorState::Searching {
-                    style: SearchStyle::Substring,
-                    ..
-                },
-            ) => Action::HistoryIncSearchBackwards,
-            (
-                Action::HistoryNext,
-                EditorState::Searching {
-                    style: SearchStyle::Substring,
-                    ..
-                },
-            ) => Action::HistoryIncSearchForwards,
+            (Action::HistoryPrevious, EditorState::Searching { .. }) => {
+                Action::HistoryIncSearchBackwards
+            }
+            (Action::HistoryNext, EditorState::Searching { .. }) => {
+                Action::HistoryIncSearchForwards
+            }
             (action, _) => action,
         };
 
@@ -817,7 +1322,13 @@ impl<'term> LineEditor<'term> {
                 // line to be accepted, rather than the search pattern!
                 self.cancel_search_state();
 
-                self.state = EditorState::Accepted;
+                if host.is_line_complete(&self.buffer.line) {
+                    self.state = EditorState::Accepted;
+                } else {
+                    self.clear_completion();
+                    self.buffer.line.insert(self.buffer.cursor, '\n');
+                    self.buffer.cursor += 1;
+                }
             }
             Action::EndOfFile => {
                 return Err(
@@ -825,36 +1336,102 @@ impl<'term> LineEditor<'term> {
                 )
             }
             Action::Kill(movement) => {
-                self.kill_text(movement, movement);
+                self.kill_text(movement, None);
                 self.reapply_search_pattern(host);
             }
             Action::KillAndMove(kill_movement, move_movement) => {
-                self.kill_text(kill_movement, move_movement);
+                self.kill_text(kill_movement, Some(move_movement));
+                self.reapply_search_pattern(host);
+            }
+            Action::KillTextObject(object) => {
+                self.cancel_search_state();
+                if let Some(range) = self.buffer.eval_text_object(object) {
+                    self.kill_range(range);
+                }
+            }
+            Action::Copy(movement) => {
+                self.cancel_search_state();
+                let range = self.buffer.movement_range(movement);
+                let text = self.buffer.line[range].to_string();
+                self.save_killed_text(&text, false);
+            }
+            Action::CopyTextObject(object) => {
+                self.cancel_search_state();
+                if let Some(range) = self.buffer.eval_text_object(object) {
+                    let text = self.buffer.line[range.clone()].to_string();
+                    self.save_killed_text(&text, false);
+                    self.buffer.cursor = range.start;
+                }
+            }
+            Action::Yank(rep) => {
+                self.clear_completion();
+                if let Some(text) = self.kill_ring.front() {
+                    let text = text.repeat(rep);
+                    let start = self.buffer.cursor;
+                    self.buffer.line.insert_str(start, &text);
+                    self.buffer.cursor += text.len();
+                    self.yank = Some(YankState {
+                        range: start..self.buffer.cursor,
+                        kill_ring_index: 0,
+                    });
+                }
                 self.reapply_search_pattern(host);
             }
+            Action::YankPop => {
+                if let Some(YankState {
+                    range,
+                    kill_ring_index,
+                }) = yank
+                {
+                    let kill_ring_index = (kill_ring_index + 1) % self.kill_ring.len();
+                    let text = &self.kill_ring[kill_ring_index];
+                    self.buffer.line.replace_range(range.clone(), text);
+                    self.buffer.cursor = range.start + text.len();
+                    self.yank = Some(YankState {
+                        range: range.start..self.buffer.cursor,
+                        kill_ring_index,
+                    });
+                    self.reapply_search_pattern(host);
+                }
+            }
+            Action::ReplaceChar(rep, c) => {
+                self.clear_completion();
+                self.cancel_search_state();
+                let end = self.buffer.eval_movement(Movement::ForwardChar(rep));
+                let bounds = self.buffer.line_bounds(self.buffer.cursor);
+                // Like vi, do nothing unless there are enough
+                // characters to replace on this line
+                let replaced = self.buffer.line[self.buffer.cursor..end]
+                    .graphemes(true)
+                    .count();
+                if end <= bounds.end && replaced == rep && rep > 0 {
+                    let replacement = c.to_string().repeat(rep);
+                    self.buffer
+                        .line
+                        .replace_range(self.buffer.cursor..end, &replacement);
+                    self.buffer.cursor += replacement.len() - c.len_utf8();
+                }
+            }
+            Action::Undo => self.undo(true),
+            Action::Redo => self.undo(false),
 
             Action::Move(movement) => {
                 self.clear_completion();
                 self.cancel_search_state();
-                self.cursor = self.eval_movement(movement);
+                self.buffer.cursor = self.buffer.eval_movement(movement);
             }
 
             Action::InsertChar(rep, c) => {
                 self.clear_completion();
                 for _ in 0..rep {
-                    self.line.insert(self.cursor, c);
-                    let mut cursor = GraphemeCursor::new(self.cursor, self.line.len(), false);
-                    if let Ok(Some(pos)) = cursor.next_boundary(&self.line, 0) {
-                        self.cursor = pos;
-                    }
+                    self.buffer.insert_char(c);
                 }
                 self.reapply_search_pattern(host);
             }
             Action::InsertText(rep, text) => {
                 self.clear_completion();
                 for _ in 0..rep {
-                    self.line.insert_str(self.cursor, &text);
-                    self.cursor += text.len();
+                    self.buffer.insert_text(&text);
                 }
                 self.reapply_search_pattern(host);
             }
@@ -866,113 +1443,220 @@ impl<'term> LineEditor<'term> {
                 self.clear_completion();
                 self.cancel_search_state();
 
-                if let Some(cur_pos) = self.history_pos.as_ref() {
+                if self.buffer.lines_around_cursor().0 > 0 {
+                    // Move within a multi-line buffer before moving
+                    // through the history
+                    self.buffer.cursor = self.buffer.eval_movement(Movement::PreviousLine(1));
+                } else if let Some(cur_pos) = self.history_pos.as_ref() {
                     let prior_idx = cur_pos.saturating_sub(1);
                     if let Some(prior) = host.history().get(prior_idx) {
                         self.history_pos = Some(prior_idx);
-                        self.line = prior.to_string();
-                        self.cursor = self.line.len();
+                        self.buffer.line = prior.to_string();
+                        self.buffer.cursor = self.buffer.line.len();
                     }
                 } else if let Some(last) = host.history().last() {
-                    self.bottom_line = Some(self.line.clone());
+                    self.bottom_line = Some(self.buffer.line.clone());
                     self.history_pos = Some(last);
-                    self.line = host
+                    self.buffer.line = host
                         .history()
                         .get(last)
                         .expect("History::last and History::get to be consistent")
                         .to_string();
-                    self.cursor = self.line.len();
+                    self.buffer.cursor = self.buffer.line.len();
                 }
             }
             Action::HistoryNext => {
                 self.clear_completion();
                 self.cancel_search_state();
 
-                if let Some(cur_pos) = self.history_pos.as_ref() {
+                if self.buffer.lines_around_cursor().1 > 0 {
+                    self.buffer.cursor = self.buffer.eval_movement(Movement::NextLine(1));
+                } else if let Some(cur_pos) = self.history_pos.as_ref() {
                     let next_idx = cur_pos.saturating_add(1);
                     if let Some(next) = host.history().get(next_idx) {
                         self.history_pos = Some(next_idx);
-                        self.line = next.to_string();
-                        self.cursor = self.line.len();
+                        self.buffer.line = next.to_string();
+                        self.buffer.cursor = self.buffer.line.len();
                     } else if let Some(bottom) = self.bottom_line.take() {
-                        self.line = bottom;
-                        self.cursor = self.line.len();
+                        self.buffer.line = bottom;
+                        self.buffer.cursor = self.buffer.line.len();
                     } else {
-                        self.line.clear();
-                        self.cursor = 0;
+                        self.buffer.line.clear();
+                        self.buffer.cursor = 0;
                     }
                 }
             }
 
             Action::HistoryIncSearchBackwards => {
-                self.trigger_search(SearchStyle::Substring, SearchDirection::Backwards, host);
+                self.trigger_search(self.search_style, SearchDirection::Backwards, host);
             }
             Action::HistoryIncSearchForwards => {
-                self.trigger_search(SearchStyle::Substring, SearchDirection::Forwards, host);
+                self.trigger_search(self.search_style, SearchDirection::Forwards, host);
             }
 
             Action::Complete => {
                 self.cancel_search_state();
 
                 if self.completion.is_none() {
-                    let candidates = host.complete(&self.line, self.cursor);
-                    if !candidates.is_empty() {
-                        let state = CompletionState {
-                            candidates,
-                            index: 0,
-                            original_line: self.line.clone(),
-                            original_cursor: self.cursor,
-                        };
-
-                        let (cursor, line) = state.current();
-                        self.cursor = cursor;
-                        self.line = line;
-
-                        // If there is only a single completion then don't
-                        // leave us in a state where we just cycle on the
-                        // same completion over and over.
-                        if state.candidates.len() > 1 {
-                            self.completion = Some(state);
-                        }
-                    }
-                } else if let Some(state) = self.completion.as_mut() {
-                    state.next();
-                    let (cursor, line) = state.current();
-                    self.cursor = cursor;
-                    self.line = line;
+                    self.start_completion(host);
+                } else {
+                    self.select_completion(CompletionMovement::Next);
+                }
+            }
+            Action::SelectCompletion(movement) => {
+                self.select_completion(movement);
+            }
+            Action::AcceptCompletion => {
+                self.clear_completion();
+            }
+            Action::CancelCompletion => {
+                if let Some(state) = self.completion.take() {
+                    self.buffer.line = state.original_line;
+                    self.buffer.cursor = state.original_cursor;
                 }
             }
         }
 
+        if refilter_completion {
+            let candidates = host.complete(&self.buffer.line, self.buffer.cursor);
+            if !candidates.is_empty() {
+                self.completion = Some(CompletionState::new(
+                    candidates,
+                    &self.buffer.line,
+                    self.buffer.cursor,
+                ));
+            }
+        }
+
         Ok(())
     }
 
+    fn start_completion(&mut self, host: &mut dyn LineEditorHost) {
+        let candidates = host.complete(&self.buffer.line, self.buffer.cursor);
+        let mut state = CompletionState::new(candidates, &self.buffer.line, self.buffer.cursor);
+        match state.candidates.len() {
+            0 => {}
+            1 => {
+                // There's no need to show a menu for a single candidate
+                state.index = Some(0);
+                let (cursor, line) = state.current();
+                self.buffer.cursor = cursor;
+                self.buffer.line = line;
+            }
+            _ => {
+                // Insert the text that all of the candidates have in
+                // common, and then list them in the menu
+                if let Some(prefix) = state.common_prefix() {
+                    let range = state.candidates[0].range.clone();
+                    self.buffer.line.replace_range(range.clone(), &prefix);
+                    self.buffer.cursor =
+                        self.buffer.cursor + prefix.len() - (range.end - range.start);
+                    let mut candidates = state.candidates;
+                    for candidate in &mut candidates {
+                        candidate.range = range.start..range.start + prefix.len();
+                    }
+                    state = CompletionState::new(candidates, &self.buffer.line, self.buffer.cursor);
+                }
+                self.completion = Some(state);
+            }
+        }
+    }
+
+    fn select_completion(&mut self, movement: CompletionMovement) {
+        if let Some(state) = self.completion.as_mut() {
+            state.select(movement);
+            let (cursor, line) = state.current();
+            self.buffer.cursor = cursor;
+            self.buffer.line = line;
+        }
+    }
+
     fn read_line_impl(&mut self, host: &mut dyn LineEditorHost) -> Result<Option<String>> {
-        self.line.clear();
-        self.cursor = 0;
+        self.buffer.line.clear();
+        self.buffer.cursor = 0;
         self.history_pos = None;
         self.bottom_line = None;
         self.clear_completion();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.undo_coalesce = false;
+        self.vi.reset();
 
         self.render(host)?;
         while let Some(event) = self.terminal.poll_input(None)? {
-            if let Some(action) = self.resolve_action(&event, host) {
-                self.apply_action(host, action)?;
+            if let Some(actions) = self.resolve_actions(&event, host) {
+                self.apply_actions(host, actions)?;
                 // Editor state might have changed. Re-render to clear
                 // preview or highlight lines differently.
                 self.render(host)?;
                 match self.state {
                     EditorState::Searching { .. } | EditorState::Editing => {}
                     EditorState::Cancelled => return Ok(None),
-                    EditorState::Accepted => return Ok(Some(self.line.clone())),
+                    EditorState::Accepted => return Ok(Some(self.buffer.line.clone())),
                     EditorState::Inactive => bail!("editor is inactive during read line!?"),
                 }
             } else {
                 self.render(host)?;
             }
         }
-        Ok(Some(self.line.clone()))
+        Ok(Some(self.buffer.line.clone()))
+    }
+}
+
+/// Renders the portion of the highlighted line that falls within
+/// `selection`, a byte range of the line, in reverse video.
+/// This relies on the host preserving the text of the line when
+/// highlighting it, as `LineEditorHost::highlight_line` requires.
+fn highlight_selection(
+    elements: Vec<OutputElement>,
+    selection: Range<usize>,
+) -> Vec<OutputElement> {
+    let mut result = vec![];
+    let mut offset = 0;
+    let mut selected = false;
+    for element in elements {
+        match element {
+            OutputElement::Text(text) => {
+                let mut remaining = text.as_str();
+                let mut start = offset;
+                while !remaining.is_empty() {
+                    let in_selection = selection.contains(&start);
+                    let boundary = if in_selection {
+                        selection.end
+                    } else if start < selection.start {
+                        selection.start
+                    } else {
+                        usize::MAX
+                    };
+                    let mut len = boundary.saturating_sub(start).min(remaining.len());
+                    if !remaining.is_char_boundary(len) {
+                        len = remaining.len();
+                    }
+                    if in_selection != selected {
+                        result.push(OutputElement::Attribute(AttributeChange::Reverse(
+                            in_selection,
+                        )));
+                        selected = in_selection;
+                    }
+                    result.push(OutputElement::Text(remaining[..len].to_string()));
+                    remaining = &remaining[len..];
+                    start += len;
+                }
+                offset += text.len();
+            }
+            OutputElement::AllAttributes(attrs) => {
+                result.push(OutputElement::AllAttributes(attrs));
+                if selected {
+                    result.push(OutputElement::Attribute(AttributeChange::Reverse(true)));
+                }
+            }
+            element => result.push(element),
+        }
+    }
+    if selected {
+        result.push(OutputElement::Attribute(AttributeChange::Reverse(false)));
     }
+    result
 }
 
 /// Create a `Terminal` with the recommended settings for use with
@@ -982,3 +1666,321 @@ pub fn line_editor_terminal() -> Result<impl Terminal> {
     let caps = Capabilities::new_with_hints(hints)?;
     new_terminal(caps)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::terminal::{ScreenSize, TerminalWaker};
+    use std::time::Duration;
+
+    /// A terminal that replays a scripted sequence of input events
+    struct ScriptedTerminal {
+        input: VecDeque<InputEvent>,
+    }
+
+    impl Terminal for ScriptedTerminal {
+        fn set_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn set_cooked_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn enter_alternate_screen(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn exit_alternate_screen(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn get_screen_size(&mut self) -> Result<ScreenSize> {
+            Ok(ScreenSize {
+                rows: 24,
+                cols: 80,
+                xpixel: 0,
+                ypixel: 0,
+            })
+        }
+
+        fn set_screen_size(&mut self, _size: ScreenSize) -> Result<()> {
+            Ok(())
+        }
+
+        fn render(&mut self, _changes: &[Change]) -> Result<()> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn poll_input(&mut self, _wait: Option<Duration>) -> Result<Option<InputEvent>> {
+            Ok(self.input.pop_front())
+        }
+
+        fn waker(&self) -> TerminalWaker {
+            unimplemented!();
+        }
+    }
+
+    fn key(key: KeyCode, modifiers: Modifiers) -> InputEvent {
+        InputEvent::Key(KeyEvent { key, modifiers })
+    }
+
+    /// Translates `text` into key presses; ESC, CR and TAB are sent as
+    /// the Escape, Enter and Tab keys, and other control characters as
+    /// their CTRL-modified letters
+    fn keys(text: &str) -> Vec<InputEvent> {
+        text.chars()
+            .map(|c| match c {
+                '\x1b' => key(KeyCode::Escape, Modifiers::NONE),
+                '\r' => key(KeyCode::Enter, Modifiers::NONE),
+                '\t' => key(KeyCode::Tab, Modifiers::NONE),
+                '\x01'..='\x1a' => {
+                    key(KeyCode::Char((c as u8 + b'A' - 1) as char), Modifiers::CTRL)
+                }
+                c => key(KeyCode::Char(c), Modifiers::NONE),
+            })
+            .collect()
+    }
+
+    fn edit_with_host(
+        mode: EditMode,
+        input: Vec<InputEvent>,
+        host: &mut dyn LineEditorHost,
+    ) -> Option<String> {
+        let mut terminal = ScriptedTerminal {
+            input: input.into(),
+        };
+        let mut editor = LineEditor::new(&mut terminal);
+        editor.set_edit_mode(mode);
+        editor.read_line(host).unwrap()
+    }
+
+    fn edit(mode: EditMode, input: &str) -> String {
+        let mut host = NopLineEditorHost::default();
+        edit_with_host(mode, keys(input), &mut host).unwrap()
+    }
+
+    #[test]
+    fn emacs_kill_and_yank() {
+        // Consecutive kills are accumulated into a single entry
+        assert_eq!(edit(EditMode::Emacs, "one two\x17\x17\x19"), "one two");
+        assert_eq!(edit(EditMode::Emacs, "foo bar\x17\x01\x19"), "barfoo ");
+
+        let mut input = keys("x\x17y\x17\x19");
+        input.push(key(KeyCode::Char('y'), Modifiers::ALT));
+        let mut host = NopLineEditorHost::default();
+        assert_eq!(
+            edit_with_host(EditMode::Emacs, input, &mut host).unwrap(),
+            "x"
+        );
+    }
+
+    #[test]
+    fn emacs_undo() {
+        // Typing is undone as a single step
+        assert_eq!(edit(EditMode::Emacs, "foo bar\x1f"), "");
+        assert_eq!(edit(EditMode::Emacs, "foo\x17bar\x1f"), "");
+        assert_eq!(edit(EditMode::Emacs, "foo\x17bar\x1f\x1f"), "foo");
+    }
+
+    #[test]
+    fn vi_motions_and_operators() {
+        assert_eq!(edit(EditMode::Vi, "foo bar baz\x1b0dw"), "bar baz");
+        assert_eq!(edit(EditMode::Vi, "foo bar baz\x1b02dw"), "baz");
+        assert_eq!(edit(EditMode::Vi, "foo bar baz\x1b0d2w"), "baz");
+        assert_eq!(edit(EditMode::Vi, "foo.bar baz\x1b0dw"), ".bar baz");
+        assert_eq!(edit(EditMode::Vi, "foo.bar baz\x1b0dW"), "baz");
+        assert_eq!(edit(EditMode::Vi, "foo bar baz\x1b0de"), " bar baz");
+        assert_eq!(edit(EditMode::Vi, "foo bar baz\x1bdb"), "foo bar z");
+        assert_eq!(edit(EditMode::Vi, "a,b,c,d\x1b0f,;D"), "a,b");
+        assert_eq!(edit(EditMode::Vi, "a,b,c,d\x1b0dt,"), ",b,c,d");
+        assert_eq!(edit(EditMode::Vi, "a,b,c,d\x1bdF,"), "a,b,cd");
+        assert_eq!(edit(EditMode::Vi, "  indented\x1b^d$"), "  ");
+        assert_eq!(edit(EditMode::Vi, "abcd\x1b0x"), "bcd");
+        assert_eq!(edit(EditMode::Vi, "abcd\x1bX"), "abd");
+        assert_eq!(edit(EditMode::Vi, "abcd\x1b02rx"), "xxcd");
+        assert_eq!(edit(EditMode::Vi, "abcd\x1bdd"), "");
+    }
+
+    #[test]
+    fn vi_change() {
+        assert_eq!(edit(EditMode::Vi, "foo bar\x1b0cwqux\x1b"), "qux bar");
+        // cw on the last character of a word only changes that character
+        assert_eq!(edit(EditMode::Vi, "foo bar\x1b0llcwX\x1b"), "foX bar");
+        assert_eq!(edit(EditMode::Vi, "foo bar\x1bbCbaz\x1b"), "foo baz");
+        assert_eq!(edit(EditMode::Vi, "foo bar\x1bSnew\x1b"), "new");
+        assert_eq!(edit(EditMode::Vi, "foo\x1b0iX\x1bAY\x1b"), "XfooY");
+    }
+
+    #[test]
+    fn vi_text_objects() {
+        assert_eq!(
+            edit(EditMode::Vi, "say \"hello there\" now\x1b0fhci\"bye\x1b"),
+            "say \"bye\" now"
+        );
+        assert_eq!(
+            edit(EditMode::Vi, "say \"hello there\" now\x1b0fhda\""),
+            "say  now"
+        );
+        assert_eq!(edit(EditMode::Vi, "call(a, (b))\x1b0fbdi("), "call(a, ())");
+        assert_eq!(edit(EditMode::Vi, "call(a, (b))\x1b0f,di("), "call()");
+        assert_eq!(edit(EditMode::Vi, "call(a, (b))\x1b0f,da("), "call");
+        assert_eq!(edit(EditMode::Vi, "one two three\x1b0fwdiw"), "one  three");
+        assert_eq!(edit(EditMode::Vi, "one two three\x1b0fwdaw"), "one three");
+    }
+
+    #[test]
+    fn vi_repeat_undo_and_put() {
+        assert_eq!(edit(EditMode::Vi, "a b c d\x1b0dw.."), "d");
+        assert_eq!(edit(EditMode::Vi, "a b c d\x1b0cwX\x1bw."), "X X c d");
+        assert_eq!(edit(EditMode::Vi, "foo bar\x1b0dwu"), "foo bar");
+        assert_eq!(edit(EditMode::Vi, "foo bar\x1b0dwu\x12"), "bar");
+        // The whole insert session is undone at once
+        assert_eq!(edit(EditMode::Vi, "foo\x1bobar baz\x1bu"), "foo");
+        assert_eq!(edit(EditMode::Vi, "abc\x1b0xp"), "bac");
+        assert_eq!(edit(EditMode::Vi, "foo bar\x1b0yw$p"), "foo barfoo ");
+        assert_eq!(edit(EditMode::Vi, "foo bar\x1b0ywP"), "foo foo bar");
+    }
+
+    #[test]
+    fn vi_visual() {
+        assert_eq!(edit(EditMode::Vi, "hello world\x1b0vlld"), "lo world");
+        assert_eq!(edit(EditMode::Vi, "hello world\x1bvbd"), "hello ");
+        assert_eq!(
+            edit(EditMode::Vi, "hello world\x1b0vecbye\x1b"),
+            "bye world"
+        );
+        assert_eq!(
+            edit(EditMode::Vi, "hello world\x1b0vey$p"),
+            "hello worldhello"
+        );
+    }
+
+    #[test]
+    fn multi_line() {
+        struct Host {
+            history: BasicHistory,
+        }
+        impl LineEditorHost for Host {
+            fn history(&mut self) -> &mut dyn History {
+                &mut self.history
+            }
+
+            fn is_line_complete(&self, line: &str) -> bool {
+                !line.ends_with('\\')
+            }
+        }
+        let mut host = Host {
+            history: BasicHistory::default(),
+        };
+
+        let line = edit_with_host(EditMode::Emacs, keys("one\\\rtwo\rthree"), &mut host);
+        assert_eq!(line.unwrap(), "one\\\ntwo");
+
+        // Up moves within the buffer before moving through the history
+        let mut input = keys("ab");
+        input.push(key(KeyCode::Enter, Modifiers::ALT));
+        input.extend(keys("cd"));
+        input.push(key(KeyCode::UpArrow, Modifiers::NONE));
+        input.extend(keys("X\x01Y"));
+        let line = edit_with_host(EditMode::Emacs, input, &mut host);
+        assert_eq!(line.unwrap(), "YabX\ncd");
+
+        // dd removes the current line of the buffer
+        let mut input = keys("ab");
+        input.push(key(KeyCode::Enter, Modifiers::ALT));
+        input.extend(keys("cd\x1bkdd"));
+        let line = edit_with_host(EditMode::Vi, input, &mut host);
+        assert_eq!(line.unwrap(), "cd");
+    }
+
+    struct CompletingHost {
+        history: BasicHistory,
+        words: Vec<&'static str>,
+    }
+
+    impl LineEditorHost for CompletingHost {
+        fn history(&mut self) -> &mut dyn History {
+            &mut self.history
+        }
+
+        fn complete(&self, line: &str, cursor_position: usize) -> Vec<CompletionCandidate> {
+            let start = line[..cursor_position]
+                .rfind(' ')
+                .map(|idx| idx + 1)
+                .unwrap_or(0);
+            let word = &line[start..cursor_position];
+            self.words
+                .iter()
+                .filter(|w| w.starts_with(word))
+                .map(|w| CompletionCandidate::new(start..cursor_position, *w))
+                .collect()
+        }
+    }
+
+    fn complete(input: Vec<InputEvent>) -> String {
+        let mut host = CompletingHost {
+            history: BasicHistory::default(),
+            words: vec!["hello", "help", "hat", "world"],
+        };
+        edit_with_host(EditMode::Emacs, input, &mut host).unwrap()
+    }
+
+    #[test]
+    fn completion_menu() {
+        // A sole candidate is inserted immediately
+        assert_eq!(complete(keys("say w\t")), "say world");
+        // Otherwise the common prefix is inserted, and Tab cycles
+        // through the candidates
+        assert_eq!(complete(keys("say he\t")), "say hel");
+        assert_eq!(complete(keys("say he\t\t")), "say hello");
+        assert_eq!(complete(keys("say he\t\t\t")), "say help");
+        assert_eq!(complete(keys("say he\t\t\t\t")), "say hello");
+
+        let mut input = keys("h\t\t\t");
+        input.push(key(KeyCode::Tab, Modifiers::SHIFT));
+        assert_eq!(complete(input), "hello");
+
+        // Escape restores the line
+        assert_eq!(complete(keys("say h\t\t\t\x1b")), "say h");
+
+        // Typing filters the candidates in the menu
+        assert_eq!(complete(keys("h\te\t\t")), "help");
+        assert_eq!(complete(keys("h\tex\t")), "hex");
+
+        // Enter keeps the selection rather than accepting the line
+        assert_eq!(complete(keys("h\t\t\r!")), "hello!");
+        assert_eq!(complete(keys("h\t\r!")), "h");
+    }
+
+    #[test]
+    fn completion_menu_navigation() {
+        let candidates = (0..10)
+            .map(|i| CompletionCandidate::new(0..0, i.to_string()))
+            .collect();
+        let mut state = CompletionState::new(candidates, "", 0);
+        state.columns = 3;
+        state.page_rows = 2;
+
+        let mut select = |movement| {
+            state.select(movement);
+            state.index.unwrap()
+        };
+        assert_eq!(select(CompletionMovement::Up), 9);
+        assert_eq!(select(CompletionMovement::Down), 0);
+        assert_eq!(select(CompletionMovement::Right), 1);
+        assert_eq!(select(CompletionMovement::Down), 4);
+        assert_eq!(select(CompletionMovement::Down), 7);
+        assert_eq!(select(CompletionMovement::Down), 1);
+        assert_eq!(select(CompletionMovement::Up), 7);
+        assert_eq!(select(CompletionMovement::Left), 6);
+        assert_eq!(select(CompletionMovement::NextPage), 9);
+        assert_eq!(select(CompletionMovement::PreviousPage), 3);
+        assert_eq!(select(CompletionMovement::Next), 4);
+        assert_eq!(select(CompletionMovement::Previous), 3);
+    }
+}
@@ -0,0 +1,617 @@
+//! A modal keymap that emulates the editing commands of vi
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
+use crate::lineedit::actions::{Action, Movement, RepeatCount, TextObject};
+use crate::lineedit::{char_class, CharClass};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The modes of the vi keymap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViMode {
+    /// Keys are interpreted as commands
+    Normal,
+    /// Keys insert text, with the same bindings as the emacs keymap
+    Insert,
+    /// Movement commands extend a selection that can then be
+    /// deleted, changed or copied
+    Visual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Copy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    /// Waiting for the character argument of f, F, t or T
+    Find(FindChar, RepeatCount),
+    /// Waiting for the replacement character of r
+    Replace(RepeatCount),
+    /// Waiting for the kind of text object after i or a
+    TextObject { around: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FindChar {
+    forward: bool,
+    till: bool,
+    c: char,
+}
+
+impl FindChar {
+    fn movement(self, count: RepeatCount) -> Movement {
+        match (self.forward, self.till) {
+            (true, false) => Movement::ForwardFind(count, self.c),
+            (true, true) => Movement::ForwardTill(count, self.c),
+            (false, false) => Movement::BackwardFind(count, self.c),
+            (false, true) => Movement::BackwardTill(count, self.c),
+        }
+    }
+
+    fn reversed(self) -> Self {
+        Self {
+            forward: !self.forward,
+            ..self
+        }
+    }
+}
+
+/// Translates input events into editor actions according to the
+/// current vi mode, and keeps track of the state that spans multiple
+/// keystrokes: counts, pending operators, the most recent character
+/// search and the most recent change, which is replayed by `.`.
+pub(crate) struct ViKeymap {
+    mode: ViMode,
+    count: Option<RepeatCount>,
+    /// The pending operator and the count that was typed before it
+    operator: Option<(Operator, Option<RepeatCount>)>,
+    pending: Option<Pending>,
+    last_find: Option<FindChar>,
+    visual_anchor: usize,
+    /// The actions of the change that is being made in insert mode
+    recording: Option<Vec<Action>>,
+    last_change: Vec<Action>,
+}
+
+impl ViKeymap {
+    pub fn new() -> Self {
+        Self {
+            mode: ViMode::Insert,
+            count: None,
+            operator: None,
+            pending: None,
+            last_find: None,
+            visual_anchor: 0,
+            recording: None,
+            last_change: vec![],
+        }
+    }
+
+    /// Prepare for a new line; editing starts in insert mode
+    pub fn reset(&mut self) {
+        self.mode = ViMode::Insert;
+        self.cancel_pending();
+        self.recording = None;
+    }
+
+    pub fn mode(&self) -> ViMode {
+        self.mode
+    }
+
+    /// Returns the position at which the selection was started
+    /// when in visual mode
+    pub fn visual_anchor(&self) -> Option<usize> {
+        match self.mode {
+            ViMode::Visual => Some(self.visual_anchor),
+            _ => None,
+        }
+    }
+
+    fn cancel_pending(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.pending = None;
+    }
+
+    /// Returns the effective count for a command, taking into account
+    /// any count that was typed before a pending operator
+    fn take_count(&mut self) -> RepeatCount {
+        let count = self.count.take().unwrap_or(1);
+        match self.operator {
+            Some((_, Some(op_count))) => op_count * count,
+            _ => count,
+        }
+    }
+
+    /// Resolves `event` to the actions that should be applied to the
+    /// editor, whose buffer and cursor are `line` and `cursor`.
+    /// `fallback` is the action that the emacs keymap would produce;
+    /// it is used in insert mode and for keys that have no meaning
+    /// in normal mode.
+    pub fn resolve(
+        &mut self,
+        event: &InputEvent,
+        line: &str,
+        cursor: usize,
+        fallback: Option<Action>,
+    ) -> Option<Vec<Action>> {
+        match self.mode {
+            ViMode::Insert => self.resolve_insert(event, fallback),
+            ViMode::Normal | ViMode::Visual => {
+                let actions = self.resolve_command(event, line, cursor);
+                match actions {
+                    Some(actions) => Some(actions),
+                    None => match fallback {
+                        // Typing in normal mode is not a thing
+                        Some(Action::InsertChar(..)) | None => None,
+                        Some(action) => {
+                            self.cancel_pending();
+                            Some(vec![action])
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    fn resolve_insert(
+        &mut self,
+        event: &InputEvent,
+        fallback: Option<Action>,
+    ) -> Option<Vec<Action>> {
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape,
+                modifiers: Modifiers::NONE,
+            })
+            | InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('['),
+                modifiers: Modifiers::CTRL,
+            }) => {
+                self.mode = ViMode::Normal;
+                // The cursor moves back onto the last inserted character
+                let actions = vec![Action::Move(Movement::BackwardChar(1))];
+                if let Some(mut recording) = self.recording.take() {
+                    recording.extend(actions.iter().cloned());
+                    self.last_change = recording;
+                }
+                Some(actions)
+            }
+            _ => {
+                let action = fallback?;
+                if let Some(recording) = self.recording.as_mut() {
+                    match &action {
+                        Action::InsertChar(..)
+                        | Action::InsertText(..)
+                        | Action::Kill(_)
+                        | Action::KillAndMove(..)
+                        | Action::Move(_)
+                        | Action::Yank(_)
+                        | Action::YankPop => recording.push(action.clone()),
+                        _ => {}
+                    }
+                }
+                Some(vec![action])
+            }
+        }
+    }
+
+    /// Records `actions` as the most recent change, or, if `insert`
+    /// is true, as the start of a change that continues in insert mode
+    fn change(&mut self, actions: Vec<Action>, insert: bool) -> Option<Vec<Action>> {
+        if insert {
+            self.mode = ViMode::Insert;
+            self.recording = Some(actions.clone());
+        } else {
+            self.mode = ViMode::Normal;
+            self.last_change = actions.clone();
+        }
+        Some(actions)
+    }
+
+    fn resolve_command(
+        &mut self,
+        event: &InputEvent,
+        line: &str,
+        cursor: usize,
+    ) -> Option<Vec<Action>> {
+        let (key, modifiers) = match event {
+            InputEvent::Key(KeyEvent { key, modifiers }) => (*key, *modifiers),
+            _ => return None,
+        };
+
+        let c = match (key, modifiers) {
+            (KeyCode::Char(c), Modifiers::NONE) | (KeyCode::Char(c), Modifiers::SHIFT) => Some(c),
+            _ => None,
+        };
+
+        if let Some(pending) = self.pending.take() {
+            let c = match c {
+                Some(c) => c,
+                None => {
+                    self.cancel_pending();
+                    return Some(vec![]);
+                }
+            };
+            return match pending {
+                Pending::Find(find, count) => {
+                    let find = FindChar { c, ..find };
+                    self.last_find = Some(find);
+                    self.motion(find.movement(count), line, cursor)
+                }
+                Pending::Replace(count) => {
+                    self.cancel_pending();
+                    self.change(vec![Action::ReplaceChar(count, c)], false)
+                }
+                Pending::TextObject { around } => self.text_object(c, around),
+            };
+        }
+
+        // Counts
+        if let Some(c) = c {
+            if c.is_ascii_digit() && (c != '0' || self.count.is_some()) {
+                let digit = c.to_digit(10).unwrap() as RepeatCount;
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10) + digit);
+                return Some(vec![]);
+            }
+        }
+
+        let count = self.take_count();
+
+        match (key, modifiers) {
+            (KeyCode::Escape, Modifiers::NONE) | (KeyCode::Char('['), Modifiers::CTRL) => {
+                self.cancel_pending();
+                self.mode = ViMode::Normal;
+                return Some(vec![]);
+            }
+            (KeyCode::Char('R'), Modifiers::CTRL) => {
+                self.cancel_pending();
+                return Some(vec![Action::Redo; count]);
+            }
+            (KeyCode::LeftArrow, Modifiers::NONE) | (KeyCode::Backspace, Modifiers::NONE) => {
+                return self.motion(Movement::BackwardChar(count), line, cursor)
+            }
+            (KeyCode::RightArrow, Modifiers::NONE) => {
+                return self.motion(Movement::ForwardChar(count), line, cursor)
+            }
+            (KeyCode::Home, Modifiers::NONE) => {
+                return self.motion(Movement::StartOfLine, line, cursor)
+            }
+            (KeyCode::End, Modifiers::NONE) => {
+                return self.motion(Movement::EndOfLine, line, cursor)
+            }
+            _ => {}
+        }
+
+        let c = c?;
+
+        // Motions, which are either used to move the cursor or
+        // as the extent of a pending operator
+        let movement = match c {
+            'h' => Some(Movement::BackwardChar(count)),
+            'l' | ' ' => Some(Movement::ForwardChar(count)),
+            'w' => Some(Movement::ForwardViWord(count)),
+            'W' => Some(Movement::ForwardWord(count)),
+            'b' => Some(Movement::BackwardViWord(count)),
+            'B' => Some(Movement::BackwardWord(count)),
+            'e' => Some(Movement::ForwardViWordEnd(count)),
+            'E' => Some(Movement::ForwardWordEnd(count)),
+            '0' => Some(Movement::StartOfLine),
+            '^' => Some(Movement::FirstNonBlank),
+            '$' => Some(Movement::EndOfLine),
+            ';' => self.last_find.map(|find| find.movement(count)),
+            ',' => self.last_find.map(|find| find.reversed().movement(count)),
+            'f' | 'F' | 't' | 'T' => {
+                let find = FindChar {
+                    forward: c == 'f' || c == 't',
+                    till: c == 't' || c == 'T',
+                    c,
+                };
+                self.pending.replace(Pending::Find(find, count));
+                return Some(vec![]);
+            }
+            _ => None,
+        };
+        if let Some(movement) = movement {
+            return self.motion(movement, line, cursor);
+        }
+
+        if let Some((op, _)) = self.operator {
+            return match (op, c) {
+                (Operator::Delete, 'd') => {
+                    self.cancel_pending();
+                    self.change(vec![Action::KillTextObject(TextObject::Line); count], false)
+                }
+                (Operator::Change, 'c') => {
+                    self.cancel_pending();
+                    self.change(
+                        vec![
+                            Action::Move(Movement::StartOfLine),
+                            Action::Kill(Movement::EndOfLine),
+                        ],
+                        true,
+                    )
+                }
+                (Operator::Copy, 'y') => {
+                    self.cancel_pending();
+                    Some(vec![Action::CopyTextObject(TextObject::Line)])
+                }
+                (_, 'i') | (_, 'a') => {
+                    self.pending
+                        .replace(Pending::TextObject { around: c == 'a' });
+                    Some(vec![])
+                }
+                _ => {
+                    self.cancel_pending();
+                    Some(vec![])
+                }
+            };
+        }
+
+        if self.mode == ViMode::Visual {
+            return self.resolve_visual(c, line, cursor);
+        }
+
+        match c {
+            'd' | 'c' | 'y' => {
+                let op = match c {
+                    'd' => Operator::Delete,
+                    'c' => Operator::Change,
+                    _ => Operator::Copy,
+                };
+                self.operator.replace((op, Some(count)));
+                Some(vec![])
+            }
+            'D' => self.change(Self::delete(Movement::EndOfLine), false),
+            'C' => self.change(Self::delete(Movement::EndOfLine), true),
+            'Y' => Some(vec![Action::CopyTextObject(TextObject::Line)]),
+            'x' => self.change(Self::delete(Movement::ForwardChar(count)), false),
+            'X' => self.change(Self::delete(Movement::BackwardChar(count)), false),
+            's' => self.change(Self::delete(Movement::ForwardChar(count)), true),
+            'S' => self.change(
+                vec![
+                    Action::Move(Movement::StartOfLine),
+                    Action::Kill(Movement::EndOfLine),
+                ],
+                true,
+            ),
+            'p' => {
+                let mut actions = vec![];
+                if !at_end_of_line(line, cursor) {
+                    actions.push(Action::Move(Movement::ForwardChar(1)));
+                }
+                actions.push(Action::Yank(count));
+                actions.push(Action::Move(Movement::BackwardChar(1)));
+                self.change(actions, false)
+            }
+            'P' => self.change(
+                vec![Action::Yank(count), Action::Move(Movement::BackwardChar(1))],
+                false,
+            ),
+            'r' => {
+                self.pending.replace(Pending::Replace(count));
+                Some(vec![])
+            }
+            'i' => self.change(vec![], true),
+            'a' => {
+                let mut actions = vec![];
+                if !at_end_of_line(line, cursor) {
+                    actions.push(Action::Move(Movement::ForwardChar(1)));
+                }
+                self.change(actions, true)
+            }
+            'I' => self.change(vec![Action::Move(Movement::FirstNonBlank)], true),
+            'A' => self.change(vec![Action::Move(Movement::EndOfLine)], true),
+            'o' => self.change(
+                vec![
+                    Action::Move(Movement::EndOfLine),
+                    Action::InsertChar(1, '\n'),
+                ],
+                true,
+            ),
+            'O' => self.change(
+                vec![
+                    Action::Move(Movement::StartOfLine),
+                    Action::InsertChar(1, '\n'),
+                    Action::Move(Movement::BackwardChar(1)),
+                ],
+                true,
+            ),
+            'u' => Some(vec![Action::Undo; count]),
+            '.' => {
+                let mut actions = vec![];
+                for _ in 0..count {
+                    actions.extend(self.last_change.iter().cloned());
+                }
+                Some(actions)
+            }
+            'v' => {
+                self.mode = ViMode::Visual;
+                self.visual_anchor = cursor;
+                Some(vec![])
+            }
+            'j' => Some(vec![Action::HistoryNext; count]),
+            'k' => Some(vec![Action::HistoryPrevious; count]),
+            '/' => Some(vec![Action::HistoryIncSearchBackwards]),
+            '?' => Some(vec![Action::HistoryIncSearchForwards]),
+            _ => None,
+        }
+    }
+
+    fn resolve_visual(&mut self, c: char, line: &str, cursor: usize) -> Option<Vec<Action>> {
+        let op = match c {
+            'd' | 'x' => Operator::Delete,
+            'c' | 's' => Operator::Change,
+            'y' => Operator::Copy,
+            'v' => {
+                self.mode = ViMode::Normal;
+                return Some(vec![]);
+            }
+            _ => return None,
+        };
+
+        // Express the selection as a movement back to its start,
+        // followed by a forwards movement over its extent, so that
+        // the change can be repeated at a different location by `.`
+        let anchor = if line.is_char_boundary(self.visual_anchor) {
+            self.visual_anchor
+        } else {
+            cursor
+        };
+        let (start, end) = if anchor <= cursor {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+        let mut actions = vec![];
+        if anchor < cursor {
+            let back = line[start..end].graphemes(true).count();
+            actions.push(Action::Move(Movement::BackwardChar(back)));
+        }
+        // The selection includes the character under the cursor
+        let mut len = line[start..end].graphemes(true).count();
+        if end < line.len() {
+            len += 1;
+        }
+        let extent = Movement::ForwardChar(len);
+
+        match op {
+            Operator::Delete => {
+                actions.extend(Self::delete(extent));
+                self.change(actions, false)
+            }
+            Operator::Change => {
+                actions.extend(Self::delete(extent));
+                self.change(actions, true)
+            }
+            Operator::Copy => {
+                actions.push(Action::Copy(extent));
+                self.mode = ViMode::Normal;
+                Some(actions)
+            }
+        }
+    }
+
+    /// Returns the actions that delete the text covered by `movement`,
+    /// saving it to the kill ring
+    fn delete(movement: Movement) -> Vec<Action> {
+        match movement {
+            // Single characters are not saved to the kill ring by Kill,
+            // as that would be annoying when using backspace in the
+            // emacs keymap, but vi commands always save what they delete
+            Movement::BackwardChar(_) | Movement::ForwardChar(_) => {
+                vec![Action::Copy(movement), Action::Kill(movement)]
+            }
+            _ => vec![Action::Kill(movement)],
+        }
+    }
+
+    /// Apply `movement`, either by moving the cursor or by using it
+    /// as the extent of the pending operator
+    fn motion(&mut self, movement: Movement, line: &str, cursor: usize) -> Option<Vec<Action>> {
+        let op = self.operator.take().map(|(op, _)| op);
+        self.cancel_pending();
+        match op {
+            None => Some(vec![Action::Move(movement)]),
+            Some(Operator::Delete) => self.change(Self::delete(movement), false),
+            Some(Operator::Change) => {
+                let movement = change_word_movement(movement, line, cursor);
+                self.change(Self::delete(movement), true)
+            }
+            Some(Operator::Copy) => {
+                let mut actions = vec![Action::Copy(movement)];
+                // The cursor moves to the start of the copied text
+                if is_backward(movement) {
+                    actions.push(Action::Move(movement));
+                }
+                Some(actions)
+            }
+        }
+    }
+
+    fn text_object(&mut self, c: char, around: bool) -> Option<Vec<Action>> {
+        let op = self.operator.take().map(|(op, _)| op);
+        self.cancel_pending();
+        let object = match c {
+            'w' => TextObject::Word { around },
+            'W' => TextObject::BigWord { around },
+            '"' | '\'' | '`' => TextObject::Quoted { quote: c, around },
+            '(' | ')' | 'b' => TextObject::Bracketed {
+                open: '(',
+                close: ')',
+                around,
+            },
+            '[' | ']' => TextObject::Bracketed {
+                open: '[',
+                close: ']',
+                around,
+            },
+            '{' | '}' | 'B' => TextObject::Bracketed {
+                open: '{',
+                close: '}',
+                around,
+            },
+            '<' | '>' => TextObject::Bracketed {
+                open: '<',
+                close: '>',
+                around,
+            },
+            _ => return Some(vec![]),
+        };
+        match op {
+            Some(Operator::Delete) => self.change(vec![Action::KillTextObject(object)], false),
+            Some(Operator::Change) => self.change(vec![Action::KillTextObject(object)], true),
+            Some(Operator::Copy) => Some(vec![Action::CopyTextObject(object)]),
+            None => Some(vec![]),
+        }
+    }
+}
+
+fn at_end_of_line(line: &str, cursor: usize) -> bool {
+    matches!(line[cursor..].chars().next(), None | Some('\n'))
+}
+
+fn is_backward(movement: Movement) -> bool {
+    matches!(
+        movement,
+        Movement::BackwardChar(_)
+            | Movement::BackwardWord(_)
+            | Movement::BackwardViWord(_)
+            | Movement::BackwardFind(..)
+            | Movement::BackwardTill(..)
+            | Movement::StartOfLine
+            | Movement::FirstNonBlank
+    )
+}
+
+/// `cw` is a special case in vi: when the cursor is on a word it
+/// behaves like `ce` rather than also changing the whitespace that
+/// follows the word, and the word under the cursor counts as the
+/// first word even if the cursor is on its last character.
+fn change_word_movement(movement: Movement, line: &str, cursor: usize) -> Movement {
+    let (count, big_word) = match movement {
+        Movement::ForwardViWord(count) => (count, false),
+        Movement::ForwardWord(count) => (count, true),
+        _ => return movement,
+    };
+    let mut chars = line[cursor..].chars();
+    let class = match chars.next() {
+        Some(c) => char_class(c, big_word),
+        None => return movement,
+    };
+    if class == CharClass::Whitespace {
+        return movement;
+    }
+    let on_last_char = chars
+        .next()
+        .map(|next| char_class(next, big_word) != class)
+        .unwrap_or(true);
+    let remaining = if on_last_char { count - 1 } else { count };
+    match (remaining, big_word) {
+        (0, _) => Movement::ForwardChar(1),
+        (n, false) => Movement::ForwardViWordEnd(n),
+        (n, true) => Movement::ForwardWordEnd(n),
+    }
+}
@@ -0,0 +1,540 @@
+//! Helpers for converting the image data referenced by `Change::Image`
+//! into a form that can be sent to a terminal using one of the
+//! supported graphics protocols.
+use crate::color::RgbColor;
+use crate::escape::apc::{
+    KittyImage, KittyImageCompression, KittyImageData, KittyImageFormat, KittyImagePlacement,
+    KittyImageTransmit, KittyImageVerbosity,
+};
+use crate::escape::{Sixel, SixelData};
+use crate::image::{ImageDataType, TextureCoordinate};
+use std::collections::HashMap;
+
+/// The kitty protocol limits the size of each chunk of base64
+/// encoded data
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Pixels with an alpha value below this are considered to
+/// be transparent when encoding as sixel
+const SIXEL_ALPHA_THRESHOLD: u8 = 128;
+
+/// Sixel color registers are usually limited to 256 entries
+const SIXEL_MAX_COLORS: usize = 256;
+
+/// A single frame of decoded RGBA pixel data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Returns the RGBA pixels for the image data.
+    /// Only the first frame of an animation is used.
+    /// Encoded files can only be decoded when the `use_image`
+    /// feature is enabled; otherwise `None` is returned for them.
+    pub fn from_image_data(data: &ImageDataType) -> Option<Self> {
+        let image = match data {
+            ImageDataType::Rgba8 {
+                data,
+                width,
+                height,
+                ..
+            } => Self {
+                width: *width,
+                height: *height,
+                data: data.clone(),
+            },
+            ImageDataType::AnimRgba8 {
+                width,
+                height,
+                frames,
+                ..
+            } => Self {
+                width: *width,
+                height: *height,
+                data: frames.first()?.clone(),
+            },
+            ImageDataType::EncodedFile(data) => {
+                return match ImageDataType::EncodedFile(data.clone()).decode() {
+                    ImageDataType::EncodedFile(_) => None,
+                    decoded => Self::from_image_data(&decoded),
+                };
+            }
+        };
+
+        if image.data.len() == image.width as usize * image.height as usize * 4 {
+            Some(image)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the region of the image that lies between the
+    /// texture coordinates `top_left` and `bottom_right`.
+    pub fn crop(&self, top_left: TextureCoordinate, bottom_right: TextureCoordinate) -> Self {
+        let scale = |coord: f32, size: u32| {
+            ((coord * size as f32).round().max(0.) as u32).min(size) as usize
+        };
+        let x0 = scale(*top_left.x, self.width);
+        let y0 = scale(*top_left.y, self.height);
+        let x1 = scale(*bottom_right.x, self.width).max(x0);
+        let y1 = scale(*bottom_right.y, self.height).max(y0);
+
+        let stride = self.width as usize * 4;
+        let mut data = Vec::with_capacity((x1 - x0) * (y1 - y0) * 4);
+        for y in y0..y1 {
+            let row = y * stride;
+            data.extend_from_slice(&self.data[row + x0 * 4..row + x1 * 4]);
+        }
+
+        Self {
+            width: (x1 - x0) as u32,
+            height: (y1 - y0) as u32,
+            data,
+        }
+    }
+
+    /// Returns the image scaled to `width` by `height` pixels,
+    /// using the nearest pixel of the original for each pixel
+    pub fn resize(&self, width: u32, height: u32) -> Self {
+        if (width, height) == (self.width, self.height) || self.is_empty() {
+            return self.clone();
+        }
+        let stride = self.width as usize * 4;
+        let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height as usize {
+            let row = (y * self.height as usize / height as usize) * stride;
+            for x in 0..width as usize {
+                let pixel = row + (x * self.width as usize / width as usize) * 4;
+                data.extend_from_slice(&self.data[pixel..pixel + 4]);
+            }
+        }
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Encodes the image as a PNG file
+    #[cfg(feature = "use_image")]
+    pub fn to_png(&self) -> Option<Vec<u8>> {
+        use image::ImageEncoder;
+
+        let mut png = vec![];
+        image::codecs::png::PngEncoder::new(&mut png)
+            .write_image(&self.data, self.width, self.height, image::ColorType::Rgba8)
+            .ok()?;
+        Some(png)
+    }
+
+    #[cfg(not(feature = "use_image"))]
+    pub fn to_png(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Encodes the image as sixel data.
+    /// Images that have no more than 256 distinct colors are encoded
+    /// exactly, otherwise the colors are quantized to a 6x6x6 color cube.
+    /// Mostly transparent pixels are left unpainted.
+    pub fn to_sixel(&self) -> Sixel {
+        let (palette, pixels) = self.sixel_palette();
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        let mut data: Vec<SixelData> = palette
+            .iter()
+            .enumerate()
+            .map(|(color_number, rgb)| SixelData::DefineColorMapRGB {
+                color_number: color_number as u16,
+                rgb: *rgb,
+            })
+            .collect();
+
+        for band_top in (0..height).step_by(6) {
+            let band_bottom = (band_top + 6).min(height);
+
+            let mut used = vec![false; palette.len()];
+            for y in band_top..band_bottom {
+                for color in pixels[y * width..(y + 1) * width].iter().flatten() {
+                    used[*color as usize] = true;
+                }
+            }
+
+            let mut first = true;
+            for (color, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+                if !first {
+                    data.push(SixelData::CarriageReturn);
+                }
+                first = false;
+                data.push(SixelData::SelectColorMapEntry(color as u16));
+
+                let mut sixels: Vec<u8> = (0..width)
+                    .map(|x| {
+                        (band_top..band_bottom)
+                            .enumerate()
+                            .filter(|(_, y)| pixels[y * width + x] == Some(color as u16))
+                            .fold(0, |bits, (bit, _)| bits | (1 << bit))
+                    })
+                    .collect();
+                while sixels.last() == Some(&0) {
+                    sixels.pop();
+                }
+
+                push_sixel_runs(&sixels, &mut data);
+            }
+
+            if band_bottom < height {
+                data.push(SixelData::NewLine);
+            }
+        }
+
+        Sixel {
+            pan: 1,
+            pad: 1,
+            pixel_width: Some(self.width),
+            pixel_height: Some(self.height),
+            background_is_transparent: pixels.iter().any(Option::is_none),
+            horizontal_grid_size: None,
+            data,
+        }
+    }
+
+    /// Computes the color registers for the image, along with the
+    /// register to use for each pixel.  Transparent pixels have no
+    /// register.
+    fn sixel_palette(&self) -> (Vec<RgbColor>, Vec<Option<u16>>) {
+        let mut registers = HashMap::new();
+        let mut palette = vec![];
+        let mut pixels = Vec::with_capacity(self.data.len() / 4);
+
+        for px in self.data.chunks_exact(4) {
+            if px[3] < SIXEL_ALPHA_THRESHOLD {
+                pixels.push(None);
+                continue;
+            }
+            let next = palette.len();
+            let register = *registers.entry((px[0], px[1], px[2])).or_insert_with(|| {
+                palette.push(RgbColor::new_8bpc(px[0], px[1], px[2]));
+                next
+            });
+            if palette.len() > SIXEL_MAX_COLORS {
+                return self.sixel_color_cube();
+            }
+            pixels.push(Some(register as u16));
+        }
+
+        (palette, pixels)
+    }
+
+    fn sixel_color_cube(&self) -> (Vec<RgbColor>, Vec<Option<u16>>) {
+        let level = |n: u16| (n * 255 / 5) as u8;
+        let palette = (0..216)
+            .map(|n| RgbColor::new_8bpc(level(n / 36), level((n / 6) % 6), level(n % 6)))
+            .collect();
+
+        let quantize = |c: u8| (c as u16 * 5 + 127) / 255;
+        let pixels = self
+            .data
+            .chunks_exact(4)
+            .map(|px| {
+                if px[3] < SIXEL_ALPHA_THRESHOLD {
+                    None
+                } else {
+                    Some(quantize(px[0]) * 36 + quantize(px[1]) * 6 + quantize(px[2]))
+                }
+            })
+            .collect();
+
+        (palette, pixels)
+    }
+}
+
+/// Run length encodes a line of sixels
+fn push_sixel_runs(sixels: &[u8], data: &mut Vec<SixelData>) {
+    let mut iter = sixels.iter().peekable();
+    while let Some(&value) = iter.next() {
+        let mut repeat_count = 1;
+        while iter.peek() == Some(&&value) {
+            iter.next();
+            repeat_count += 1;
+        }
+        // The repeat introducer only saves space for longer runs
+        if repeat_count > 3 {
+            data.push(SixelData::Repeat {
+                repeat_count,
+                data: value,
+            });
+        } else {
+            for _ in 0..repeat_count {
+                data.push(SixelData::Data(value));
+            }
+        }
+    }
+}
+
+/// Returns the escape sequences that transmit and display an image
+/// using the kitty graphics protocol.  The image is scaled to fit
+/// the specified number of cells and the cursor is not moved.
+/// `width` and `height` are the dimensions in pixels, and are required
+/// for the raw `Rgb` and `Rgba` formats.
+pub fn kitty_image_sequence(
+    format: KittyImageFormat,
+    data: &[u8],
+    width: Option<u32>,
+    height: Option<u32>,
+    columns: u32,
+    rows: u32,
+) -> String {
+    kitty_sequences(KittyImage::TransmitDataAndDisplay {
+        transmit: KittyImageTransmit {
+            format: Some(format),
+            data: KittyImageData::Direct(base64::encode(data)),
+            width,
+            height,
+            image_id: None,
+            image_number: None,
+            compression: KittyImageCompression::None,
+            more_data_follows: false,
+        },
+        placement: KittyImagePlacement {
+            x: None,
+            y: None,
+            w: None,
+            h: None,
+            x_offset: None,
+            y_offset: None,
+            columns: Some(columns),
+            rows: Some(rows),
+            do_not_move_cursor: true,
+            placement_id: None,
+            z_index: None,
+        },
+        // We don't read responses from the terminal
+        verbosity: KittyImageVerbosity::Quiet,
+    })
+    .concat()
+}
+
+/// Returns the escape sequences, each terminated by ST, for a kitty
+/// image command.  If the command carries more base64 encoded data
+/// than the protocol allows in a single escape sequence, the data is
+/// split across as many continuation sequences as are required.
+pub fn kitty_sequences(mut image: KittyImage) -> Vec<String> {
+    let transmit = match &mut image {
+        KittyImage::TransmitData { transmit, .. }
+        | KittyImage::TransmitDataAndDisplay { transmit, .. }
+        | KittyImage::TransmitFrame { transmit, .. }
+        | KittyImage::Query { transmit } => transmit,
+        _ => return vec![format!("{}\x1b\\", image)],
+    };
+    let encoded = match &mut transmit.data {
+        KittyImageData::Direct(data) if data.len() > KITTY_CHUNK_SIZE => std::mem::take(data),
+        _ => return vec![format!("{}\x1b\\", image)],
+    };
+
+    // base64 is pure ascii, so splitting on byte boundaries is safe
+    let mut chunks = encoded
+        .as_bytes()
+        .chunks(KITTY_CHUNK_SIZE)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .peekable();
+
+    transmit.data = KittyImageData::Direct(chunks.next().unwrap_or_default());
+    transmit.more_data_follows = true;
+    let mut result = vec![format!("{}\x1b\\", image)];
+
+    // Continuation chunks carry only the `m` key
+    while let Some(chunk) = chunks.next() {
+        let more = if chunks.peek().is_some() { 1 } else { 0 };
+        result.push(format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::escape::parser::Parser;
+    use crate::escape::Action;
+
+    fn rgba(width: u32, height: u32, pixels: &[[u8; 4]]) -> RgbaImage {
+        RgbaImage {
+            width,
+            height,
+            data: pixels.iter().flatten().copied().collect(),
+        }
+    }
+
+    const RED: [u8; 4] = [0xff, 0, 0, 0xff];
+    const BLUE: [u8; 4] = [0, 0, 0xff, 0xff];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    #[test]
+    fn crop() {
+        let image = rgba(2, 2, &[RED, BLUE, BLUE, RED]);
+        assert_eq!(
+            image.crop(
+                TextureCoordinate::new_f32(0.5, 0.0),
+                TextureCoordinate::new_f32(1.0, 1.0)
+            ),
+            rgba(1, 2, &[BLUE, RED])
+        );
+        assert_eq!(
+            image.crop(
+                TextureCoordinate::new_f32(0.0, 0.0),
+                TextureCoordinate::new_f32(1.0, 1.0)
+            ),
+            image
+        );
+        assert!(image
+            .crop(
+                TextureCoordinate::new_f32(1.0, 0.0),
+                TextureCoordinate::new_f32(1.0, 1.0)
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn resize() {
+        let image = rgba(2, 1, &[RED, BLUE]);
+        assert_eq!(image.resize(2, 1), image);
+        assert_eq!(
+            image.resize(4, 2),
+            rgba(4, 2, &[RED, RED, BLUE, BLUE, RED, RED, BLUE, BLUE])
+        );
+        assert_eq!(image.resize(1, 1), rgba(1, 1, &[RED]));
+        assert!(image.resize(0, 3).is_empty());
+        assert_eq!(rgba(0, 0, &[]).resize(2, 2), rgba(0, 0, &[]));
+    }
+
+    #[test]
+    fn from_image_data() {
+        let data = ImageDataType::new_single_frame(1, 1, RED.to_vec());
+        assert_eq!(RgbaImage::from_image_data(&data), Some(rgba(1, 1, &[RED])));
+
+        let truncated = ImageDataType::Rgba8 {
+            data: RED.to_vec(),
+            width: 2,
+            height: 2,
+            hash: [0; 32],
+        };
+        assert_eq!(RgbaImage::from_image_data(&truncated), None);
+    }
+
+    #[test]
+    fn sixel() {
+        // A 5x7 image: red on the first row, a blue column
+        // on the right and a transparent hole on the second row
+        let mut pixels = vec![];
+        for y in 0..7 {
+            for x in 0..5 {
+                pixels.push(match (x, y) {
+                    (_, 0) => RED,
+                    (4, _) => BLUE,
+                    (0, 1) => CLEAR,
+                    _ => RED,
+                });
+            }
+        }
+        let sixel = rgba(5, 7, &pixels).to_sixel();
+
+        use SixelData::*;
+        assert_eq!(
+            sixel,
+            Sixel {
+                pan: 1,
+                pad: 1,
+                pixel_width: Some(5),
+                pixel_height: Some(7),
+                background_is_transparent: true,
+                horizontal_grid_size: None,
+                data: vec![
+                    DefineColorMapRGB {
+                        color_number: 0,
+                        rgb: RgbColor::new_8bpc(0xff, 0, 0),
+                    },
+                    DefineColorMapRGB {
+                        color_number: 1,
+                        rgb: RgbColor::new_8bpc(0, 0, 0xff),
+                    },
+                    SelectColorMapEntry(0),
+                    Data(0b111101),
+                    Data(0b111111),
+                    Data(0b111111),
+                    Data(0b111111),
+                    Data(0b000001),
+                    CarriageReturn,
+                    SelectColorMapEntry(1),
+                    Repeat {
+                        repeat_count: 4,
+                        data: 0
+                    },
+                    Data(0b111110),
+                    NewLine,
+                    SelectColorMapEntry(0),
+                    Repeat {
+                        repeat_count: 4,
+                        data: 1
+                    },
+                    CarriageReturn,
+                    SelectColorMapEntry(1),
+                    Repeat {
+                        repeat_count: 4,
+                        data: 0
+                    },
+                    Data(1),
+                ],
+            }
+        );
+
+        // And verify that it survives a trip through the parser
+        let encoded = format!("{}\x1b\\", sixel);
+        let mut parser = Parser::new();
+        let actions = parser.parse_as_vec(encoded.as_bytes());
+        match &actions[0] {
+            Action::Sixel(parsed) => {
+                assert_eq!(parsed.dimensions(), (5, 7));
+                assert_eq!(parsed.data, sixel.data);
+            }
+            wat => panic!("expected sixel, got {:?}", wat),
+        }
+    }
+
+    #[test]
+    fn sixel_color_cube() {
+        let pixels: Vec<[u8; 4]> = (0..300u32)
+            .map(|n| [(n % 256) as u8, (n / 256) as u8 * 0x80, 0x40, 0xff])
+            .collect();
+        let sixel = rgba(300, 1, &pixels).to_sixel();
+        let registers = sixel
+            .data
+            .iter()
+            .filter(|d| matches!(d, SixelData::DefineColorMapRGB { .. }))
+            .count();
+        assert_eq!(registers, 216);
+    }
+
+    #[test]
+    fn kitty() {
+        assert_eq!(
+            kitty_image_sequence(KittyImageFormat::Png, b"hello", None, None, 3, 2),
+            "\x1b_GC=1,a=T,c=3,f=100,q=2,r=2;aGVsbG8=\x1b\\"
+        );
+
+        let data = vec![0u8; KITTY_CHUNK_SIZE];
+        let seq = kitty_image_sequence(KittyImageFormat::Rgba, &data, Some(32), Some(32), 4, 2);
+        let chunks: Vec<&str> = seq.split("\x1b\\").filter(|s| !s.is_empty()).collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].contains("m=1"));
+        assert!(chunks[0].contains("f=32"));
+        assert!(chunks[1].starts_with("\x1b_Gm=0;"));
+    }
+}
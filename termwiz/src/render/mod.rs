@@ -1,3 +1,4 @@
+pub mod graphics;
 pub mod terminfo;
 #[cfg(windows)]
 pub mod windows;
@@ -5,4 +6,10 @@ pub mod windows;
 pub trait RenderTty: std::io::Write {
     /// Returns the (cols, rows) for the terminal
     fn get_size_in_cells(&mut self) -> crate::Result<(usize, usize)>;
+
+    /// Returns the (width, height) of a cell in pixels, if the
+    /// terminal reports its size in pixels
+    fn get_cell_size_in_pixels(&mut self) -> crate::Result<Option<(usize, usize)>> {
+        Ok(None)
+    }
 }
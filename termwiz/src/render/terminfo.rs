@@ -2,16 +2,21 @@
 use crate::caps::{Capabilities, ColorLevel};
 use crate::cell::{AttributeChange, Blink, CellAttributes, Intensity, Underline};
 use crate::color::{ColorAttribute, ColorSpec};
+use crate::escape::apc::KittyImageFormat;
 use crate::escape::csi::{Cursor, Edit, EraseInDisplay, EraseInLine, Sgr, CSI};
+use crate::escape::esc::{Esc, EscCode};
 use crate::escape::osc::{ITermDimension, ITermFileData, ITermProprietary, OperatingSystemCommand};
 use crate::escape::OneBased;
 use crate::image::{ImageDataType, TextureCoordinate};
+use crate::render::graphics::{kitty_image_sequence, RgbaImage};
 use crate::render::RenderTty;
-use crate::surface::{Change, CursorShape, CursorVisibility, Position};
+use crate::surface::{Change, CursorShape, CursorVisibility, Image, Position};
 use crate::Result;
 use std::io::Write;
 use terminfo::{capability as cap, Capability as TermInfoCapability};
 
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
 pub struct TerminfoRenderer {
     caps: Capabilities,
     current_attr: CellAttributes,
@@ -275,6 +280,125 @@ impl TerminfoRenderer {
         }
     }
 
+    /// Draws the image using the best graphics protocol supported
+    /// by the terminal, leaving the cursor just to the right of its
+    /// top row.  Returns false if the image could not be drawn, either
+    /// because no graphics protocol is available, or because the image
+    /// data would need to be decoded or encoded without the `use_image`
+    /// feature.
+    fn render_image<W: RenderTty + Write>(&mut self, image: &Image, out: &mut W) -> Result<bool> {
+        let data = image.image.data();
+        let whole_image = image.top_left == TextureCoordinate::new_f32(0.0, 0.0)
+            && image.bottom_right == TextureCoordinate::new_f32(1.0, 1.0);
+        let region = || {
+            RgbaImage::from_image_data(&data)
+                .map(|rgba| {
+                    if whole_image {
+                        rgba
+                    } else {
+                        rgba.crop(image.top_left, image.bottom_right)
+                    }
+                })
+                .filter(|rgba| !rgba.is_empty())
+        };
+
+        if self.caps.kitty_graphics() {
+            let columns = image.width as u32;
+            let rows = image.height as u32;
+            let seq = match &*data {
+                // kitty understands PNG files, so we can send the
+                // original image bytes over
+                ImageDataType::EncodedFile(png) if whole_image && png.starts_with(PNG_MAGIC) => {
+                    Some(kitty_image_sequence(
+                        KittyImageFormat::Png,
+                        png,
+                        None,
+                        None,
+                        columns,
+                        rows,
+                    ))
+                }
+                _ => region().map(|rgba| {
+                    kitty_image_sequence(
+                        KittyImageFormat::Rgba,
+                        &rgba.data,
+                        Some(rgba.width),
+                        Some(rgba.height),
+                        columns,
+                        rows,
+                    )
+                }),
+            };
+            if let Some(seq) = seq {
+                write!(out, "{}", seq)?;
+                self.cursor_right(columns, out)?;
+                return Ok(true);
+            }
+        }
+
+        if self.caps.iterm2_image() {
+            let file_data = match &*data {
+                // The whole image is requested, so we can send the
+                // original image bytes over
+                ImageDataType::EncodedFile(file_data) if whole_image => Some(file_data.to_vec()),
+                // Otherwise, slice out the requested region of the
+                // image and encode it as a PNG
+                _ => region().and_then(|rgba| rgba.to_png()),
+            };
+            if let Some(data) = file_data {
+                let file = ITermFileData {
+                    name: None,
+                    size: Some(data.len()),
+                    width: ITermDimension::Cells(image.width as i64),
+                    height: ITermDimension::Cells(image.height as i64),
+                    preserve_aspect_ratio: true,
+                    inline: true,
+                    do_not_move_cursor: false,
+                    data,
+                };
+
+                let osc = OperatingSystemCommand::ITermProprietary(ITermProprietary::File(
+                    Box::new(file),
+                ));
+
+                write!(out, "{}", osc)?;
+                return Ok(true);
+            }
+        }
+
+        if self.caps.sixel() {
+            if let Some(mut rgba) = region() {
+                // Sixel images are drawn at their pixel size, so scale
+                // the image to fill its cells.  If the terminal doesn't
+                // tell us how large its cells are, the best that we can
+                // do is to draw it at its native size.
+                if let Some((cell_width, cell_height)) = out.get_cell_size_in_pixels()? {
+                    rgba = rgba.resize(
+                        (image.width * cell_width) as u32,
+                        (image.height * cell_height) as u32,
+                    );
+                    if rgba.is_empty() {
+                        return Ok(false);
+                    }
+                }
+                // Terminals disagree about where the cursor ends up after
+                // a sixel image, so save and restore it around the image.
+                write!(
+                    out,
+                    "{}{}{}{}",
+                    Esc::Code(EscCode::DecSaveCursorPosition),
+                    rgba.to_sixel(),
+                    Esc::Code(EscCode::StringTerminator),
+                    Esc::Code(EscCode::DecRestoreCursorPosition)
+                )?;
+                self.cursor_right(image.width as u32, out)?;
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
     fn move_cursor_absolute<W: RenderTty + Write>(
         &mut self,
         x: u32,
@@ -561,43 +685,7 @@ impl TerminfoRenderer {
                     }
                 },
                 Change::Image(image) => {
-                    if self.caps.iterm2_image() {
-                        let data = if image.top_left == TextureCoordinate::new_f32(0.0, 0.0)
-                            && image.bottom_right == TextureCoordinate::new_f32(1.0, 1.0)
-                        {
-                            // The whole image is requested, so we can send the
-                            // original image bytes over
-                            match &*image.image.data() {
-                                ImageDataType::EncodedFile(data) => data.to_vec(),
-                                ImageDataType::AnimRgba8 { .. } | ImageDataType::Rgba8 { .. } => {
-                                    unimplemented!()
-                                }
-                            }
-                        } else {
-                            // TODO: slice out the requested region of the image,
-                            // and encode as a PNG.
-                            unimplemented!();
-                        };
-
-                        let file = ITermFileData {
-                            name: None,
-                            size: Some(data.len()),
-                            width: ITermDimension::Cells(image.width as i64),
-                            height: ITermDimension::Cells(image.height as i64),
-                            preserve_aspect_ratio: true,
-                            inline: true,
-                            do_not_move_cursor: false,
-                            data,
-                        };
-
-                        let osc = OperatingSystemCommand::ITermProprietary(ITermProprietary::File(
-                            Box::new(file),
-                        ));
-
-                        write!(out, "{}", osc)?;
-
-                    // TODO: } else if self.caps.sixel() {
-                    } else {
+                    if !self.render_image(image, out)? {
                         // Blank out the cells and move the cursor to the right spot
                         for y in 0..image.height {
                             for _ in 0..image.width {
@@ -746,6 +834,10 @@ mod test {
         fn get_size_in_cells(&mut self) -> Result<(usize, usize)> {
             Ok((self.size.ws_col as usize, self.size.ws_row as usize))
         }
+
+        fn get_cell_size_in_pixels(&mut self) -> Result<Option<(usize, usize)>> {
+            Ok(crate::terminal::unix::cell_size_in_pixels(&self.size))
+        }
     }
 
     impl UnixTty for FakeTty {
@@ -1220,4 +1312,50 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn sixel_fills_cells() {
+        use crate::image::ImageData;
+        use std::sync::Arc;
+
+        let render = |xpixel, ypixel| {
+            let caps = Capabilities::new_with_hints(
+                ProbeHints::default()
+                    .sixel(Some(true))
+                    .color_level(Some(ColorLevel::TrueColor)),
+            )
+            .unwrap();
+            let mut out = FakeTerm::new_with_size(caps, 10, 5);
+            out.set_screen_size(ScreenSize {
+                cols: 10,
+                rows: 5,
+                xpixel,
+                ypixel,
+            })
+            .unwrap();
+
+            let data = ImageDataType::new_single_frame(1, 1, vec![0xff, 0, 0, 0xff]);
+            out.render(&[Change::Image(Image {
+                width: 2,
+                height: 1,
+                top_left: TextureCoordinate::new_f32(0., 0.),
+                bottom_right: TextureCoordinate::new_f32(1., 1.),
+                image: Arc::new(ImageData::with_data(data)),
+            })])
+            .unwrap();
+
+            out.parse()
+                .into_iter()
+                .filter_map(|action| match action {
+                    Action::Sixel(sixel) => Some((sixel.pixel_width, sixel.pixel_height)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Scaled to fill 2x1 cells of 8x16 pixels
+        assert_eq!(render(80, 80), vec![(Some(16), Some(16))]);
+        // The native size when the cell size is unknown
+        assert_eq!(render(0, 0), vec![(Some(1), Some(1))]);
+    }
 }
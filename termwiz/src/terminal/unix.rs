@@ -126,6 +126,26 @@ impl RenderTty for TtyWriteHandle {
         let size = self.get_size()?;
         Ok((size.ws_col as usize, size.ws_row as usize))
     }
+
+    fn get_cell_size_in_pixels(&mut self) -> Result<Option<(usize, usize)>> {
+        let size = self.get_size()?;
+        Ok(cell_size_in_pixels(&size))
+    }
+}
+
+/// Computes the size of a cell from the pixel dimensions in `size`,
+/// which are zero when the terminal doesn't report them
+pub(crate) fn cell_size_in_pixels(size: &winsize) -> Option<(usize, usize)> {
+    if size.ws_col == 0 || size.ws_row == 0 {
+        return None;
+    }
+    match (
+        size.ws_xpixel as usize / size.ws_col as usize,
+        size.ws_ypixel as usize / size.ws_row as usize,
+    ) {
+        (0, _) | (_, 0) => None,
+        cell => Some(cell),
+    }
 }
 
 impl UnixTty for TtyWriteHandle {
@@ -28,6 +28,49 @@ pub struct WindowLayout {
     pub layout_id: String,
     pub width: u64,
     pub height: u64,
+    /// The arrangement of the panes within the window
+    pub root: LayoutCell,
+}
+
+/// A rectangular region of a tmux window layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutCell {
+    pub width: u64,
+    pub height: u64,
+    pub x: u64,
+    pub y: u64,
+    pub content: LayoutContent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutContent {
+    /// The cell holds a single pane
+    Pane(TmuxPaneId),
+    /// The cell is split into children arranged from left to right
+    LeftRight(Vec<LayoutCell>),
+    /// The cell is split into children arranged from top to bottom
+    TopBottom(Vec<LayoutCell>),
+}
+
+impl LayoutCell {
+    /// Returns the ids of the panes in this cell, from left to right
+    /// and top to bottom
+    pub fn pane_ids(&self) -> Vec<TmuxPaneId> {
+        let mut ids = vec![];
+        self.collect_pane_ids(&mut ids);
+        ids
+    }
+
+    fn collect_pane_ids(&self, ids: &mut Vec<TmuxPaneId>) {
+        match &self.content {
+            LayoutContent::Pane(id) => ids.push(*id),
+            LayoutContent::LeftRight(cells) | LayoutContent::TopBottom(cells) => {
+                for cell in cells {
+                    cell.collect_pane_ids(ids);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -164,24 +207,71 @@ fn parse_window_layout(pair: Pair<Rule>) -> Option<WindowLayout> {
     match pair.as_rule() {
         Rule::window_layout => {
             let mut pairs = pair.into_inner();
-            let layout_id_option = pairs.next()?.as_str().parse::<String>().ok();
-            let width_option = pairs.next()?.as_str().parse::<u64>().ok();
-            let height_option = pairs.next()?.as_str().parse::<u64>().ok();
-            if let (Some(layout_id), Some(width), Some(height)) =
-                (layout_id_option, width_option, height_option)
-            {
-                return Some(WindowLayout {
-                    layout_id,
-                    width,
-                    height,
-                });
-            }
-            return None;
+            let layout_id = pairs.next()?.as_str().to_owned();
+            let root = parse_layout_cell(pairs.next()?)?;
+            Some(WindowLayout {
+                layout_id,
+                width: root.width,
+                height: root.height,
+                root,
+            })
         }
         _ => None,
     }
 }
 
+/// Parses a cell within a window_layout, for example "80x24,0,0,0"
+/// or "160x40,0,0{80x40,0,0,1,79x40,81,0,2}"
+fn parse_layout_cell(pair: Pair<Rule>) -> Option<LayoutCell> {
+    match pair.as_rule() {
+        Rule::layout_cell => {
+            let mut pairs = pair.into_inner();
+            let width = pairs.next()?.as_str().parse::<u64>().ok()?;
+            let height = pairs.next()?.as_str().parse::<u64>().ok()?;
+            let x = pairs.next()?.as_str().parse::<u64>().ok()?;
+            let y = pairs.next()?.as_str().parse::<u64>().ok()?;
+            let content = pairs.next()?;
+            let content = match content.as_rule() {
+                Rule::layout_pane => {
+                    LayoutContent::Pane(content.into_inner().next()?.as_str().parse::<u64>().ok()?)
+                }
+                Rule::layout_left_right => LayoutContent::LeftRight(
+                    content
+                        .into_inner()
+                        .map(parse_layout_cell)
+                        .collect::<Option<Vec<_>>>()?,
+                ),
+                Rule::layout_top_bottom => LayoutContent::TopBottom(
+                    content
+                        .into_inner()
+                        .map(parse_layout_cell)
+                        .collect::<Option<Vec<_>>>()?,
+                ),
+                _ => return None,
+            };
+            Some(LayoutCell {
+                width,
+                height,
+                x,
+                y,
+                content,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Parses a window layout string, such as is reported by the
+/// `#{window_layout}` format, for example
+/// "c4ab,160x40,0,0{80x40,0,0,1,79x40,81,0,2}"
+pub fn parse_layout(layout: &str) -> anyhow::Result<WindowLayout> {
+    let mut pairs = parser::TmuxParser::parse(Rule::window_layout_entire, layout)?;
+    pairs
+        .next()
+        .and_then(parse_window_layout)
+        .ok_or_else(|| anyhow::anyhow!("invalid tmux window layout {}", layout))
+}
+
 fn parse_line(line: &str) -> anyhow::Result<Event> {
     let mut pairs = parser::TmuxParser::parse(Rule::line_entire, line)?;
     let pair = pairs.next().ok_or_else(|| anyhow::anyhow!("no pairs!?"))?;
@@ -301,6 +391,11 @@ fn parse_line(line: &str) -> anyhow::Result<Event> {
         | Rule::window_id
         | Rule::session_id
         | Rule::window_layout
+        | Rule::window_layout_entire
+        | Rule::layout_cell
+        | Rule::layout_pane
+        | Rule::layout_left_right
+        | Rule::layout_top_bottom
         | Rule::any_text
         | Rule::line
         | Rule::line_entire
@@ -654,6 +749,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_layout() {
+        let layout =
+            parse_layout("c4ab,160x40,0,0{80x40,0,0,1,79x40,81,0[79x20,81,0,2,79x19,81,21,3]}")
+                .unwrap();
+        assert_eq!(layout.layout_id, "c4ab");
+        assert_eq!((layout.width, layout.height), (160, 40));
+        assert_eq!(layout.root.pane_ids(), vec![1, 2, 3]);
+        assert_eq!(
+            layout.root.content,
+            LayoutContent::LeftRight(vec![
+                LayoutCell {
+                    width: 80,
+                    height: 40,
+                    x: 0,
+                    y: 0,
+                    content: LayoutContent::Pane(1),
+                },
+                LayoutCell {
+                    width: 79,
+                    height: 40,
+                    x: 81,
+                    y: 0,
+                    content: LayoutContent::TopBottom(vec![
+                        LayoutCell {
+                            width: 79,
+                            height: 20,
+                            x: 81,
+                            y: 0,
+                            content: LayoutContent::Pane(2),
+                        },
+                        LayoutCell {
+                            width: 79,
+                            height: 19,
+                            x: 81,
+                            y: 21,
+                            content: LayoutContent::Pane(3),
+                        },
+                    ]),
+                },
+            ])
+        );
+
+        assert!(parse_layout("c4ab,160x40,0,0{").is_err());
+    }
+
     #[test]
     fn test_parse_sequence() {
         let input = b"%sessions-changed
@@ -710,7 +851,14 @@ here
                     layout: WindowLayout {
                         layout_id: "b25d".to_owned(),
                         width: 80,
-                        height: 24
+                        height: 24,
+                        root: LayoutCell {
+                            width: 80,
+                            height: 24,
+                            x: 0,
+                            y: 0,
+                            content: LayoutContent::Pane(0),
+                        },
                     },
                     visible_layout: None,
                     raw_flags: None
@@ -720,12 +868,26 @@ here
                     layout: WindowLayout {
                         layout_id: "cafd".to_owned(),
                         width: 120,
-                        height: 29
+                        height: 29,
+                        root: LayoutCell {
+                            width: 120,
+                            height: 29,
+                            x: 0,
+                            y: 0,
+                            content: LayoutContent::Pane(0),
+                        },
                     },
                     visible_layout: Some(WindowLayout {
                         layout_id: "cafd".to_owned(),
                         width: 120,
-                        height: 29
+                        height: 29,
+                        root: LayoutCell {
+                            width: 120,
+                            height: 29,
+                            x: 0,
+                            y: 0,
+                            content: LayoutContent::Pane(0),
+                        },
                     }),
                     raw_flags: Some("*".to_owned())
                 },
@@ -0,0 +1,224 @@
+//! A scrollable list of items, one of which may be selected
+use crate::cell::AttributeChange;
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons, MouseEvent};
+use crate::lineedit::truncate_to_width;
+use crate::surface::{Change, Position, Surface};
+use crate::widgets::{CursorShapeAndPosition, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+
+/// Tracks the selected item and the scroll position of a list of
+/// items that are displayed one per row.  This is shared by `List`
+/// and `Table`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Selection {
+    pub(crate) selected: Option<usize>,
+    /// The index of the item displayed in the first row
+    pub(crate) top: usize,
+    /// The number of rows that were available to display items
+    /// the last time that the widget was rendered
+    pub(crate) height: usize,
+}
+
+impl Selection {
+    /// Moves the selection in response to the arrow, Home, End,
+    /// PageUp and PageDown keys.  Returns true if the key was handled.
+    pub(crate) fn process_key(&mut self, key: &KeyEvent, len: usize) -> bool {
+        if key.modifiers != Modifiers::NONE || len == 0 {
+            return false;
+        }
+        let page = self.height.max(1);
+        let current = self.selected;
+        self.selected = Some(match key.key {
+            KeyCode::UpArrow | KeyCode::ApplicationUpArrow => {
+                current.map_or(0, |idx| idx.saturating_sub(1))
+            }
+            KeyCode::DownArrow | KeyCode::ApplicationDownArrow => {
+                current.map_or(0, |idx| (idx + 1).min(len - 1))
+            }
+            KeyCode::PageUp => current.map_or(0, |idx| idx.saturating_sub(page)),
+            KeyCode::PageDown => current.map_or(0, |idx| (idx + page).min(len - 1)),
+            KeyCode::Home => 0,
+            KeyCode::End => len - 1,
+            _ => return false,
+        });
+        self.scroll_into_view(len);
+        true
+    }
+
+    /// Selects the item in the clicked row, or moves the selection
+    /// when the wheel is used.  `first_row` is the row of the widget
+    /// in which the item at `top` is displayed.
+    /// Returns true if the event was handled.
+    pub(crate) fn process_mouse(
+        &mut self,
+        event: &MouseEvent,
+        len: usize,
+        first_row: usize,
+    ) -> bool {
+        if len == 0 {
+            return false;
+        }
+        if event.mouse_buttons.contains(MouseButtons::VERT_WHEEL) {
+            let current = self.selected.unwrap_or(0);
+            self.selected = Some(
+                if event.mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                    current.saturating_sub(1)
+                } else {
+                    (current + 1).min(len - 1)
+                },
+            );
+            self.scroll_into_view(len);
+            return true;
+        }
+        if event.mouse_buttons.contains(MouseButtons::LEFT) {
+            let row = event.y as usize;
+            if row >= first_row && self.top + row - first_row < len {
+                self.selected = Some(self.top + row - first_row);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Adjusts `top` so that the selected item is visible, and
+    /// so that there are no unused rows after the last item
+    pub(crate) fn scroll_into_view(&mut self, len: usize) {
+        let height = self.height.max(1);
+        self.selected = match self.selected {
+            Some(_) if len == 0 => None,
+            Some(idx) => Some(idx.min(len - 1)),
+            None => None,
+        };
+        if let Some(idx) = self.selected {
+            if idx < self.top {
+                self.top = idx;
+            } else if idx >= self.top + height {
+                self.top = idx + 1 - height;
+            }
+        }
+        self.top = self.top.min(len.saturating_sub(height));
+    }
+
+    /// Returns true if the item is selected
+    pub(crate) fn is_selected(&self, idx: usize) -> bool {
+        self.selected == Some(idx)
+    }
+}
+
+/// Draws `text` on row `y` of the surface, truncating or padding it
+/// to fill the width of the surface.  The row is drawn in reverse
+/// video if `selected` is true.
+pub(crate) fn draw_row(surface: &mut Surface, y: usize, text: &str, selected: bool) {
+    let (width, _) = surface.dimensions();
+    let (text, used) = truncate_to_width(text, width);
+    surface.add_changes(vec![
+        Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(y),
+        },
+        Change::Attribute(AttributeChange::Reverse(selected)),
+        Change::Text(format!("{}{}", text, " ".repeat(width - used))),
+        Change::Attribute(AttributeChange::Reverse(false)),
+    ]);
+}
+
+/// Displays a list of items, one per row, and allows selecting one of
+/// them using the keyboard or mouse.  The list scrolls to keep the
+/// selected item in view.
+#[derive(Debug, Clone, Default)]
+pub struct List {
+    items: Vec<String>,
+    selection: Selection,
+}
+
+impl List {
+    /// Creates a list of items, with the first item selected
+    pub fn new(items: Vec<String>) -> Self {
+        let mut list = Self::default();
+        list.set_items(items);
+        list
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Replaces the items.  The selection is preserved if possible.
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        if self.selection.selected.is_none() && !self.items.is_empty() {
+            self.selection.selected = Some(0);
+        }
+        self.selection.scroll_into_view(self.items.len());
+    }
+
+    /// Returns the index of the selected item
+    pub fn selected(&self) -> Option<usize> {
+        self.selection.selected
+    }
+
+    /// Returns the selected item
+    pub fn selected_item(&self) -> Option<&str> {
+        self.selected()
+            .and_then(|idx| self.items.get(idx))
+            .map(String::as_str)
+    }
+
+    /// Changes the selected item
+    pub fn select(&mut self, idx: Option<usize>) {
+        self.selection.selected = idx;
+        self.selection.scroll_into_view(self.items.len());
+    }
+}
+
+impl Widget for List {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (_width, height) = args.surface.dimensions();
+        self.selection.height = height;
+        self.selection.scroll_into_view(self.items.len());
+
+        args.surface
+            .add_change(Change::ClearScreen(Default::default()));
+        for (row, idx) in (self.selection.top..self.items.len())
+            .take(height)
+            .enumerate()
+        {
+            draw_row(
+                args.surface,
+                row,
+                &self.items[idx],
+                self.selection.is_selected(idx),
+            );
+        }
+
+        let row = self.selection.selected.unwrap_or(self.selection.top) - self.selection.top;
+        *args.cursor = CursorShapeAndPosition {
+            coords: (0, row).into(),
+            ..Default::default()
+        };
+    }
+
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        match event {
+            WidgetEvent::Input(InputEvent::Key(key)) => {
+                self.selection.process_key(key, self.items.len())
+            }
+            WidgetEvent::Input(InputEvent::Mouse(m)) => {
+                self.selection.process_mouse(m, self.items.len(), 0)
+            }
+            _ => false,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
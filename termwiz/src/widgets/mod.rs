@@ -2,17 +2,34 @@
 // right place for it to take effect
 #![allow(clippy::new_without_default)]
 use crate::color::ColorAttribute;
-use crate::input::InputEvent;
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons};
 use crate::surface::{Change, CursorShape, Position, SequenceNo, Surface};
 use crate::Result;
 use fnv::FnvHasher;
-use std::collections::{HashMap, VecDeque};
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::BuildHasherDefault;
 
 /// fnv is a more appropriate hasher for the WidgetIds we use in this module.
 type FnvHashMap<K, V> = HashMap<K, V, BuildHasherDefault<FnvHasher>>;
+type FnvHashSet<K> = HashSet<K, BuildHasherDefault<FnvHasher>>;
 
 pub mod layout;
+mod list;
+mod modal;
+mod progress;
+mod scroll_view;
+mod tab_strip;
+mod table;
+mod text_input;
+
+pub use list::List;
+pub use modal::Modal;
+pub use progress::ProgressBar;
+pub use scroll_view::ScrollView;
+pub use tab_strip::TabStrip;
+pub use table::{Alignment, Column, Table};
+pub use text_input::TextInput;
 
 /// Describes an event that may need to be processed by the widget
 pub enum WidgetEvent {
@@ -72,6 +89,26 @@ pub trait Widget {
     fn process_event(&mut self, _event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
         false
     }
+
+    /// Override this to return `true` if your widget accepts keyboard
+    /// input, so that it can be focused by `Ui::focus_next`,
+    /// `Ui::focus_previous` and by clicking on it.
+    fn is_focusable(&self) -> bool {
+        false
+    }
+
+    /// Override this to return `Some(self)` to allow the widget to be
+    /// accessed via `Ui::widget` after it has been added to the `Ui`.
+    /// This is only possible for widgets that don't borrow any data.
+    fn as_any(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    /// Override this to return `Some(self)` to allow the widget to be
+    /// accessed via `Ui::widget_mut` after it has been added to the `Ui`.
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
 }
 
 /// Relative to the top left of the parent container
@@ -174,6 +211,44 @@ impl Graph {
             .map(|v| v.as_slice())
             .unwrap_or_else(|| &[])
     }
+
+    /// Appends id and its descendants to `ids`, in depth first order
+    fn subtree(&self, id: WidgetId, ids: &mut Vec<WidgetId>) {
+        ids.push(id);
+        for child in self.children(id) {
+            self.subtree(*child, ids);
+        }
+    }
+
+    /// Removes id and its descendants, returning the removed ids
+    fn remove(&mut self, id: WidgetId) -> Vec<WidgetId> {
+        let mut removed = vec![];
+        if !self.children.contains_key(&id) {
+            return removed;
+        }
+        self.subtree(id, &mut removed);
+
+        if let Some(parent) = self.parent.get(&id) {
+            if let Some(siblings) = self.children.get_mut(parent) {
+                siblings.retain(|child| *child != id);
+            }
+        }
+        for id in &removed {
+            self.children.remove(id);
+            self.parent.remove(id);
+        }
+        if self.root == Some(id) {
+            self.root = None;
+        }
+        removed
+    }
+}
+
+/// A widget that is laid out and drawn above the root widget
+struct Overlay {
+    id: WidgetId,
+    /// The focus to restore when the overlay is removed
+    prior_focus: Option<WidgetId>,
 }
 
 /// Manages the widgets on the display
@@ -183,6 +258,7 @@ pub struct Ui<'widget> {
     render: FnvHashMap<WidgetId, RenderData<'widget>>,
     input_queue: VecDeque<WidgetEvent>,
     focused: Option<WidgetId>,
+    overlays: Vec<Overlay>,
 }
 
 impl<'widget> Ui<'widget> {
@@ -218,6 +294,120 @@ impl<'widget> Ui<'widget> {
         self.add(Some(parent), w)
     }
 
+    /// Adds a widget that is laid out independently of the root widget
+    /// and drawn above it, such as a `Modal` dialog.  The overlay
+    /// receives the focus, and while it is present, mouse input and
+    /// focus traversal are confined to the topmost overlay.
+    /// Use `Ui::remove` to dismiss it.
+    pub fn add_overlay<W: Widget + 'widget>(&mut self, w: W) -> WidgetId {
+        if self.graph.root.is_none() {
+            return self.set_root(w);
+        }
+        let prior_focus = self.focused;
+        let id = self.add(None, w);
+        self.overlays.push(Overlay { id, prior_focus });
+        self.focused = Some(id);
+        id
+    }
+
+    /// Removes a widget and all of its children from the Ui.
+    /// If the focus was within the removed widgets, it moves to the
+    /// parent of the widget, or for an overlay, to the widget that
+    /// was focused prior to the overlay being added.
+    pub fn remove(&mut self, id: WidgetId) {
+        let parent = self.graph.parent.get(&id).copied();
+        let removed = self.graph.remove(id);
+        for id in &removed {
+            self.render.remove(id);
+        }
+
+        let mut restore_focus = parent;
+        if let Some(idx) = self.overlays.iter().position(|o| o.id == id) {
+            restore_focus = self.overlays.remove(idx).prior_focus;
+        }
+        // Overlays may have remembered a focus that is now gone
+        for overlay in &mut self.overlays {
+            if matches!(overlay.prior_focus, Some(f) if removed.contains(&f)) {
+                overlay.prior_focus = None;
+            }
+        }
+
+        if matches!(self.focused, Some(f) if removed.contains(&f)) {
+            self.focused = restore_focus
+                .filter(|f| self.render.contains_key(f))
+                .or_else(|| self.focus_scope());
+        }
+    }
+
+    /// Returns a reference to the widget, if it is of type `W` and
+    /// its `Widget::as_any` method is implemented
+    pub fn widget<W: Widget + 'static>(&self, id: WidgetId) -> Option<&W> {
+        self.render.get(&id)?.widget.as_any()?.downcast_ref()
+    }
+
+    /// Returns a mutable reference to the widget, if it is of type `W`
+    /// and its `Widget::as_any_mut` method is implemented
+    pub fn widget_mut<W: Widget + 'static>(&mut self, id: WidgetId) -> Option<&mut W> {
+        self.render
+            .get_mut(&id)?
+            .widget
+            .as_any_mut()?
+            .downcast_mut()
+    }
+
+    /// Returns the widget that has the keyboard focus
+    pub fn focused(&self) -> Option<WidgetId> {
+        self.focused
+    }
+
+    /// The widget that contains the widgets that may currently
+    /// receive the focus: the topmost overlay, or else the root
+    fn focus_scope(&self) -> Option<WidgetId> {
+        self.overlays.last().map(|o| o.id).or(self.graph.root)
+    }
+
+    /// Returns the focusable widgets in the current focus scope,
+    /// in depth first order
+    fn focusable_widgets(&self) -> Vec<WidgetId> {
+        let mut ids = vec![];
+        if let Some(scope) = self.focus_scope() {
+            self.graph.subtree(scope, &mut ids);
+        }
+        ids.retain(|id| self.render[id].widget.is_focusable());
+        ids
+    }
+
+    /// Moves the focus to the next focusable widget, wrapping around
+    /// from the last to the first.  The Ui calls this when Tab is
+    /// pressed and the focused widget doesn't handle it.
+    pub fn focus_next(&mut self) {
+        self.move_focus(1);
+    }
+
+    /// Moves the focus to the previous focusable widget, wrapping
+    /// around from the first to the last.  The Ui calls this when
+    /// Shift-Tab is pressed and the focused widget doesn't handle it.
+    pub fn focus_previous(&mut self) {
+        self.move_focus(-1);
+    }
+
+    fn move_focus(&mut self, delta: isize) {
+        let ids = self.focusable_widgets();
+        if ids.is_empty() {
+            return;
+        }
+        let len = ids.len() as isize;
+        let next = match self
+            .focused
+            .and_then(|focused| ids.iter().position(|id| *id == focused))
+        {
+            Some(idx) => (idx as isize + delta).rem_euclid(len),
+            None if delta > 0 => 0,
+            None => len - 1,
+        };
+        self.focused = Some(ids[next as usize]);
+    }
+
     fn do_deliver(&mut self, id: WidgetId, event: &WidgetEvent) -> bool {
         let render_data = self.render.get_mut(&id).unwrap();
         let mut args = UpdateArgs {
@@ -228,7 +418,9 @@ impl<'widget> Ui<'widget> {
         render_data.widget.process_event(event, &mut args)
     }
 
-    fn deliver_event(&mut self, mut id: WidgetId, event: &WidgetEvent) {
+    /// Delivers the event to the widget and then to its parents,
+    /// until one of them handles it.  Returns true if it was handled.
+    fn deliver_event(&mut self, mut id: WidgetId, event: &WidgetEvent) -> bool {
         loop {
             let handled = match event {
                 WidgetEvent::Input(InputEvent::Resized { .. }) => true,
@@ -250,63 +442,60 @@ impl<'widget> Ui<'widget> {
             };
 
             if handled {
-                return;
+                return true;
             }
 
             id = match self.graph.parent.get(&id) {
                 Some(parent) => *parent,
-                None => return,
+                None => return false,
             };
         }
     }
 
-    /// find the best matching widget that is under the mouse cursor.
-    /// We're looking for the latest, deepest widget that contains the input
-    /// coordinates.
-    fn hovered_widget(&self, coords: &ScreenRelativeCoords) -> Option<WidgetId> {
-        let root = match self.graph.root {
-            Some(id) => id,
-            _ => return None,
-        };
-
-        let depth = 0;
-        let mut best = (depth, root);
-        self.hovered_recursive(root, depth, coords.x, coords.y, &mut best);
-
-        Some(best.1)
+    /// Returns the widget that is under the specified screen
+    /// coordinates.  This is the latest, deepest widget that contains
+    /// them, considering only the topmost overlay, if any.
+    pub fn hit_test(&self, coords: &ScreenRelativeCoords) -> Option<WidgetId> {
+        let scope = self.focus_scope()?;
+        self.hit_test_recursive(scope, 0, 0, coords)
     }
 
-    /// Recursive helper for hovered_widget().  The `best` tuple holds the
-    /// best (depth, widget) pair.  Depth is incremented each time the function
-    /// recurses.
-    fn hovered_recursive(
+    /// Recursive helper for hit_test(); `left` and `top` are the screen
+    /// coordinates of the parent of `widget`
+    fn hit_test_recursive(
         &self,
         widget: WidgetId,
-        depth: usize,
-        x: usize,
-        y: usize,
-        best: &mut (usize, WidgetId),
-    ) {
+        left: usize,
+        top: usize,
+        coords: &ScreenRelativeCoords,
+    ) -> Option<WidgetId> {
         let render = &self.render[&widget];
+        let left = left + render.coordinates.x;
+        let top = top + render.coordinates.y;
+        let (width, height) = render.surface.dimensions();
 
-        // only consider the dimensions if this node is at the same or a deeper
-        // depth.  If so, then we check to see if the coords are within the bounds.
-        if depth >= best.0 && x >= render.coordinates.x && y >= render.coordinates.y {
-            let (width, height) = render.surface.dimensions();
-
-            if (x - render.coordinates.x < width) && (y - render.coordinates.y < height) {
-                *best = (depth, widget);
-            }
+        if coords.x < left || coords.y < top || coords.x >= left + width || coords.y >= top + height
+        {
+            return None;
         }
 
-        for child in self.graph.children(widget) {
-            self.hovered_recursive(
-                *child,
-                depth + 1,
-                x + render.coordinates.x,
-                y + render.coordinates.y,
-                best,
-            );
+        // Later children are drawn above earlier children
+        self.graph
+            .children(widget)
+            .iter()
+            .rev()
+            .find_map(|child| self.hit_test_recursive(*child, left, top, coords))
+            .or(Some(widget))
+    }
+
+    /// Returns the nearest focusable widget, starting from `id`
+    /// and moving up through its parents
+    fn focusable_ancestor(&self, mut id: WidgetId) -> Option<WidgetId> {
+        loop {
+            if self.render[&id].widget.is_focusable() {
+                return Some(id);
+            }
+            id = *self.graph.parent.get(&id)?;
         }
     }
 
@@ -318,13 +507,39 @@ impl<'widget> Ui<'widget> {
                 }
                 WidgetEvent::Input(InputEvent::Mouse(ref m)) => {
                     if let Some(hover) =
-                        self.hovered_widget(&ScreenRelativeCoords::new(m.x as usize, m.y as usize))
+                        self.hit_test(&ScreenRelativeCoords::new(m.x as usize, m.y as usize))
                     {
+                        let clicked = m.mouse_buttons.intersects(
+                            MouseButtons::LEFT | MouseButtons::RIGHT | MouseButtons::MIDDLE,
+                        );
+                        if clicked {
+                            if let Some(id) = self.focusable_ancestor(hover) {
+                                self.focused = Some(id);
+                            }
+                        }
                         self.deliver_event(hover, &event);
                     }
                 }
-                WidgetEvent::Input(InputEvent::Key(_))
-                | WidgetEvent::Input(InputEvent::Paste(_))
+                WidgetEvent::Input(InputEvent::Key(ref key)) => {
+                    let handled = match self.focused {
+                        Some(focus) => self.deliver_event(focus, &event),
+                        None => false,
+                    };
+                    if !handled {
+                        match key {
+                            KeyEvent {
+                                key: KeyCode::Tab,
+                                modifiers: Modifiers::NONE,
+                            } => self.focus_next(),
+                            KeyEvent {
+                                key: KeyCode::Tab,
+                                modifiers: Modifiers::SHIFT,
+                            } => self.focus_previous(),
+                            _ => {}
+                        }
+                    }
+                }
+                WidgetEvent::Input(InputEvent::Paste(_))
                 | WidgetEvent::Input(InputEvent::PixelMouse(_))
                 | WidgetEvent::Input(InputEvent::Wake) => {
                     if let Some(focus) = self.focused {
@@ -349,6 +564,29 @@ impl<'widget> Ui<'widget> {
         self.focused = Some(id);
     }
 
+    /// Removes the state of widgets that can no longer be reached
+    /// from the root or an overlay, such as those that were added
+    /// without a parent after the root was set
+    fn collect_garbage(&mut self) {
+        let mut reachable = vec![];
+        for id in self
+            .graph
+            .root
+            .iter()
+            .chain(self.overlays.iter().map(|o| &o.id))
+        {
+            self.graph.subtree(*id, &mut reachable);
+        }
+        let reachable: FnvHashSet<WidgetId> = reachable.into_iter().collect();
+
+        self.render.retain(|id, _| reachable.contains(id));
+        self.graph.children.retain(|id, _| reachable.contains(id));
+        self.graph.parent.retain(|id, _| reachable.contains(id));
+        if matches!(self.focused, Some(f) if !reachable.contains(&f)) {
+            self.focused = self.focus_scope();
+        }
+    }
+
     /// Helper for applying the surfaces from the widgets to the target
     /// screen in the correct order (from the root to the leaves)
     fn render_recursive(
@@ -392,9 +630,23 @@ impl<'widget> Ui<'widget> {
     /// Reconsider the layout constraints and apply them.
     /// Returns true if the layout was changed, false if no changes were made.
     fn compute_layout(&mut self, width: usize, height: usize) -> Result<bool> {
-        let mut layout = layout::LayoutState::new();
+        let mut changed = false;
+        // Overlays are laid out relative to the screen, just like the root
+        let roots: Vec<WidgetId> = self
+            .graph
+            .root
+            .iter()
+            .copied()
+            .chain(self.overlays.iter().map(|o| o.id))
+            .collect();
+        for root in roots {
+            changed |= self.compute_layout_for(root, width, height)?;
+        }
+        Ok(changed)
+    }
 
-        let root = self.graph.root.unwrap();
+    fn compute_layout_for(&mut self, root: WidgetId, width: usize, height: usize) -> Result<bool> {
+        let mut layout = layout::LayoutState::new();
         self.add_widget_to_layout(&mut layout, root)?;
         let mut changed = false;
 
@@ -440,16 +692,21 @@ impl<'widget> Ui<'widget> {
     /// Returns true if the Ui may need to be updated again; for example,
     /// if the most recent update operation changed layout.
     pub fn render_to_screen(&mut self, screen: &mut Surface) -> Result<bool> {
+        self.collect_garbage();
+
         if let Some(root) = self.graph.root {
             let (width, height) = screen.dimensions();
             // Render from scratch into a fresh screen buffer
             let mut alt_screen = Surface::new(width, height);
             self.render_recursive(root, &mut alt_screen, &ScreenRelativeCoords::new(0, 0))?;
+            let overlays: Vec<WidgetId> = self.overlays.iter().map(|o| o.id).collect();
+            for overlay in overlays {
+                self.render_recursive(overlay, &mut alt_screen, &ScreenRelativeCoords::new(0, 0))?;
+            }
             // Now compute a delta and apply it to the actual screen
             let diff = screen.diff_screens(&alt_screen);
             screen.add_changes(diff);
         }
-        // TODO: garbage collect unreachable WidgetId's from self.state
 
         if let Some(id) = self.focused {
             let cursor = &self.render[&id].cursor;
@@ -512,3 +769,284 @@ impl<'widget> Ui<'widget> {
         ParentRelativeCoords { x, y }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::layout::ChildOrientation;
+    use super::*;
+    use crate::input::MouseEvent;
+
+    /// Stacks its children vertically
+    struct VBox;
+
+    impl Widget for VBox {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn get_size_constraints(&self) -> layout::Constraints {
+            let mut c = layout::Constraints::default();
+            c.child_orientation = ChildOrientation::Vertical;
+            c
+        }
+    }
+
+    fn key(key: KeyCode, modifiers: Modifiers) -> WidgetEvent {
+        WidgetEvent::Input(InputEvent::Key(KeyEvent { key, modifiers }))
+    }
+
+    fn typed(ui: &mut Ui, text: &str) {
+        for c in text.chars() {
+            ui.queue_event(key(KeyCode::Char(c), Modifiers::NONE));
+        }
+    }
+
+    fn click(x: u16, y: u16, mouse_buttons: MouseButtons) -> WidgetEvent {
+        WidgetEvent::Input(InputEvent::Mouse(MouseEvent {
+            x,
+            y,
+            mouse_buttons,
+            modifiers: Modifiers::NONE,
+        }))
+    }
+
+    /// Processes the queued events and renders until the layout settles
+    fn render(ui: &mut Ui, screen: &mut Surface) {
+        loop {
+            ui.process_event_queue().unwrap();
+            if !ui.render_to_screen(screen).unwrap() {
+                break;
+            }
+        }
+    }
+
+    fn items(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn focus_traversal() {
+        let mut ui = Ui::new();
+        let mut screen = Surface::new(20, 6);
+        let root = ui.set_root(VBox);
+        let input = ui.add_child(root, TextInput::new());
+        ui.add_child(root, ProgressBar::new());
+        let list = ui.add_child(root, List::new(items(&["one", "two"])));
+        render(&mut ui, &mut screen);
+
+        // The root isn't focusable, so the first Tab moves to the input
+        assert_eq!(ui.focused(), Some(root));
+        ui.queue_event(key(KeyCode::Tab, Modifiers::NONE));
+        ui.process_event_queue().unwrap();
+        assert_eq!(ui.focused(), Some(input));
+
+        // Tab isn't an editing key, so it moves the focus on
+        ui.queue_event(key(KeyCode::Tab, Modifiers::NONE));
+        ui.process_event_queue().unwrap();
+        assert_eq!(ui.focused(), Some(list));
+        ui.queue_event(key(KeyCode::Tab, Modifiers::NONE));
+        ui.process_event_queue().unwrap();
+        assert_eq!(ui.focused(), Some(input));
+        ui.queue_event(key(KeyCode::Tab, Modifiers::SHIFT));
+        ui.process_event_queue().unwrap();
+        assert_eq!(ui.focused(), Some(list));
+
+        ui.queue_event(key(KeyCode::DownArrow, Modifiers::NONE));
+        ui.focus_next();
+        typed(&mut ui, "hello");
+        ui.queue_event(key(KeyCode::Char('W'), Modifiers::CTRL));
+        typed(&mut ui, "wor");
+        ui.queue_event(key(KeyCode::Char('A'), Modifiers::CTRL));
+        typed(&mut ui, "¡");
+        render(&mut ui, &mut screen);
+
+        // The DownArrow was queued while the list was focused, but
+        // events are delivered to the focus at the time they are processed
+        assert_eq!(ui.widget::<List>(list).unwrap().selected(), Some(0));
+        assert_eq!(ui.widget::<TextInput>(input).unwrap().text(), "¡wor");
+        assert_eq!(
+            ui.to_screen_coords(input, &ParentRelativeCoords::new(0, 0)),
+            ScreenRelativeCoords::new(0, 0)
+        );
+        assert_eq!(screen.cursor_position(), (1, 0));
+    }
+
+    #[test]
+    fn hit_test_and_click_to_focus() {
+        let mut ui = Ui::new();
+        let mut screen = Surface::new(20, 6);
+        let root = ui.set_root(VBox);
+        let input = ui.add_child(root, TextInput::new());
+        let list = ui.add_child(root, List::new(items(&["one", "two", "three"])));
+        render(&mut ui, &mut screen);
+
+        let list_origin = ui.to_screen_coords(list, &ParentRelativeCoords::new(0, 0));
+        assert_eq!(list_origin, ScreenRelativeCoords::new(0, 1));
+        assert_eq!(ui.hit_test(&ScreenRelativeCoords::new(5, 0)), Some(input));
+        assert_eq!(ui.hit_test(&ScreenRelativeCoords::new(5, 3)), Some(list));
+        assert_eq!(ui.hit_test(&ScreenRelativeCoords::new(20, 3)), None);
+
+        // Clicking on the third row of the list selects the third item
+        ui.queue_event(click(2, 3, MouseButtons::LEFT));
+        render(&mut ui, &mut screen);
+        assert_eq!(ui.focused(), Some(list));
+        assert_eq!(
+            ui.widget::<List>(list).unwrap().selected_item(),
+            Some("three")
+        );
+
+        // Moving the mouse doesn't change the focus
+        ui.queue_event(click(2, 0, MouseButtons::NONE));
+        ui.process_event_queue().unwrap();
+        assert_eq!(ui.focused(), Some(list));
+        ui.queue_event(click(2, 0, MouseButtons::LEFT));
+        ui.process_event_queue().unwrap();
+        assert_eq!(ui.focused(), Some(input));
+    }
+
+    #[test]
+    fn modal_overlay() {
+        let mut ui = Ui::new();
+        let mut screen = Surface::new(30, 9);
+        let root = ui.set_root(VBox);
+        let input = ui.add_child(root, TextInput::new());
+        ui.set_focus(input);
+        render(&mut ui, &mut screen);
+
+        let modal = ui.add_overlay(Modal::new("Quit", "Are you sure?", items(&["Yes", "No"])));
+        assert_eq!(ui.focused(), Some(modal));
+        render(&mut ui, &mut screen);
+
+        assert_eq!(
+            screen.screen_chars_to_string(),
+            "                              \n\
+             \x20                             \n\
+             \x20     ┌ Quit ──────────┐      \n\
+             \x20     │ Are you sure?  │      \n\
+             \x20     │                │      \n\
+             \x20     │ [ Yes ] [ No ] │      \n\
+             \x20     └────────────────┘      \n\
+             \x20                             \n\
+             \x20                             \n"
+        );
+
+        // Input is confined to the modal
+        assert_eq!(ui.hit_test(&ScreenRelativeCoords::new(0, 0)), None);
+        assert_eq!(ui.hit_test(&ScreenRelativeCoords::new(8, 3)), Some(modal));
+        ui.queue_event(key(KeyCode::Tab, Modifiers::NONE));
+        ui.queue_event(click(0, 0, MouseButtons::LEFT));
+        ui.process_event_queue().unwrap();
+        assert_eq!(ui.focused(), Some(modal));
+        assert_eq!(ui.widget::<Modal>(modal).unwrap().selected(), 1);
+        assert_eq!(ui.widget::<Modal>(modal).unwrap().result(), None);
+
+        ui.queue_event(click(10, 5, MouseButtons::LEFT));
+        ui.process_event_queue().unwrap();
+        assert_eq!(ui.widget::<Modal>(modal).unwrap().result(), Some(0));
+
+        ui.remove(modal);
+        assert_eq!(ui.focused(), Some(input));
+        assert!(ui.widget::<Modal>(modal).is_none());
+        render(&mut ui, &mut screen);
+        assert_eq!(ui.hit_test(&ScreenRelativeCoords::new(0, 0)), Some(input));
+    }
+
+    #[test]
+    fn garbage_collection() {
+        let mut ui = Ui::new();
+        let mut screen = Surface::new(10, 3);
+        let root = ui.set_root(VBox);
+        let child = ui.add_child(root, TextInput::new());
+        let orphan = ui.add(None, TextInput::new());
+        render(&mut ui, &mut screen);
+
+        assert!(ui.widget::<TextInput>(child).is_some());
+        assert!(ui.widget::<TextInput>(orphan).is_none());
+
+        ui.set_focus(child);
+        ui.remove(child);
+        assert_eq!(ui.focused(), Some(root));
+        assert!(ui.widget::<TextInput>(child).is_none());
+    }
+
+    #[test]
+    fn stock_widgets_render() {
+        let mut ui = Ui::new();
+        let mut screen = Surface::new(16, 8);
+        let root = ui.set_root(VBox);
+        let tabs = ui.add_child(root, TabStrip::new(items(&["One", "Two"])));
+        let mut progress = ProgressBar::new();
+        progress.set_fraction(0.3);
+        progress.set_label("30%");
+        ui.add_child(root, progress);
+        let mut input = TextInput::new();
+        input.set_placeholder("Search");
+        ui.add_child(root, input);
+        let table = ui.add_child(
+            root,
+            Table::new(
+                vec![
+                    Column {
+                        name: "Name".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "Size".to_string(),
+                        alignment: Alignment::Right,
+                    },
+                ],
+                vec![
+                    items(&["a", "1"]),
+                    items(&["bb", "1234"]),
+                    items(&["ccc", "12", "x"]),
+                ],
+            ),
+        );
+        ui.set_focus(tabs);
+        ui.queue_event(key(KeyCode::RightArrow, Modifiers::NONE));
+        render(&mut ui, &mut screen);
+
+        assert_eq!(ui.widget::<TabStrip>(tabs).unwrap().active(), 1);
+        assert_eq!(ui.widget::<Table>(table).unwrap().selected(), Some(0));
+        assert_eq!(
+            screen.screen_chars_to_string(),
+            " One │ Two │    \n\
+             ███▋         30%\n\
+             Search          \n\
+             Name Size       \n\
+             a       1       \n\
+             bb   1234       \n\
+             ccc    12 x     \n\
+             \x20               \n"
+        );
+    }
+
+    #[test]
+    fn scroll_view() {
+        let mut ui = Ui::new();
+        let mut screen = Surface::new(8, 3);
+        let view = ui.set_root(ScrollView::new("1\n2\n3\n4\n5\n6"));
+        render(&mut ui, &mut screen);
+        assert_eq!(
+            screen.screen_chars_to_string(),
+            "1      █\n2      │\n3      │\n"
+        );
+
+        ui.queue_event(key(KeyCode::PageDown, Modifiers::NONE));
+        render(&mut ui, &mut screen);
+        assert_eq!(ui.widget::<ScrollView>(view).unwrap().top(), 2);
+
+        ui.queue_event(click(0, 0, MouseButtons::VERT_WHEEL));
+        render(&mut ui, &mut screen);
+        assert_eq!(
+            screen.screen_chars_to_string(),
+            "4      │\n5      │\n6      █\n"
+        );
+
+        // Appending while at the end keeps the view at the end
+        ui.widget_mut::<ScrollView>(view).unwrap().append("\n7\n8");
+        render(&mut ui, &mut screen);
+        assert_eq!(
+            screen.screen_chars_to_string(),
+            "6      │\n7      │\n8      █\n"
+        );
+    }
+}
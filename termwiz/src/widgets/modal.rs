@@ -0,0 +1,261 @@
+//! A dialog box with a message and some buttons
+use crate::cell::{unicode_column_width, AttributeChange};
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons};
+use crate::surface::{Change, Position};
+use crate::widgets::layout::{HorizontalAlignment, VerticalAlignment};
+use crate::widgets::{layout, CursorShapeAndPosition, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+use std::ops::Range;
+
+/// The message is wrapped to fit within this many columns
+const MAX_TEXT_WIDTH: usize = 60;
+
+/// A dialog box that displays a message and a row of buttons.
+/// It is intended to be added to the `Ui` using `Ui::add_overlay`,
+/// which centers it on the screen and confines input to it until it
+/// is removed using `Ui::remove`.
+/// The button can be chosen using the arrow and Tab keys followed by
+/// Enter or Space, or by clicking on it; the application can then
+/// check `Modal::result` to find out which button was chosen.
+#[derive(Debug, Clone, Default)]
+pub struct Modal {
+    title: String,
+    lines: Vec<String>,
+    buttons: Vec<String>,
+    selected: usize,
+    result: Option<usize>,
+    /// The row and columns occupied by the buttons the last time
+    /// that the widget was rendered
+    button_row: usize,
+    button_extents: Vec<Range<usize>>,
+}
+
+/// Breaks text into lines of no more than `width` columns, at word
+/// boundaries where possible
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in paragraph.split_whitespace() {
+            let word_width = unicode_column_width(word, None);
+            if line_width > 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+            line.push_str(word);
+            line_width += word_width;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The width of the labels when they are separated by spaces
+fn row_width(labels: &[String]) -> usize {
+    labels
+        .iter()
+        .map(|label| unicode_column_width(label, None))
+        .sum::<usize>()
+        + labels.len().saturating_sub(1)
+}
+
+impl Modal {
+    pub fn new(title: &str, message: &str, buttons: Vec<String>) -> Self {
+        Self {
+            title: title.to_string(),
+            lines: wrap(message, MAX_TEXT_WIDTH),
+            buttons,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the index of the button that was chosen, or None
+    /// if no button has been chosen yet
+    pub fn result(&self) -> Option<usize> {
+        self.result
+    }
+
+    /// Returns the index of the highlighted button
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    fn button_labels(&self) -> Vec<String> {
+        self.buttons.iter().map(|b| format!("[ {} ]", b)).collect()
+    }
+
+    /// The width and height of the content within the border
+    fn inner_size(&self) -> (usize, usize) {
+        let labels = self.button_labels();
+        let buttons_width = row_width(&labels);
+        let text_width = self
+            .lines
+            .iter()
+            .map(|line| unicode_column_width(line, None))
+            .max()
+            .unwrap_or(0);
+        let title_width = unicode_column_width(&self.title, None) + 2;
+        let width = buttons_width.max(text_width).max(title_width);
+        // The message, a blank line and the buttons
+        (width, self.lines.len() + 2)
+    }
+
+    fn move_selection(&mut self, forwards: bool) {
+        let len = self.buttons.len();
+        if len > 0 {
+            self.selected = if forwards {
+                (self.selected + 1) % len
+            } else {
+                (self.selected + len - 1) % len
+            };
+        }
+    }
+}
+
+impl Widget for Modal {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        if width < 2 || height < 2 {
+            return;
+        }
+        let inner_width = width - 2;
+        let at = |x: usize, y: usize| Change::CursorPosition {
+            x: Position::Absolute(x),
+            y: Position::Absolute(y),
+        };
+
+        args.surface
+            .add_change(Change::ClearScreen(Default::default()));
+
+        let title = if self.title.is_empty() {
+            String::new()
+        } else {
+            format!(" {} ", self.title)
+        };
+        let (title, title_width) = crate::lineedit::truncate_to_width(&title, inner_width);
+        args.surface.add_changes(vec![
+            at(0, 0),
+            Change::Text(format!(
+                "┌{}{}┐",
+                title,
+                "─".repeat(inner_width - title_width)
+            )),
+        ]);
+        for y in 1..height - 1 {
+            args.surface.add_changes(vec![
+                at(0, y),
+                Change::Text("│".to_string()),
+                at(width - 1, y),
+                Change::Text("│".to_string()),
+            ]);
+        }
+        args.surface.add_changes(vec![
+            at(0, height - 1),
+            Change::Text(format!("└{}┘", "─".repeat(inner_width))),
+        ]);
+
+        for (y, line) in self.lines.iter().enumerate().take(height.saturating_sub(4)) {
+            let (line, _) = crate::lineedit::truncate_to_width(line, width.saturating_sub(4));
+            args.surface
+                .add_changes(vec![at(2, y + 1), Change::Text(line)]);
+        }
+
+        // The buttons are centered on the row above the bottom border
+        let labels = self.button_labels();
+        let buttons_width = row_width(&labels);
+        let mut x = 1 + inner_width.saturating_sub(buttons_width) / 2;
+        self.button_row = height - 2;
+        self.button_extents.clear();
+        for (idx, label) in labels.iter().enumerate() {
+            let label_width = unicode_column_width(label, None);
+            if x + label_width > width - 1 {
+                break;
+            }
+            args.surface.add_changes(vec![
+                at(x, self.button_row),
+                Change::Attribute(AttributeChange::Reverse(idx == self.selected)),
+                Change::Text(label.clone()),
+                Change::Attribute(AttributeChange::Reverse(false)),
+            ]);
+            self.button_extents.push(x..x + label_width);
+            x += label_width + 1;
+        }
+
+        let cursor_x = self
+            .button_extents
+            .get(self.selected)
+            .map_or(1, |r| r.start + 2);
+        *args.cursor = CursorShapeAndPosition {
+            coords: (cursor_x, self.button_row).into(),
+            ..Default::default()
+        };
+    }
+
+    fn get_size_constraints(&self) -> layout::Constraints {
+        let (width, height) = self.inner_size();
+        // Allow for the border and for a column of padding on each side
+        let mut c = layout::Constraints::with_fixed_width_height(
+            (width + 4).min(u16::MAX as usize) as u16,
+            (height + 2).min(u16::MAX as usize) as u16,
+        );
+        c.set_halign(HorizontalAlignment::Center);
+        c.set_valign(VerticalAlignment::Middle);
+        c
+    }
+
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        match event {
+            WidgetEvent::Input(InputEvent::Key(KeyEvent { key, modifiers })) => {
+                match (key, *modifiers) {
+                    (KeyCode::LeftArrow, Modifiers::NONE)
+                    | (KeyCode::ApplicationLeftArrow, Modifiers::NONE)
+                    | (KeyCode::Tab, Modifiers::SHIFT) => self.move_selection(false),
+                    (KeyCode::RightArrow, Modifiers::NONE)
+                    | (KeyCode::ApplicationRightArrow, Modifiers::NONE)
+                    | (KeyCode::Tab, Modifiers::NONE) => self.move_selection(true),
+                    (KeyCode::Enter, Modifiers::NONE) | (KeyCode::Char(' '), Modifiers::NONE)
+                        if !self.buttons.is_empty() =>
+                    {
+                        self.result = Some(self.selected);
+                    }
+                    _ => return false,
+                }
+                true
+            }
+            WidgetEvent::Input(InputEvent::Mouse(m))
+                if m.mouse_buttons.contains(MouseButtons::LEFT) =>
+            {
+                let (x, y) = (m.x as usize, m.y as usize);
+                if y != self.button_row {
+                    return false;
+                }
+                match self.button_extents.iter().position(|r| r.contains(&x)) {
+                    Some(idx) => {
+                        self.selected = idx;
+                        self.result = Some(idx);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
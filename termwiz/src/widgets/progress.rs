@@ -0,0 +1,95 @@
+//! A horizontal bar that shows the progress of an operation
+use crate::cell::unicode_column_width;
+use crate::lineedit::truncate_to_width;
+use crate::surface::Change;
+use crate::widgets::{layout, CursorShapeAndPosition, RenderArgs, Widget};
+use std::any::Any;
+
+/// Block elements that fill 1/8th through 7/8ths of a cell from the left
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// Displays a bar that is filled in proportion to the progress of an
+/// operation, using the eighth block characters to show progress at a
+/// finer granularity than a whole cell.  An optional label, such as a
+/// percentage, is displayed to the right of the bar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProgressBar {
+    fraction: f64,
+    label: String,
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the proportion of the operation that is complete,
+    /// in the range 0.0 to 1.0
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+
+    /// Sets the proportion of the operation that is complete.
+    /// The value is clamped to the range 0.0 to 1.0.
+    pub fn set_fraction(&mut self, fraction: f64) {
+        self.fraction = if fraction.is_nan() {
+            0.0
+        } else {
+            fraction.clamp(0.0, 1.0)
+        };
+    }
+
+    /// Sets the text displayed to the right of the bar
+    pub fn set_label(&mut self, label: &str) {
+        self.label = label.to_string();
+    }
+
+    /// Returns the text that fills `width` cells to represent
+    /// the current fraction
+    fn bar_text(&self, width: usize) -> String {
+        let eighths = (self.fraction * (width * 8) as f64).round() as usize;
+        let mut bar = "█".repeat(eighths / 8);
+        let partial = eighths % 8;
+        if partial > 0 {
+            bar.push(PARTIAL_BLOCKS[partial - 1]);
+        }
+        let used = unicode_column_width(&bar, None);
+        bar.push_str(&" ".repeat(width - used));
+        bar
+    }
+}
+
+impl Widget for ProgressBar {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, _height) = args.surface.dimensions();
+        args.surface
+            .add_change(Change::ClearScreen(Default::default()));
+        *args.cursor = CursorShapeAndPosition::default();
+        if width == 0 {
+            return;
+        }
+
+        let (label, label_width) = if self.label.is_empty() {
+            (String::new(), 0)
+        } else {
+            let (label, used) = truncate_to_width(&self.label, width.saturating_sub(1));
+            (format!(" {}", label), used + 1)
+        };
+        args.surface
+            .add_change(self.bar_text(width - label_width) + &label);
+    }
+
+    fn get_size_constraints(&self) -> layout::Constraints {
+        let mut c = layout::Constraints::default();
+        c.set_fixed_height(1);
+        c
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
@@ -0,0 +1,177 @@
+//! A read-only view of some text that can be scrolled vertically
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons};
+use crate::lineedit::truncate_to_width;
+use crate::surface::{Change, Position};
+use crate::widgets::{CursorShapeAndPosition, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+
+/// The number of lines scrolled by each step of the mouse wheel
+const WHEEL_LINES: usize = 3;
+
+/// Displays text that may be longer than the widget is tall, and allows
+/// scrolling through it using the keyboard or mouse wheel.  A scrollbar
+/// is shown in the rightmost column when the text doesn't fit.
+/// Lines that are wider than the widget are truncated.
+#[derive(Debug, Clone)]
+pub struct ScrollView {
+    lines: Vec<String>,
+    /// The index of the line displayed in the first row
+    top: usize,
+    /// The number of rows that were available the last time that
+    /// the widget was rendered
+    height: usize,
+}
+
+impl Default for ScrollView {
+    fn default() -> Self {
+        Self {
+            lines: vec![String::new()],
+            top: 0,
+            height: 0,
+        }
+    }
+}
+
+impl ScrollView {
+    pub fn new(text: &str) -> Self {
+        let mut view = Self::default();
+        view.set_text(text);
+        view
+    }
+
+    /// Replaces the text and scrolls back to the start of it
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = vec![String::new()];
+        self.top = 0;
+        self.push_text(text);
+    }
+
+    /// Appends text to the end of the existing text; this continues
+    /// the last line unless the existing text ends with a newline.
+    /// If the view was scrolled to the end, it remains at the end,
+    /// which is useful for displaying a log or the output of a process.
+    pub fn append(&mut self, text: &str) {
+        let at_end = self.top >= self.max_top();
+        self.push_text(text);
+        if at_end {
+            self.top = self.max_top();
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let mut segments = text.split('\n');
+        if let (Some(first), Some(last)) = (segments.next(), self.lines.last_mut()) {
+            last.push_str(first);
+        }
+        self.lines.extend(segments.map(str::to_string));
+        for line in &mut self.lines {
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+    }
+
+    /// Returns the index of the line that is displayed in the first row
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    /// Scrolls so that the specified line is displayed in the first
+    /// row, or as close to it as the length of the text allows
+    pub fn scroll_to(&mut self, line: usize) {
+        self.top = line.min(self.max_top());
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.height.max(1))
+    }
+
+    fn scroll_by(&mut self, lines: usize, up: bool) {
+        if up {
+            self.scroll_to(self.top.saturating_sub(lines));
+        } else {
+            self.scroll_to(self.top + lines);
+        }
+    }
+}
+
+impl Widget for ScrollView {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        self.height = height;
+        self.top = self.top.min(self.max_top());
+
+        let show_scrollbar = self.lines.len() > height && width > 1;
+        let text_width = if show_scrollbar { width - 1 } else { width };
+
+        args.surface
+            .add_change(Change::ClearScreen(Default::default()));
+        for (row, line) in self.lines[self.top..].iter().take(height).enumerate() {
+            let (text, _) = truncate_to_width(line, text_width);
+            args.surface.add_changes(vec![
+                Change::CursorPosition {
+                    x: Position::Absolute(0),
+                    y: Position::Absolute(row),
+                },
+                Change::Text(text),
+            ]);
+        }
+
+        if show_scrollbar {
+            let thumb_size = (height * height / self.lines.len()).max(1);
+            let thumb_top = self.top * (height - thumb_size) / self.max_top();
+            for row in 0..height {
+                let thumb = row >= thumb_top && row < thumb_top + thumb_size;
+                args.surface.add_changes(vec![
+                    Change::CursorPosition {
+                        x: Position::Absolute(width - 1),
+                        y: Position::Absolute(row),
+                    },
+                    Change::Text(if thumb { "█" } else { "│" }.to_string()),
+                ]);
+            }
+        }
+
+        *args.cursor = CursorShapeAndPosition::default();
+    }
+
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        let page = self.height.saturating_sub(1).max(1);
+        match event {
+            WidgetEvent::Input(InputEvent::Key(KeyEvent {
+                key,
+                modifiers: Modifiers::NONE,
+            })) => match key {
+                KeyCode::UpArrow | KeyCode::ApplicationUpArrow => self.scroll_by(1, true),
+                KeyCode::DownArrow | KeyCode::ApplicationDownArrow => self.scroll_by(1, false),
+                KeyCode::PageUp => self.scroll_by(page, true),
+                KeyCode::PageDown => self.scroll_by(page, false),
+                KeyCode::Home => self.scroll_to(0),
+                KeyCode::End => self.scroll_to(self.lines.len()),
+                _ => return false,
+            },
+            WidgetEvent::Input(InputEvent::Mouse(m))
+                if m.mouse_buttons.contains(MouseButtons::VERT_WHEEL) =>
+            {
+                self.scroll_by(
+                    WHEEL_LINES,
+                    m.mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE),
+                );
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
@@ -0,0 +1,130 @@
+//! A row of tab titles, one of which is active
+use crate::cell::{unicode_column_width, AttributeChange};
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons};
+use crate::lineedit::truncate_to_width;
+use crate::surface::Change;
+use crate::widgets::{layout, CursorShapeAndPosition, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+use std::ops::Range;
+
+/// Displays a single row of tab titles, with the active tab shown in
+/// reverse video.  The active tab can be changed using the left and
+/// right arrow keys or by clicking on a title.
+/// The TabStrip only tracks which tab is active; the application is
+/// responsible for showing the content that corresponds to it.
+#[derive(Debug, Clone, Default)]
+pub struct TabStrip {
+    titles: Vec<String>,
+    active: usize,
+    /// The columns occupied by each title the last time that the
+    /// widget was rendered
+    extents: Vec<Range<usize>>,
+}
+
+impl TabStrip {
+    pub fn new(titles: Vec<String>) -> Self {
+        Self {
+            titles,
+            ..Default::default()
+        }
+    }
+
+    pub fn titles(&self) -> &[String] {
+        &self.titles
+    }
+
+    /// Returns the index of the active tab
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Changes the active tab
+    pub fn set_active(&mut self, active: usize) {
+        self.active = active.min(self.titles.len().saturating_sub(1));
+    }
+}
+
+impl Widget for TabStrip {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, _height) = args.surface.dimensions();
+        args.surface
+            .add_change(Change::ClearScreen(Default::default()));
+
+        self.extents.clear();
+        let mut col = 0;
+        for (idx, title) in self.titles.iter().enumerate() {
+            let label = format!(" {} ", title);
+            let (label, used) = truncate_to_width(&label, width - col);
+            if used == 0 {
+                break;
+            }
+            args.surface.add_changes(vec![
+                Change::Attribute(AttributeChange::Reverse(idx == self.active)),
+                Change::Text(label),
+                Change::Attribute(AttributeChange::Reverse(false)),
+            ]);
+            self.extents.push(col..col + used);
+            col += used;
+            if col < width {
+                args.surface.add_change("│");
+                col += unicode_column_width("│", None);
+            }
+        }
+
+        let cursor_col = self.extents.get(self.active).map_or(0, |r| r.start);
+        *args.cursor = CursorShapeAndPosition {
+            coords: (cursor_col, 0).into(),
+            ..Default::default()
+        };
+    }
+
+    fn get_size_constraints(&self) -> layout::Constraints {
+        let mut c = layout::Constraints::default();
+        c.set_fixed_height(1);
+        c
+    }
+
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        match event {
+            WidgetEvent::Input(InputEvent::Key(KeyEvent {
+                key,
+                modifiers: Modifiers::NONE,
+            })) => match key {
+                KeyCode::LeftArrow | KeyCode::ApplicationLeftArrow => {
+                    self.set_active(self.active.saturating_sub(1));
+                    true
+                }
+                KeyCode::RightArrow | KeyCode::ApplicationRightArrow => {
+                    self.set_active(self.active + 1);
+                    true
+                }
+                _ => false,
+            },
+            WidgetEvent::Input(InputEvent::Mouse(m))
+                if m.mouse_buttons.contains(MouseButtons::LEFT) =>
+            {
+                let x = m.x as usize;
+                match self.extents.iter().position(|r| r.contains(&x)) {
+                    Some(idx) => {
+                        self.active = idx;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
@@ -0,0 +1,200 @@
+//! A table of rows with automatically sized columns
+use crate::cell::{unicode_column_width, AttributeChange, Intensity};
+use crate::input::InputEvent;
+use crate::surface::Change;
+use crate::widgets::list::{draw_row, Selection};
+use crate::widgets::{CursorShapeAndPosition, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+
+/// Describes the alignment of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// Describes a column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    /// The name of the column; this is the column header text
+    pub name: String,
+    /// How the column should be aligned
+    pub alignment: Alignment,
+}
+
+/// Displays rows of data beneath a header row, with each column sized
+/// to fit its widest cell, in the same way as the `tabout` crate.
+/// If a row has more cells than there are columns, the extra cells are
+/// displayed in left aligned columns with no header.
+/// One row may be selected using the keyboard or mouse, and the rows
+/// scroll to keep the selected row in view.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+    selection: Selection,
+}
+
+/// Pads `text` to `width` columns according to the alignment
+fn align(text: &str, width: usize, alignment: Alignment) -> String {
+    let text_width = unicode_column_width(text, None);
+    let (left_pad, right_pad) = match alignment {
+        Alignment::Left => (0, width - text_width),
+        Alignment::Center => {
+            let left_pad = (width - text_width) / 2;
+            (left_pad, width - (text_width + left_pad))
+        }
+        Alignment::Right => (width - text_width, 0),
+    };
+    format!("{}{}{}", " ".repeat(left_pad), text, " ".repeat(right_pad))
+}
+
+impl Table {
+    /// Creates a table with the first row selected
+    pub fn new(columns: Vec<Column>, rows: Vec<Vec<String>>) -> Self {
+        let mut table = Self {
+            columns,
+            ..Default::default()
+        };
+        table.set_rows(rows);
+        table
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    /// Replaces the rows.  The selection is preserved if possible.
+    pub fn set_rows(&mut self, rows: Vec<Vec<String>>) {
+        self.rows = rows;
+        if self.selection.selected.is_none() && !self.rows.is_empty() {
+            self.selection.selected = Some(0);
+        }
+        self.selection.scroll_into_view(self.rows.len());
+    }
+
+    /// Returns the index of the selected row
+    pub fn selected(&self) -> Option<usize> {
+        self.selection.selected
+    }
+
+    /// Returns the selected row
+    pub fn selected_row(&self) -> Option<&[String]> {
+        self.selected()
+            .and_then(|idx| self.rows.get(idx))
+            .map(Vec::as_slice)
+    }
+
+    /// Changes the selected row
+    pub fn select(&mut self, idx: Option<usize>) {
+        self.selection.selected = idx;
+        self.selection.scroll_into_view(self.rows.len());
+    }
+
+    /// Computes the width of each column
+    fn column_widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .map(|c| unicode_column_width(&c.name, None))
+            .collect();
+        for row in &self.rows {
+            for (idx, cell) in row.iter().enumerate() {
+                let width = unicode_column_width(cell, None);
+                if let Some(w) = widths.get_mut(idx) {
+                    *w = (*w).max(width);
+                } else {
+                    widths.push(width);
+                }
+            }
+        }
+        widths
+    }
+
+    /// Formats the cells into a single line
+    fn format_row<'a>(&self, cells: impl Iterator<Item = &'a str>, widths: &[usize]) -> String {
+        cells
+            .enumerate()
+            .map(|(idx, cell)| {
+                let alignment = self
+                    .columns
+                    .get(idx)
+                    .map(|c| c.alignment)
+                    .unwrap_or(Alignment::Left);
+                align(cell, widths[idx], alignment)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Widget for Table {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (_width, height) = args.surface.dimensions();
+        // The first row is used for the column headers
+        self.selection.height = height.saturating_sub(1);
+        self.selection.scroll_into_view(self.rows.len());
+
+        let widths = self.column_widths();
+        args.surface
+            .add_change(Change::ClearScreen(Default::default()));
+        args.surface
+            .add_change(Change::Attribute(AttributeChange::Intensity(
+                Intensity::Bold,
+            )));
+        let header = self.format_row(self.columns.iter().map(|c| c.name.as_str()), &widths);
+        draw_row(args.surface, 0, &header, false);
+        args.surface
+            .add_change(Change::Attribute(AttributeChange::Intensity(
+                Intensity::Normal,
+            )));
+
+        for (row, idx) in (self.selection.top..self.rows.len())
+            .take(self.selection.height)
+            .enumerate()
+        {
+            let line = self.format_row(self.rows[idx].iter().map(String::as_str), &widths);
+            draw_row(
+                args.surface,
+                row + 1,
+                &line,
+                self.selection.is_selected(idx),
+            );
+        }
+
+        let row = self.selection.selected.unwrap_or(self.selection.top) - self.selection.top;
+        *args.cursor = CursorShapeAndPosition {
+            coords: (0, row + 1).into(),
+            ..Default::default()
+        };
+    }
+
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        match event {
+            WidgetEvent::Input(InputEvent::Key(key)) => {
+                self.selection.process_key(key, self.rows.len())
+            }
+            WidgetEvent::Input(InputEvent::Mouse(m)) => {
+                self.selection.process_mouse(m, self.rows.len(), 1)
+            }
+            _ => false,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
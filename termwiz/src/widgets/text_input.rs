@@ -0,0 +1,185 @@
+//! A single line text input field
+use crate::cell::{grapheme_column_width, unicode_column_width, AttributeChange, Intensity};
+use crate::input::{InputEvent, MouseButtons};
+use crate::lineedit::{Action, LineBuffer, LineEditor};
+use crate::surface::{Change, CursorShape};
+use crate::widgets::{layout, CursorShapeAndPosition, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A single line text input field that supports the same emacs style
+/// key bindings for moving around and deleting text as the `LineEditor`.
+/// Keys that are not related to editing, such as Enter and Tab, are
+/// not handled, so that they can be processed by a parent widget or
+/// used for focus traversal.
+/// The text is scrolled horizontally to keep the cursor in view.
+#[derive(Debug, Clone, Default)]
+pub struct TextInput {
+    buffer: LineBuffer,
+    /// Shown in place of the text when the text is empty
+    placeholder: String,
+    /// The column of the text that is displayed at the left edge
+    /// of the widget
+    scroll: usize,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the text that has been entered
+    pub fn text(&self) -> &str {
+        &self.buffer.line
+    }
+
+    /// Replaces the text, and moves the cursor to the end of it
+    pub fn set_text(&mut self, text: &str) {
+        self.buffer.line = single_line(text);
+        self.buffer.cursor = self.buffer.line.len();
+    }
+
+    /// Sets the text that is displayed while the input is empty
+    pub fn set_placeholder(&mut self, placeholder: &str) {
+        self.placeholder = placeholder.to_string();
+    }
+
+    fn apply_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::Move(movement) => {
+                self.buffer.cursor = self.buffer.eval_movement(movement);
+            }
+            Action::Kill(movement) => {
+                let range = self.buffer.movement_range(movement);
+                self.buffer.cursor = range.start;
+                self.buffer.line.replace_range(range, "");
+            }
+            Action::KillAndMove(kill_movement, move_movement) => {
+                let range = self.buffer.movement_range(kill_movement);
+                let cursor = self.buffer.eval_movement(move_movement);
+                self.buffer.line.replace_range(range, "");
+                self.buffer.cursor = cursor.min(self.buffer.line.len());
+            }
+            Action::InsertChar(rep, c) if c != '\n' => {
+                for _ in 0..rep {
+                    self.buffer.insert_char(c);
+                }
+            }
+            Action::InsertText(rep, text) => {
+                let text = single_line(&text);
+                for _ in 0..rep {
+                    self.buffer.insert_text(&text);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns the byte index of the grapheme that is displayed at
+    /// the specified column of the widget
+    fn index_at_column(&self, column: usize) -> usize {
+        let column = column + self.scroll;
+        let mut used = 0;
+        for (idx, grapheme) in self.buffer.line.grapheme_indices(true) {
+            let width = grapheme_column_width(grapheme, None);
+            if used + width > column {
+                return idx;
+            }
+            used += width;
+        }
+        self.buffer.line.len()
+    }
+}
+
+/// Pasted text may span multiple lines; we join them with spaces
+fn single_line(text: &str) -> String {
+    text.replace("\r\n", " ").replace(['\r', '\n'], " ")
+}
+
+impl Widget for TextInput {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, _height) = args.surface.dimensions();
+        let width = width.max(1);
+        args.surface
+            .add_change(Change::ClearScreen(Default::default()));
+
+        if self.buffer.line.is_empty() {
+            self.scroll = 0;
+            args.surface
+                .add_change(Change::Attribute(AttributeChange::Intensity(
+                    Intensity::Half,
+                )));
+            let (placeholder, _) = crate::lineedit::truncate_to_width(&self.placeholder, width);
+            args.surface.add_change(placeholder);
+        } else {
+            // Keep the cursor within the visible portion of the text,
+            // allowing room for it to sit just after the last character
+            let cursor_col = unicode_column_width(&self.buffer.line[..self.buffer.cursor], None);
+            if cursor_col < self.scroll {
+                self.scroll = cursor_col;
+            } else if cursor_col >= self.scroll + width {
+                self.scroll = cursor_col + 1 - width;
+            }
+
+            let mut visible = String::new();
+            let mut col = 0;
+            for grapheme in self.buffer.line.graphemes(true) {
+                let grapheme_width = grapheme_column_width(grapheme, None);
+                if col + grapheme_width > self.scroll + width {
+                    break;
+                }
+                if col >= self.scroll {
+                    visible.push_str(grapheme);
+                } else if col + grapheme_width > self.scroll {
+                    // A double width grapheme that straddles the left edge
+                    visible.push(' ');
+                }
+                col += grapheme_width;
+            }
+            args.surface.add_change(visible);
+        }
+
+        let cursor_col = unicode_column_width(&self.buffer.line[..self.buffer.cursor], None);
+        *args.cursor = CursorShapeAndPosition {
+            coords: (cursor_col - self.scroll, 0).into(),
+            shape: CursorShape::SteadyBar,
+            ..Default::default()
+        };
+    }
+
+    fn get_size_constraints(&self) -> layout::Constraints {
+        let mut c = layout::Constraints::default();
+        c.set_fixed_height(1);
+        c
+    }
+
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        match event {
+            WidgetEvent::Input(InputEvent::Mouse(m)) => {
+                if m.mouse_buttons.contains(MouseButtons::LEFT) {
+                    self.buffer.cursor = self.index_at_column(m.x as usize);
+                    true
+                } else {
+                    false
+                }
+            }
+            WidgetEvent::Input(input) => match LineEditor::resolve_emacs_action(input) {
+                Some(action) => self.apply_action(action),
+                None => false,
+            },
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
@@ -529,10 +529,25 @@ impl VTParser {
             }
             Action::Hook => {
                 self.finish_param();
+                // Private markers such as `=` in `DCS = 1 s` were promoted
+                // to params; report them as intermediates instead.  The
+                // params of sequences without a private marker are passed
+                // through unchanged, including the zeros that stand in for
+                // the `;` separators at the end of the integer params.
+                let mut intermediates = vec![];
+                for p in &self.params[0..self.num_params] {
+                    if let CsiParam::P(b) = p {
+                        if (0x3c..=0x3f).contains(b) {
+                            intermediates.push(*b);
+                        }
+                    }
+                }
+                let num_params = self.num_params - intermediates.len();
+                intermediates.extend_from_slice(&self.intermediates[0..self.num_intermediates]);
                 actor.dcs_hook(
                     param,
-                    &self.as_integer_params()[0..self.num_params],
-                    &self.intermediates[0..self.num_intermediates],
+                    &self.as_integer_params()[0..num_params],
+                    &intermediates,
                     self.ignored_excess_intermediates,
                 );
             }
@@ -1029,6 +1044,72 @@ mod test {
         );
     }
 
+    #[test]
+    fn dcs_private_marker() {
+        assert_eq!(
+            parse_as_vec("\x1bP=1s\x1b\\".as_bytes()),
+            vec![
+                VTAction::DcsHook {
+                    byte: b's',
+                    params: vec![1],
+                    intermediates: vec![b'='],
+                    ignored_excess_intermediates: false,
+                },
+                VTAction::DcsUnhook,
+                VTAction::EscDispatch {
+                    params: vec![],
+                    intermediates: vec![],
+                    ignored_excess_intermediates: false,
+                    byte: b'\\',
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn dcs_params() {
+        assert_eq!(
+            parse_as_vec("\x1bP0;1q\x1b\\".as_bytes()),
+            vec![
+                VTAction::DcsHook {
+                    byte: b'q',
+                    params: vec![0, 1, 0],
+                    intermediates: vec![],
+                    ignored_excess_intermediates: false,
+                },
+                VTAction::DcsUnhook,
+                VTAction::EscDispatch {
+                    params: vec![],
+                    intermediates: vec![],
+                    ignored_excess_intermediates: false,
+                    byte: b'\\',
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn dcs_private_marker_with_params() {
+        assert_eq!(
+            parse_as_vec("\x1bP?1;2$q\x1b\\".as_bytes()),
+            vec![
+                VTAction::DcsHook {
+                    byte: b'q',
+                    params: vec![1, 2, 0],
+                    intermediates: vec![b'?', b'$'],
+                    ignored_excess_intermediates: false,
+                },
+                VTAction::DcsUnhook,
+                VTAction::EscDispatch {
+                    params: vec![],
+                    intermediates: vec![],
+                    ignored_excess_intermediates: false,
+                    byte: b'\\',
+                }
+            ]
+        );
+    }
+
     #[test]
     fn tmux_passthru() {
         // I'm not convinced that we *should* represent this tmux sequence
@@ -27,8 +27,9 @@ use std::net::TcpStream;
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 enum ReaderMessage {
@@ -36,7 +37,10 @@ enum ReaderMessage {
         pdu: Pdu,
         promise: Sender<anyhow::Result<Pdu>>,
     },
+    SubscribeToMuxEvents(Sender<MuxEvent>),
     Readable,
+    /// The server didn't respond within the response timeout
+    TimedOut,
 }
 
 #[derive(Clone)]
@@ -46,6 +50,9 @@ pub struct Client {
     client_id: ClientId,
     pub is_reconnectable: bool,
     pub is_local: bool,
+    /// Holds the time at which the connection to the server was lost
+    /// while we are trying to re-establish it
+    disconnected_since: Arc<Mutex<Option<Instant>>>,
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -268,19 +275,44 @@ async fn client_thread_async(
     let mut promises = Promises {
         map: HashMap::new(),
     };
+    let mut mux_event_subscribers: Vec<Sender<MuxEvent>> = vec![];
 
     let mut stream = reconnectable.take_stream().unwrap();
+    let response_timeout = reconnectable.response_timeout();
+    // The time since which we have been waiting for the server
+    // to respond to one of our requests
+    let mut waiting_since = Instant::now();
 
     loop {
         let rx_msg = rx.recv();
         let wait_for_read = stream
             .wait_for_readable()
             .map(|_| Ok(ReaderMessage::Readable));
+        // A network connection can silently stop working, for example,
+        // when the machine is suspended, so if we are waiting for a
+        // response for longer than the timeout we consider the
+        // connection to have been lost
+        let deadline = match response_timeout {
+            Some(timeout) if !promises.map.is_empty() => Some(waiting_since + timeout),
+            _ => None,
+        };
+        let timed_out = async move {
+            match deadline {
+                Some(deadline) => {
+                    smol::Timer::at(deadline).await;
+                    Ok(ReaderMessage::TimedOut)
+                }
+                None => smol::future::pending().await,
+            }
+        };
 
-        match smol::future::or(rx_msg, wait_for_read).await {
+        match smol::future::or(smol::future::or(rx_msg, wait_for_read), timed_out).await {
             Ok(ReaderMessage::SendPdu { pdu, promise }) => {
                 let serial = next_serial;
                 next_serial += 1;
+                if promises.map.is_empty() {
+                    waiting_since = Instant::now();
+                }
                 promises.map.insert(serial, promise);
 
                 pdu.encode_async(&mut stream, serial)
@@ -288,10 +320,17 @@ async fn client_thread_async(
                     .context("encoding a PDU to send to the server")?;
                 stream.flush().await.context("flushing PDU to server")?;
             }
+            Ok(ReaderMessage::SubscribeToMuxEvents(subscriber)) => {
+                mux_event_subscribers.push(subscriber);
+            }
             Ok(ReaderMessage::Readable) => match Pdu::decode_async(&mut stream).await {
                 Ok(decoded) => {
+                    waiting_since = Instant::now();
                     log::trace!("decoded serial {}", decoded.serial);
-                    if decoded.serial == 0 {
+                    if let (0, Pdu::MuxEvent(event)) = (decoded.serial, &decoded.pdu) {
+                        mux_event_subscribers
+                            .retain(|subscriber| subscriber.try_send(event.clone()).is_ok());
+                    } else if decoded.serial == 0 {
                         process_unilateral(local_domain_id, decoded)
                             .context("processing unilateral PDU from server")
                             .map_err(|e| {
@@ -316,6 +355,15 @@ async fn client_thread_async(
                     return Err(err).context("Error while decoding response pdu");
                 }
             },
+            Ok(ReaderMessage::TimedOut) => {
+                let reason = format!(
+                    "no response from the server in {:?}",
+                    waiting_since.elapsed()
+                );
+                log::error!("{}", reason);
+                promises.fail_all(&reason);
+                return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, reason).into());
+            }
             Err(_) => {
                 return Err(NotReconnectableError::ClientWasDestroyed.into());
             }
@@ -428,6 +476,24 @@ struct Reconnectable {
     config: ClientDomainConfig,
     stream: Option<Box<dyn AsyncReadAndWrite>>,
     tls_creds: Option<GetTlsCredsResponse>,
+    ssh: Option<SshConnection>,
+}
+
+/// Tracks the ssh session that is running the proxy for an ssh domain,
+/// so that we can re-use it (and avoid authenticating again) when
+/// reconnecting, and so that we can tell from the exit status of the
+/// proxy whether the server closed the connection or whether the
+/// connection was lost
+struct SshConnection {
+    session: wezterm_ssh::Session,
+    /// Receives true if the proxy exited successfully
+    proxy_exit: Receiver<bool>,
+}
+
+impl std::fmt::Debug for SshConnection {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(fmt, "SshConnection {{...}}")
+    }
 }
 
 struct SshStream {
@@ -476,6 +542,7 @@ impl Reconnectable {
             config,
             stream,
             tls_creds: None,
+            ssh: None,
         }
     }
 
@@ -508,12 +575,47 @@ impl Reconnectable {
             // the set of tabs and we'd have confusing and inconsistent state
             ClientDomainConfig::Unix(_) => false,
             ClientDomainConfig::Tls(_) => true,
-            // It *does* make sense to reconnect with an ssh session, but we
-            // need to grow some smarts about whether the disconnect was because
-            // we sent CTRL-D to close the last session, or whether it was a network
-            // level disconnect, because we will otherwise throw up authentication
-            // dialogs that would be annoying
-            ClientDomainConfig::Ssh(_) => false,
+            // We use lost_connection() to distinguish between the server
+            // closing the connection (eg: because we sent CTRL-D to close
+            // the last session) and a network level disconnect, and only
+            // reconnect in the latter case
+            ClientDomainConfig::Ssh(_) => true,
+        }
+    }
+
+    /// Called when the stream reported EOF; returns true if that was
+    /// because the connection was lost rather than because the server
+    /// deliberately closed it.
+    fn lost_connection(&mut self) -> bool {
+        match &self.ssh {
+            Some(ssh) => {
+                // The proxy exits successfully a couple of seconds after
+                // the server closes its end of the connection; if the
+                // connection to the remote host was lost then the session
+                // will report a failure, or may not report anything at all
+                // until it times out.
+                let exit = ssh.proxy_exit.clone();
+                let status = smol::block_on(smol::future::or(
+                    async move { exit.recv().await.ok() },
+                    async {
+                        smol::Timer::after(Duration::from_secs(5)).await;
+                        None
+                    },
+                ));
+                status != Some(true)
+            }
+            None => false,
+        }
+    }
+
+    /// The longest time that we'll wait for the server to respond to
+    /// a request before concluding that the connection has been lost.
+    /// This only applies to ssh domains; the other domain types have
+    /// socket level read timeouts.
+    fn response_timeout(&self) -> Option<Duration> {
+        match &self.config {
+            ClientDomainConfig::Ssh(ssh) => Some(ssh.timeout),
+            _ => None,
         }
     }
 
@@ -552,9 +654,6 @@ impl Reconnectable {
         initial: bool,
         ui: &mut ConnectionUI,
     ) -> anyhow::Result<()> {
-        let ssh_config = mux::ssh::ssh_domain_to_ssh_config(&ssh_dom)?;
-
-        let sess = ssh_connect_with_ui(ssh_config, ui)?;
         let proxy_bin = Self::wezterm_bin_path(&ssh_dom.remote_wezterm_path);
 
         let cmd = if initial {
@@ -562,10 +661,33 @@ impl Reconnectable {
         } else {
             format!("{} cli --no-auto-start proxy", proxy_bin)
         };
-        ui.output_str(&format!("Running: {}\n", cmd));
-        log::error!("going to run {}", cmd);
 
-        let exec = smol::block_on(sess.exec(&cmd, None))?;
+        // When reconnecting, try to re-use the existing session first;
+        // if the network was only briefly interrupted it may still be
+        // usable, and that avoids prompting for authentication again.
+        let mut exec = None;
+        if let Some(ssh) = self.ssh.take() {
+            ui.output_str(&format!("Running: {}\n", cmd));
+            match Self::ssh_exec(&ssh.session, &cmd, ssh_dom.timeout) {
+                Ok(result) => exec = Some((ssh.session, result)),
+                Err(err) => {
+                    log::warn!("existing ssh session is not usable: {:#}", err);
+                    ui.output_str("Existing ssh session is not usable; reconnecting\n");
+                }
+            }
+        }
+
+        let (sess, exec) = match exec {
+            Some(exec) => exec,
+            None => {
+                let ssh_config = mux::ssh::ssh_domain_to_ssh_config(&ssh_dom)?;
+                let sess = ssh_connect_with_ui(ssh_config, ui)?;
+                ui.output_str(&format!("Running: {}\n", cmd));
+                log::error!("going to run {}", cmd);
+                let exec = Self::ssh_exec(&sess, &cmd, ssh_dom.timeout)?;
+                (sess, exec)
+            }
+        };
 
         let mut stderr = exec.stderr;
         std::thread::spawn(move || {
@@ -584,10 +706,20 @@ impl Reconnectable {
         // the proxy, and prevents us from hanging forever after the process
         // has died
         let mut child = exec.child;
-        std::thread::spawn(move || match child.wait() {
-            Err(err) => log::error!("waiting on {} failed: {:#}", cmd, err),
-            Ok(status) if !status.success() => log::error!("{} failed", cmd),
-            _ => {}
+        let (exit_tx, exit_rx) = bounded(1);
+        std::thread::spawn(move || {
+            let success = match child.wait() {
+                Err(err) => {
+                    log::error!("waiting on {} failed: {:#}", cmd, err);
+                    false
+                }
+                Ok(status) if !status.success() => {
+                    log::error!("{} failed", cmd);
+                    false
+                }
+                _ => true,
+            };
+            exit_tx.try_send(success).ok();
         });
 
         let stream: Box<dyn AsyncReadAndWrite> = Box::new(Async::new(SshStream {
@@ -595,9 +727,26 @@ impl Reconnectable {
             stdout: exec.stdout,
         })?);
         self.stream.replace(stream);
+        self.ssh.replace(SshConnection {
+            session: sess,
+            proxy_exit: exit_rx,
+        });
         Ok(())
     }
 
+    /// Runs `cmd` in the ssh session, giving up after `timeout`
+    /// in case the session is no longer connected
+    fn ssh_exec(
+        sess: &wezterm_ssh::Session,
+        cmd: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<wezterm_ssh::ExecResult> {
+        smol::block_on(smol::future::or(sess.exec(cmd, None), async {
+            smol::Timer::after(timeout).await;
+            bail!("timed out running {} after {:?}", cmd, timeout)
+        }))
+    }
+
     fn unix_connect(
         &mut self,
         unix_dom: UnixDomain,
@@ -914,6 +1063,8 @@ impl Client {
         let is_local = reconnectable.is_local();
         let (sender, mut receiver) = unbounded();
         let client_id = ClientId::new();
+        let disconnected_since = Arc::new(Mutex::new(None));
+        let disconnected = Arc::clone(&disconnected_since);
 
         thread::spawn(move || {
             const BASE_INTERVAL: Duration = Duration::from_secs(1);
@@ -931,9 +1082,12 @@ impl Client {
 
                     if let Some(ioerr) = e.root_cause().downcast_ref::<std::io::Error>() {
                         if let std::io::ErrorKind::UnexpectedEof = ioerr.kind() {
-                            // Don't reconnect for a simple EOF
-                            log::error!("server closed connection ({})", e);
-                            break;
+                            // Don't reconnect for a simple EOF, unless
+                            // it was caused by losing the connection
+                            if !reconnectable.lost_connection() {
+                                log::error!("server closed connection ({})", e);
+                                break;
+                            }
                         }
                     }
 
@@ -942,6 +1096,8 @@ impl Client {
                         break;
                     }
 
+                    disconnected.lock().unwrap().replace(Instant::now());
+
                     let mut ui = ConnectionUI::new();
                     ui.title("wezterm: Reconnecting...");
 
@@ -957,8 +1113,10 @@ impl Client {
                             Ok(_) => {
                                 backoff = BASE_INTERVAL;
                                 log::error!("Reconnected!");
+                                let disconnected = Arc::clone(&disconnected);
                                 promise::spawn::spawn_into_main_thread(async move {
                                     ClientDomain::reattach(local_domain_id, ui).await.ok();
+                                    disconnected.lock().unwrap().take();
                                 })
                                 .detach();
                                 break;
@@ -1007,9 +1165,19 @@ impl Client {
             is_reconnectable,
             is_local,
             client_id,
+            disconnected_since,
         }
     }
 
+    /// If the connection to the server was lost and we are trying
+    /// to re-establish it, returns how long ago that happened
+    pub fn reconnecting_for(&self) -> Option<Duration> {
+        self.disconnected_since
+            .lock()
+            .unwrap()
+            .map(|since| since.elapsed())
+    }
+
     pub async fn verify_version_compat(
         &self,
         ui: &ConnectionUI,
@@ -1056,7 +1224,7 @@ impl Client {
         self.local_domain_id
     }
 
-    fn compute_unix_domain(
+    pub fn compute_unix_domain(
         prefer_mux: bool,
         class_name: &str,
     ) -> anyhow::Result<config::UnixDomain> {
@@ -1146,6 +1314,25 @@ impl Client {
         rx.recv().await?
     }
 
+    /// Asks the server to stream the mux notifications that match
+    /// `subscription`, returning the receiving end of that stream
+    pub async fn subscribe_to_mux_events(
+        &self,
+        subscription: SubscribeToMuxEvents,
+    ) -> anyhow::Result<Receiver<MuxEvent>> {
+        let (tx, rx) = unbounded();
+        self.sender
+            .send(ReaderMessage::SubscribeToMuxEvents(tx))
+            .await?;
+        match self
+            .send_pdu(Pdu::SubscribeToMuxEvents(subscription))
+            .await?
+        {
+            Pdu::UnitResponse(_) => Ok(rx),
+            pdu => bail!("unexpected response {:?}", pdu),
+        }
+    }
+
     rpc!(ping, Ping = (), Pong);
     rpc!(list_panes, ListPanes = (), ListPanesResponse);
     rpc!(spawn_v2, SpawnV2, SpawnResponse);
@@ -1153,6 +1340,7 @@ impl Client {
     rpc!(write_to_pane, WriteToPane, UnitResponse);
     rpc!(send_paste, SendPaste, UnitResponse);
     rpc!(key_down, SendKeyDown, UnitResponse);
+    rpc!(key_up, SendKeyUp, UnitResponse);
     rpc!(mouse_event, SendMouseEvent, UnitResponse);
     rpc!(resize, Resize, UnitResponse);
     rpc!(set_zoomed, SetPaneZoomed, UnitResponse);
@@ -1162,6 +1350,12 @@ impl Client {
         LivenessResponse
     );
     rpc!(get_lines, GetLines, GetLinesResponse);
+    rpc!(
+        get_dimensions,
+        GetPaneRenderableDimensions,
+        GetPaneRenderableDimensionsResponse
+    );
+    rpc!(get_palette, GetPalette, GetPaletteResponse);
     rpc!(get_codec_version, GetCodecVersion, GetCodecVersionResponse);
     rpc!(get_tls_creds, GetTlsCreds = (), GetTlsCredsResponse);
     rpc!(
@@ -1170,9 +1364,24 @@ impl Client {
         SearchScrollbackResponse
     );
     rpc!(kill_pane, KillPane, UnitResponse);
+    rpc!(sftp_transfer, SftpTransfer, SftpTransferResponse);
+    rpc!(save_session, SaveSession, SaveSessionResponse);
+    rpc!(restore_session, RestoreSession, RestoreSessionResponse);
     rpc!(set_client_id, SetClientId, UnitResponse);
     rpc!(list_clients, GetClientList, GetClientListResponse);
     rpc!(set_window_workspace, SetWindowWorkspace, UnitResponse);
     rpc!(set_focused_pane_id, SetFocusedPane, UnitResponse);
     rpc!(get_image_cell, GetImageCell, GetImageCellResponse);
+    rpc!(activate_pane, ActivatePane, UnitResponse);
+    rpc!(activate_pane_direction, ActivatePaneDirection, UnitResponse);
+    rpc!(adjust_pane_size, AdjustPaneSize, UnitResponse);
+    rpc!(
+        move_pane_to_new_tab,
+        MovePaneToNewTab,
+        MovePaneToNewTabResponse
+    );
+    rpc!(set_tab_title, SetTabTitle, UnitResponse);
+    rpc!(set_window_title, SetWindowTitle, UnitResponse);
+    rpc!(activate_tab, ActivateTab, UnitResponse);
+    rpc!(rename_workspace, RenameWorkspace, UnitResponse);
 }
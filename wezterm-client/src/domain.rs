@@ -308,6 +308,24 @@ impl ClientDomain {
         let panes = inner.client.list_panes().await?;
         Self::process_pane_list(inner, panes)?;
 
+        // Output may have been produced while we were disconnected,
+        // so refetch the content of the panes
+        let mux = Mux::get().expect("to be called on main thread");
+        for pane in mux.iter_panes() {
+            if pane.domain_id() != domain_id {
+                continue;
+            }
+            if let Some(client_pane) = pane.downcast_ref::<ClientPane>() {
+                client_pane
+                    .renderable
+                    .borrow()
+                    .inner
+                    .borrow_mut()
+                    .make_all_stale();
+                mux.notify(MuxNotification::PaneOutput(pane.pane_id()));
+            }
+        }
+
         ui.close();
         Ok(())
     }
@@ -324,7 +342,7 @@ impl ClientDomain {
         let mux = Mux::get().expect("to be called on main thread");
         log::debug!("ListPanes result {:#?}", panes);
 
-        for tabroot in panes.tabs {
+        for (tabroot, tab_title) in panes.tabs.into_iter().zip(panes.tab_titles.into_iter()) {
             let root_size = match tabroot.root_size() {
                 Some(size) => size,
                 None => continue,
@@ -352,6 +370,8 @@ impl ClientDomain {
                     inner.record_remote_to_local_tab_mapping(remote_tab_id, tab.tab_id());
                 }
 
+                tab.set_title(&tab_title);
+
                 log::debug!("tree: {:#?}", tabroot);
                 let mut workspace = None;
                 tab.sync_with_pane_tree(root_size, tabroot, |entry| {
@@ -393,6 +413,11 @@ impl ClientDomain {
                     }
                 });
 
+                let window_title = panes
+                    .window_titles
+                    .get(&remote_window_id)
+                    .map(String::as_str)
+                    .unwrap_or("");
                 if let Some(local_window_id) = inner.remote_to_local_window(remote_window_id) {
                     let mut window = mux
                         .get_window_mut(local_window_id)
@@ -400,10 +425,14 @@ impl ClientDomain {
                     if window.idx_by_id(tab.tab_id()).is_none() {
                         window.push(&tab);
                     }
+                    window.set_title(window_title);
                 } else {
                     let local_window_id = mux.new_empty_window(workspace.take());
                     inner.record_remote_to_local_window_mapping(remote_window_id, *local_window_id);
                     mux.add_tab_to_window(&tab, *local_window_id)?;
+                    if let Some(mut window) = mux.get_window_mut(*local_window_id) {
+                        window.set_title(window_title);
+                    }
                 }
             }
         }
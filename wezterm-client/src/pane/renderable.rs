@@ -130,6 +130,22 @@ impl RenderableInner {
         }
     }
 
+    /// Returns the text of the status indicator to overlay on the top
+    /// row of the pane, if the connection is laggy or was lost and
+    /// is being re-established.
+    fn connection_status(&self) -> Option<String> {
+        if let Some(elapsed) = self.client.client.reconnecting_for() {
+            Some(format!("wezterm: reconnecting… {:.0?}", elapsed))
+        } else if self.is_tardy() {
+            Some(format!(
+                "wezterm: {:.0?}⏳since last response",
+                self.last_recv_time.elapsed()
+            ))
+        } else {
+            None
+        }
+    }
+
     /// Predictive echo can be noisy when the link is working well,
     /// so we only employ it when it looks like the latency is high.
     fn should_predict(&self) -> bool {
@@ -732,11 +748,7 @@ impl RenderableState {
             };
 
             if idx == inner.dimensions.physical_top {
-                if inner.is_tardy() {
-                    let status = format!(
-                        "wezterm: {:.0?}⏳since last response",
-                        inner.last_recv_time.elapsed()
-                    );
+                if let Some(status) = inner.connection_status() {
                     // Right align it in the tab
                     let col = inner
                         .dimensions
@@ -806,9 +818,10 @@ impl RenderableState {
             }
         }
 
-        // If we're behind receiving an update, invalidate the top row so
-        // that the indicator will update in a more timely fashion
-        if inner.is_tardy() {
+        // If we're behind receiving an update, or are reconnecting,
+        // invalidate the top row so that the indicator will update
+        // in a more timely fashion
+        if inner.connection_status().is_some() {
             // ... but take care to avoid always reporting it as dirty, so
             // that we don't end up busy looping just to repaint it
             if inner.last_late_dirty.elapsed() >= Duration::from_secs(1) {
@@ -7,7 +7,7 @@ use config::{configuration, FreeTypeLoadFlags, FreeTypeLoadTarget};
 pub use freetype::*;
 use memmap2::{Mmap, MmapOptions};
 use rangeset::RangeSet;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
 use std::ffi::CStr;
 use std::fs::File;
@@ -192,7 +192,139 @@ impl Face {
         }
     }
 
+    /// Returns the axes of a variable font, along with its named
+    /// instances.  Returns None if this is not a variable font.
+    pub fn variation_axes(
+        &self,
+    ) -> anyhow::Result<Option<(Vec<VariationAxis>, Vec<NamedInstance>)>> {
+        let is_variable = unsafe {
+            (((*self.face).face_flags as u32) & (FT_FACE_FLAG_MULTIPLE_MASTERS as u32)) != 0
+        };
+        if !is_variable {
+            return Ok(None);
+        }
+
+        let mut mm = std::ptr::null_mut();
+
+        unsafe {
+            ft_result(FT_Get_MM_Var(self.face, &mut mm), ()).context("FT_Get_MM_Var")?;
+
+            let num_axis = (*mm).num_axis as usize;
+            let axes = std::slice::from_raw_parts((*mm).axis, num_axis)
+                .iter()
+                .map(|axis| VariationAxis {
+                    tag: tag_to_string(axis.tag),
+                    name: if axis.name.is_null() {
+                        String::new()
+                    } else {
+                        CStr::from_ptr(axis.name).to_string_lossy().to_string()
+                    },
+                    minimum: fixed_to_f64(axis.minimum),
+                    default: fixed_to_f64(axis.def),
+                    maximum: fixed_to_f64(axis.maximum),
+                })
+                .collect();
+
+            let instances = if (*mm).namedstyle.is_null() {
+                vec![]
+            } else {
+                std::slice::from_raw_parts((*mm).namedstyle, (*mm).num_namedstyles as usize)
+                    .iter()
+                    .map(|style| NamedInstance {
+                        name_id: style.strid,
+                        coords: std::slice::from_raw_parts(style.coords, num_axis)
+                            .iter()
+                            .map(|&c| fixed_to_f64(c))
+                            .collect(),
+                    })
+                    .collect()
+            };
+
+            FT_Done_MM_Var(self.lib, mm);
+
+            Ok(Some((axes, instances)))
+        }
+    }
+
+    /// Applies explicit design coordinates to the axes of a variable font.
+    /// Axes that are not mentioned in `axes` retain the values from the
+    /// named instance that was used to load the face.
+    /// Returns the resultant design coordinates of every axis of the font.
+    pub fn set_variation_axes(
+        &mut self,
+        axes: &BTreeMap<String, f64>,
+    ) -> anyhow::Result<Vec<(String, f64)>> {
+        let mut mm = std::ptr::null_mut();
+
+        unsafe {
+            ft_result(FT_Get_MM_Var(self.face, &mut mm), ()).context("FT_Get_MM_Var")?;
+
+            let num_axis = (*mm).num_axis;
+            let font_axes = std::slice::from_raw_parts((*mm).axis, num_axis as usize);
+            let mut coords: Vec<FT_Fixed> = vec![0; num_axis as usize];
+
+            let result = ft_result(
+                FT_Get_Var_Design_Coordinates(self.face, num_axis, coords.as_mut_ptr()),
+                (),
+            )
+            .context("FT_Get_Var_Design_Coordinates")
+            .and_then(|_| {
+                for (tag, value) in axes {
+                    let idx = tag_from_str(tag)
+                        .and_then(|wanted| font_axes.iter().position(|axis| axis.tag == wanted));
+                    match idx {
+                        // FreeType clamps the coordinates to the range of the axis
+                        Some(idx) => coords[idx] = (value * 65536.0) as FT_Fixed,
+                        None => log::warn!(
+                            "{} has no variation axis named `{}`",
+                            self.source.diagnostic_string(),
+                            tag
+                        ),
+                    }
+                }
+                ft_result(
+                    FT_Set_Var_Design_Coordinates(self.face, num_axis, coords.as_mut_ptr()),
+                    (),
+                )
+                .context("FT_Set_Var_Design_Coordinates")?;
+                // Read back the coordinates, as FreeType may have clamped them
+                ft_result(
+                    FT_Get_Var_Design_Coordinates(self.face, num_axis, coords.as_mut_ptr()),
+                    (),
+                )
+                .context("FT_Get_Var_Design_Coordinates")?;
+                Ok(font_axes
+                    .iter()
+                    .zip(coords.iter())
+                    .map(|(axis, &c)| (tag_to_string(axis.tag), fixed_to_f64(c)))
+                    .collect())
+            });
+
+            FT_Done_MM_Var(self.lib, mm);
+
+            result
+        }
+    }
+
     pub fn get_sfnt_names(&self) -> HashMap<u32, Vec<NameRecord>> {
+        self.get_sfnt_names_matching(|name_id| {
+            matches!(
+                name_id,
+                TT_NAME_ID_TYPOGRAPHIC_FAMILY
+                    | TT_NAME_ID_TYPOGRAPHIC_SUBFAMILY
+                    | TT_NAME_ID_FONT_FAMILY
+                    | TT_NAME_ID_FONT_SUBFAMILY
+                    | TT_NAME_ID_PS_NAME
+            )
+        })
+    }
+
+    /// Returns the names from the name table whose name_id is
+    /// accepted by `filter`
+    pub fn get_sfnt_names_matching<F: Fn(u32) -> bool>(
+        &self,
+        filter: F,
+    ) -> HashMap<u32, Vec<NameRecord>> {
         let num_names = unsafe { FT_Get_Sfnt_Name_Count(self.face) };
 
         let mut names = HashMap::new();
@@ -215,14 +347,7 @@ impl Face {
                 continue;
             }
 
-            if !matches!(
-                sfnt_name.name_id as u32,
-                TT_NAME_ID_TYPOGRAPHIC_FAMILY
-                    | TT_NAME_ID_TYPOGRAPHIC_SUBFAMILY
-                    | TT_NAME_ID_FONT_FAMILY
-                    | TT_NAME_ID_FONT_SUBFAMILY
-                    | TT_NAME_ID_PS_NAME
-            ) {
+            if !filter(sfnt_name.name_id as u32) {
                 continue;
             }
 
@@ -1109,6 +1234,49 @@ impl FreeTypeStream {
     }
 }
 
+/// Describes one of the axes of a variable font
+#[derive(Debug, Clone)]
+pub struct VariationAxis {
+    pub tag: String,
+    pub name: String,
+    pub minimum: f64,
+    pub default: f64,
+    pub maximum: f64,
+}
+
+/// A named instance of a variable font, expressed as a set of
+/// coordinates; one for each of the axes of the font
+#[derive(Debug, Clone)]
+pub struct NamedInstance {
+    /// The name table id that holds the name of this instance
+    pub name_id: FT_UInt,
+    pub coords: Vec<f64>,
+}
+
+fn fixed_to_f64(f: FT_Fixed) -> f64 {
+    f as f64 / 65536.0
+}
+
+/// Converts an OpenType tag, such as `wght`, into its numeric
+/// representation.  Short tags are padded with spaces.
+/// Returns None if the tag is not 1-4 printable ASCII characters.
+fn tag_from_str(tag: &str) -> Option<FT_ULong> {
+    if tag.is_empty() || tag.len() > 4 || !tag.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+        return None;
+    }
+    Some(
+        tag.bytes()
+            .chain(std::iter::repeat(b' '))
+            .take(4)
+            .fold(0, |acc, b| (acc << 8) | b as FT_ULong),
+    )
+}
+
+fn tag_to_string(tag: FT_ULong) -> String {
+    let bytes = (tag as u32).to_be_bytes();
+    String::from_utf8_lossy(&bytes).trim_end().to_string()
+}
+
 #[derive(Debug)]
 pub struct NameRecord {
     pub platform_id: u16,
@@ -1117,3 +1285,25 @@ pub struct NameRecord {
     pub name_id: u16,
     pub name: String,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tags() {
+        assert_eq!(tag_from_str("wght"), Some(0x77676874));
+        assert_eq!(tag_to_string(0x77676874), "wght");
+
+        // Short tags are padded with spaces, which are trimmed again
+        assert_eq!(tag_from_str("ab"), Some(0x61622020));
+        assert_eq!(tag_to_string(0x61622020), "ab");
+        assert_eq!(tag_from_str("a b"), Some(0x61206220));
+        assert_eq!(tag_to_string(0x61206220), "a b");
+
+        assert_eq!(tag_from_str(""), None);
+        assert_eq!(tag_from_str("weight"), None);
+        assert_eq!(tag_from_str("w\tht"), None);
+        assert_eq!(tag_from_str("w\u{e9}"), None);
+    }
+}
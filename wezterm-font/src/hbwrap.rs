@@ -36,6 +36,15 @@ pub fn feature_from_string(s: &str) -> Result<hb_feature_t, Error> {
     }
 }
 
+/// Returns the variation setting that assigns `value` to the
+/// variable font axis identified by `tag`, eg: `wght`
+pub fn variation(tag: &str, value: f64) -> hb_variation_t {
+    hb_variation_t {
+        tag: unsafe { hb_tag_from_string(tag.as_ptr() as *const c_char, tag.len() as i32) },
+        value: value as f32,
+    }
+}
+
 pub struct Font {
     font: *mut hb_font_t,
 }
@@ -72,6 +81,14 @@ impl Font {
         }
     }
 
+    /// Set the design coordinates of the axes of a variable font.
+    /// Axes that are not specified are set to their default values.
+    pub fn set_variations(&mut self, variations: &[hb_variation_t]) {
+        unsafe {
+            hb_font_set_variations(self.font, variations.as_ptr(), variations.len() as u32);
+        }
+    }
+
     /// Perform shaping.  On entry, Buffer holds the text to shape.
     /// Once done, Buffer holds the output glyph and position info
     pub fn shape(&mut self, buf: &mut Buffer, features: &[hb_feature_t]) {
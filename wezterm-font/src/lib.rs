@@ -284,95 +284,175 @@ struct FallbackResolveInfo {
     config: ConfigHandle,
 }
 
-impl FallbackResolveInfo {
-    fn process(self) {
-        let fallback_str = self.no_glyphs.iter().collect::<String>();
-        let mut extra_handles = vec![];
+/// Identifies where a fallback font candidate was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FallbackSource {
+    /// The system font locator, eg: fontconfig
+    FontLocator,
+    /// The fonts found in the configured font_dirs
+    FontDirs,
+    /// The fonts that are compiled into wezterm
+    BuiltIn,
+}
 
-        log::trace!(
-            "Looking for {} in fallback fonts",
-            fallback_str.escape_unicode()
-        );
+/// A font that was considered while resolving fallback fonts
+#[derive(Debug, Clone)]
+pub struct FallbackCandidate {
+    pub source: FallbackSource,
+    pub font: ParsedFont,
+    /// The codepoints that this font was selected to provide.
+    /// This is empty if the font was not selected, either because
+    /// it has no coverage, or because an earlier candidate already
+    /// provides its glyphs.
+    pub selected_for: RangeSet<u32>,
+}
 
-        match self.locator.locate_fallback_for_codepoints(&self.no_glyphs) {
-            Ok(ref mut handles) => extra_handles.append(handles),
-            Err(err) => log::error!(
-                "Error: {:#} while resolving fallback for {} from font-locator",
-                err,
-                fallback_str.escape_unicode()
-            ),
-        }
+/// Describes the outcome of resolving fallback fonts for a set
+/// of codepoints
+#[derive(Debug, Clone)]
+pub struct FallbackResolution {
+    /// The candidate fonts, in the order that they were considered
+    pub candidates: Vec<FallbackCandidate>,
+    /// The codepoints for which no font could be found
+    pub unresolved: RangeSet<u32>,
+}
 
-        if self.config.search_font_dirs_for_fallback {
-            match self
-                .font_dirs
-                .locate_fallback_for_codepoints(&self.no_glyphs)
-            {
-                Ok(ref mut handles) => extra_handles.append(handles),
-                Err(err) => log::error!(
-                    "Error: {:#} while resolving fallback for {} from font_dirs",
-                    err,
-                    fallback_str.escape_unicode()
-                ),
-            }
-        }
+impl FallbackResolution {
+    /// Returns the fonts that were selected to provide glyphs
+    pub fn selected(&self) -> Vec<ParsedFont> {
+        self.candidates
+            .iter()
+            .filter(|c| !c.selected_for.is_empty())
+            .map(|c| c.font.clone())
+            .collect()
+    }
+}
 
-        match self
-            .built_in
-            .locate_fallback_for_codepoints(&self.no_glyphs)
-        {
-            Ok(ref mut handles) => extra_handles.append(handles),
+/// Search the font-locator, font_dirs and built-in fonts for fonts
+/// that can provide glyphs for `no_glyphs`
+fn resolve_fallback(
+    no_glyphs: &[char],
+    locator: &dyn FontLocator,
+    font_dirs: &FontDatabase,
+    built_in: &FontDatabase,
+    config: &ConfigHandle,
+) -> FallbackResolution {
+    let fallback_str = no_glyphs.iter().collect::<String>();
+    let mut extra_handles = vec![];
+
+    log::trace!(
+        "Looking for {} in fallback fonts",
+        fallback_str.escape_unicode()
+    );
+
+    match locator.locate_fallback_for_codepoints(no_glyphs) {
+        Ok(handles) => extra_handles.extend(
+            handles
+                .into_iter()
+                .map(|p| (FallbackSource::FontLocator, p)),
+        ),
+        Err(err) => log::error!(
+            "Error: {:#} while resolving fallback for {} from font-locator",
+            err,
+            fallback_str.escape_unicode()
+        ),
+    }
+
+    if config.search_font_dirs_for_fallback {
+        match font_dirs.locate_fallback_for_codepoints(no_glyphs) {
+            Ok(handles) => {
+                extra_handles.extend(handles.into_iter().map(|p| (FallbackSource::FontDirs, p)))
+            }
             Err(err) => log::error!(
-                "Error: {:#} while resolving fallback for {} for built-in fonts",
+                "Error: {:#} while resolving fallback for {} from font_dirs",
                 err,
                 fallback_str.escape_unicode()
             ),
         }
+    }
 
-        let mut wanted = RangeSet::new();
-        for c in self.no_glyphs {
-            wanted.add(c as u32);
+    match built_in.locate_fallback_for_codepoints(no_glyphs) {
+        Ok(handles) => {
+            extra_handles.extend(handles.into_iter().map(|p| (FallbackSource::BuiltIn, p)))
         }
+        Err(err) => log::error!(
+            "Error: {:#} while resolving fallback for {} for built-in fonts",
+            err,
+            fallback_str.escape_unicode()
+        ),
+    }
+
+    let mut wanted = RangeSet::new();
+    for &c in no_glyphs {
+        wanted.add(c as u32);
+    }
+    log::trace!(
+        "Fallback fonts that match {} before sorting are: {:#?}",
+        fallback_str.escape_unicode(),
+        extra_handles
+    );
+
+    if wanted.len() > 1 && config.sort_fallback_fonts_by_coverage {
+        // Sort by ascending coverage
+        extra_handles.sort_by_cached_key(|(_, p)| {
+            p.coverage_intersection(&wanted)
+                .map(|r| r.len())
+                .unwrap_or(0)
+        });
+        // Re-arrange to descending coverage
+        extra_handles.reverse();
         log::trace!(
-            "Fallback fonts that match {} before sorting are: {:#?}",
+            "Fallback fonts that match {} after sorting are: {:#?}",
             fallback_str.escape_unicode(),
             extra_handles
         );
+    }
 
-        if wanted.len() > 1 && self.config.sort_fallback_fonts_by_coverage {
-            // Sort by ascending coverage
-            extra_handles.sort_by_cached_key(|p| {
-                p.coverage_intersection(&wanted)
-                    .map(|r| r.len())
-                    .unwrap_or(0)
-            });
-            // Re-arrange to descending coverage
-            extra_handles.reverse();
-            log::trace!(
-                "Fallback fonts that match {} after sorting are: {:#?}",
-                fallback_str.escape_unicode(),
-                extra_handles
-            );
-        }
-
-        // iteratively reduce to just the fonts that we need
-        extra_handles.retain(|p| match p.coverage_intersection(&wanted) {
-            Ok(cov) if cov.is_empty() => false,
-            Ok(cov) => {
-                // Remove the matches from the set, so that we avoid
-                // picking up multiple fonts for the same glyphs
-                wanted = wanted.difference(&cov);
-                true
+    // iteratively reduce to just the fonts that we need
+    let candidates = extra_handles
+        .into_iter()
+        .map(|(source, font)| {
+            let selected_for = match font.coverage_intersection(&wanted) {
+                Ok(cov) => {
+                    // Remove the matches from the set, so that we avoid
+                    // picking up multiple fonts for the same glyphs
+                    wanted = wanted.difference(&cov);
+                    cov
+                }
+                Err(_) => RangeSet::new(),
+            };
+            FallbackCandidate {
+                source,
+                font,
+                selected_for,
             }
-            Err(_) => false,
-        });
+        })
+        .collect();
+
+    FallbackResolution {
+        candidates,
+        unresolved: wanted,
+    }
+}
+
+impl FallbackResolveInfo {
+    fn process(self) {
+        let resolution = resolve_fallback(
+            &self.no_glyphs,
+            &*self.locator,
+            &self.font_dirs,
+            &self.built_in,
+            &self.config,
+        );
 
+        let mut extra_handles = resolution.selected();
         if !extra_handles.is_empty() {
             let mut pending = self.pending.lock().unwrap();
             pending.append(&mut extra_handles);
             (self.completion)();
         }
 
+        let wanted = resolution.unresolved;
         if !wanted.is_empty() {
             // There were some glyphs we couldn't resolve!
             let fallback_str = wanted
@@ -974,6 +1054,21 @@ impl FontConfiguration {
         self.inner.locator.enumerate_all_fonts()
     }
 
+    /// Explains how fallback fonts are resolved for `codepoints` that
+    /// are not present in any of the fonts of a text style.
+    /// This performs the same search that happens when shaping text,
+    /// and is intended for diagnostic purposes.
+    pub fn explain_fallback(&self, codepoints: &[char]) -> FallbackResolution {
+        let config = self.inner.config.borrow().clone();
+        resolve_fallback(
+            codepoints,
+            &*self.inner.locator,
+            &self.inner.font_dirs.borrow(),
+            &self.inner.built_in.borrow(),
+            &config,
+        )
+    }
+
     /// Apply the defined font_rules from the user configuration to
     /// produce the text style that best matches the supplied input
     /// cell attributes.
@@ -4,6 +4,7 @@ use config::{FontAttributes, FontStyle, FreeTypeLoadFlags, FreeTypeLoadTarget};
 pub use config::{FontStretch, FontWeight};
 use rangeset::RangeSet;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::sync::Mutex;
 
 #[derive(Debug)]
@@ -32,6 +33,7 @@ pub struct ParsedFont {
     pub freetype_render_target: Option<FreeTypeLoadTarget>,
     pub freetype_load_flags: Option<FreeTypeLoadFlags>,
     pub scale: Option<f64>,
+    pub axes: Option<BTreeMap<String, f64>>,
 }
 
 impl std::fmt::Debug for ParsedFont {
@@ -53,6 +55,7 @@ impl std::fmt::Debug for ParsedFont {
             .field("freetype_render_target", &self.freetype_render_target)
             .field("freetype_load_flags", &self.freetype_load_flags)
             .field("scale", &self.scale)
+            .field("axes", &self.axes)
             .finish()
     }
 }
@@ -77,6 +80,7 @@ impl Clone for ParsedFont {
             freetype_render_target: self.freetype_render_target,
             freetype_load_flags: self.freetype_load_flags,
             scale: self.scale,
+            axes: self.axes.clone(),
         }
     }
 }
@@ -116,6 +120,13 @@ impl PartialOrd for ParsedFont {
     }
 }
 
+/// Describes the axes and named instances of a variable font
+#[derive(Debug, Clone)]
+pub struct VariableFontInfo {
+    pub axes: Vec<crate::ftwrap::VariationAxis>,
+    pub named_instances: Vec<String>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord)]
 pub struct Names {
     pub full_name: String,
@@ -299,6 +310,20 @@ impl ParsedFont {
             for aka in &p.names.aliases {
                 code.push_str(&format!("  -- AKA: \"{}\"\n", aka));
             }
+            if let Ok(Some(info)) = p.variable_font_info() {
+                for axis in &info.axes {
+                    code.push_str(&format!(
+                        "  -- Axis: {} \"{}\" {}..{} (default {})\n",
+                        axis.tag, axis.name, axis.minimum, axis.maximum, axis.default
+                    ));
+                }
+                if !info.named_instances.is_empty() {
+                    code.push_str(&format!(
+                        "  -- Named instances: {}\n",
+                        info.named_instances.join(", ")
+                    ));
+                }
+            }
 
             if p.weight == FontWeight::REGULAR
                 && p.stretch == FontStretch::Normal
@@ -308,6 +333,7 @@ impl ParsedFont {
                 && p.freetype_load_flags.is_none()
                 && p.harfbuzz_features.is_none()
                 && p.scale.is_none()
+                && p.axes.is_none()
             {
                 code.push_str(&format!("  \"{}\",\n", p.names.family));
             } else {
@@ -345,6 +371,16 @@ impl ParsedFont {
                     }
                     code.push('}');
                 }
+                if let Some(axes) = &p.axes {
+                    code.push_str(", axes={");
+                    for (idx, (tag, value)) in axes.iter().enumerate() {
+                        if idx > 0 {
+                            code.push_str(", ");
+                        }
+                        code.push_str(&format!("[\"{}\"]={}", tag, value));
+                    }
+                    code.push('}');
+                }
                 code.push_str("},\n")
             }
             code.push_str("\n");
@@ -413,9 +449,38 @@ impl ParsedFont {
             freetype_load_target: None,
             freetype_load_flags: None,
             scale: None,
+            axes: None,
         })
     }
 
+    /// If this is a variable font, returns the ranges of its axes and
+    /// the names of its named instances
+    pub fn variable_font_info(&self) -> anyhow::Result<Option<VariableFontInfo>> {
+        let lib = crate::ftwrap::Library::new()?;
+        let face = lib.face_from_locator(&self.handle)?;
+        let (axes, instances) = match face.variation_axes()? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+
+        let names = face.get_sfnt_names_matching(|name_id| {
+            instances.iter().any(|inst| inst.name_id == name_id)
+        });
+        let named_instances = instances
+            .iter()
+            .enumerate()
+            .map(|(idx, inst)| {
+                name_from_table(&names, &[inst.name_id])
+                    .unwrap_or_else(|| format!("instance {}", idx + 1))
+            })
+            .collect();
+
+        Ok(Some(VariableFontInfo {
+            axes,
+            named_instances,
+        }))
+    }
+
     /// Computes the intersection of the wanted set of codepoints with
     /// the set of codepoints covered by this font entry.
     /// Computes the codepoint coverage for this font entry if we haven't
@@ -654,6 +719,11 @@ impl ParsedFont {
         self.freetype_load_target = attr.freetype_load_target;
         self.freetype_load_flags = attr.freetype_load_flags;
         self.scale = attr.scale.map(|f| *f);
+        self.axes = attr.axes.as_ref().map(|axes| {
+            axes.iter()
+                .map(|(tag, value)| (tag.clone(), **value))
+                .collect()
+        });
 
         self.synthesize_italic = self.style == FontStyle::Normal && attr.style != FontStyle::Normal;
         self.synthesize_bold = attr.weight >= FontWeight::BOLD
@@ -299,6 +299,15 @@ impl FreeTypeRasterizer {
         log::trace!("Rasterizier wants {:?}", parsed);
         let lib = ftwrap::Library::new()?;
         let mut face = lib.face_from_locator(&parsed.handle)?;
+        if let Some(axes) = &parsed.axes {
+            if let Err(err) = face.set_variation_axes(axes) {
+                log::warn!(
+                    "Unable to set variation axes for {}: {:#}",
+                    parsed.handle.diagnostic_string(),
+                    err
+                );
+            }
+        }
         let has_color = unsafe {
             (((*face.face).face_flags as u32) & (ftwrap::FT_FACE_FLAG_COLOR as u32)) != 0
         };
@@ -137,8 +137,28 @@ impl HarfbuzzShaper {
                 if opt_pair.is_none() {
                     let handle = &self.handles[font_idx];
                     log::trace!("shaper wants {} {:?}", font_idx, handle);
-                    let face = self.lib.face_from_locator(&handle.handle)?;
+                    let mut face = self.lib.face_from_locator(&handle.handle)?;
+                    let variations = match &handle.axes {
+                        Some(axes) => match face.set_variation_axes(axes) {
+                            Ok(coords) => coords
+                                .iter()
+                                .map(|(tag, value)| harfbuzz::variation(tag, *value))
+                                .collect(),
+                            Err(err) => {
+                                log::warn!(
+                                    "Unable to set variation axes for {}: {:#}",
+                                    handle.handle.diagnostic_string(),
+                                    err
+                                );
+                                vec![]
+                            }
+                        },
+                        None => vec![],
+                    };
                     let mut font = harfbuzz::Font::new(face.face);
+                    if !variations.is_empty() {
+                        font.set_variations(&variations);
+                    }
                     let (load_flags, _) = ftwrap::compute_load_flags_from_config(
                         handle.freetype_load_flags,
                         handle.freetype_load_target,
@@ -682,6 +702,7 @@ mod test {
                     freetype_render_target: None,
                     harfbuzz_features: None,
                     scale: None,
+                    axes: None,
                 },
                 14,
             )
@@ -90,6 +90,17 @@ pub struct SshCommand {
         number_of_values = 1)]
     pub config_override: Vec<(String, String)>,
 
+    /// Connect via the specified jump hosts, which is equivalent
+    /// to the `ProxyJump` ssh_config option.
+    /// Multiple jump hosts are separated by commas and are
+    /// traversed in order, using the form `[username@]host[:port]`.
+    ///
+    /// For example:
+    ///
+    /// `wezterm ssh -J bastion,admin@inner:2222 some-host`
+    #[structopt(short = "J", long = "jump")]
+    pub jump: Option<String>,
+
     /// Enable verbose ssh protocol tracing.
     /// The trace information is printed to the stderr stream of
     /// the process.
@@ -173,4 +184,9 @@ pub struct LsFontsCommand {
     /// Explain which fonts are used to render the supplied text string
     #[structopt(long = "text", conflicts_with = "list-system")]
     pub text: Option<String>,
+
+    /// When used together with --text, print each glyph as a bitmap
+    /// drawn using block characters
+    #[structopt(long = "rasterize", requires = "text")]
+    pub rasterize: bool,
 }
@@ -10,6 +10,7 @@ use mux::ssh::RemoteSshDomain;
 use mux::Mux;
 use portable_pty::cmdbuilder::CommandBuilder;
 use promise::spawn::block_on;
+use rangeset::RangeSet;
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::path::PathBuf;
@@ -114,6 +115,9 @@ async fn async_run_ssh(opts: SshCommand) -> anyhow::Result<()> {
     for (k, v) in opts.config_override {
         ssh_option.insert(k.to_lowercase().to_string(), v);
     }
+    if let Some(jump) = opts.jump {
+        ssh_option.insert("proxyjump".to_string(), jump);
+    }
 
     let dom = SshDomain {
         name: format!("SSH to {}", opts.user_at_host_and_port),
@@ -689,6 +693,23 @@ pub fn run_ls_fonts(config: config::ConfigHandle, cmd: &LsFontsCommand) -> anyho
         for cluster in cell_clusters {
             let style = font_config.match_style(&config, &cluster.attrs);
             let font = font_config.resolve_font(style)?;
+
+            // Figure out which codepoints aren't covered by the fonts
+            // that are already loaded for this style, so that we can
+            // explain how the fallback fonts are found for them
+            let mut missing = RangeSet::new();
+            for c in cluster.text.chars() {
+                missing.add(c as u32);
+            }
+            for parsed in font.clone_handles() {
+                if missing.is_empty() {
+                    break;
+                }
+                if let Ok(cov) = parsed.coverage_intersection(&missing) {
+                    missing = missing.difference(&cov);
+                }
+            }
+
             let presentation_width = PresentationWidth::with_cluster(&cluster);
             let infos = font
                 .blocking_shape(
@@ -716,6 +737,14 @@ pub fn run_ls_fonts(config: config::ConfigHandle, cmd: &LsFontsCommand) -> anyho
 
             println!("{:?}", cluster.direction);
 
+            if !missing.is_empty() {
+                let missing: Vec<char> = missing
+                    .iter_values()
+                    .filter_map(std::char::from_u32)
+                    .collect();
+                print_fallback_resolution(&missing, &font_config.explain_fallback(&missing));
+            }
+
             while let Some(info) = iter.next() {
                 let idx = cluster.byte_to_cell_idx(info.cluster as usize);
 
@@ -756,6 +785,30 @@ pub fn run_ls_fonts(config: config::ConfigHandle, cmd: &LsFontsCommand) -> anyho
                     "",
                     parsed.handle.diagnostic_string()
                 );
+                if info.x_offset.get() != 0. || info.y_offset.get() != 0. {
+                    println!(
+                        "{:38}x_offset={} y_offset={}",
+                        "",
+                        info.x_offset.get(),
+                        info.y_offset.get()
+                    );
+                }
+                if info.font_idx > 0 {
+                    // Show the fonts that were passed over for this glyph
+                    let skipped = handles[0..info.font_idx]
+                        .iter()
+                        .map(|p| p.names().full_name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    println!("{:38}fallback after: {}", "", skipped);
+                }
+
+                if cmd.rasterize {
+                    match font.rasterize_glyph(info.glyph_pos, info.font_idx) {
+                        Ok(glyph) => print_glyph_bitmap(&glyph),
+                        Err(err) => println!("{:38}failed to rasterize: {:#}", "", err),
+                    }
+                }
             }
         }
         return Ok(());
@@ -850,6 +903,90 @@ pub fn run_ls_fonts(config: config::ConfigHandle, cmd: &LsFontsCommand) -> anyho
     Ok(())
 }
 
+/// Print the set of fonts that were considered when resolving
+/// fallback fonts for `missing`, and which of those were chosen
+fn print_fallback_resolution(missing: &[char], resolution: &wezterm_font::FallbackResolution) {
+    let missing: String = missing.iter().collect();
+    println!(
+        "Fallback resolution for {} ({}):",
+        missing,
+        missing.escape_unicode()
+    );
+    if resolution.candidates.is_empty() {
+        println!("  no candidate fonts were found");
+    }
+    for candidate in &resolution.candidates {
+        let status = if candidate.selected_for.is_empty() {
+            "skipped: provides nothing new".to_string()
+        } else {
+            let chars: String = candidate
+                .selected_for
+                .iter_values()
+                .filter_map(std::char::from_u32)
+                .collect();
+            format!("selected for {}", chars.escape_unicode())
+        };
+        println!(
+            "  {:?}: {} -- {}\n    {}",
+            candidate.source,
+            candidate.font.lua_name(),
+            status,
+            candidate.font.handle.diagnostic_string()
+        );
+    }
+    if !resolution.unresolved.is_empty() {
+        let chars: String = resolution
+            .unresolved
+            .iter_values()
+            .filter_map(std::char::from_u32)
+            .collect();
+        println!(
+            "  no fonts contain glyphs for {}; the Last Resort font will be used",
+            chars.escape_unicode()
+        );
+    }
+}
+
+/// Print a rasterized glyph using block characters, with two pixel
+/// rows per line of output.  Pixels are considered to be set when
+/// their alpha channel is at least half of the maximum.
+fn print_glyph_bitmap(glyph: &wezterm_font::RasterizedGlyph) {
+    println!(
+        "{:38}{}x{} bearing_x={} bearing_y={}{}",
+        "",
+        glyph.width,
+        glyph.height,
+        glyph.bearing_x.get(),
+        glyph.bearing_y.get(),
+        if glyph.has_color { " (color)" } else { "" }
+    );
+
+    let is_set = |x: usize, y: usize| -> bool {
+        if y >= glyph.height {
+            return false;
+        }
+        let alpha_idx = (y * glyph.width + x) * 4 + 3;
+        glyph
+            .data
+            .get(alpha_idx)
+            .map(|&a| a >= 0x80)
+            .unwrap_or(false)
+    };
+
+    for y in (0..glyph.height).step_by(2) {
+        let mut row = String::new();
+        for x in 0..glyph.width {
+            row.push(match (is_set(x, y), is_set(x, y + 1)) {
+                (true, true) => '\u{2588}',
+                (true, false) => '\u{2580}',
+                (false, true) => '\u{2584}',
+                (false, false) => ' ',
+            });
+        }
+        println!("{:38}|{}|", "", row);
+    }
+}
+
 fn run() -> anyhow::Result<()> {
     // Inform the system of our AppUserModelID.
     // Without this, our toast notifications won't be correctly
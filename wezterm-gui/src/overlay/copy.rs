@@ -1,8 +1,8 @@
-use crate::selection::{SelectionCoordinate, SelectionRange};
+use crate::selection::{SelectionCoordinate, SelectionMode, SelectionRange};
 use crate::termwindow::{TermWindow, TermWindowNotif};
 use config::keyassignment::ScrollbackEraseMode;
 use mux::domain::DomainId;
-use mux::pane::{Pane, PaneId};
+use mux::pane::{Pane, PaneId, Pattern, SearchResult};
 use mux::renderable::*;
 use portable_pty::PtySize;
 use rangeset::RangeSet;
@@ -10,7 +10,8 @@ use std::cell::{RefCell, RefMut};
 use std::ops::Range;
 use std::rc::Rc;
 use std::sync::Arc;
-use termwiz::surface::{CursorVisibility, SequenceNo};
+use termwiz::cell::{Cell, CellAttributes};
+use termwiz::surface::{CursorVisibility, SequenceNo, SEQ_ZERO};
 use unicode_segmentation::*;
 use url::Url;
 use wezterm_term::color::ColorPalette;
@@ -28,11 +29,62 @@ struct CopyRenderable {
     cursor: StableCursorPosition,
     delegate: Rc<dyn Pane>,
     start: Option<SelectionCoordinate>,
+    /// The shape of the selection; one of Cell, Line or Block
+    selection_mode: SelectionMode,
     viewport: Option<StableRowIndex>,
+    /// The search pattern that is being typed after `/` or `?`
+    search_input: Option<SearchInput>,
+    /// Where the cursor was when the search pattern was begun; the
+    /// pattern is searched for from here as it is typed, and the
+    /// cursor returns here if the search is cancelled
+    search_origin: StableCursorPosition,
+    /// Describes why the pattern that is being typed is invalid
+    search_error: Option<String>,
+    /// Incremented for each search, so that the results of a search
+    /// that has been superseded can be ignored
+    search_generation: usize,
+    /// The most recently executed search, repeated by `n` and `N`
+    last_search: Option<SearchInput>,
+    /// Set after `f`, `F`, `t` or `T` while waiting for the target char
+    pending_jump: Option<CharJump>,
+    /// The most recent char jump, repeated by `;` and `,`
+    last_jump: Option<(CharJump, char)>,
+    /// Rows that need to be repainted because the search bar moved
+    dirty_rows: RangeSet<StableRowIndex>,
     /// We use this to cancel ourselves later
     window: ::window::Window,
 }
 
+#[derive(Clone, Debug)]
+struct SearchInput {
+    pattern: String,
+    /// true for `?`, which searches towards the top of the scrollback
+    backwards: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CharJump {
+    /// `f`: forwards, onto the char
+    Forward,
+    /// `F`: backwards, onto the char
+    Backward,
+    /// `t`: forwards, stopping before the char
+    ForwardTill,
+    /// `T`: backwards, stopping after the char
+    BackwardTill,
+}
+
+impl CharJump {
+    fn reverse(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+            Self::ForwardTill => Self::BackwardTill,
+            Self::BackwardTill => Self::ForwardTill,
+        }
+    }
+}
+
 struct Dimensions {
     vertical_gap: isize,
     dims: RenderableDimensions,
@@ -51,7 +103,16 @@ impl CopyOverlay {
             window,
             delegate: Rc::clone(pane),
             start: None,
+            selection_mode: SelectionMode::Cell,
             viewport: term_window.get_viewport(pane.pane_id()),
+            search_input: None,
+            search_origin: cursor,
+            search_error: None,
+            search_generation: 0,
+            last_search: None,
+            pending_jump: None,
+            last_jump: None,
+            dirty_rows: RangeSet::default(),
         };
         Rc::new(CopyOverlay {
             delegate: Rc::clone(pane),
@@ -84,16 +145,28 @@ impl CopyRenderable {
     fn select_to_cursor_pos(&mut self) {
         self.clamp_cursor_to_scrollback();
         if let Some(start) = self.start {
-            let start = SelectionCoordinate {
+            let mut start = SelectionCoordinate {
                 x: start.x,
                 y: start.y,
             };
 
-            let end = SelectionCoordinate {
+            let mut end = SelectionCoordinate {
                 x: self.cursor.x,
                 y: self.cursor.y,
             };
 
+            if self.selection_mode == SelectionMode::Line {
+                // Line-wise selection spans whole rows; arrange the
+                // columns so that they survive normalization
+                if start.y <= end.y {
+                    start.x = 0;
+                    end.x = usize::max_value();
+                } else {
+                    start.x = usize::max_value();
+                    end.x = 0;
+                }
+            }
+
             self.adjust_selection(start, SelectionRange { start, end });
         } else {
             self.adjust_viewport_for_cursor_position();
@@ -104,11 +177,13 @@ impl CopyRenderable {
     fn adjust_selection(&self, start: SelectionCoordinate, range: SelectionRange) {
         let pane_id = self.delegate.pane_id();
         let window = self.window.clone();
+        let rectangular = self.selection_mode == SelectionMode::Block;
         self.window
             .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
                 let mut selection = term_window.selection(pane_id);
                 selection.start = Some(start);
                 selection.range = Some(range);
+                selection.rectangular = rectangular;
                 window.invalidate();
             })));
         self.adjust_viewport_for_cursor_position();
@@ -364,12 +439,381 @@ impl CopyRenderable {
     }
 
     fn toggle_selection_by_cell(&mut self) {
-        if self.start.take().is_none() {
+        self.toggle_selection(SelectionMode::Cell);
+    }
+
+    fn toggle_selection_by_line(&mut self) {
+        self.toggle_selection(SelectionMode::Line);
+    }
+
+    fn toggle_selection_by_block(&mut self) {
+        self.toggle_selection(SelectionMode::Block);
+    }
+
+    fn toggle_selection(&mut self, mode: SelectionMode) {
+        if self.start.is_some() && self.selection_mode != mode {
+            // Switch the shape of the existing selection, as vim does
+            self.selection_mode = mode;
+            self.select_to_cursor_pos();
+        } else if self.start.take().is_none() {
             let coord = SelectionCoordinate {
                 x: self.cursor.x,
                 y: self.cursor.y,
             };
             self.start.replace(coord);
+            self.selection_mode = mode;
+            self.select_to_cursor_pos();
+        }
+    }
+
+    /// Returns the row on which the search bar is displayed
+    fn compute_search_row(&self) -> StableRowIndex {
+        let dims = self.delegate.get_dimensions();
+        let top = self.viewport.unwrap_or_else(|| dims.physical_top);
+        (top + dims.viewport_rows as StableRowIndex).saturating_sub(1)
+    }
+
+    fn begin_search(&mut self, backwards: bool) {
+        self.search_input.replace(SearchInput {
+            pattern: String::new(),
+            backwards,
+        });
+        self.search_origin = self.cursor;
+        self.search_error.take();
+        self.dirty_rows.add(self.compute_search_row());
+        self.window.invalidate();
+    }
+
+    /// Returns the cursor to where it was when the search began
+    fn return_to_search_origin(&mut self) {
+        self.search_generation += 1;
+        if (self.cursor.x, self.cursor.y) != (self.search_origin.x, self.search_origin.y) {
+            self.cursor = self.search_origin;
+            self.select_to_cursor_pos();
+        }
+    }
+
+    fn cancel_search(&mut self) {
+        self.search_input.take();
+        self.search_error.take();
+        self.return_to_search_origin();
+    }
+
+    /// Searches for the pattern that is being typed, starting from
+    /// where the cursor was when the search began
+    fn search_incremental(&mut self) {
+        self.return_to_search_origin();
+        self.search_error.take();
+        let input = match self.search_input.clone() {
+            Some(input) if !input.pattern.is_empty() => input,
+            _ => return,
+        };
+        match regex::Regex::new(&input.pattern) {
+            Ok(_) => self.search(input.pattern, input.backwards),
+            Err(err) => {
+                // The syntax errors repeat the pattern and point to the
+                // problem with it over several lines; the last line
+                // describes the problem
+                let err = err.to_string();
+                self.search_error.replace(
+                    err.lines()
+                        .last()
+                        .unwrap_or(err.as_str())
+                        .trim()
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    /// Handles keyboard input while the search pattern is being typed
+    fn search_key_down(&mut self, key: KeyCode, mods: KeyModifiers) {
+        let search_row = self.compute_search_row();
+        self.dirty_rows.add(search_row);
+        match (key, mods) {
+            (KeyCode::Escape, KeyModifiers::NONE) | (KeyCode::Char('c'), KeyModifiers::CTRL) => {
+                self.cancel_search();
+            }
+            (KeyCode::Enter, KeyModifiers::NONE) => {
+                if self.search_error.is_some() {
+                    // Leave the invalid pattern to be corrected
+                } else if let Some(input) = self.search_input.take() {
+                    if input.pattern.is_empty() {
+                        // Like vim, an empty pattern re-uses the last one,
+                        // but in the newly requested direction
+                        if let Some(last) = self.last_search.as_mut() {
+                            last.backwards = input.backwards;
+                        }
+                    } else {
+                        self.last_search.replace(input);
+                    }
+                    // Search again from the origin, in case the results
+                    // of the incremental search have yet to arrive
+                    self.return_to_search_origin();
+                    self.search_next(false);
+                }
+            }
+            (KeyCode::Backspace, KeyModifiers::NONE) => {
+                if let Some(input) = self.search_input.as_mut() {
+                    if input.pattern.pop().is_none() {
+                        // Backspace on an empty pattern cancels the search
+                        self.cancel_search();
+                    } else {
+                        self.search_incremental();
+                    }
+                }
+            }
+            (KeyCode::Char('u'), KeyModifiers::CTRL) => {
+                if let Some(input) = self.search_input.as_mut() {
+                    input.pattern.clear();
+                    self.search_incremental();
+                }
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE) | (KeyCode::Char(c), KeyModifiers::SHIFT) => {
+                if let Some(input) = self.search_input.as_mut() {
+                    input.pattern.push(c);
+                    self.search_incremental();
+                }
+            }
+            _ => {}
+        }
+        self.window.invalidate();
+    }
+
+    /// Runs the last search and moves the cursor to the next match in
+    /// the search direction, or the opposite direction if `reverse`
+    /// is true, as for `N`.
+    fn search_next(&mut self, reverse: bool) {
+        let input = match self.last_search.clone() {
+            Some(input) => input,
+            None => return,
+        };
+        self.search(input.pattern, input.backwards != reverse);
+    }
+
+    /// Searches the pane for `pattern` and then moves the cursor to
+    /// the next match in the specified direction
+    fn search(&mut self, pattern: String, backwards: bool) {
+        self.search_generation += 1;
+        let generation = self.search_generation;
+
+        let pane: Rc<dyn Pane> = self.delegate.clone();
+        let window = self.window.clone();
+        promise::spawn::spawn(async move {
+            let mut results = pane.search(Pattern::Regex(pattern)).await?;
+            results.sort();
+
+            let pane_id = pane.pane_id();
+            let mut results = Some(results);
+            window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                let state = term_window.pane_state(pane_id);
+                if let Some(overlay) = state.overlay.as_ref() {
+                    if let Some(copy_overlay) = overlay.downcast_ref::<CopyOverlay>() {
+                        let mut render = copy_overlay.render.borrow_mut();
+                        if render.search_generation == generation {
+                            render.move_to_search_result(&results.take().unwrap(), backwards);
+                            render.window.invalidate();
+                        }
+                    }
+                }
+            })));
+            anyhow::Result::<()>::Ok(())
+        })
+        .detach();
+    }
+
+    /// Moves the cursor to the first of the (sorted) results that lies
+    /// beyond the cursor in the specified direction, wrapping around
+    /// the scrollback if there are none.
+    fn move_to_search_result(&mut self, results: &[SearchResult], backwards: bool) {
+        let cursor = (self.cursor.y, self.cursor.x);
+        if let Some(result) = next_search_result(results, cursor, backwards) {
+            self.cursor.y = result.start_y;
+            self.cursor.x = result.start_x;
+            self.select_to_cursor_pos();
+        }
+    }
+
+    /// Moves the cursor within the current row to the next occurrence
+    /// of `target` in the direction specified by `jump`.
+    fn jump_to_char(&mut self, jump: CharJump, target: char) {
+        self.last_jump.replace((jump, target));
+
+        let y = self.cursor.y;
+        let (top, lines) = self.delegate.get_lines(y..y + 1);
+        let line = match lines.get(0) {
+            Some(line) => line,
+            None => return,
+        };
+        let mut buf = [0u8; 4];
+        let target = target.encode_utf8(&mut buf);
+        let x = self.cursor.x;
+
+        let found = match jump {
+            CharJump::Forward | CharJump::ForwardTill => line
+                .visible_cells()
+                .filter(|(idx, _)| {
+                    // `t` at a position immediately before the target
+                    // must skip over it, otherwise it wouldn't move
+                    let min = if jump == CharJump::ForwardTill {
+                        x + 1
+                    } else {
+                        x
+                    };
+                    *idx > min
+                })
+                .find(|(_, cell)| cell.str() == target)
+                .map(|(idx, _)| {
+                    if jump == CharJump::ForwardTill {
+                        idx - 1
+                    } else {
+                        idx
+                    }
+                }),
+            CharJump::Backward | CharJump::BackwardTill => line
+                .visible_cells()
+                .filter(|(idx, _)| {
+                    let max = if jump == CharJump::BackwardTill {
+                        x.saturating_sub(1)
+                    } else {
+                        x
+                    };
+                    *idx < max
+                })
+                .filter(|(_, cell)| cell.str() == target)
+                .last()
+                .map(|(idx, _)| {
+                    if jump == CharJump::BackwardTill {
+                        idx + 1
+                    } else {
+                        idx
+                    }
+                }),
+        };
+
+        if let Some(x) = found {
+            self.cursor.y = top;
+            self.cursor.x = x;
+            self.select_to_cursor_pos();
+        }
+    }
+
+    fn repeat_char_jump(&mut self, reverse: bool) {
+        if let Some((jump, target)) = self.last_jump {
+            let direction = if reverse { jump.reverse() } else { jump };
+            self.jump_to_char(direction, target);
+            // Remember the original direction, as vim does
+            self.last_jump.replace((jump, target));
+        }
+    }
+
+    /// Implements `%`: finds the first bracket at or after the cursor
+    /// on the current row and moves to its matching partner, which
+    /// may be on another row.
+    fn move_to_matching_bracket(&mut self) {
+        const PAIRS: &[(&str, &str)] = &[("(", ")"), ("[", "]"), ("{", "}")];
+
+        let y = self.cursor.y;
+        let (top, lines) = self.delegate.get_lines(y..y + 1);
+        let line = match lines.get(0) {
+            Some(line) => line,
+            None => return,
+        };
+
+        let (start_x, open, close, forwards) = match line
+            .visible_cells()
+            .filter(|(idx, _)| *idx >= self.cursor.x)
+            .find_map(|(idx, cell)| {
+                PAIRS.iter().find_map(|&(open, close)| {
+                    if cell.str() == open {
+                        Some((idx, open, close, true))
+                    } else if cell.str() == close {
+                        Some((idx, close, open, false))
+                    } else {
+                        None
+                    }
+                })
+            }) {
+            Some(found) => found,
+            None => return,
+        };
+
+        let dims = self.delegate.get_dimensions();
+        let max_row = dims.scrollback_top + dims.scrollback_rows as StableRowIndex;
+        let mut depth = 0;
+        let mut row = top;
+
+        loop {
+            let (_, lines) = self.delegate.get_lines(row..row + 1);
+            let line = match lines.get(0) {
+                Some(line) => line,
+                None => return,
+            };
+            let mut cells: Vec<(usize, &Cell)> = line
+                .visible_cells()
+                .filter(|(idx, _)| {
+                    if row != top {
+                        true
+                    } else if forwards {
+                        *idx >= start_x
+                    } else {
+                        *idx <= start_x
+                    }
+                })
+                .collect();
+            if !forwards {
+                cells.reverse();
+            }
+
+            for (idx, cell) in cells {
+                if cell.str() == open {
+                    depth += 1;
+                } else if cell.str() == close {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.cursor.y = row;
+                        self.cursor.x = idx;
+                        self.select_to_cursor_pos();
+                        return;
+                    }
+                }
+            }
+
+            if forwards {
+                row += 1;
+                if row >= max_row {
+                    return;
+                }
+            } else {
+                if row <= dims.scrollback_top {
+                    return;
+                }
+                row -= 1;
+            }
+        }
+    }
+
+    /// Moves the cursor to the start of the next (or prior) semantic
+    /// zone, such as a prompt or the output of a command.
+    fn move_by_semantic_zone(&mut self, forwards: bool) {
+        let zones = match self.delegate.get_semantic_zones() {
+            Ok(zones) => zones,
+            Err(_) => return,
+        };
+        let cursor = (self.cursor.y, self.cursor.x);
+        let zone = if forwards {
+            zones
+                .iter()
+                .find(|zone| (zone.start_y, zone.start_x) > cursor)
+        } else {
+            zones
+                .iter()
+                .rev()
+                .find(|zone| (zone.start_y, zone.start_x) < cursor)
+        };
+        if let Some(zone) = zone {
+            self.cursor.y = zone.start_y;
+            self.cursor.x = zone.start_x;
             self.select_to_cursor_pos();
         }
     }
@@ -405,7 +849,73 @@ impl Pane for CopyOverlay {
     }
 
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
+        {
+            let mut render = self.render.borrow_mut();
+            if render.search_input.is_some() {
+                render.search_key_down(key, mods);
+                return Ok(());
+            }
+            if let Some(jump) = render.pending_jump.take() {
+                if let KeyCode::Char(c) = key {
+                    if mods == KeyModifiers::NONE || mods == KeyModifiers::SHIFT {
+                        render.jump_to_char(jump, c);
+                    }
+                }
+                return Ok(());
+            }
+        }
+
         match (key, mods) {
+            (KeyCode::Char('/'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().begin_search(false);
+            }
+            (KeyCode::Char('?'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
+            (KeyCode::Char('?'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().begin_search(true);
+            }
+            (KeyCode::Char('n'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().search_next(false);
+            }
+            (KeyCode::Char('N'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
+            (KeyCode::Char('N'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().search_next(true);
+            }
+            (KeyCode::Char('f'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().pending_jump.replace(CharJump::Forward);
+            }
+            (KeyCode::Char('F'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
+            (KeyCode::Char('F'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().pending_jump.replace(CharJump::Backward);
+            }
+            (KeyCode::Char('t'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().pending_jump.replace(CharJump::ForwardTill);
+            }
+            (KeyCode::Char('T'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
+            (KeyCode::Char('T'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().pending_jump.replace(CharJump::BackwardTill);
+            }
+            (KeyCode::Char(';'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().repeat_char_jump(false);
+            }
+            (KeyCode::Char(','), KeyModifiers::NONE) => {
+                self.render.borrow_mut().repeat_char_jump(true);
+            }
+            (KeyCode::Char('%'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
+            (KeyCode::Char('%'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().move_to_matching_bracket();
+            }
+            (KeyCode::Char('}'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
+            (KeyCode::Char('}'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().move_by_semantic_zone(true);
+            }
+            (KeyCode::Char('{'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
+            (KeyCode::Char('{'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().move_by_semantic_zone(false);
+            }
+            (KeyCode::Char('V'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
+            (KeyCode::Char('V'), KeyModifiers::NONE) => {
+                self.render.borrow_mut().toggle_selection_by_line();
+            }
             (KeyCode::Char('c'), KeyModifiers::CTRL)
             | (KeyCode::Char('g'), KeyModifiers::CTRL)
             | (KeyCode::Char('q'), KeyModifiers::NONE)
@@ -455,6 +965,9 @@ impl Pane for CopyOverlay {
             (KeyCode::Char(' '), KeyModifiers::NONE) | (KeyCode::Char('v'), KeyModifiers::NONE) => {
                 self.render.borrow_mut().toggle_selection_by_cell();
             }
+            (KeyCode::Char('v'), KeyModifiers::CTRL) => {
+                self.render.borrow_mut().toggle_selection_by_block();
+            }
             (KeyCode::Char('G'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
             (KeyCode::Char('G'), KeyModifiers::NONE) => {
                 self.render.borrow_mut().move_to_bottom();
@@ -523,7 +1036,16 @@ impl Pane for CopyOverlay {
     }
 
     fn get_cursor_position(&self) -> StableCursorPosition {
-        self.render.borrow().cursor
+        let render = self.render.borrow();
+        match render.search_input.as_ref() {
+            // move to the search bar
+            Some(input) => StableCursorPosition {
+                x: 1 + unicode_column_width(&input.pattern, None),
+                y: render.compute_search_row(),
+                ..render.cursor
+            },
+            None => render.cursor,
+        }
     }
 
     fn get_current_seqno(&self) -> SequenceNo {
@@ -535,11 +1057,41 @@ impl Pane for CopyOverlay {
         lines: Range<StableRowIndex>,
         seqno: SequenceNo,
     ) -> RangeSet<StableRowIndex> {
-        self.delegate.get_changed_since(lines, seqno)
+        let mut dirty = self.delegate.get_changed_since(lines.clone(), seqno);
+        dirty.add_set(&self.render.borrow().dirty_rows);
+        dirty.intersection_with_range(lines)
     }
 
     fn get_lines(&self, lines: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
-        self.delegate.get_lines(lines)
+        let mut render = self.render.borrow_mut();
+        let (top, mut lines) = self.delegate.get_lines(lines);
+
+        let search_row = render.compute_search_row();
+        for (idx, line) in lines.iter_mut().enumerate() {
+            let stable_idx = idx as StableRowIndex + top;
+            render.dirty_rows.remove(stable_idx);
+            if stable_idx != search_row {
+                continue;
+            }
+            if let Some(input) = render.search_input.as_ref() {
+                // Replace with the search bar
+                let cols = self.delegate.get_dimensions().cols;
+                let rev = CellAttributes::default().set_reverse(true).clone();
+                line.fill_range(0..cols, &Cell::new(' ', rev.clone()), SEQ_ZERO);
+                let mut text = format!(
+                    "{}{}",
+                    if input.backwards { '?' } else { '/' },
+                    input.pattern
+                );
+                if let Some(err) = render.search_error.as_ref() {
+                    text.push_str("    ");
+                    text.push_str(err);
+                }
+                line.overlay_text_with_attribute(0, &text, rev, SEQ_ZERO);
+            }
+        }
+
+        (top, lines)
     }
 
     fn get_dimensions(&self) -> RenderableDimensions {
@@ -547,6 +1099,28 @@ impl Pane for CopyOverlay {
     }
 }
 
+/// Returns the first of the (sorted) results that starts beyond
+/// `cursor`, a `(y, x)` position, in the specified direction,
+/// wrapping around to the other end of the results if there are none.
+fn next_search_result(
+    results: &[SearchResult],
+    cursor: (StableRowIndex, usize),
+    backwards: bool,
+) -> Option<&SearchResult> {
+    if backwards {
+        results
+            .iter()
+            .rev()
+            .find(|r| (r.start_y, r.start_x) < cursor)
+            .or_else(|| results.last())
+    } else {
+        results
+            .iter()
+            .find(|r| (r.start_y, r.start_x) > cursor)
+            .or_else(|| results.first())
+    }
+}
+
 fn is_whitespace_word(word: &str) -> bool {
     if let Some(c) = word.chars().next() {
         c.is_whitespace()
@@ -554,3 +1128,81 @@ fn is_whitespace_word(word: &str) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn result(y: StableRowIndex, x: usize) -> SearchResult {
+        SearchResult {
+            start_y: y,
+            start_x: x,
+            end_y: y,
+            end_x: x + 1,
+            match_id: 0,
+        }
+    }
+
+    fn position(result: Option<&SearchResult>) -> Option<(StableRowIndex, usize)> {
+        result.map(|r| (r.start_y, r.start_x))
+    }
+
+    #[test]
+    fn next_result() {
+        let results = [result(1, 4), result(3, 0), result(3, 7)];
+
+        assert_eq!(
+            position(next_search_result(&results, (1, 4), false)),
+            Some((3, 0))
+        );
+        assert_eq!(
+            position(next_search_result(&results, (3, 2), false)),
+            Some((3, 7))
+        );
+        assert_eq!(
+            position(next_search_result(&results, (3, 2), true)),
+            Some((3, 0))
+        );
+        assert_eq!(
+            position(next_search_result(&results, (3, 7), true)),
+            Some((3, 0))
+        );
+    }
+
+    #[test]
+    fn next_result_wraps_around() {
+        let results = [result(1, 4), result(3, 0), result(3, 7)];
+
+        // Beyond the last match, forwards wraps to the first
+        assert_eq!(
+            position(next_search_result(&results, (3, 7), false)),
+            Some((1, 4))
+        );
+        assert_eq!(
+            position(next_search_result(&results, (10, 0), false)),
+            Some((1, 4))
+        );
+        // and before the first match, backwards wraps to the last
+        assert_eq!(
+            position(next_search_result(&results, (1, 4), true)),
+            Some((3, 7))
+        );
+        assert_eq!(
+            position(next_search_result(&results, (0, 0), true)),
+            Some((3, 7))
+        );
+
+        // A single match is found from anywhere, including itself
+        let results = [result(2, 2)];
+        assert_eq!(
+            position(next_search_result(&results, (2, 2), false)),
+            Some((2, 2))
+        );
+        assert_eq!(
+            position(next_search_result(&results, (2, 2), true)),
+            Some((2, 2))
+        );
+
+        assert_eq!(position(next_search_result(&[], (0, 0), false)), None);
+    }
+}
@@ -3,6 +3,7 @@ use chrono::prelude::*;
 use log::Level;
 use luahelper::ValueWrapper;
 use mlua::Value;
+use mux::pane::PaneId;
 use mux::termwiztermtab::TermWizTerminal;
 use termwiz::cell::{AttributeChange, CellAttributes, Intensity};
 use termwiz::color::AnsiColor;
@@ -65,7 +66,11 @@ impl LineEditorHost for LuaReplHost {
     }
 }
 
-pub fn show_debug_overlay(mut term: TermWizTerminal, gui_win: GuiWin) -> anyhow::Result<()> {
+pub fn show_debug_overlay(
+    mut term: TermWizTerminal,
+    gui_win: GuiWin,
+    sync_state: Vec<(PaneId, bool)>,
+) -> anyhow::Result<()> {
     term.no_grab_mouse_in_raw_mode();
 
     let lua = config::Config::load()?
@@ -82,6 +87,21 @@ pub fn show_debug_overlay(mut term: TermWizTerminal, gui_win: GuiWin) -> anyhow:
 
     term.render(&[Change::Title("Debug".to_string())])?;
 
+    let mut changes = vec![
+        AttributeChange::Intensity(Intensity::Bold).into(),
+        Change::Text("Synchronized output:".to_string()),
+        Change::AllAttributes(CellAttributes::default()),
+    ];
+    for (pane_id, active) in sync_state {
+        changes.push(Change::Text(format!(
+            " pane {} {}",
+            pane_id,
+            if active { "held" } else { "idle" }
+        )));
+    }
+    changes.push(Change::Text("\r\n".to_string()));
+    term.render(&changes)?;
+
     fn print_new_log_entries(
         term: &mut TermWizTerminal,
         latest: &mut Option<DateTime<Local>>,
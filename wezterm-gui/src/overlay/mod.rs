@@ -12,6 +12,7 @@ mod debug;
 mod launcher;
 mod quickselect;
 mod search;
+mod sftp;
 
 pub use confirm_close_pane::{
     confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program,
@@ -21,6 +22,7 @@ pub use debug::show_debug_overlay;
 pub use launcher::{launcher, LauncherArgs, LauncherFlags};
 pub use quickselect::QuickSelectOverlay;
 pub use search::SearchOverlay;
+pub use sftp::sftp_browser;
 
 pub fn start_overlay<T, F>(
     term_window: &TermWindow,
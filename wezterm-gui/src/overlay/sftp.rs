@@ -0,0 +1,393 @@
+//! The sftp browser is an overlay that lists the files in the remote
+//! working directory of a pane that belongs to an ssh domain, and
+//! allows downloading them into the local downloads directory, or
+//! uploading local files into that remote directory.
+use config::lua::truncate_right;
+use mux::sftp::{format_size, PaneSftp, TransferProgress};
+use mux::termwiztermtab::TermWizTerminal;
+use std::path::PathBuf;
+use termwiz::cell::{AttributeChange, CellAttributes};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, MouseButtons, MouseEvent};
+use termwiz::lineedit::{LineEditor, NopLineEditorHost};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::Terminal;
+use wezterm_ssh::{Metadata, Utf8PathBuf};
+
+/// The title, path and status lines
+const ROW_OVERHEAD: usize = 3;
+
+#[derive(Clone, Debug)]
+struct Entry {
+    name: String,
+    path: Utf8PathBuf,
+    is_dir: bool,
+    size: Option<u64>,
+}
+
+impl Entry {
+    fn label(&self) -> String {
+        if self.is_dir {
+            format!("{}/", self.name)
+        } else {
+            match self.size {
+                Some(size) => format!("{}  ({})", self.name, format_size(size)),
+                None => self.name.clone(),
+            }
+        }
+    }
+}
+
+struct SftpBrowserState {
+    pane_sftp: PaneSftp,
+    cwd: Utf8PathBuf,
+    entries: Vec<Entry>,
+    active_idx: usize,
+    top_row: usize,
+    max_items: usize,
+    status: String,
+    download_dir: PathBuf,
+}
+
+impl SftpBrowserState {
+    fn load_dir(&mut self, dir: Utf8PathBuf) -> anyhow::Result<()> {
+        let dir = smol::block_on(self.pane_sftp.sftp.canonicalize(dir))?;
+        let mut entries: Vec<Entry> = smol::block_on(self.pane_sftp.sftp.read_dir(dir.clone()))?
+            .into_iter()
+            .map(|(path, metadata): (Utf8PathBuf, Metadata)| Entry {
+                name: path.file_name().unwrap_or(path.as_str()).to_string(),
+                is_dir: metadata.is_dir(),
+                size: metadata.size,
+                path,
+            })
+            .collect();
+        // Directories first, then files, each sorted by name
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        if let Some(parent) = dir.parent() {
+            entries.insert(
+                0,
+                Entry {
+                    name: "..".to_string(),
+                    path: parent.to_path_buf(),
+                    is_dir: true,
+                    size: None,
+                },
+            );
+        }
+
+        self.cwd = dir;
+        self.entries = entries;
+        self.active_idx = 0;
+        self.top_row = 0;
+        Ok(())
+    }
+
+    fn change_dir(&mut self, dir: Utf8PathBuf) {
+        if let Err(err) = self.load_dir(dir.clone()) {
+            self.status = format!("Unable to open {}: {:#}", dir, err);
+        } else {
+            self.status.clear();
+        }
+    }
+
+    fn render(&mut self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let max_width = size.cols.saturating_sub(6);
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+            Change::Text(format!(
+                "{}\r\n",
+                truncate_right(
+                    "Enter=open/download  d=download  u=upload  \
+                     Backspace=parent  r=refresh  Esc=close",
+                    max_width
+                )
+            )),
+            Change::Text(format!(
+                "{}\r\n",
+                truncate_right(self.cwd.as_str(), max_width)
+            )),
+            Change::AllAttributes(CellAttributes::default()),
+        ];
+
+        for (row_num, (entry_idx, entry)) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.top_row)
+            .enumerate()
+        {
+            if row_num >= self.max_items {
+                break;
+            }
+            if entry_idx == self.active_idx {
+                changes.push(AttributeChange::Reverse(true).into());
+            }
+            changes.push(Change::Text(format!(
+                "  {} \r\n",
+                truncate_right(&entry.label(), max_width)
+            )));
+            if entry_idx == self.active_idx {
+                changes.push(AttributeChange::Reverse(false).into());
+            }
+        }
+
+        changes.append(&mut status_line(term, &self.status)?);
+        term.render(&changes)
+    }
+
+    fn move_up(&mut self) {
+        self.active_idx = self.active_idx.saturating_sub(1);
+        if self.active_idx < self.top_row {
+            self.top_row = self.active_idx;
+        }
+    }
+
+    fn move_down(&mut self) {
+        self.active_idx = (self.active_idx + 1).min(self.entries.len().saturating_sub(1));
+        if self.active_idx >= self.top_row + self.max_items {
+            self.top_row = (self.active_idx + 1).saturating_sub(self.max_items);
+        }
+    }
+
+    fn go_to_parent(&mut self) {
+        if let Some(parent) = self.cwd.parent() {
+            let parent = parent.to_path_buf();
+            self.change_dir(parent);
+        }
+    }
+
+    fn activate(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
+        let entry = match self.entries.get(self.active_idx) {
+            Some(entry) => entry.clone(),
+            None => return Ok(()),
+        };
+        if entry.is_dir {
+            self.change_dir(entry.path);
+            Ok(())
+        } else {
+            self.download(term, &entry)
+        }
+    }
+
+    fn download(&mut self, term: &mut TermWizTerminal, entry: &Entry) -> anyhow::Result<()> {
+        if entry.is_dir {
+            self.status = format!("{} is a directory", entry.name);
+            return Ok(());
+        }
+        let name = entry.name.clone();
+        let result = smol::block_on(self.pane_sftp.download(
+            entry.path.as_str(),
+            &self.download_dir,
+            |progress| show_progress(term, "Downloading", &name, progress),
+        ));
+        self.status = match result {
+            Ok((_, local)) => format!("Downloaded {} to {}", name, local.display()),
+            Err(err) => format!("Download failed: {:#}", err),
+        };
+        Ok(())
+    }
+
+    fn upload(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
+        let local = {
+            let size = term.get_screen_size()?;
+            term.render(&[
+                Change::CursorPosition {
+                    x: Position::Absolute(0),
+                    y: Position::Absolute(size.rows.saturating_sub(1)),
+                },
+                Change::ClearToEndOfLine(ColorAttribute::Default),
+            ])?;
+            let mut host = NopLineEditorHost::default();
+            let mut editor = LineEditor::new(term);
+            editor.set_prompt("Upload local file: ");
+            editor.read_line(&mut host)?
+        };
+        let local = match local {
+            Some(local) if !local.is_empty() => PathBuf::from(local),
+            _ => {
+                self.status.clear();
+                return Ok(());
+            }
+        };
+
+        let name = local.display().to_string();
+        let result = smol::block_on(
+            self.pane_sftp
+                .upload(&local, self.cwd.as_str(), |progress| {
+                    show_progress(term, "Uploading", &name, progress)
+                }),
+        );
+        match result {
+            Ok(remote) => {
+                let cwd = self.cwd.clone();
+                self.change_dir(cwd);
+                self.status = format!("Uploaded {} to {}", name, remote);
+            }
+            Err(err) => self.status = format!("Upload failed: {:#}", err),
+        }
+        Ok(())
+    }
+
+    fn run_loop(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
+        while let Ok(Some(event)) = term.poll_input(None) {
+            match event {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('j'),
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::DownArrow,
+                    ..
+                }) => {
+                    self.move_down();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('k'),
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::UpArrow,
+                    ..
+                }) => {
+                    self.move_up();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('h'),
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Backspace,
+                    ..
+                }) => {
+                    self.go_to_parent();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                }) => {
+                    self.activate(term)?;
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('d'),
+                    ..
+                }) => {
+                    if let Some(entry) = self.entries.get(self.active_idx).cloned() {
+                        self.download(term, &entry)?;
+                    }
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('u'),
+                    ..
+                }) => {
+                    self.upload(term)?;
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('r'),
+                    ..
+                }) => {
+                    let cwd = self.cwd.clone();
+                    self.change_dir(cwd);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('q'),
+                    ..
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                }) => {
+                    break;
+                }
+                InputEvent::Mouse(MouseEvent { mouse_buttons, .. })
+                    if mouse_buttons.contains(MouseButtons::VERT_WHEEL) =>
+                {
+                    if mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                        self.move_up();
+                    } else {
+                        self.move_down();
+                    }
+                }
+                InputEvent::Mouse(MouseEvent {
+                    y, mouse_buttons, ..
+                }) => {
+                    let row = (y as usize).saturating_sub(ROW_OVERHEAD - 1);
+                    if y as usize >= ROW_OVERHEAD - 1
+                        && row < self.max_items
+                        && self.top_row + row < self.entries.len()
+                    {
+                        self.active_idx = self.top_row + row;
+                        if mouse_buttons == MouseButtons::LEFT {
+                            self.activate(term)?;
+                        }
+                    }
+                }
+                InputEvent::Resized { rows, .. } => {
+                    self.max_items = rows.saturating_sub(ROW_OVERHEAD);
+                }
+                _ => {}
+            }
+            self.render(term)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the changes that show `status` on the bottom row
+fn status_line(term: &mut TermWizTerminal, status: &str) -> termwiz::Result<Vec<Change>> {
+    let size = term.get_screen_size()?;
+    Ok(vec![
+        Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(size.rows.saturating_sub(1)),
+        },
+        Change::ClearToEndOfLine(ColorAttribute::Default),
+        AttributeChange::Reverse(true).into(),
+        Change::Text(truncate_right(status, size.cols.saturating_sub(1))),
+        AttributeChange::Reverse(false).into(),
+    ])
+}
+
+fn show_progress(term: &mut TermWizTerminal, verb: &str, name: &str, progress: TransferProgress) {
+    let status = format!("{} {}: {}", verb, name, progress);
+    if let Ok(changes) = status_line(term, &status) {
+        term.render(&changes).ok();
+    }
+}
+
+pub fn sftp_browser(pane_sftp: PaneSftp, mut term: TermWizTerminal) -> anyhow::Result<()> {
+    let size = term.get_screen_size()?;
+    let download_dir = dirs_next::download_dir()
+        .or_else(dirs_next::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let mut state = SftpBrowserState {
+        cwd: Utf8PathBuf::from("."),
+        pane_sftp,
+        entries: vec![],
+        active_idx: 0,
+        top_row: 0,
+        max_items: size.rows.saturating_sub(ROW_OVERHEAD),
+        status: String::new(),
+        download_dir,
+    };
+
+    term.set_raw_mode()?;
+    term.render(&[Change::Title("SFTP".to_string())])?;
+
+    let start_dir = state
+        .pane_sftp
+        .cwd
+        .clone()
+        .unwrap_or_else(|| Utf8PathBuf::from("."));
+    state.change_dir(start_dir);
+    state.render(&mut term)?;
+    state.run_loop(&mut term)
+}
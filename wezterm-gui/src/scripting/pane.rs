@@ -3,8 +3,11 @@ use super::luaerr;
 use anyhow::anyhow;
 use mlua::{UserData, UserDataMethods};
 use mux::pane::{Pane, PaneId};
+use mux::sftp::PaneSftp;
 use mux::Mux;
+use std::path::PathBuf;
 use std::rc::Rc;
+use wezterm_term::export::{export_lines, ExportFormat};
 
 #[derive(Clone)]
 pub struct PaneObject {
@@ -84,6 +87,26 @@ impl UserData for PaneObject {
             Ok(text)
         });
 
+        // Like get_lines_as_text, but retains the colors, attributes
+        // and hyperlinks of the text by exporting it in the specified
+        // format, which may be one of "text", "html", "svg" or "ansi".
+        methods.add_method(
+            "get_lines_as",
+            |_, this, (format, nlines): (String, Option<usize>)| {
+                let format: ExportFormat = format
+                    .parse()
+                    .map_err(|e| anyhow!("{}", e))
+                    .map_err(luaerr)?;
+                let pane = this.pane()?;
+                let dims = pane.get_dimensions();
+                let nlines = nlines.unwrap_or(dims.viewport_rows);
+                let bottom_row = dims.physical_top + dims.viewport_rows as isize;
+                let top_row = bottom_row.saturating_sub(nlines as isize);
+                let (_first_row, lines) = pane.get_lines(top_row..bottom_row);
+                Ok(export_lines(&lines, &pane.palette(), format))
+            },
+        );
+
         methods.add_method(
             "get_logical_lines_as_text",
             |_, this, nlines: Option<usize>| {
@@ -107,5 +130,61 @@ impl UserData for PaneObject {
                 Ok(text)
             },
         );
+
+        // Downloads a file from the remote host of a pane that belongs
+        // to an ssh domain, returning the local path of the file.
+        // The local path defaults to the downloads directory.
+        methods.add_async_method(
+            "sftp_download",
+            |_, this, (remote, local): (String, Option<String>)| async move {
+                let pane_sftp = PaneSftp::for_pane(this.pane).map_err(luaerr)?;
+                let local = match local {
+                    Some(local) => PathBuf::from(local),
+                    None => dirs_next::download_dir()
+                        .or_else(dirs_next::home_dir)
+                        .ok_or_else(|| anyhow!("unable to determine the downloads directory"))
+                        .map_err(luaerr)?,
+                };
+                let (_remote, local) = run_transfer(move || {
+                    smol::block_on(pane_sftp.download(&remote, &local, |_| {}))
+                })
+                .await?;
+                Ok(local.display().to_string())
+            },
+        );
+
+        // Uploads a file to the remote host of a pane that belongs to
+        // an ssh domain, returning the remote path of the file.
+        // The remote path defaults to the cwd of the pane.
+        methods.add_async_method(
+            "sftp_upload",
+            |_, this, (local, remote): (String, Option<String>)| async move {
+                let pane_sftp = PaneSftp::for_pane(this.pane).map_err(luaerr)?;
+                let remote = run_transfer(move || {
+                    let remote = remote.unwrap_or_default();
+                    smol::block_on(pane_sftp.upload(&PathBuf::from(local), &remote, |_| {}))
+                })
+                .await?;
+                Ok(remote.to_string())
+            },
+        );
     }
 }
+
+/// Runs a transfer on its own thread so that it doesn't block the
+/// gui while it is in progress
+async fn run_transfer<T, F>(func: F) -> mlua::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+{
+    let (tx, rx) = smol::channel::bounded(1);
+    std::thread::spawn(move || {
+        tx.try_send(func()).ok();
+    });
+    rx.recv()
+        .await
+        .map_err(|e| anyhow!("{:#}", e))
+        .map_err(luaerr)?
+        .map_err(luaerr)
+}
@@ -17,6 +17,10 @@ pub struct Selection {
     pub range: Option<SelectionRange>,
     /// When the selection was made wrt. the pane content
     pub seqno: SequenceNo,
+    /// When true, the selection is a rectangular block whose
+    /// corners are the start and end coordinates, rather than
+    /// a linear run of text.
+    pub rectangular: bool,
 }
 
 pub use config::keyassignment::SelectionMode;
@@ -26,6 +30,7 @@ impl Selection {
     pub fn clear(&mut self) {
         self.range = None;
         self.start = None;
+        self.rectangular = false;
     }
 
     pub fn begin(&mut self, start: SelectionCoordinate) {
@@ -216,12 +221,14 @@ impl SelectionRange {
     /// indicates that the selection extends to the end of that row.
     /// Since this struct has no knowledge of line length, it cannot be
     /// more precise than that.
+    /// When `rectangular` is true, the same span of columns is selected
+    /// on every row.
     /// Must be called on a normalized range!
-    pub fn cols_for_row(&self, row: StableRowIndex) -> Range<usize> {
+    pub fn cols_for_row(&self, row: StableRowIndex, rectangular: bool) -> Range<usize> {
         let norm = self.normalize();
         if row < norm.start.y || row > norm.end.y {
             0..0
-        } else if norm.start.y == norm.end.y {
+        } else if rectangular || norm.start.y == norm.end.y {
             // A single line selection
             if norm.start.x <= norm.end.x {
                 norm.start.x..norm.end.x.saturating_add(1)
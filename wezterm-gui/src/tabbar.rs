@@ -111,7 +111,12 @@ fn compute_tab_title(
         Some(title) => title,
         None => {
             let title = if let Some(pane) = &tab.active_pane {
-                let mut title = pane.title.clone();
+                let pane_title = if tab.tab_title.is_empty() {
+                    &pane.title
+                } else {
+                    &tab.tab_title
+                };
+                let mut title = pane_title.clone();
                 let classic_spacing = if config.use_fancy_tab_bar { "" } else { " " };
                 if config.show_tab_index_in_tab_bar {
                     title = format!(
@@ -123,7 +128,7 @@ fn compute_tab_title(
                             } else {
                                 1
                             },
-                        pane.title,
+                        pane_title,
                         classic_spacing,
                     );
                 }
@@ -168,6 +168,7 @@ pub struct TabInformation {
     pub tab_index: usize,
     pub is_active: bool,
     pub active_pane: Option<PaneInformation>,
+    pub tab_title: String,
 }
 
 impl UserData for TabInformation {
@@ -175,6 +176,7 @@ impl UserData for TabInformation {
         fields.add_field_method_get("tab_id", |_, this| Ok(this.tab_id));
         fields.add_field_method_get("tab_index", |_, this| Ok(this.tab_index));
         fields.add_field_method_get("is_active", |_, this| Ok(this.is_active));
+        fields.add_field_method_get("tab_title", |_, this| Ok(this.tab_title.clone()));
         fields.add_field_method_get("active_pane", |_, this| {
             if let Some(pane) = &this.active_pane {
                 Ok(Some(pane.clone()))
@@ -1022,6 +1024,8 @@ impl TermWindow {
                     self.mux_pane_output_event(pane_id);
                 }
                 MuxNotification::WindowInvalidated(_) => {
+                    // Tab and window titles may have been changed
+                    self.update_title();
                     window.invalidate();
                 }
                 MuxNotification::WindowRemoved(_window_id) => {
@@ -1550,6 +1554,7 @@ impl TermWindow {
         if num_tabs == 0 {
             return;
         }
+        let window_title = window.get_title().to_string();
         drop(window);
 
         let title = match config::run_immediate_with_lua_config(|lua| {
@@ -1587,6 +1592,7 @@ impl TermWindow {
 
         let title = match title {
             Some(title) => title,
+            None if !window_title.is_empty() => window_title,
             None => {
                 if let (Some(pos), Some(tab)) = (active_pane, active_tab) {
                     if num_tabs == 1 {
@@ -1766,9 +1772,14 @@ impl TermWindow {
         };
 
         let gui_win = GuiWin::new(self);
+        let sync_state = tab
+            .iter_panes()
+            .iter()
+            .map(|pos| (pos.pane.pane_id(), pos.pane.is_synchronized_output_active()))
+            .collect();
 
         let (overlay, future) = start_overlay(self, &tab, move |_tab_id, term| {
-            crate::overlay::show_debug_overlay(term, gui_win)
+            crate::overlay::show_debug_overlay(term, gui_win, sync_state)
         });
         self.assign_overlay(tab.tab_id(), overlay);
         promise::spawn::spawn(future).detach();
@@ -1825,6 +1836,26 @@ impl TermWindow {
         promise::spawn::spawn(future).detach();
     }
 
+    fn show_sftp_browser(&mut self) {
+        let pane = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane,
+            None => return,
+        };
+        let pane_sftp = match mux::sftp::PaneSftp::for_pane(pane.pane_id()) {
+            Ok(pane_sftp) => pane_sftp,
+            Err(err) => {
+                log::error!("Unable to show sftp browser: {:#}", err);
+                return;
+            }
+        };
+
+        let (overlay, future) = start_overlay_pane(self, &pane, move |_pane_id, term| {
+            crate::overlay::sftp_browser(pane_sftp, term)
+        });
+        self.assign_overlay_for_pane(pane.pane_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+
     /// Returns the Prompt semantic zones
     fn get_semantic_prompt_zones(&mut self, pane: &Rc<dyn Pane>) -> &[StableRowIndex] {
         let mut cache = self
@@ -2065,6 +2096,7 @@ impl TermWindow {
             ShowLauncherArgs(args) => {
                 self.show_launcher_impl(args.title.as_deref().unwrap_or("Launcher"), args.flags)
             }
+            ShowSftpBrowser => self.show_sftp_browser(),
             HideApplication => {
                 let con = Connection::get().expect("call on gui thread");
                 con.hide_application();
@@ -2553,6 +2585,7 @@ impl TermWindow {
                         .iter()
                         .find(|p| p.is_active)
                         .map(Self::pos_pane_to_pane_info),
+                    tab_title: tab.get_title(),
                 }
             })
             .collect()
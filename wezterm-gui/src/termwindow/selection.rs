@@ -1,18 +1,52 @@
 use crate::selection::{SelectionCoordinate, SelectionMode, SelectionRange};
 use ::window::WindowOps;
 use mux::pane::Pane;
+use std::ops::Range;
 use std::rc::Rc;
-use wezterm_term::StableRowIndex;
+use wezterm_term::{Line, StableRowIndex};
+
+/// Returns the text of the cells that overlap the specified columns.
+/// Unlike `Line::columns_as_str`, a double-width cell that straddles
+/// either edge of the range is included rather than dropped.
+fn block_columns_as_str(line: &Line, cols: Range<usize>) -> String {
+    let mut s = String::new();
+    for (n, c) in line.visible_cells() {
+        if n >= cols.end {
+            break;
+        }
+        if n + c.width().max(1) > cols.start {
+            s.push_str(c.str());
+        }
+    }
+    s
+}
+
+/// Returns the text of a rectangular selection, given the lines that
+/// it spans starting at stable row `top`.  Each physical line
+/// contributes its own row of text, even when it is wrapped.
+fn block_selection_text(sel: &SelectionRange, top: StableRowIndex, lines: &[Line]) -> String {
+    let mut s = String::new();
+    for (idx, line) in lines.iter().enumerate() {
+        if idx > 0 {
+            s.push('\n');
+        }
+        let cols = sel.cols_for_row(top + idx as StableRowIndex, true);
+        s.push_str(block_columns_as_str(line, cols).trim_end());
+    }
+    s
+}
 
 impl super::TermWindow {
     pub fn selection_text(&self, pane: &Rc<dyn Pane>) -> String {
         let mut s = String::new();
-        if let Some(sel) = self
-            .selection(pane.pane_id())
-            .range
-            .as_ref()
-            .map(|r| r.normalize())
-        {
+        let (range, rectangular) = {
+            let sel = self.selection(pane.pane_id());
+            (sel.range.as_ref().map(|r| r.normalize()), sel.rectangular)
+        };
+        if let (Some(sel), true) = (range, rectangular) {
+            let (top, lines) = pane.get_lines(sel.rows());
+            s = block_selection_text(&sel, top, &lines);
+        } else if let Some(sel) = range {
             let mut last_was_wrapped = false;
             let first_row = sel.rows().start;
             let last_row = sel.rows().end;
@@ -26,7 +60,7 @@ impl super::TermWindow {
                     let this_row = line.first_row + idx as StableRowIndex;
                     if this_row >= first_row && this_row < last_row {
                         let last_phys_idx = phys.cells().len().saturating_sub(1);
-                        let cols = sel.cols_for_row(this_row);
+                        let cols = sel.cols_for_row(this_row, false);
                         let last_col_idx = cols.end.saturating_sub(1).min(last_phys_idx);
                         let col_span = phys.columns_as_str(cols);
                         // Only trim trailing whitespace if we are the last line
@@ -57,13 +91,21 @@ impl super::TermWindow {
         pane: &Rc<dyn Pane>,
     ) {
         self.selection(pane.pane_id()).seqno = pane.get_current_seqno();
-        let mode = mode.unwrap_or(SelectionMode::Cell);
+        let mode = mode.unwrap_or_else(|| {
+            // Preserve the shape of an existing block selection
+            if self.selection(pane.pane_id()).rectangular {
+                SelectionMode::Block
+            } else {
+                SelectionMode::Cell
+            }
+        });
         let (x, y) = match self.pane_state(pane.pane_id()).mouse_terminal_coords {
             Some(coords) => coords,
             None => return,
         };
+        self.selection(pane.pane_id()).rectangular = mode == SelectionMode::Block;
         match mode {
-            SelectionMode::Cell => {
+            SelectionMode::Cell | SelectionMode::Block => {
                 let end = SelectionCoordinate { x, y };
                 let selection_range = self.selection(pane.pane_id()).range.take();
                 let sel = match selection_range {
@@ -174,13 +216,66 @@ impl super::TermWindow {
                 self.selection(pane.pane_id()).start = Some(selection_range.start);
                 self.selection(pane.pane_id()).range = Some(selection_range);
             }
-            SelectionMode::Cell => {
+            SelectionMode::Cell | SelectionMode::Block => {
                 self.selection(pane.pane_id())
                     .begin(SelectionCoordinate { x, y });
             }
         }
 
+        self.selection(pane.pane_id()).rectangular = mode == SelectionMode::Block;
+
         self.selection(pane.pane_id()).seqno = pane.get_current_seqno();
         self.window.as_ref().unwrap().invalidate();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wezterm_term::CellAttributes;
+
+    fn block(start: (usize, StableRowIndex), end: (usize, StableRowIndex)) -> SelectionRange {
+        SelectionRange {
+            start: SelectionCoordinate {
+                x: start.0,
+                y: start.1,
+            },
+            end: SelectionCoordinate { x: end.0, y: end.1 },
+        }
+        .normalize()
+    }
+
+    #[test]
+    fn block_selection() {
+        let attrs = CellAttributes::default();
+        let mut wrapped = Line::from_text("01234567", &attrs, 0);
+        wrapped.set_last_cell_was_wrapped(true, 0);
+        let lines = vec![
+            Line::from_text("ab\u{65e5}\u{672c}cd", &attrs, 0),
+            wrapped,
+            Line::from_text("89", &attrs, 0),
+            Line::from_text("x\u{65e5}  y", &attrs, 0),
+        ];
+
+        // Double-width cells that straddle either edge are included
+        assert_eq!(
+            block_selection_text(&block((3, 10), (4, 12)), 10, &lines[0..3]),
+            "\u{65e5}\u{672c}\n34\n"
+        );
+        // and the corners may be given in any order
+        assert_eq!(
+            block_selection_text(&block((6, 11), (5, 10)), 10, &lines[0..2]),
+            "\u{672c}c\n56"
+        );
+        // Each row of a wrapped line is a separate row of the block
+        assert_eq!(
+            block_selection_text(&block((0, 11), (1, 12)), 11, &lines[1..3]),
+            "01\n89"
+        );
+        // Trailing blanks are trimmed from every row
+        assert_eq!(
+            block_selection_text(&block((1, 12), (4, 13)), 12, &lines[2..4]),
+            "9\n\u{65e5}"
+        );
+    }
+}
@@ -66,7 +66,12 @@ where
         let rx_msg = item_rx.recv();
         let wait_for_read = stream.readable().map(|_| Ok(Item::Readable));
 
-        match smol::future::or(rx_msg, wait_for_read).await {
+        let item = smol::future::or(rx_msg, wait_for_read).await;
+        if let Ok(Item::Notif(notification)) = &item {
+            handler.send_mux_event(notification);
+        }
+
+        match item {
             Ok(Item::Readable) => {
                 let decoded = Pdu::decode_async(&mut stream).await?;
                 handler.process_one(decoded);
@@ -4,10 +4,13 @@ use codec::*;
 use mux::client::ClientId;
 use mux::pane::{Pane, PaneId};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
-use mux::tab::TabId;
-use mux::Mux;
+use mux::sftp::PaneSftp;
+use mux::tab::{Tab, TabId};
+use mux::window::WindowId;
+use mux::{Mux, MuxNotification};
 use promise::spawn::spawn_into_main_thread;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
@@ -191,6 +194,10 @@ pub struct SessionHandler {
     to_write_tx: PduSender,
     per_pane: HashMap<TabId, Arc<Mutex<PerPane>>>,
     client_id: Option<Arc<ClientId>>,
+    mux_events: Option<SubscribeToMuxEvents>,
+    /// Remembers which window each pane was in, so that events for
+    /// panes that have since been removed can still be filtered
+    pane_windows: HashMap<PaneId, WindowId>,
 }
 
 impl Drop for SessionHandler {
@@ -208,6 +215,106 @@ impl SessionHandler {
             to_write_tx,
             per_pane: HashMap::new(),
             client_id: None,
+            mux_events: None,
+            pane_windows: HashMap::new(),
+        }
+    }
+
+    fn window_for_pane(&mut self, pane_id: PaneId) -> Option<WindowId> {
+        let mux = Mux::get().unwrap();
+        match mux.resolve_pane_id(pane_id) {
+            Some((_domain_id, window_id, _tab_id)) => {
+                self.pane_windows.insert(pane_id, window_id);
+                Some(window_id)
+            }
+            None => self.pane_windows.get(&pane_id).copied(),
+        }
+    }
+
+    /// If the client has subscribed to mux events, and the notification
+    /// matches its subscription, sends it to the client as a MuxEvent
+    pub fn send_mux_event(&mut self, notification: &MuxNotification) {
+        if self.mux_events.is_none() {
+            return;
+        }
+        let mux = Mux::get().unwrap();
+
+        let pane_event = |kind, pane_id| (kind, Some(pane_id), None, None, None);
+        let window_event = |kind, window_id| (kind, None, Some(window_id), None, None);
+
+        let (kind, pane_id, window_id, workspace, alert) = match notification {
+            MuxNotification::PaneOutput(pane_id) => pane_event(MuxEventKind::PaneOutput, *pane_id),
+            MuxNotification::PaneAdded(pane_id) => pane_event(MuxEventKind::PaneAdded, *pane_id),
+            MuxNotification::PaneRemoved(pane_id) => {
+                pane_event(MuxEventKind::PaneRemoved, *pane_id)
+            }
+            MuxNotification::WindowCreated(window_id) => {
+                window_event(MuxEventKind::WindowCreated, *window_id)
+            }
+            MuxNotification::WindowRemoved(window_id) => {
+                window_event(MuxEventKind::WindowRemoved, *window_id)
+            }
+            MuxNotification::WindowInvalidated(window_id) => {
+                window_event(MuxEventKind::WindowInvalidated, *window_id)
+            }
+            MuxNotification::WindowWorkspaceChanged(window_id) => (
+                MuxEventKind::WindowWorkspaceChanged,
+                None,
+                Some(*window_id),
+                mux.get_window(*window_id)
+                    .map(|w| w.get_workspace().to_string()),
+                None,
+            ),
+            MuxNotification::ActiveWorkspaceChanged(client_id) => (
+                MuxEventKind::ActiveWorkspaceChanged,
+                None,
+                None,
+                Some(mux.active_workspace_for_client(client_id)),
+                None,
+            ),
+            MuxNotification::Alert { pane_id, alert } => (
+                MuxEventKind::Alert,
+                Some(*pane_id),
+                None,
+                None,
+                Some(alert.clone()),
+            ),
+            MuxNotification::Empty => (MuxEventKind::Empty, None, None, None, None),
+            // These carry clipboard and file contents, which
+            // we don't want to broadcast
+            MuxNotification::AssignClipboard { .. } | MuxNotification::SaveToDownloads { .. } => {
+                return
+            }
+        };
+
+        let window_id = match (window_id, pane_id) {
+            (Some(window_id), _) => Some(window_id),
+            (None, Some(pane_id)) => self.window_for_pane(pane_id),
+            (None, None) => None,
+        };
+        if let MuxNotification::PaneRemoved(pane_id) = notification {
+            self.pane_windows.remove(pane_id);
+        }
+
+        let event = MuxEvent {
+            kind,
+            pane_id,
+            window_id,
+            workspace,
+            alert,
+        };
+        let matches = self
+            .mux_events
+            .as_ref()
+            .map(|filter| filter.matches(&event))
+            .unwrap_or(false);
+        if matches {
+            self.to_write_tx
+                .send(DecodedPdu {
+                    pdu: Pdu::MuxEvent(event),
+                    serial: 0,
+                })
+                .ok();
         }
     }
 
@@ -323,14 +430,22 @@ impl SessionHandler {
                         move || {
                             let mux = Mux::get().unwrap();
                             let mut tabs = vec![];
+                            let mut tab_titles = vec![];
+                            let mut window_titles = HashMap::new();
                             for window_id in mux.iter_windows().into_iter() {
                                 let window = mux.get_window(window_id).unwrap();
                                 for tab in window.iter() {
                                     tabs.push(tab.codec_pane_tree());
+                                    tab_titles.push(tab.get_title());
                                 }
+                                window_titles.insert(window_id, window.get_title().to_string());
                             }
                             log::trace!("ListPanes {:#?}", tabs);
-                            Ok(Pdu::ListPanesResponse(ListPanesResponse { tabs }))
+                            Ok(Pdu::ListPanesResponse(ListPanesResponse {
+                                tabs,
+                                tab_titles,
+                                window_titles,
+                            }))
                         },
                         send_response,
                     )
@@ -505,6 +620,22 @@ impl SessionHandler {
                 })
                 .detach();
             }
+            Pdu::SendKeyUp(SendKeyUp { pane_id, event }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            pane.key_up(event.key, event.modifiers)?;
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
             Pdu::SendMouseEvent(SendMouseEvent { pane_id, event }) => {
                 let sender = self.to_write_tx.clone();
                 let per_pane = self.per_pane(pane_id);
@@ -594,6 +725,221 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::GetPaneRenderableDimensions(GetPaneRenderableDimensions { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            Ok(Pdu::GetPaneRenderableDimensionsResponse(
+                                GetPaneRenderableDimensionsResponse {
+                                    pane_id,
+                                    cursor_position: pane.get_cursor_position(),
+                                    dimensions: pane.get_dimensions(),
+                                },
+                            ))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::GetPalette(GetPalette { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            Ok(Pdu::GetPaletteResponse(GetPaletteResponse {
+                                pane_id,
+                                palette: pane.palette(),
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::SubscribeToMuxEvents(subscription) => {
+                self.mux_events.replace(subscription);
+                send_response(Ok(Pdu::UnitResponse(UnitResponse {})))
+            }
+
+            Pdu::ActivatePane(ActivatePane { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let (tab, window_id) = activate_pane(&mux, pane_id)?;
+                            let mut window = mux
+                                .get_window_mut(window_id)
+                                .ok_or_else(|| anyhow!("no such window {}", window_id))?;
+                            if let Some(idx) = window.idx_by_id(tab.tab_id()) {
+                                window.save_and_then_set_active(idx);
+                            }
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::ActivatePaneDirection(ActivatePaneDirection { pane_id, direction }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let (tab, window_id) = activate_pane(&mux, pane_id)?;
+                            tab.activate_pane_direction(direction);
+                            mux.notify(MuxNotification::WindowInvalidated(window_id));
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::AdjustPaneSize(AdjustPaneSize {
+                pane_id,
+                direction,
+                amount,
+            }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let (tab, window_id) = activate_pane(&mux, pane_id)?;
+                            tab.adjust_pane_size(direction, amount);
+                            mux.notify(MuxNotification::WindowInvalidated(window_id));
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::MovePaneToNewTab(MovePaneToNewTab {
+                pane_id,
+                window_id,
+                new_window,
+                workspace,
+            }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let window_id = if new_window {
+                                None
+                            } else {
+                                match window_id {
+                                    Some(window_id) => Some(window_id),
+                                    None => {
+                                        let (_domain_id, window_id, _tab_id) = mux
+                                            .resolve_pane_id(pane_id)
+                                            .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                                        Some(window_id)
+                                    }
+                                }
+                            };
+                            let (tab, window_id) =
+                                mux.move_pane_to_new_tab(pane_id, window_id, workspace)?;
+                            Ok(Pdu::MovePaneToNewTabResponse(MovePaneToNewTabResponse {
+                                tab_id: tab.tab_id(),
+                                window_id,
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::SetTabTitle(SetTabTitle { tab_id, title }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let tab = mux
+                                .get_tab(tab_id)
+                                .ok_or_else(|| anyhow!("no such tab {}", tab_id))?;
+                            tab.set_title(&title);
+                            if let Some(window_id) = mux.window_containing_tab(tab_id) {
+                                mux.notify(MuxNotification::WindowInvalidated(window_id));
+                            }
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::SetWindowTitle(SetWindowTitle { window_id, title }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let mut window = mux
+                                .get_window_mut(window_id)
+                                .ok_or_else(|| anyhow!("no such window {}", window_id))?;
+                            window.set_title(&title);
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::ActivateTab(ActivateTab { tab_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let window_id = mux
+                                .window_containing_tab(tab_id)
+                                .ok_or_else(|| anyhow!("no such tab {}", tab_id))?;
+                            let mut window = mux
+                                .get_window_mut(window_id)
+                                .ok_or_else(|| anyhow!("no such window {}", window_id))?;
+                            let idx = window
+                                .idx_by_id(tab_id)
+                                .ok_or_else(|| anyhow!("no such tab {}", tab_id))?;
+                            window.save_and_then_set_active(idx);
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::RenameWorkspace(RenameWorkspace {
+                old_workspace,
+                new_workspace,
+            }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            mux.rename_workspace(&old_workspace, &new_workspace)?;
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
             Pdu::GetImageCell(GetImageCell {
                 pane_id,
                 line_idx,
@@ -634,6 +980,45 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::SftpTransfer(transfer) => {
+                spawn_into_main_thread(async move {
+                    schedule_sftp_transfer(transfer, send_response);
+                })
+                .detach();
+            }
+
+            Pdu::SaveSession(SaveSession { path }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let config = config::configuration();
+                            let path = path
+                                .map(PathBuf::from)
+                                .unwrap_or_else(|| config.mux_session.session_file());
+                            let snapshot = mux::snapshot::save_session(
+                                &path,
+                                config.mux_session.scrollback_lines,
+                            )?;
+                            Ok(Pdu::SaveSessionResponse(SaveSessionResponse {
+                                path: path.display().to_string(),
+                                num_windows: snapshot.windows.len(),
+                                num_panes: snapshot.num_panes(),
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::RestoreSession(restore) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_restore_session(restore, send_response, client_id);
+                })
+                .detach();
+            }
+
             Pdu::GetCodecVersion(_) => {
                 match std::env::current_exe().context("resolving current_exe") {
                     Err(err) => send_response(Err(err)),
@@ -681,6 +1066,13 @@ impl SessionHandler {
             | Pdu::GetClientListResponse { .. }
             | Pdu::PaneRemoved { .. }
             | Pdu::GetImageCellResponse { .. }
+            | Pdu::SftpTransferResponse { .. }
+            | Pdu::SaveSessionResponse { .. }
+            | Pdu::RestoreSessionResponse { .. }
+            | Pdu::GetPaneRenderableDimensionsResponse { .. }
+            | Pdu::GetPaletteResponse { .. }
+            | Pdu::MovePaneToNewTabResponse { .. }
+            | Pdu::MuxEvent { .. }
             | Pdu::ErrorResponse { .. } => {
                 send_response(Err(anyhow!("expected a request, got {:?}", decoded.pdu)))
             }
@@ -688,6 +1080,25 @@ impl SessionHandler {
     }
 }
 
+/// Makes `pane_id` the active pane in its containing tab,
+/// returning that tab and the id of the window that contains it.
+fn activate_pane(mux: &Rc<Mux>, pane_id: PaneId) -> anyhow::Result<(Rc<Tab>, WindowId)> {
+    let pane = mux
+        .get_pane(pane_id)
+        .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+    let (_domain_id, window_id, tab_id) = mux
+        .resolve_pane_id(pane_id)
+        .ok_or_else(|| anyhow!("pane {} is not in any tab", pane_id))?;
+    let tab = mux
+        .get_tab(tab_id)
+        .ok_or_else(|| anyhow!("no such tab {}", tab_id))?;
+    if tab.get_active_pane().map(|p| p.pane_id()) != Some(pane_id) {
+        tab.set_zoomed(false);
+        tab.set_active_pane(&pane);
+    }
+    Ok((tab, window_id))
+}
+
 // Dancing around a little bit here; we can't directly spawn_into_main_thread the domain_spawn
 // function below because the compiler thinks that all of its locals then need to be Send.
 // We need to shimmy through this helper to break that aspect of the compiler flow
@@ -711,6 +1122,80 @@ where
         .detach();
 }
 
+fn schedule_restore_session<SND>(
+    restore: RestoreSession,
+    send_response: SND,
+    client_id: Option<Arc<ClientId>>,
+) where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(restore_session(restore, client_id).await) })
+        .detach();
+}
+
+async fn restore_session(
+    restore: RestoreSession,
+    client_id: Option<Arc<ClientId>>,
+) -> anyhow::Result<Pdu> {
+    let mux = Mux::get().unwrap();
+    let _identity = mux.with_identity(client_id);
+
+    let config = config::configuration();
+    let path = restore
+        .path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| config.mux_session.session_file());
+    let window_ids =
+        mux::snapshot::restore_session(&path, config.mux_session.restore_foreground_commands)
+            .await?;
+    Ok(Pdu::RestoreSessionResponse(RestoreSessionResponse {
+        window_ids,
+    }))
+}
+
+/// The sftp session is resolved on the main thread, but the transfer
+/// itself is carried out on a separate thread so that a large file
+/// doesn't stall the mux
+fn schedule_sftp_transfer<SND>(transfer: SftpTransfer, send_response: SND)
+where
+    SND: Fn(anyhow::Result<Pdu>) + Send + 'static,
+{
+    let pane_sftp = match PaneSftp::for_pane(transfer.pane_id) {
+        Ok(pane_sftp) => pane_sftp,
+        Err(err) => return send_response(Err(err)),
+    };
+    std::thread::spawn(move || {
+        send_response(smol::block_on(sftp_transfer(pane_sftp, transfer)));
+    });
+}
+
+async fn sftp_transfer(pane_sftp: PaneSftp, transfer: SftpTransfer) -> anyhow::Result<Pdu> {
+    let mut bytes = 0;
+    let local = std::path::PathBuf::from(&transfer.local_path);
+    let (remote, local) = match transfer.direction {
+        SftpTransferDirection::Download => {
+            pane_sftp
+                .download(&transfer.remote_path, &local, |progress| {
+                    bytes = progress.transferred
+                })
+                .await?
+        }
+        SftpTransferDirection::Upload => {
+            let remote = pane_sftp
+                .upload(&local, &transfer.remote_path, |progress| {
+                    bytes = progress.transferred
+                })
+                .await?;
+            (remote, local)
+        }
+    };
+    Ok(Pdu::SftpTransferResponse(SftpTransferResponse {
+        remote_path: remote.to_string(),
+        local_path: local.display().to_string(),
+        bytes,
+    }))
+}
+
 async fn split_pane(split: SplitPane, client_id: Option<Arc<ClientId>>) -> anyhow::Result<Pdu> {
     let mux = Mux::get().unwrap();
     let _identity = mux.with_identity(client_id);
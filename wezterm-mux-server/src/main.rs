@@ -207,11 +207,38 @@ async fn async_run(cmd: Option<CommandBuilder>) -> anyhow::Result<()> {
     domain.attach().await?;
 
     let config = config::configuration();
-    let window_id = mux.new_empty_window(None);
-    let _tab = mux
-        .default_domain()
-        .spawn(config.initial_size(), cmd, None, *window_id)
-        .await?;
+
+    let mut restored = false;
+    if config.mux_session.persist {
+        let options = &config.mux_session;
+        let path = options.session_file();
+        let mut save = true;
+        if path.exists() {
+            match mux::snapshot::restore_startup_session(&path, options.restore_foreground_commands)
+                .await
+            {
+                Ok(window_ids) => restored = !window_ids.is_empty(),
+                Err(err) => {
+                    // Don't overwrite the session that we couldn't restore
+                    log::error!("{:#}; the session will not be saved", err);
+                    save = false;
+                }
+            }
+        }
+        if save {
+            mux::snapshot::schedule_periodic_save();
+        }
+    }
+
+    // Explicitly requested programs are spawned even when
+    // the session was restored
+    if !restored || cmd.is_some() {
+        let window_id = mux.new_empty_window(None);
+        let _tab = mux
+            .default_domain()
+            .spawn(config.initial_size(), cmd, None, *window_id)
+            .await?;
+    }
     Ok(())
 }
 
@@ -43,6 +43,9 @@ impl LineEditorHost for PasswordPromptHost {
 struct Opt {
     #[structopt(long = "user", short = "l")]
     pub user: Option<String>,
+    /// Connect via these comma separated jump hosts
+    #[structopt(short = "J")]
+    pub jump: Option<String>,
     pub destination: String,
     pub cmd: Vec<String>,
 }
@@ -53,6 +56,9 @@ fn main() {
 
     let mut config = Config::new();
     config.add_default_config_files();
+    if let Some(jump) = opts.jump.as_ref() {
+        config.set_option("proxyjump", jump);
+    }
 
     let mut config = config.for_host(&opts.destination);
     if let Some(user) = opts.user.as_ref() {
@@ -5,6 +5,108 @@ use std::path::{Path, PathBuf};
 
 pub type ConfigMap = BTreeMap<String, String>;
 
+/// A host to connect through, as specified by `ProxyJump`
+/// or `-J`, using the form `[user@]host[:port]` or
+/// `ssh://[user@]host[:port]`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct JumpHost {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl JumpHost {
+    /// Parse a comma separated list of jump hosts
+    pub fn parse_list(s: &str) -> anyhow::Result<Vec<Self>> {
+        s.split(',').map(|host| host.trim().parse()).collect()
+    }
+}
+
+impl std::str::FromStr for JumpHost {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.strip_prefix("ssh://").unwrap_or(s);
+        let (user, host_and_port) = match s.rsplit_once('@') {
+            Some((user, rest)) => (Some(user.to_string()), rest),
+            None => (None, s),
+        };
+
+        let (host, port) = if let Some(rest) = host_and_port.strip_prefix('[') {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| anyhow::anyhow!("missing `]` in jump host {}", s))?;
+            match rest.strip_prefix(':') {
+                Some(port) => (host, Some(port)),
+                None if rest.is_empty() => (host, None),
+                None => anyhow::bail!("unexpected `{}` after `]` in jump host {}", rest, s),
+            }
+        } else {
+            match host_and_port.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_and_port, None),
+            }
+        };
+
+        if host.is_empty() {
+            anyhow::bail!("missing host name in jump host {}", s);
+        }
+        let port = match port {
+            Some(port) => Some(
+                port.parse::<u16>()
+                    .map_err(|err| anyhow::anyhow!("invalid port in jump host {}: {}", s, err))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            user,
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl std::fmt::Display for JumpHost {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(user) = &self.user {
+            write!(fmt, "{}@", user)?;
+        }
+        if self.host.contains(':') {
+            write!(fmt, "[{}]", self.host)?;
+        } else {
+            write!(fmt, "{}", self.host)?;
+        }
+        if let Some(port) = self.port {
+            write!(fmt, ":{}", port)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the resolved configuration of each of the jump hosts that
+/// must be traversed, in order, to reach the host described by `config`.
+/// These are recorded in the map by `Config::for_host` as
+/// `proxyjump.N.option` entries.
+pub(crate) fn jump_host_configs(config: &ConfigMap) -> Vec<ConfigMap> {
+    let mut hops: Vec<ConfigMap> = vec![];
+    for (k, v) in config {
+        let rest = match k.strip_prefix("proxyjump.") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        if let Some((idx, key)) = rest.split_once('.') {
+            if let Ok(idx) = idx.parse::<usize>() {
+                if hops.len() <= idx {
+                    hops.resize_with(idx + 1, ConfigMap::new);
+                }
+                hops[idx].insert(key.to_string(), v.to_string());
+            }
+        }
+    }
+    hops
+}
+
 /// A Pattern in a `Host` list
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Pattern {
@@ -348,13 +450,18 @@ impl ParsedConfigFile {
 
                 fn add_option(options: &mut ConfigMap, k: String, v: &str) {
                     // first option wins in ssh_config, except for identityfile
-                    // which explicitly allows multiple entries to combine together
-                    let is_identity_file = k == "identityfile";
+                    // and the forwarding directives, which explicitly allow
+                    // multiple entries to combine together
+                    let separator = match k.as_str() {
+                        "identityfile" => Some(' '),
+                        "localforward" | "remoteforward" | "dynamicforward" => Some(','),
+                        _ => None,
+                    };
                     options
                         .entry(k)
                         .and_modify(|e| {
-                            if is_identity_file {
-                                e.push(' ');
+                            if let Some(separator) = separator {
+                                e.push(separator);
                                 e.push_str(v);
                             }
                         })
@@ -494,12 +601,28 @@ impl Config {
     /// (such as CanonicalHostname), the tokens should be updated and
     /// the config parsed a second time in order for value expansion
     /// to have the same results as `ssh`.
+    ///
+    /// If `ProxyJump` is set, the configuration for each of the jump
+    /// hosts is resolved too, and recorded in the returned map as
+    /// `proxyjump.N.option` entries.
     pub fn for_host<H: AsRef<str>>(&self, host: H) -> ConfigMap {
-        let host = host.as_ref();
+        let mut result = self.resolve_host(host.as_ref(), false);
+        self.resolve_proxy_jump(&mut result);
+        result
+    }
+
+    /// Resolve the configuration for a given host.
+    /// Options assigned via `set_option` only apply to the destination
+    /// host, so they are not applied when resolving a jump host.
+    fn resolve_host(&self, host: &str, is_jump_host: bool) -> ConfigMap {
         let local_user = self.resolve_local_user();
         let target_user = &local_user;
 
-        let mut result = self.options.clone();
+        let mut result = if is_jump_host {
+            ConfigMap::new()
+        } else {
+            self.options.clone()
+        };
         let mut needs_reparse = false;
 
         for config in &self.config_files {
@@ -573,6 +696,58 @@ impl Config {
         result
     }
 
+    /// Resolve the configuration of the jump hosts listed by `ProxyJump`.
+    /// Like ssh, the first jump host may itself use a `ProxyCommand`,
+    /// but any `ProxyJump` or port forwarding in the configuration
+    /// of a jump host is ignored.
+    /// `for_host` calls this; it is only needed by callers that change
+    /// `ProxyJump` in the returned map, and replaces any jump hosts
+    /// that were resolved previously.
+    pub fn resolve_proxy_jump(&self, result: &mut ConfigMap) {
+        result.retain(|k, _| !k.starts_with("proxyjump."));
+        let proxy_jump = match result.get("proxyjump") {
+            Some(proxy_jump) => proxy_jump.to_string(),
+            None => return,
+        };
+        if proxy_jump == "none" {
+            result.remove("proxyjump");
+            return;
+        }
+
+        let hosts = match JumpHost::parse_list(&proxy_jump) {
+            Ok(hosts) => hosts,
+            Err(err) => {
+                log::error!("Ignoring ProxyJump {}: {:#}", proxy_jump, err);
+                result.remove("proxyjump");
+                return;
+            }
+        };
+
+        // The connection is made via the jump hosts instead
+        result.remove("proxycommand");
+
+        for (idx, jump_host) in hosts.iter().enumerate() {
+            let mut hop = self.resolve_host(&jump_host.host, true);
+            hop.remove("proxyjump");
+            // Like `ssh -W`, the jump hosts only relay the connection
+            for key in &["localforward", "remoteforward", "dynamicforward"] {
+                hop.remove(*key);
+            }
+            if idx > 0 {
+                hop.remove("proxycommand");
+            }
+            if let Some(user) = &jump_host.user {
+                hop.insert("user".to_string(), user.to_string());
+            }
+            if let Some(port) = jump_host.port {
+                hop.insert("port".to_string(), port.to_string());
+            }
+            for (k, v) in hop {
+                result.insert(format!("proxyjump.{}.{}", idx, k), v);
+            }
+        }
+    }
+
     /// Return true if a given option name is subject to environment variable
     /// expansion.
     fn should_expand_environment(&self, key: &str) -> bool {
@@ -827,6 +1002,138 @@ Config {
         );
     }
 
+    #[test]
+    fn multiple_forwards() {
+        let mut config = Config::new();
+
+        let mut fake_env = ConfigMap::new();
+        fake_env.insert("HOME".to_string(), "/home/me".to_string());
+        fake_env.insert("USER".to_string(), "me".to_string());
+        config.assign_environment(fake_env);
+
+        config.add_config_string(
+            r#"
+        Host foo
+            HostName 10.0.0.1
+            LocalForward 5432 db:5432
+            LocalForward 8080 web:80
+            RemoteForward 2222 localhost:22
+            DynamicForward 1080
+            "#,
+        );
+
+        let opts = config.for_host("foo");
+        snapshot!(
+            opts,
+            r#"
+{
+    "dynamicforward": "1080",
+    "hostname": "10.0.0.1",
+    "identityfile": "/home/me/.ssh/id_dsa /home/me/.ssh/id_ecdsa /home/me/.ssh/id_ed25519 /home/me/.ssh/id_rsa",
+    "localforward": "5432 db:5432,8080 web:80",
+    "port": "22",
+    "remoteforward": "2222 localhost:22",
+    "user": "me",
+    "userknownhostsfile": "/home/me/.ssh/known_hosts /home/me/.ssh/known_hosts2",
+}
+"#
+        );
+    }
+
+    #[test]
+    fn parse_jump_hosts() {
+        assert_eq!(
+            JumpHost::parse_list("bastion,me@inner:2222,ssh://[::1]:22").unwrap(),
+            vec![
+                JumpHost {
+                    user: None,
+                    host: "bastion".to_string(),
+                    port: None,
+                },
+                JumpHost {
+                    user: Some("me".to_string()),
+                    host: "inner".to_string(),
+                    port: Some(2222),
+                },
+                JumpHost {
+                    user: None,
+                    host: "::1".to_string(),
+                    port: Some(22),
+                },
+            ]
+        );
+        assert!(JumpHost::parse_list("bastion:ssh").is_err());
+        assert!(JumpHost::parse_list("me@").is_err());
+    }
+
+    #[test]
+    fn proxy_jump() {
+        let mut config = Config::new();
+
+        let mut fake_env = ConfigMap::new();
+        fake_env.insert("HOME".to_string(), "/home/me".to_string());
+        fake_env.insert("USER".to_string(), "me".to_string());
+        config.assign_environment(fake_env);
+
+        config.add_config_string(
+            r#"
+        Host bastion
+            HostName 10.0.0.1
+            User admin
+            IdentityFile ~/.ssh/bastion
+
+        Host db
+            ProxyJump bastion,jump@inner:2222
+            ProxyCommand nc %h %p
+            "#,
+        );
+
+        let opts = config.for_host("db");
+        snapshot!(
+            opts,
+            r#"
+{
+    "hostname": "db",
+    "identityfile": "/home/me/.ssh/id_dsa /home/me/.ssh/id_ecdsa /home/me/.ssh/id_ed25519 /home/me/.ssh/id_rsa",
+    "port": "22",
+    "proxyjump": "bastion,jump@inner:2222",
+    "proxyjump.0.hostname": "10.0.0.1",
+    "proxyjump.0.identityfile": "/home/me/.ssh/bastion",
+    "proxyjump.0.port": "22",
+    "proxyjump.0.user": "admin",
+    "proxyjump.0.userknownhostsfile": "/home/me/.ssh/known_hosts /home/me/.ssh/known_hosts2",
+    "proxyjump.1.hostname": "inner",
+    "proxyjump.1.identityfile": "/home/me/.ssh/id_dsa /home/me/.ssh/id_ecdsa /home/me/.ssh/id_ed25519 /home/me/.ssh/id_rsa",
+    "proxyjump.1.port": "2222",
+    "proxyjump.1.user": "jump",
+    "proxyjump.1.userknownhostsfile": "/home/me/.ssh/known_hosts /home/me/.ssh/known_hosts2",
+    "user": "me",
+    "userknownhostsfile": "/home/me/.ssh/known_hosts /home/me/.ssh/known_hosts2",
+}
+"#
+        );
+
+        let hops = jump_host_configs(&opts);
+        assert_eq!(hops.len(), 2);
+        assert_eq!(hops[0].get("hostname").unwrap(), "10.0.0.1");
+        assert_eq!(hops[1].get("user").unwrap(), "jump");
+
+        // Changing ProxyJump in a resolved map replaces the jump hosts
+        let mut replaced = opts.clone();
+        replaced.insert("proxyjump".to_string(), "me@bastion".to_string());
+        config.resolve_proxy_jump(&mut replaced);
+        let hops = jump_host_configs(&replaced);
+        assert_eq!(hops.len(), 1);
+        assert_eq!(hops[0].get("hostname").unwrap(), "10.0.0.1");
+        assert_eq!(hops[0].get("user").unwrap(), "me");
+
+        // An override takes precedence, and `none` disables jumping
+        config.set_option("ProxyJump", "none");
+        let opts = config.for_host("db");
+        assert!(jump_host_configs(&opts).is_empty());
+        assert!(!opts.contains_key("proxyjump"));
+    }
+
     #[test]
     fn sub_tilde() {
         let mut config = Config::new();
@@ -0,0 +1,883 @@
+//! Port forwarding specifications and the SOCKS handshake used
+//! by dynamic forwarding
+use crate::channelwrap::ChannelWrap;
+use crate::config::ConfigMap;
+use crate::session::SessionEvent;
+use crate::sessioninner::{ChannelId, ChannelInfo, DescriptorState};
+use crate::sessionwrap::{RemoteListener, SessionWrap};
+use anyhow::Context;
+use filedescriptor::{socketpair, FileDescriptor};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The address on which a forwarding listens for connections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenAddress {
+    /// The address to bind; `None` means the loopback interface,
+    /// while `*` means all interfaces
+    pub bind_address: Option<String>,
+    /// The port to listen on; `0` picks any free port
+    pub port: u16,
+}
+
+/// The address to which forwarded connections are made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectAddress {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Forward {
+    /// Connections to `listen` on the local host are forwarded
+    /// to `connect` from the remote host, like `ssh -L`
+    Local {
+        listen: ListenAddress,
+        connect: ConnectAddress,
+    },
+    /// Connections to `listen` on the remote host are forwarded
+    /// to `connect` from the local host, like `ssh -R`
+    Remote {
+        listen: ListenAddress,
+        connect: ConnectAddress,
+    },
+    /// Connections to `listen` on the local host are SOCKS requests
+    /// whose destination is connected from the remote host, like `ssh -D`
+    Dynamic { listen: ListenAddress },
+}
+
+/// Splits a forwarding specification into its fields.
+/// Fields are separated by either whitespace, as in `ssh_config`,
+/// or by `:`, as on the `ssh` command line.  IPv6 addresses can be
+/// enclosed in square brackets.
+fn split_fields(spec: &str) -> anyhow::Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut current = String::new();
+    let mut in_brackets = false;
+
+    for c in spec.trim().chars() {
+        match c {
+            '[' if !in_brackets && current.is_empty() => in_brackets = true,
+            ']' if in_brackets => in_brackets = false,
+            ':' if !in_brackets => fields.push(std::mem::take(&mut current)),
+            c if c.is_whitespace() && !in_brackets => {
+                if !current.is_empty() {
+                    fields.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if in_brackets {
+        anyhow::bail!("unterminated `[` in forwarding specification {}", spec);
+    }
+    fields.push(current);
+    Ok(fields)
+}
+
+fn parse_port(port: &str, spec: &str) -> anyhow::Result<u16> {
+    port.parse()
+        .with_context(|| format!("invalid port `{}` in forwarding {}", port, spec))
+}
+
+fn parse_listen(fields: &[String], spec: &str) -> anyhow::Result<ListenAddress> {
+    match fields {
+        [port] => Ok(ListenAddress {
+            bind_address: None,
+            port: parse_port(port, spec)?,
+        }),
+        [bind_address, port] => Ok(ListenAddress {
+            bind_address: Some(bind_address.to_string()),
+            port: parse_port(port, spec)?,
+        }),
+        _ => anyhow::bail!("invalid listen address in forwarding {}", spec),
+    }
+}
+
+fn parse_listen_and_connect(spec: &str) -> anyhow::Result<(ListenAddress, ConnectAddress)> {
+    let fields = split_fields(spec)?;
+    if fields.len() < 3 {
+        anyhow::bail!(
+            "forwarding {} must be of the form [bind_address:]port:host:hostport",
+            spec
+        );
+    }
+    let (listen, connect) = fields.split_at(fields.len() - 2);
+    Ok((
+        parse_listen(listen, spec)?,
+        ConnectAddress {
+            host: connect[0].to_string(),
+            port: parse_port(&connect[1], spec)?,
+        },
+    ))
+}
+
+impl Forward {
+    /// Parses a `LocalForward` value, or the argument to `ssh -L`
+    pub fn parse_local(spec: &str) -> anyhow::Result<Self> {
+        let (listen, connect) = parse_listen_and_connect(spec)?;
+        Ok(Self::Local { listen, connect })
+    }
+
+    /// Parses a `RemoteForward` value, or the argument to `ssh -R`
+    pub fn parse_remote(spec: &str) -> anyhow::Result<Self> {
+        if split_fields(spec)?.len() < 3 {
+            anyhow::bail!("remote dynamic forwarding {} is not supported", spec);
+        }
+        let (listen, connect) = parse_listen_and_connect(spec)?;
+        Ok(Self::Remote { listen, connect })
+    }
+
+    /// Parses a `DynamicForward` value, or the argument to `ssh -D`
+    pub fn parse_dynamic(spec: &str) -> anyhow::Result<Self> {
+        let fields = split_fields(spec)?;
+        Ok(Self::Dynamic {
+            listen: parse_listen(&fields, spec)?,
+        })
+    }
+
+    /// Returns the forwardings specified by the `LocalForward`,
+    /// `RemoteForward` and `DynamicForward` options.
+    /// When an option is given multiple times, the values are
+    /// separated by `,`.
+    pub fn from_config(config: &ConfigMap) -> anyhow::Result<Vec<Self>> {
+        let mut forwards = vec![];
+        type Parser = fn(&str) -> anyhow::Result<Forward>;
+        let options: [(&str, Parser); 3] = [
+            ("localforward", Self::parse_local),
+            ("remoteforward", Self::parse_remote),
+            ("dynamicforward", Self::parse_dynamic),
+        ];
+        for (option, parse) in options.iter() {
+            if let Some(value) = config.get(*option) {
+                for spec in value.split(',') {
+                    if !spec.trim().is_empty() {
+                        forwards.push(parse(spec)?);
+                    }
+                }
+            }
+        }
+        Ok(forwards)
+    }
+}
+
+impl ListenAddress {
+    /// Returns the host to bind when listening.  Like ssh, the
+    /// loopback interface is used unless an address is specified,
+    /// and `*` or an empty address means all interfaces.
+    pub(crate) fn bind_host(&self) -> &str {
+        match self.bind_address.as_deref() {
+            None | Some("localhost") => "localhost",
+            Some("") | Some("*") => "0.0.0.0",
+            Some(addr) => addr,
+        }
+    }
+}
+
+fn fmt_host(host: &str) -> String {
+    if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+impl Display for ListenAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.bind_address {
+            Some(bind) => write!(f, "{}:{}", fmt_host(bind), self.port),
+            None => write!(f, "{}", self.port),
+        }
+    }
+}
+
+impl Display for ConnectAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", fmt_host(&self.host), self.port)
+    }
+}
+
+impl Display for Forward {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Local { listen, connect } => write!(f, "-L {}:{}", listen, connect),
+            Self::Remote { listen, connect } => write!(f, "-R {}:{}", listen, connect),
+            Self::Dynamic { listen } => write!(f, "-D {}", listen),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SocksVersion {
+    V4,
+    V5,
+}
+
+/// The destination requested by a SOCKS client
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SocksRequest {
+    pub version: SocksVersion,
+    pub destination: ConnectAddress,
+}
+
+fn read_u8<R: Read>(r: &mut R) -> std::io::Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16<R: Read>(r: &mut R) -> std::io::Result<u16> {
+    let mut buf = [0u8; 2];
+    r.read_exact(&mut buf)?;
+    Ok(u16::from_be_bytes(buf))
+}
+
+/// Reads a NUL terminated string, as used by SOCKS4
+fn read_cstr<R: Read>(r: &mut R) -> anyhow::Result<String> {
+    let mut s = vec![];
+    loop {
+        match read_u8(r)? {
+            0 => break,
+            c => s.push(c),
+        }
+        if s.len() > 255 {
+            anyhow::bail!("SOCKS4 string is too long");
+        }
+    }
+    Ok(String::from_utf8(s)?)
+}
+
+/// Performs the server side of a SOCKS4, SOCKS4a or SOCKS5 handshake
+/// up to the point where the client has requested a destination.
+/// Only the CONNECT command is supported, and SOCKS5 clients must
+/// offer the "no authentication" method.
+/// The final reply is sent using `socks_reply` once the outcome
+/// of connecting to the destination is known.
+pub(crate) fn socks_handshake<S: Read + Write>(stream: &mut S) -> anyhow::Result<SocksRequest> {
+    match read_u8(stream)? {
+        4 => {
+            let command = read_u8(stream)?;
+            let port = read_u16(stream)?;
+            let mut ip = [0u8; 4];
+            stream.read_exact(&mut ip)?;
+            let _user = read_cstr(stream)?;
+            if command != 1 {
+                stream.write_all(&socks_reply(SocksVersion::V4, false))?;
+                anyhow::bail!("unsupported SOCKS4 command {}", command);
+            }
+            // SOCKS4a signals that the host name follows by using
+            // an address of the form 0.0.0.x
+            let host = if ip[0..3] == [0, 0, 0] && ip[3] != 0 {
+                read_cstr(stream)?
+            } else {
+                Ipv4Addr::from(ip).to_string()
+            };
+            Ok(SocksRequest {
+                version: SocksVersion::V4,
+                destination: ConnectAddress { host, port },
+            })
+        }
+        5 => {
+            let num_methods = read_u8(stream)?;
+            let mut methods = vec![0u8; num_methods as usize];
+            stream.read_exact(&mut methods)?;
+            if !methods.contains(&0) {
+                stream.write_all(&[5, 0xff])?;
+                anyhow::bail!("SOCKS5 client requires authentication");
+            }
+            stream.write_all(&[5, 0])?;
+
+            let mut header = [0u8; 4];
+            stream.read_exact(&mut header)?;
+            let [version, command, _reserved, address_type] = header;
+            if version != 5 {
+                anyhow::bail!("invalid SOCKS5 request version {}", version);
+            }
+            let host = match address_type {
+                1 => {
+                    let mut ip = [0u8; 4];
+                    stream.read_exact(&mut ip)?;
+                    Ipv4Addr::from(ip).to_string()
+                }
+                3 => {
+                    let len = read_u8(stream)?;
+                    let mut name = vec![0u8; len as usize];
+                    stream.read_exact(&mut name)?;
+                    String::from_utf8(name)?
+                }
+                4 => {
+                    let mut ip = [0u8; 16];
+                    stream.read_exact(&mut ip)?;
+                    Ipv6Addr::from(ip).to_string()
+                }
+                _ => {
+                    stream.write_all(&[5, 8, 0, 1, 0, 0, 0, 0, 0, 0])?;
+                    anyhow::bail!("unsupported SOCKS5 address type {}", address_type);
+                }
+            };
+            let port = read_u16(stream)?;
+            if command != 1 {
+                stream.write_all(&[5, 7, 0, 1, 0, 0, 0, 0, 0, 0])?;
+                anyhow::bail!("unsupported SOCKS5 command {}", command);
+            }
+            Ok(SocksRequest {
+                version: SocksVersion::V5,
+                destination: ConnectAddress { host, port },
+            })
+        }
+        version => anyhow::bail!("unsupported SOCKS version {}", version),
+    }
+}
+
+/// Returns the reply that completes a SOCKS handshake
+pub(crate) fn socks_reply(version: SocksVersion, success: bool) -> Vec<u8> {
+    match version {
+        SocksVersion::V4 => vec![0, if success { 0x5a } else { 0x5b }, 0, 0, 0, 0, 0, 0],
+        SocksVersion::V5 => vec![5, if success { 0 } else { 1 }, 0, 1, 0, 0, 0, 0, 0, 0],
+    }
+}
+
+/// A connection accepted by a local or dynamic forwarding that
+/// is waiting for its channel to be opened
+#[derive(Debug)]
+pub(crate) struct PendingConnection {
+    pub stream: TcpStream,
+    pub originator: SocketAddr,
+    pub destination: ConnectAddress,
+    pub socks: Option<SocksVersion>,
+}
+
+/// A connection whose channel is being opened without blocking
+pub(crate) struct OpeningConnection {
+    pub conn: PendingConnection,
+    /// The channel, for backends that keep the state of the
+    /// open in the channel rather than in the session
+    pub channel: Option<ChannelWrap>,
+    /// Whether the remote host has been asked to open the channel
+    pub started: bool,
+}
+
+/// The result of connecting to the local destination of a channel
+/// that the remote host opened for one of our remote forwardings
+#[derive(Debug)]
+pub(crate) struct RemoteConnection {
+    pub id: ChannelId,
+    pub connect: ConnectAddress,
+    pub stream: std::io::Result<TcpStream>,
+}
+
+/// A local port that is listening for connections to forward
+pub(crate) struct LocalListener {
+    pub listener: TcpListener,
+    pub forward: Forward,
+}
+
+fn stream_to_fd(stream: TcpStream) -> FileDescriptor {
+    #[cfg(unix)]
+    unsafe {
+        use std::os::unix::io::{FromRawFd, IntoRawFd};
+        FileDescriptor::from_raw_fd(stream.into_raw_fd())
+    }
+    #[cfg(windows)]
+    unsafe {
+        use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+        FileDescriptor::from_raw_socket(stream.into_raw_socket())
+    }
+}
+
+impl crate::sessioninner::SessionInner {
+    /// Establishes a forwarding, returning the port that is listening
+    /// for connections
+    pub fn add_forward(&mut self, sess: &mut SessionWrap, forward: Forward) -> anyhow::Result<u16> {
+        match &forward {
+            Forward::Local { listen, .. } | Forward::Dynamic { listen } => {
+                let listener = TcpListener::bind((listen.bind_host(), listen.port))
+                    .with_context(|| format!("listening on {}", listen))?;
+                listener.set_nonblocking(true)?;
+                let port = listener.local_addr()?.port();
+                log::info!("forwarding {} is listening on port {}", forward, port);
+                self.listeners.push(LocalListener { listener, forward });
+                Ok(port)
+            }
+            Forward::Remote { listen, connect } => {
+                let (listener, bound_port) = sess
+                    .listen_forward(listen.bind_host(), listen.port)
+                    .with_context(|| {
+                    format!("requesting the remote host listen on {}", listen)
+                })?;
+                log::info!(
+                    "forwarding {} is listening on remote port {}",
+                    forward,
+                    bound_port
+                );
+                self.remote_listeners.push(RemoteListener {
+                    listener,
+                    bound_port,
+                    connect: connect.clone(),
+                });
+                Ok(bound_port)
+            }
+        }
+    }
+
+    /// Establishes the forwardings specified by the ssh config.
+    /// Like ssh, failing to establish a forwarding is only fatal
+    /// when `ExitOnForwardFailure` is enabled.
+    pub fn add_forwards_from_config(&mut self, sess: &mut SessionWrap) -> anyhow::Result<()> {
+        let exit_on_failure =
+            self.config.get("exitonforwardfailure").map(|s| s.as_str()) == Some("yes");
+
+        for forward in Forward::from_config(&self.config)? {
+            if let Err(err) = self.add_forward(sess, forward.clone()) {
+                if exit_on_failure {
+                    return Err(err.context(format!("establishing forwarding {}", forward)));
+                }
+                log::warn!("establishing forwarding {}: {:#}", forward, err);
+                self.tx_event
+                    .try_send(SessionEvent::Banner(Some(format!(
+                        "Failed to establish forwarding {}: {:#}",
+                        forward, err
+                    ))))
+                    .ok();
+            }
+        }
+        Ok(())
+    }
+
+    /// Accepts the connections that are pending on the local
+    /// listener at `idx`
+    pub fn accept_local_connections(&mut self, sess: &mut SessionWrap, idx: usize) {
+        loop {
+            let (stream, originator) = match self.listeners[idx].listener.accept() {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    if err.kind() != std::io::ErrorKind::WouldBlock {
+                        log::error!(
+                            "accepting connection for {}: {:#}",
+                            self.listeners[idx].forward,
+                            err
+                        );
+                    }
+                    break;
+                }
+            };
+            // Some systems have accepted sockets inherit the
+            // non-blocking mode of the listener
+            if let Err(err) = stream.set_nonblocking(false) {
+                log::error!("configuring connection from {}: {:#}", originator, err);
+                continue;
+            }
+
+            let destination = match &self.listeners[idx].forward {
+                Forward::Local { connect, .. } => Some(connect.clone()),
+                _ => None,
+            };
+            match destination {
+                Some(destination) => self.queue_pending_connection(PendingConnection {
+                    stream,
+                    originator,
+                    destination,
+                    socks: None,
+                }),
+                None => self.spawn_socks_handshake(stream, originator),
+            }
+        }
+        self.open_pending_connections(sess);
+    }
+
+    /// The SOCKS handshake needs a couple of round trips with the
+    /// client before the destination is known, so it is performed
+    /// on a separate thread to avoid stalling the session.
+    /// The connection is handed back via `tx_pending` once the
+    /// destination is known.
+    fn spawn_socks_handshake(&self, mut stream: TcpStream, originator: SocketAddr) {
+        let tx = self.tx_pending.clone();
+        let wakeup = Arc::clone(&self.wakeup);
+        std::thread::spawn(move || {
+            stream.set_read_timeout(Some(Duration::from_secs(30))).ok();
+            match socks_handshake(&mut stream) {
+                Ok(request) => {
+                    stream.set_read_timeout(None).ok();
+                    let conn = PendingConnection {
+                        stream,
+                        originator,
+                        destination: request.destination,
+                        socks: Some(request.version),
+                    };
+                    if tx.try_send(conn).is_ok() {
+                        let _ = wakeup.lock().unwrap().write(b"x");
+                    }
+                }
+                Err(err) => {
+                    log::error!("SOCKS handshake with {} failed: {:#}", originator, err);
+                }
+            }
+        });
+    }
+
+    fn queue_pending_connection(&mut self, conn: PendingConnection) {
+        self.opening.push_back(OpeningConnection {
+            conn,
+            channel: None,
+            started: false,
+        });
+    }
+
+    /// Returns true while the remote host is being asked to open
+    /// the channel for a forwarded connection
+    pub fn is_opening_channel(&self) -> bool {
+        self.opening
+            .front()
+            .map(|opening| opening.started)
+            .unwrap_or(false)
+    }
+
+    /// Opens channels for connections whose SOCKS handshake has completed,
+    /// and for connections that are still waiting for their channel.
+    /// The channels are opened without blocking, so that a slow destination
+    /// doesn't stall the other channels of the session; a connection stays
+    /// queued until the remote host has responded.
+    pub fn open_pending_connections(&mut self, sess: &mut SessionWrap) {
+        while let Ok(conn) = self.rx_pending.try_recv() {
+            self.queue_pending_connection(conn);
+        }
+
+        // libssh2 can only open one channel at a time, so the
+        // channels are opened in the order that connections were made
+        while let Some(opening) = self.opening.front_mut() {
+            let conn = &opening.conn;
+            let channel = match sess.open_direct_tcpip(
+                &conn.destination.host,
+                conn.destination.port,
+                conn.originator,
+                &mut opening.channel,
+            ) {
+                Ok(None) => {
+                    opening.started = true;
+                    return;
+                }
+                Ok(Some(channel)) => Ok(channel),
+                Err(err) => Err(err),
+            };
+            if let Some(opening) = self.opening.pop_front() {
+                self.forward_pending_connection(opening.conn, channel);
+            }
+        }
+    }
+
+    fn forward_pending_connection(
+        &mut self,
+        conn: PendingConnection,
+        channel: anyhow::Result<ChannelWrap>,
+    ) {
+        let PendingConnection {
+            mut stream,
+            originator,
+            destination,
+            socks,
+        } = conn;
+
+        match channel {
+            Ok(mut channel) => {
+                if let Some(version) = socks {
+                    if let Err(err) = stream.write_all(&socks_reply(version, true)) {
+                        log::error!("replying to SOCKS client {}: {:#}", originator, err);
+                        channel.close();
+                        return;
+                    }
+                }
+                stream.set_nodelay(true).ok();
+                if let Err(err) = self.add_forwarded_channel(channel, stream_to_fd(stream)) {
+                    log::error!("forwarding connection from {}: {:#}", originator, err);
+                }
+            }
+            Err(err) => {
+                log::error!(
+                    "forwarding connection from {} to {}: {:#}",
+                    originator,
+                    destination,
+                    err
+                );
+                if let Some(version) = socks {
+                    stream.write_all(&socks_reply(version, false)).ok();
+                }
+            }
+        }
+    }
+
+    /// Accepts the channels that the remote host opened for
+    /// connections made to our remote forwardings.
+    /// Connecting to the local destination may take a while,
+    /// so it is performed on a separate thread to avoid stalling
+    /// the session; the channel waits in `connecting` until
+    /// the result is handed back via `tx_connected`.
+    pub fn accept_remote_connections(&mut self, sess: &mut SessionWrap) {
+        if self.remote_listeners.is_empty() {
+            return;
+        }
+        while let Some((idx, channel)) = sess.accept_forward(&mut self.remote_listeners) {
+            let connect = self.remote_listeners[idx].connect.clone();
+            let id = self.next_channel_id;
+            self.next_channel_id += 1;
+            self.connecting.insert(id, channel);
+
+            let tx = self.tx_connected.clone();
+            let wakeup = Arc::clone(&self.wakeup);
+            std::thread::spawn(move || {
+                let stream = TcpStream::connect((connect.host.as_str(), connect.port));
+                if tx
+                    .try_send(RemoteConnection {
+                        id,
+                        connect,
+                        stream,
+                    })
+                    .is_ok()
+                {
+                    let _ = wakeup.lock().unwrap().write(b"x");
+                }
+            });
+        }
+    }
+
+    /// Relays the channels whose local connection has been made
+    pub fn open_remote_connections(&mut self) {
+        while let Ok(conn) = self.rx_connected.try_recv() {
+            let mut channel = match self.connecting.remove(&conn.id) {
+                Some(channel) => channel,
+                None => continue,
+            };
+            match conn.stream {
+                Ok(stream) => {
+                    stream.set_nodelay(true).ok();
+                    if let Err(err) = self.add_forwarded_channel(channel, stream_to_fd(stream)) {
+                        log::error!("forwarding connection to {}: {:#}", conn.connect, err);
+                    }
+                }
+                Err(err) => {
+                    log::error!("forwarding connection to {}: {:#}", conn.connect, err);
+                    channel.close();
+                }
+            }
+        }
+    }
+
+    /// Opens a channel to `destination` and returns a socket that is
+    /// relayed to it, for use as the transport of a session that
+    /// hops through this one
+    pub fn open_tunnel(
+        &mut self,
+        sess: &mut SessionWrap,
+        destination: ConnectAddress,
+    ) -> anyhow::Result<FileDescriptor> {
+        let originator = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        // Requests are dispatched with the session in blocking mode,
+        // so the channel is open once this returns
+        let channel = sess
+            .open_direct_tcpip(&destination.host, destination.port, originator, &mut None)
+            .and_then(|channel| channel.ok_or_else(|| anyhow::anyhow!("the open would block")))
+            .with_context(|| format!("opening channel to {}", destination))?;
+        let (tunnel, relay) = socketpair()?;
+        self.add_forwarded_channel(channel, relay)?;
+        Ok(tunnel)
+    }
+
+    /// Relays data between `channel` and `stream`, using the same
+    /// machinery as the stdin and stdout of a channel for a process
+    fn add_forwarded_channel(
+        &mut self,
+        channel: ChannelWrap,
+        stream: FileDescriptor,
+    ) -> anyhow::Result<()> {
+        let mut read_from_stream = stream;
+        let mut write_to_stream = read_from_stream.try_clone()?;
+        read_from_stream.set_non_blocking(true)?;
+        write_to_stream.set_non_blocking(true)?;
+
+        let channel_id = self.next_channel_id;
+        self.next_channel_id += 1;
+
+        let info = ChannelInfo {
+            channel_id,
+            channel,
+            exit: None,
+            descriptors: [
+                DescriptorState {
+                    fd: Some(read_from_stream),
+                    buf: VecDeque::with_capacity(8192),
+                },
+                DescriptorState {
+                    fd: Some(write_to_stream),
+                    buf: VecDeque::with_capacity(8192),
+                },
+                DescriptorState {
+                    fd: None,
+                    buf: VecDeque::new(),
+                },
+            ],
+            forwarded: true,
+        };
+
+        self.channels.insert(channel_id, info);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A stream that reads from a canned request and records the replies
+    struct FakeStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl FakeStream {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: Cursor::new(input.to_vec()),
+                output: vec![],
+            }
+        }
+    }
+
+    impl Read for FakeStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for FakeStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn listen(bind_address: Option<&str>, port: u16) -> ListenAddress {
+        ListenAddress {
+            bind_address: bind_address.map(|s| s.to_string()),
+            port,
+        }
+    }
+
+    fn connect(host: &str, port: u16) -> ConnectAddress {
+        ConnectAddress {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    #[test]
+    fn parse_local() {
+        assert_eq!(
+            Forward::parse_local("5432 db.internal:5432").unwrap(),
+            Forward::Local {
+                listen: listen(None, 5432),
+                connect: connect("db.internal", 5432),
+            }
+        );
+        assert_eq!(
+            Forward::parse_local("127.0.0.1:8080:localhost:80").unwrap(),
+            Forward::Local {
+                listen: listen(Some("127.0.0.1"), 8080),
+                connect: connect("localhost", 80),
+            }
+        );
+        assert_eq!(
+            Forward::parse_local("[::1]:8080 [fe80::1]:80").unwrap(),
+            Forward::Local {
+                listen: listen(Some("::1"), 8080),
+                connect: connect("fe80::1", 80),
+            }
+        );
+        assert!(Forward::parse_local("8080").is_err());
+        assert!(Forward::parse_local("8080 host:http").is_err());
+    }
+
+    #[test]
+    fn parse_remote_and_dynamic() {
+        assert_eq!(
+            Forward::parse_remote("*:9000 localhost:3000").unwrap(),
+            Forward::Remote {
+                listen: listen(Some("*"), 9000),
+                connect: connect("localhost", 3000),
+            }
+        );
+        assert!(Forward::parse_remote("9000").is_err());
+        assert_eq!(
+            Forward::parse_dynamic("1080").unwrap(),
+            Forward::Dynamic {
+                listen: listen(None, 1080)
+            }
+        );
+        assert_eq!(
+            Forward::parse_dynamic("0.0.0.0:1080").unwrap(),
+            Forward::Dynamic {
+                listen: listen(Some("0.0.0.0"), 1080)
+            }
+        );
+    }
+
+    #[test]
+    fn from_config() {
+        let mut config = ConfigMap::new();
+        config.insert(
+            "localforward".to_string(),
+            "5432 db:5432,6379 cache:6379".to_string(),
+        );
+        config.insert("dynamicforward".to_string(), "1080".to_string());
+        let forwards = Forward::from_config(&config).unwrap();
+        assert_eq!(
+            forwards.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            vec![
+                "-L 5432:db:5432".to_string(),
+                "-L 6379:cache:6379".to_string(),
+                "-D 1080".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn socks4a() {
+        let mut request = vec![4, 1, 0, 80, 0, 0, 0, 1];
+        request.extend_from_slice(b"user\0example.com\0");
+        let mut stream = FakeStream::new(&request);
+        assert_eq!(
+            socks_handshake(&mut stream).unwrap(),
+            SocksRequest {
+                version: SocksVersion::V4,
+                destination: connect("example.com", 80),
+            }
+        );
+        assert!(stream.output.is_empty());
+    }
+
+    #[test]
+    fn socks5() {
+        let mut request = vec![5, 2, 2, 0, 5, 1, 0, 3, 11];
+        request.extend_from_slice(b"example.com");
+        request.extend_from_slice(&443u16.to_be_bytes());
+        let mut stream = FakeStream::new(&request);
+        assert_eq!(
+            socks_handshake(&mut stream).unwrap(),
+            SocksRequest {
+                version: SocksVersion::V5,
+                destination: connect("example.com", 443),
+            }
+        );
+        assert_eq!(stream.output, vec![5, 0]);
+
+        let mut stream = FakeStream::new(&[5, 1, 2]);
+        assert!(socks_handshake(&mut stream).is_err());
+        assert_eq!(stream.output, vec![5, 0xff]);
+    }
+}
@@ -0,0 +1,117 @@
+//! Connecting to a host via the jump hosts listed by `ProxyJump`
+use crate::config::{jump_host_configs, ConfigMap};
+use crate::session::{Session, SessionEvent};
+use anyhow::{anyhow, Context};
+use smol::channel::Receiver;
+
+/// Options that are specific to wezterm rather than to a host,
+/// and which should apply to the jump hosts too
+const INHERITED_OPTIONS: &[&str] = &["wezterm_ssh_backend", "wezterm_ssh_verbose"];
+
+fn host_and_port(config: &ConfigMap) -> anyhow::Result<(String, u16)> {
+    let hostname = config
+        .get("hostname")
+        .ok_or_else(|| anyhow!("hostname not present in config"))?
+        .to_string();
+    let port = config
+        .get("port")
+        .ok_or_else(|| anyhow!("port is always set in config loader"))?
+        .parse::<u16>()?;
+    Ok((hostname, port))
+}
+
+fn describe_host(config: &ConfigMap) -> String {
+    format!(
+        "{}@{}:{}",
+        config.get("user").map(|s| s.as_str()).unwrap_or("?"),
+        config.get("hostname").map(|s| s.as_str()).unwrap_or("?"),
+        config.get("port").map(|s| s.as_str()).unwrap_or("22")
+    )
+}
+
+impl crate::sessioninner::SessionInner {
+    /// Connects to each of the jump hosts in turn, each one via a
+    /// channel opened through the previous one, leaving a connection
+    /// to the destination host in `self.transport`.
+    /// The host verification and authentication events for each
+    /// jump host are passed through to our own event stream.
+    pub fn connect_via_jump_hosts(&mut self) -> anyhow::Result<()> {
+        let hops = jump_host_configs(&self.config);
+        let num_hops = hops.len();
+        if num_hops == 0 {
+            return Ok(());
+        }
+
+        // Each hop connects on to the next, and the last one
+        // connects to the destination
+        let mut destinations = hops
+            .iter()
+            .skip(1)
+            .map(host_and_port)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        destinations.push(host_and_port(&self.config)?);
+
+        for ((idx, mut hop), (hostname, port)) in hops.into_iter().enumerate().zip(destinations) {
+            for &key in INHERITED_OPTIONS {
+                if let Some(value) = self.config.get(key) {
+                    hop.entry(key.to_string())
+                        .or_insert_with(|| value.to_string());
+                }
+            }
+            let name = describe_host(&hop);
+
+            self.tx_event
+                .try_send(SessionEvent::Banner(Some(format!(
+                    "Connecting to jump host {} ({} of {})",
+                    name,
+                    idx + 1,
+                    num_hops
+                ))))
+                .context("notifying user of banner")?;
+
+            let (session, events) = Session::connect_with_transport(hop, self.transport.take())?;
+            self.relay_jump_host_events(&name, &events)
+                .with_context(|| format!("connecting to jump host {}", name))?;
+
+            let transport =
+                smol::block_on(session.open_tunnel(&hostname, port)).with_context(|| {
+                    format!("connecting to {}:{} via jump host {}", hostname, port, name)
+                })?;
+
+            self.transport.replace(transport);
+            self.jump_sessions.push(session);
+        }
+
+        Ok(())
+    }
+
+    /// Passes the events from a jump host session through to our own
+    /// event stream until it has authenticated
+    fn relay_jump_host_events(
+        &mut self,
+        name: &str,
+        events: &Receiver<SessionEvent>,
+    ) -> anyhow::Result<()> {
+        loop {
+            let event = smol::block_on(events.recv())
+                .context("jump host session terminated unexpectedly")?;
+            let event = match event {
+                SessionEvent::Authenticated => return Ok(()),
+                SessionEvent::Error(err) => anyhow::bail!("{}", err),
+                SessionEvent::Authenticate(mut auth) => {
+                    // Prompts such as "Password: " don't say which host
+                    // they are for, so make that clear
+                    auth.instructions = if auth.instructions.is_empty() {
+                        format!("Authenticating to jump host {}", name)
+                    } else {
+                        format!("Jump host {}: {}", name, auth.instructions)
+                    };
+                    SessionEvent::Authenticate(auth)
+                }
+                event => event,
+            };
+            smol::block_on(self.tx_event.send(event))
+                .context("relaying jump host event to user")?;
+        }
+    }
+}
@@ -6,7 +6,9 @@ mod channelwrap;
 mod config;
 mod dirwrap;
 mod filewrap;
+mod forward;
 mod host;
+mod jump;
 mod pty;
 mod session;
 mod sessioninner;
@@ -16,6 +18,7 @@ mod sftpwrap;
 
 pub use auth::*;
 pub use config::*;
+pub use forward::*;
 pub use host::*;
 pub use pty::*;
 pub use session::*;
@@ -1,13 +1,14 @@
 use crate::auth::*;
 use crate::config::ConfigMap;
+use crate::forward::{ConnectAddress, Forward};
 use crate::host::*;
 use crate::pty::*;
 use crate::sessioninner::*;
 use crate::sftp::{Sftp, SftpRequest};
 use filedescriptor::{socketpair, FileDescriptor};
 use portable_pty::PtySize;
-use smol::channel::{bounded, Receiver, Sender};
-use std::collections::HashMap;
+use smol::channel::{bounded, unbounded, Receiver, Sender};
+use std::collections::{HashMap, VecDeque};
 use std::io::Write;
 use std::sync::{Arc, Mutex};
 
@@ -52,6 +53,8 @@ pub(crate) enum SessionRequest {
     Exec(Exec, Sender<anyhow::Result<ExecResult>>),
     Sftp(SftpRequest),
     SignalChannel(SignalChannel),
+    Forward(Forward, Sender<anyhow::Result<u16>>),
+    Tunnel(ConnectAddress, Sender<anyhow::Result<FileDescriptor>>),
 }
 
 #[derive(Debug)]
@@ -79,12 +82,24 @@ impl Drop for Session {
 
 impl Session {
     pub fn connect(config: ConfigMap) -> anyhow::Result<(Self, Receiver<SessionEvent>)> {
+        Self::connect_with_transport(config, None)
+    }
+
+    /// Connect using `transport` as the connection to the ssh server,
+    /// rather than making a connection based on the config
+    pub(crate) fn connect_with_transport(
+        config: ConfigMap,
+        transport: Option<FileDescriptor>,
+    ) -> anyhow::Result<(Self, Receiver<SessionEvent>)> {
         let (tx_event, rx_event) = bounded(8);
         let (tx_req, rx_req) = bounded(8);
         let (mut sender_write, mut sender_read) = socketpair()?;
         sender_write.set_non_blocking(true)?;
         sender_read.set_non_blocking(true)?;
 
+        let (tx_pending, rx_pending) = unbounded();
+        let (tx_connected, rx_connected) = unbounded();
+
         let session_sender = SessionSender {
             tx: tx_req,
             pipe: Arc::new(Mutex::new(sender_write)),
@@ -100,6 +115,17 @@ impl Session {
             next_channel_id: 1,
             next_file_id: 1,
             sender_read,
+            wakeup: Arc::clone(&session_sender.pipe),
+            listeners: vec![],
+            remote_listeners: vec![],
+            tx_pending,
+            rx_pending,
+            opening: VecDeque::new(),
+            connecting: HashMap::new(),
+            tx_connected,
+            rx_connected,
+            transport,
+            jump_sessions: vec![],
         };
         std::thread::spawn(move || inner.run());
         Ok((Self { tx: session_sender }, rx_event))
@@ -150,6 +176,37 @@ impl Session {
         Ok(exec)
     }
 
+    /// Establishes a port forwarding over this session, returning
+    /// the port that is listening for connections to forward.
+    /// Passing port 0 in the listen address allocates a free port.
+    pub async fn forward(&self, forward: Forward) -> anyhow::Result<u16> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::Forward(forward, reply))
+            .await?;
+        rx.recv().await?
+    }
+
+    /// Opens a channel to `host:port` via the remote host, returning
+    /// a socket that is relayed to it
+    pub(crate) async fn open_tunnel(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> anyhow::Result<FileDescriptor> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::Tunnel(
+                ConnectAddress {
+                    host: host.to_string(),
+                    port,
+                },
+                reply,
+            ))
+            .await?;
+        rx.recv().await?
+    }
+
     /// Creates a new reference to the sftp channel for filesystem operations
     ///
     /// ### Note
@@ -2,9 +2,10 @@ use crate::channelwrap::ChannelWrap;
 use crate::config::ConfigMap;
 use crate::dirwrap::DirWrap;
 use crate::filewrap::FileWrap;
+use crate::forward::{LocalListener, OpeningConnection, PendingConnection, RemoteConnection};
 use crate::pty::*;
-use crate::session::{Exec, ExecResult, SessionEvent, SessionRequest, SignalChannel};
-use crate::sessionwrap::SessionWrap;
+use crate::session::{Exec, ExecResult, Session, SessionEvent, SessionRequest, SignalChannel};
+use crate::sessionwrap::{RemoteListener, SessionWrap};
 use crate::sftp::dir::{Dir, DirId, DirRequest};
 use crate::sftp::file::{File, FileId, FileRequest};
 use crate::sftp::{OpenWithMode, SftpChannelResult, SftpRequest};
@@ -18,6 +19,7 @@ use portable_pty::ExitStatus;
 use smol::channel::{bounded, Receiver, Sender, TryRecvError};
 use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 #[derive(Debug)]
@@ -31,6 +33,9 @@ pub(crate) struct ChannelInfo {
     pub channel: ChannelWrap,
     pub exit: Option<Sender<ExitStatus>>,
     pub descriptors: [DescriptorState; 3],
+    /// The channel relays a forwarded connection rather than
+    /// the stdio of a process
+    pub forwarded: bool,
 }
 
 pub(crate) type ChannelId = usize;
@@ -45,6 +50,25 @@ pub(crate) struct SessionInner {
     pub next_channel_id: ChannelId,
     pub next_file_id: FileId,
     pub sender_read: FileDescriptor,
+    /// Wakes up the request loop; shared with `SessionSender`
+    pub wakeup: Arc<Mutex<FileDescriptor>>,
+    pub listeners: Vec<LocalListener>,
+    pub remote_listeners: Vec<RemoteListener>,
+    pub tx_pending: Sender<PendingConnection>,
+    pub rx_pending: Receiver<PendingConnection>,
+    /// Connections that are waiting for their channel to be opened,
+    /// in the order that they were made
+    pub opening: VecDeque<OpeningConnection>,
+    /// Channels opened by the remote host for our remote forwardings,
+    /// whose local connection is still being made
+    pub connecting: HashMap<ChannelId, ChannelWrap>,
+    pub tx_connected: Sender<RemoteConnection>,
+    pub rx_connected: Receiver<RemoteConnection>,
+    /// The connection to use instead of connecting to the host,
+    /// when the host is reached via jump hosts
+    pub transport: Option<FileDescriptor>,
+    /// The sessions with the jump hosts, which must outlive this session
+    pub jump_sessions: Vec<Session>,
 }
 
 impl Drop for SessionInner {
@@ -63,6 +87,8 @@ impl SessionInner {
     }
 
     fn run_impl(&mut self) -> anyhow::Result<()> {
+        self.connect_via_jump_hosts()?;
+
         let backend = self
             .config
             .get("wezterm_ssh_backend")
@@ -188,7 +214,19 @@ impl SessionInner {
                 break;
             }
         }
-        if let Some(cmd) = self.config.get("proxycommand") {
+        if let Some(transport) = self.transport.take() {
+            #[cfg(unix)]
+            let socket = {
+                use std::os::unix::io::IntoRawFd;
+                transport.into_raw_fd()
+            };
+            #[cfg(windows)]
+            let socket = {
+                use std::os::windows::io::IntoRawSocket;
+                transport.into_raw_socket()
+            };
+            sess.set_option(libssh_rs::SshOption::Socket(socket))?;
+        } else if let Some(cmd) = self.config.get("proxycommand") {
             sess.set_option(libssh_rs::SshOption::ProxyCommand(Some(cmd.to_string())))?;
         }
         if let Some(types) = self.config.get("pubkeyacceptedtypes") {
@@ -213,12 +251,14 @@ impl SessionInner {
                 .context("notifying user of banner")?;
         }
 
+        let mut sess = SessionWrap::with_libssh(sess);
+        self.add_forwards_from_config(&mut sess)?;
+
         self.tx_event
             .try_send(SessionEvent::Authenticated)
             .context("notifying user that session is authenticated")?;
 
         sess.set_blocking(false);
-        let mut sess = SessionWrap::with_libssh(sess);
         self.request_loop(&mut sess)
     }
 
@@ -249,14 +289,24 @@ impl SessionInner {
             ))))
             .context("notifying user of banner")?;
 
-        let tcp: TcpStream = if let Some(proxy_command) =
-            self.config.get("proxycommand").and_then(|c| {
-                if !c.is_empty() && c != "none" {
-                    Some(c)
-                } else {
-                    None
-                }
-            }) {
+        let tcp: TcpStream = if let Some(transport) = self.transport.take() {
+            #[cfg(unix)]
+            unsafe {
+                use std::os::unix::io::{FromRawFd, IntoRawFd};
+                TcpStream::from_raw_fd(transport.into_raw_fd())
+            }
+            #[cfg(windows)]
+            unsafe {
+                use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+                TcpStream::from_raw_socket(transport.into_raw_socket())
+            }
+        } else if let Some(proxy_command) = self.config.get("proxycommand").and_then(|c| {
+            if !c.is_empty() && c != "none" {
+                Some(c)
+            } else {
+                None
+            }
+        }) {
             let mut cmd;
             if cfg!(windows) {
                 let comspec = std::env::var("COMSPEC").unwrap_or_else(|_| "cmd".to_string());
@@ -320,13 +370,14 @@ impl SessionInner {
         self.authenticate(&sess, &user, &hostname)
             .context("authentication")?;
 
+        let mut sess = SessionWrap::with_ssh2(sess);
+        self.add_forwards_from_config(&mut sess)?;
+
         self.tx_event
             .try_send(SessionEvent::Authenticated)
             .context("notifying user that session is authenticated")?;
 
         sess.set_blocking(false);
-
-        let mut sess = SessionWrap::with_ssh2(sess);
         self.request_loop(&mut sess)
     }
 
@@ -337,6 +388,9 @@ impl SessionInner {
             self.tick_io()?;
             self.drain_request_pipe();
             self.dispatch_pending_requests(sess)?;
+            self.open_pending_connections(sess);
+            self.accept_remote_connections(sess);
+            self.open_remote_connections();
 
             let mut poll_array = vec![
                 pollfd {
@@ -371,15 +425,29 @@ impl SessionInner {
                 }
             }
 
+            let listener_base = poll_array.len();
+            for info in &self.listeners {
+                poll_array.push(pollfd {
+                    fd: info.listener.as_socket_descriptor(),
+                    events: POLLIN,
+                    revents: 0,
+                });
+            }
+
             poll(&mut poll_array, Some(sleep_delay)).context("poll")?;
             sleep_delay += sleep_delay;
 
+            let mut ready_listeners = vec![];
             for (idx, poll) in poll_array.iter().enumerate() {
                 if poll.revents != 0 {
                     sleep_delay = Duration::from_millis(100);
                 }
                 if idx == 0 || idx == 1 {
                     // Dealt with at the top of the loop
+                } else if idx >= listener_base {
+                    if poll.revents != 0 {
+                        ready_listeners.push(idx - listener_base);
+                    }
                 } else if poll.revents != 0 {
                     let (channel_id, fd_num) = mapping[idx - 2];
                     let info = self.channels.get_mut(&channel_id).unwrap();
@@ -415,6 +483,10 @@ impl SessionInner {
                     }
                 }
             }
+
+            for idx in ready_listeners {
+                self.accept_local_connections(sess, idx);
+            }
         }
     }
 
@@ -431,8 +503,20 @@ impl SessionInner {
 
             let stdin = &mut chan.descriptors[0];
             if stdin.fd.is_some() && !stdin.buf.is_empty() {
-                write_from_buf(&mut chan.channel.writer(), &mut stdin.buf)
-                    .context("writing to channel")?;
+                if let Err(err) = write_from_buf(&mut chan.channel.writer(), &mut stdin.buf) {
+                    if !chan.forwarded {
+                        return Err(err).context("writing to channel");
+                    }
+                    // The other end of a forwarded connection went away;
+                    // that only affects this connection
+                    log::debug!(
+                        "error writing to channel {}: {:#}, closing pipe",
+                        chan.channel_id,
+                        err
+                    );
+                    stdin.fd.take();
+                    stdin.buf.clear();
+                }
             }
 
             for (idx, out) in chan
@@ -469,7 +553,17 @@ impl SessionInner {
                     }
                 }
             }
+
+            // Once the remote end of a forwarded connection has finished
+            // sending, there is nothing more to relay
+            if chan.forwarded && chan.descriptors[1].fd.is_none() {
+                chan.descriptors[0].fd.take();
+            }
         }
+
+        // Forget forwarded connections that have been closed
+        self.channels
+            .retain(|_, chan| !chan.forwarded || chan.descriptors.iter().any(|d| d.fd.is_some()));
         Ok(())
     }
 
@@ -479,6 +573,11 @@ impl SessionInner {
     }
 
     fn dispatch_pending_requests(&mut self, sess: &mut SessionWrap) -> anyhow::Result<()> {
+        if self.is_opening_channel() && sess.opens_one_channel_at_a_time() {
+            // Requests may open channels of their own, which would
+            // resume the open that is in progress instead
+            return Ok(());
+        }
         while self.dispatch_one_request(sess)? {}
         Ok(())
     }
@@ -505,6 +604,12 @@ impl SessionInner {
                     SessionRequest::Exec(exec, reply) => {
                         dispatch(reply, || self.exec(sess, exec), "exec")
                     }
+                    SessionRequest::Forward(forward, reply) => {
+                        dispatch(reply, || self.add_forward(sess, forward), "forward")
+                    }
+                    SessionRequest::Tunnel(destination, reply) => {
+                        dispatch(reply, || self.open_tunnel(sess, destination), "tunnel")
+                    }
                     SessionRequest::SignalChannel(info) => {
                         if let Err(err) = self.signal_channel(&info) {
                             log::error!("{:?} -> error: {:#}", info, err);
@@ -775,6 +880,7 @@ impl SessionInner {
                     buf: VecDeque::with_capacity(8192),
                 },
             ],
+            forwarded: false,
         };
 
         self.channels.insert(channel_id, info);
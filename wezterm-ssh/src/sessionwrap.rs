@@ -1,6 +1,8 @@
 use crate::channelwrap::ChannelWrap;
+use crate::forward::ConnectAddress;
 use crate::sftpwrap::SftpWrap;
 use filedescriptor::{AsRawSocketDescriptor, SocketDescriptor, POLLIN, POLLOUT};
+use std::net::SocketAddr;
 
 #[cfg(feature = "ssh2")]
 pub(crate) struct Ssh2Session {
@@ -14,6 +16,24 @@ pub(crate) struct LibSshSession {
     pub sftp: Option<SftpWrap>,
 }
 
+/// A port on the remote host that is listening for connections
+/// on our behalf, as the result of a `RemoteForward`
+pub(crate) enum RemoteListenerWrap {
+    #[cfg(feature = "ssh2")]
+    Ssh2(ssh2::Listener),
+
+    /// libssh accepts connections for all listeners at the
+    /// session level, so there is no per-listener state
+    #[cfg(feature = "libssh-rs")]
+    LibSsh,
+}
+
+pub(crate) struct RemoteListener {
+    pub listener: RemoteListenerWrap,
+    pub bound_port: u16,
+    pub connect: ConnectAddress,
+}
+
 pub(crate) enum SessionWrap {
     #[cfg(feature = "ssh2")]
     Ssh2(Ssh2Session),
@@ -92,4 +112,135 @@ impl SessionWrap {
             }
         }
     }
+
+    /// libssh2 keeps the state of a channel that is being opened in
+    /// the session, so a channel that is being opened without blocking
+    /// must be opened before any other channel is.
+    pub fn opens_one_channel_at_a_time(&self) -> bool {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(_) => true,
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(_) => false,
+        }
+    }
+
+    /// Opens a channel to `host:port` on behalf of `originator`, as used
+    /// for local and dynamic forwarding.
+    /// When the session is in non-blocking mode, returns `Ok(None)` while
+    /// the remote host has yet to respond, in which case it must be called
+    /// again with the same arguments and `opening` once the session is ready.
+    pub fn open_direct_tcpip(
+        &self,
+        host: &str,
+        port: u16,
+        originator: SocketAddr,
+        opening: &mut Option<ChannelWrap>,
+    ) -> anyhow::Result<Option<ChannelWrap>> {
+        let originator_ip = originator.ip().to_string();
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                // The open is resumed by repeating the call
+                debug_assert!(opening.is_none());
+                match sess.sess.channel_direct_tcpip(
+                    host,
+                    port,
+                    Some((&originator_ip, originator.port())),
+                ) {
+                    Ok(channel) => Ok(Some(ChannelWrap::Ssh2(channel))),
+                    Err(err) => {
+                        let err: std::io::Error = err.into();
+                        if err.kind() == std::io::ErrorKind::WouldBlock {
+                            Ok(None)
+                        } else {
+                            Err(err.into())
+                        }
+                    }
+                }
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let channel = match opening.take() {
+                    Some(ChannelWrap::LibSsh(channel)) => channel,
+                    _ => sess.sess.new_channel()?,
+                };
+                match channel.open_forward(host, port, &originator_ip, originator.port()) {
+                    Ok(()) => Ok(Some(ChannelWrap::LibSsh(channel))),
+                    Err(libssh_rs::Error::TryAgain) => {
+                        opening.replace(ChannelWrap::LibSsh(channel));
+                        Ok(None)
+                    }
+                    Err(err) => Err(err.into()),
+                }
+            }
+        }
+    }
+
+    /// Asks the remote end to listen on `bind_address:port`, returning
+    /// the listener and the port that was bound
+    pub fn listen_forward(
+        &self,
+        bind_address: &str,
+        port: u16,
+    ) -> anyhow::Result<(RemoteListenerWrap, u16)> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                let (listener, port) =
+                    sess.sess
+                        .channel_forward_listen(port, Some(bind_address), None)?;
+                Ok((RemoteListenerWrap::Ssh2(listener), port))
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let port = sess.sess.listen_forward(Some(bind_address), port)?;
+                Ok((RemoteListenerWrap::LibSsh, port))
+            }
+        }
+    }
+
+    /// Returns the next pending connection to one of `listeners`,
+    /// along with the index of the listener that it was made to.
+    /// Must be called with the session in non-blocking mode.
+    pub fn accept_forward(&self, listeners: &mut [RemoteListener]) -> Option<(usize, ChannelWrap)> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(_) => {
+                for (idx, remote) in listeners.iter_mut().enumerate() {
+                    match &mut remote.listener {
+                        RemoteListenerWrap::Ssh2(listener) => match listener.accept() {
+                            Ok(channel) => return Some((idx, ChannelWrap::Ssh2(channel))),
+                            Err(err) => {
+                                let err: std::io::Error = err.into();
+                                if err.kind() != std::io::ErrorKind::WouldBlock {
+                                    log::error!(
+                                        "accepting forwarded connection on port {}: {:#}",
+                                        remote.bound_port,
+                                        err
+                                    );
+                                }
+                            }
+                        },
+                        #[allow(unreachable_patterns)]
+                        _ => {}
+                    }
+                }
+                None
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let (port, channel) = sess
+                    .sess
+                    .accept_forward(std::time::Duration::from_millis(0))
+                    .ok()?;
+                let idx = listeners.iter().position(|l| l.bound_port == port)?;
+                Some((idx, ChannelWrap::LibSsh(channel)))
+            }
+        }
+    }
 }
@@ -3,20 +3,23 @@ use chrono::serde::ts_seconds_option;
 use chrono::{DateTime, Utc};
 use config::ConfigHandle;
 use filedescriptor::FileDescriptor;
+use player::{DirectSink, Fit, HeadlessSink, Player, Recording};
 use portable_pty::{native_pty_system, PtySize};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
 use std::io::{BufRead, BufReader, BufWriter, Read, Write};
-use std::path::PathBuf;
-use std::sync::mpsc::channel;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 #[cfg(unix)]
-use unix::UnixTty as Tty;
+pub use unix::UnixTty as Tty;
 use wezterm_term::color::ColorPalette;
 #[cfg(windows)]
-use win::WinTty as Tty;
+pub use win::WinTty as Tty;
+
+mod player;
 
 /// See <https://github.com/asciinema/asciinema/blob/develop/doc/asciicast-v2.md>
 /// for file format specification
@@ -124,12 +127,153 @@ pub struct Theme {
 pub struct Event(pub f32, pub String, pub String);
 
 impl Event {
-    fn log_output<W: Write>(mut w: W, elapsed: f32, output: &str) -> std::io::Result<()> {
-        let event = Event(elapsed, "o".to_string(), output.to_string());
+    /// Output printed by the child
+    const OUTPUT: &'static str = "o";
+    /// Data typed by the user
+    const INPUT: &'static str = "i";
+    /// The terminal was resized; the data is formatted as `COLSxROWS`
+    const RESIZE: &'static str = "r";
+    /// A marker; the data is an optional label
+    const MARKER: &'static str = "m";
+    /// The exit status of the child; the data is the exit code.
+    /// The v2 specification has no way to record this, so we use
+    /// the exit event type and format from asciicast v3.  Players
+    /// are required to ignore event types that they don't understand,
+    /// so the file remains a valid v2 recording.
+    const EXIT: &'static str = "x";
+
+    fn log<W: Write>(mut w: W, elapsed: f32, code: &str, data: &str) -> std::io::Result<()> {
+        let event = Event(elapsed, code.to_string(), data.to_string());
         writeln!(w, "{}", serde_json::to_string(&event)?)
     }
 }
 
+/// Accumulates bytes and yields the portion of them that is valid utf8.
+/// The end of the data may be an incomplete utf8 sequence that straddles
+/// a read boundary.  JSON requires strings to be utf-8 so we need to send
+/// the currently-valid portions through to the .cast file and buffer up
+/// the remainder.
+#[derive(Default)]
+struct Utf8Buffer {
+    buffer: Vec<u8>,
+}
+
+impl Utf8Buffer {
+    fn push(&mut self, data: &[u8]) -> String {
+        self.buffer.extend_from_slice(data);
+        match std::str::from_utf8(&self.buffer) {
+            Ok(valid) => {
+                let valid = valid.to_string();
+                self.buffer.clear();
+                valid
+            }
+            Err(error) => {
+                let valid_len = error.valid_up_to();
+                let valid = String::from_utf8_lossy(&self.buffer[0..valid_len]).to_string();
+
+                self.buffer.drain(0..valid_len);
+
+                if let Some(invalid_sequence_length) = error.error_len() {
+                    // Invalid sequence: skip it
+                    self.buffer.drain(0..invalid_sequence_length);
+                }
+                valid
+            }
+        }
+    }
+}
+
+/// Computes the timestamps of recorded events, clamping the
+/// gaps between them to the idle time limit, if any.
+struct RecordingClock {
+    last: Instant,
+    elapsed: f32,
+    idle_time_limit: Option<f32>,
+}
+
+impl RecordingClock {
+    fn new(start: f32, idle_time_limit: Option<f32>) -> Self {
+        Self {
+            last: Instant::now(),
+            elapsed: start,
+            idle_time_limit,
+        }
+    }
+
+    fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let mut delta = now.duration_since(self.last).as_secs_f32();
+        self.last = now;
+        if let Some(limit) = self.idle_time_limit {
+            delta = delta.min(limit);
+        }
+        self.elapsed += delta;
+        self.elapsed
+    }
+}
+
+/// Reads an existing recording so that it can be appended to.
+/// Returns its header, the time of its last event and the
+/// terminal size that was in effect at the end of it.
+fn read_existing_recording(path: &Path) -> anyhow::Result<(Header, f32, (u32, u32))> {
+    let mut file = BufReader::new(
+        std::fs::File::open(path)
+            .with_context(|| format!("reading cast file {}", path.display()))?,
+    );
+    let mut header_line = String::new();
+    file.read_line(&mut header_line)
+        .context("reading Header line")?;
+    let header: Header = serde_json::from_str(&header_line).context("parsing Header")?;
+
+    let mut last_time = 0.;
+    let mut size = (header.width, header.height);
+    for (idx, line) in file.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: Event = serde_json::from_str(&line)
+            .with_context(|| format!("parsing event on line {}", idx + 2))?;
+        last_time = event.0;
+        if event.1 == Event::RESIZE {
+            if let Some(new_size) = parse_resize(&event.2) {
+                size = new_size;
+            }
+        }
+    }
+
+    Ok((header, last_time, size))
+}
+
+/// Parses the `COLSxROWS` data of a resize event
+fn parse_resize(data: &str) -> Option<(u32, u32)> {
+    let mut fields = data.splitn(2, 'x');
+    let cols = fields.next()?.parse().ok()?;
+    let rows = fields.next()?.parse().ok()?;
+    Some((cols, rows))
+}
+
+/// Parses the `--marker-key` option.  Accepts either a single
+/// character, or `CTRL-` followed by a character in the range
+/// `@` through `_` (case insensitive for letters).
+fn parse_marker_key(s: &str) -> Result<u8, String> {
+    let upper = s.to_ascii_uppercase();
+    if let Some(c) = upper.strip_prefix("CTRL-") {
+        let mut chars = c.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if ('@'..='_').contains(&c) => return Ok(c as u8 - b'@'),
+            _ => {}
+        }
+    } else if s.len() == 1 && s.is_ascii() {
+        return Ok(s.as_bytes()[0]);
+    }
+    Err(format!(
+        "invalid marker key {:?}; expected a single character \
+         or CTRL- followed by a letter or one of @[\\]^_",
+        s
+    ))
+}
+
 #[cfg(windows)]
 mod win {
     use super::*;
@@ -148,6 +292,29 @@ mod win {
         write: FileDescriptor,
     }
 
+    fn get_size(write: &FileDescriptor) -> anyhow::Result<PtySize> {
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = unsafe { std::mem::zeroed() };
+        let ok = unsafe {
+            GetConsoleScreenBufferInfo(write.as_raw_handle() as *mut _, &mut info as *mut _)
+        };
+        if ok == 0 {
+            anyhow::bail!(
+                "GetConsoleScreenBufferInfo failed: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+
+        let cols = 1 + (info.srWindow.Right - info.srWindow.Left);
+        let rows = 1 + (info.srWindow.Bottom - info.srWindow.Top);
+
+        Ok(PtySize {
+            rows: rows as u16,
+            cols: cols as u16,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+    }
+
     impl WinTty {
         pub fn new() -> anyhow::Result<Self> {
             let read =
@@ -201,29 +368,27 @@ mod win {
         }
 
         pub fn get_size(&self) -> anyhow::Result<PtySize> {
-            let mut info: CONSOLE_SCREEN_BUFFER_INFO = unsafe { std::mem::zeroed() };
-            let ok = unsafe {
-                GetConsoleScreenBufferInfo(
-                    self.write.as_raw_handle() as *mut _,
-                    &mut info as *mut _,
-                )
-            };
-            if ok == 0 {
-                anyhow::bail!(
-                    "GetConsoleScreenBufferInfo failed: {}",
-                    std::io::Error::last_os_error()
-                );
-            }
-
-            let cols = 1 + (info.srWindow.Right - info.srWindow.Left);
-            let rows = 1 + (info.srWindow.Bottom - info.srWindow.Top);
+            get_size(&self.write)
+        }
 
-            Ok(PtySize {
-                rows: rows as u16,
-                cols: cols as u16,
-                pixel_width: 0,
-                pixel_height: 0,
-            })
+        /// Spawns a thread that sends a `Message::Resized` each time
+        /// that the console size changes.  There is no equivalent of
+        /// SIGWINCH that we can use without consuming console input
+        /// events, so we poll for changes instead.
+        pub fn watch_resizes(&self, tx: Sender<Message>) -> anyhow::Result<()> {
+            let write = self.write.try_clone()?;
+            let mut size = get_size(&write)?;
+            std::thread::spawn(move || -> anyhow::Result<()> {
+                loop {
+                    std::thread::sleep(Duration::from_millis(250));
+                    let new_size = get_size(&write)?;
+                    if new_size != size {
+                        size = new_size;
+                        tx.send(Message::Resized(size))?;
+                    }
+                }
+            });
+            Ok(())
         }
 
         pub fn reader(&self) -> anyhow::Result<FileDescriptor> {
@@ -246,6 +411,7 @@ mod win {
 mod unix {
     use super::*;
     use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
     use termios::{cfmakeraw, tcsetattr, Termios, TCSAFLUSH};
 
     pub struct UnixTty {
@@ -265,6 +431,25 @@ mod unix {
         tcsetattr(fd.as_raw_fd(), mode, termios).context("set_termios failed")
     }
 
+    fn get_size(tty: &FileDescriptor) -> anyhow::Result<PtySize> {
+        let mut size = std::mem::MaybeUninit::<libc::winsize>::uninit();
+        if unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCGWINSZ as _, &mut size) } != 0 {
+            anyhow::bail!(
+                "failed to ioctl(TIOCGWINSZ): {:#}",
+                std::io::Error::last_os_error()
+            );
+        }
+
+        let size = unsafe { size.assume_init() };
+
+        Ok(PtySize {
+            rows: size.ws_row.into(),
+            cols: size.ws_col.into(),
+            pixel_width: size.ws_xpixel.into(),
+            pixel_height: size.ws_ypixel.into(),
+        })
+    }
+
     impl UnixTty {
         pub fn new() -> anyhow::Result<Self> {
             let tty = FileDescriptor::new(
@@ -289,22 +474,26 @@ mod unix {
         }
 
         pub fn get_size(&self) -> anyhow::Result<PtySize> {
-            let mut size = std::mem::MaybeUninit::<libc::winsize>::uninit();
-            if unsafe { libc::ioctl(self.tty.as_raw_fd(), libc::TIOCGWINSZ as _, &mut size) } != 0 {
-                anyhow::bail!(
-                    "failed to ioctl(TIOCGWINSZ): {:#}",
-                    std::io::Error::last_os_error()
-                );
-            }
-
-            let size = unsafe { size.assume_init() };
+            get_size(&self.tty)
+        }
 
-            Ok(PtySize {
-                rows: size.ws_row.into(),
-                cols: size.ws_col.into(),
-                pixel_width: size.ws_xpixel.into(),
-                pixel_height: size.ws_ypixel.into(),
-            })
+        /// Spawns a thread that sends a `Message::Resized` each
+        /// time that SIGWINCH is delivered to this process
+        pub fn watch_resizes(&self, tx: Sender<Message>) -> anyhow::Result<()> {
+            let (mut read_pipe, write_pipe) = UnixStream::pair()?;
+            signal_hook::pipe::register(libc::SIGWINCH, write_pipe)?;
+            let tty = self.tty.try_clone()?;
+            std::thread::spawn(move || -> anyhow::Result<()> {
+                let mut buf = [0u8; 64];
+                loop {
+                    if read_pipe.read(&mut buf)? == 0 {
+                        break;
+                    }
+                    tx.send(Message::Resized(get_size(&tty)?))?;
+                }
+                Ok(())
+            });
+            Ok(())
         }
 
         pub fn reader(&self) -> anyhow::Result<FileDescriptor> {
@@ -329,12 +518,46 @@ enum Message {
     Stdin(Vec<u8>),
     /// Output from the child tty
     Stdout(Vec<u8>),
+    /// The controlling terminal was resized
+    Resized(PtySize),
     /// Child process terminated
     Terminated(portable_pty::ExitStatus),
 }
 
 #[derive(Debug, StructOpt, Clone)]
 pub struct RecordCommand {
+    /// Write the recording to this file, rather than to a
+    /// randomly named file in the temporary directory
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Append to the file specified by --output, rather than
+    /// replacing it
+    #[structopt(long = "append", requires = "output")]
+    append: bool,
+
+    /// Record the keyboard input as well as the output
+    #[structopt(long = "stdin")]
+    stdin: bool,
+
+    /// Limit the recorded idle time between events to
+    /// at most this many seconds
+    #[structopt(long = "idle-time-limit")]
+    idle_time_limit: Option<f32>,
+
+    /// Set the title of the recording
+    #[structopt(long = "title")]
+    title: Option<String>,
+
+    /// The key to press to insert a marker into the recording.
+    /// The key is not passed through to the recorded program.
+    #[structopt(
+        long = "marker-key",
+        default_value = "CTRL-]",
+        parse(try_from_str = parse_marker_key)
+    )]
+    marker_key: u8,
+
     #[structopt(parse(from_os_str))]
     prog: Vec<OsString>,
 }
@@ -346,16 +569,58 @@ impl RecordCommand {
         let mut tty = Tty::new()?;
         let size = tty.get_size()?;
 
-        let header = Header::new(&config, size, &prog);
+        let existing = match &self.output {
+            Some(path) if self.append && path.exists() => Some(read_existing_recording(path)?),
+            _ => None,
+        };
 
-        let (cast_file, cast_file_name) = tempfile::Builder::new()
-            .prefix("wezterm-recording-")
-            // We use a .txt suffix for convenice when uploading to GH
-            .suffix(".cast.txt")
-            .tempfile()?
-            .keep()?;
+        let (cast_file, cast_file_name) = match &self.output {
+            Some(path) => {
+                let file = std::fs::OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(self.append)
+                    .truncate(!self.append)
+                    .open(path)
+                    .with_context(|| format!("opening {}", path.display()))?;
+                (file, path.clone())
+            }
+            None => tempfile::Builder::new()
+                .prefix("wezterm-recording-")
+                // We use a .txt suffix for convenice when uploading to GH
+                .suffix(".cast.txt")
+                .tempfile()?
+                .keep()?,
+        };
         let mut cast_file = BufWriter::new(cast_file);
-        writeln!(cast_file, "{}", serde_json::to_string(&header)?)?;
+
+        let mut clock = match &existing {
+            Some((header, last_time, recorded_size)) => {
+                let mut clock = RecordingClock::new(
+                    *last_time,
+                    self.idle_time_limit.or(header.idle_time_limit),
+                );
+                // Let the player know if the terminal size is different
+                // from where the previous session left off
+                let elapsed = clock.tick();
+                if *recorded_size != (size.cols.into(), size.rows.into()) {
+                    Event::log(
+                        &mut cast_file,
+                        elapsed,
+                        Event::RESIZE,
+                        &format!("{}x{}", size.cols, size.rows),
+                    )?;
+                }
+                clock
+            }
+            None => {
+                let mut header = Header::new(&config, size, &prog);
+                header.title = self.title.clone();
+                header.idle_time_limit = self.idle_time_limit;
+                writeln!(cast_file, "{}", serde_json::to_string(&header)?)?;
+                RecordingClock::new(0., self.idle_time_limit)
+            }
+        };
 
         let pty_system = native_pty_system();
         let mut pair = pty_system.openpty(size)?;
@@ -409,6 +674,8 @@ impl RecordCommand {
             });
         }
 
+        tty.watch_resizes(tx.clone())?;
+
         {
             let tx = tx.clone();
             std::thread::spawn(move || -> anyhow::Result<()> {
@@ -419,50 +686,85 @@ impl RecordCommand {
         }
 
         let mut child_status = None;
-        let first_output = Instant::now();
-        let mut buffer = vec![];
-
-        for msg in rx {
+        let mut output_buffer = Utf8Buffer::default();
+        let mut input_buffer = Utf8Buffer::default();
+        let mut current_size = size;
+
+        loop {
+            let msg = match child_status {
+                // The child has exited, but its final output may still
+                // be in flight; give the reader a moment to deliver it
+                Some(_) => match rx.recv_timeout(Duration::from_millis(100)) {
+                    Ok(msg @ Message::Stdout(_)) => msg,
+                    _ => break,
+                },
+                None => match rx.recv() {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                },
+            };
             match msg {
                 Message::Stdin(data) => {
-                    pair.master.write_all(&data)?;
-                }
-                Message::Stdout(mut data) => {
-                    let elapsed = first_output.elapsed().as_secs_f32();
-                    tty.write_all(&data)?;
-
-                    // The end of the data may be an incomplete utf8 sequence
-                    // that straddles the buffer boundary.  JSON requires strings
-                    // to be utf-8 so we need to send the currently-valid portions
-                    // through to the .cast file and buffer up the remainder
-                    buffer.append(&mut data);
-                    match std::str::from_utf8(&buffer) {
-                        Ok(valid) => {
-                            Event::log_output(&mut cast_file, elapsed, valid)?;
-                            buffer.clear();
-                        }
-                        Err(error) => {
-                            let valid_len = error.valid_up_to();
-                            Event::log_output(&mut cast_file, elapsed, unsafe {
-                                std::str::from_utf8_unchecked(&buffer[0..valid_len])
-                            })?;
-
-                            buffer.drain(0..valid_len);
-
-                            if let Some(invalid_sequence_length) = error.error_len() {
-                                // Invalid sequence: skip it
-                                buffer.drain(0..invalid_sequence_length);
+                    let elapsed = clock.tick();
+                    let mut data = data.as_slice();
+                    while !data.is_empty() {
+                        let (chunk, marker) = match data.iter().position(|&b| b == self.marker_key)
+                        {
+                            Some(idx) => (&data[0..idx], true),
+                            None => (data, false),
+                        };
+
+                        if !chunk.is_empty() {
+                            pair.master.write_all(chunk)?;
+                            if self.stdin {
+                                let input = input_buffer.push(chunk);
+                                Event::log(&mut cast_file, elapsed, Event::INPUT, &input)?;
                             }
                         }
+                        if marker {
+                            Event::log(&mut cast_file, elapsed, Event::MARKER, "")?;
+                            data = &data[chunk.len() + 1..];
+                        } else {
+                            data = &[];
+                        }
                     }
                 }
+                Message::Stdout(data) => {
+                    let elapsed = clock.tick();
+                    tty.write_all(&data)?;
+                    let output = output_buffer.push(&data);
+                    Event::log(&mut cast_file, elapsed, Event::OUTPUT, &output)?;
+                }
+                Message::Resized(size) => {
+                    if size.rows == current_size.rows && size.cols == current_size.cols {
+                        continue;
+                    }
+                    current_size = size;
+                    pair.master.resize(size)?;
+                    let elapsed = clock.tick();
+                    Event::log(
+                        &mut cast_file,
+                        elapsed,
+                        Event::RESIZE,
+                        &format!("{}x{}", size.cols, size.rows),
+                    )?;
+                }
                 Message::Terminated(status) => {
                     child_status.replace(status);
-                    break;
                 }
             }
         }
 
+        if let Some(status) = &child_status {
+            let elapsed = clock.tick();
+            Event::log(
+                &mut cast_file,
+                elapsed,
+                Event::EXIT,
+                &status.exit_code().to_string(),
+            )?;
+        }
+
         tty.set_cooked()?;
         eprintln!("Child status: {:?}", child_status);
         cast_file.flush()?;
@@ -474,56 +776,173 @@ impl RecordCommand {
 
 #[derive(Debug, StructOpt, Clone)]
 pub struct PlayCommand {
+    /// Play back at this multiple of the recorded speed
+    #[structopt(long = "speed", default_value = "1")]
+    speed: f32,
+
+    /// Limit the idle time between events to at most this many
+    /// seconds, overriding the limit specified in the recording
+    #[structopt(long = "idle-time-limit")]
+    idle_time_limit: Option<f32>,
+
+    /// Feed the recording through a terminal model and render its
+    /// screen, rather than writing the recording directly to the
+    /// terminal.  This allows playing recordings that are larger
+    /// than the current terminal, which are cropped to fit.
+    /// Press `s` to switch between cropping and scaling.
+    #[structopt(long = "headless")]
+    headless: bool,
+
+    /// When playing headless, initially scale the recording down to
+    /// fit the current terminal rather than cropping it
+    #[structopt(long = "scale", requires = "headless")]
+    scale: bool,
+
+    /// Don't play the recording; print the text of its final
+    /// screen to stdout instead
+    #[structopt(long = "dump", conflicts_with = "headless")]
+    dump: bool,
+
     cast_file: PathBuf,
 }
 
 impl PlayCommand {
-    pub fn run(&self) -> anyhow::Result<()> {
-        let mut cast_file = BufReader::new(
-            std::fs::File::open(&self.cast_file)
-                .with_context(|| format!("reading cast file {}", self.cast_file.display()))?,
-        );
-        let mut header_line = String::new();
-        cast_file
-            .read_line(&mut header_line)
-            .context("reading Header line")?;
+    pub fn run(&self, config: ConfigHandle) -> anyhow::Result<()> {
+        let recording = Recording::load(&self.cast_file, self.idle_time_limit)?;
 
-        let header: Header = serde_json::from_str(&header_line).context("parsing Header")?;
+        if self.dump {
+            print!("{}", player::final_screen_text(&recording, &config));
+            return Ok(());
+        }
 
         let mut tty = Tty::new()?;
         let size = tty.get_size()?;
-        if u32::from(size.cols) < header.width || u32::from(size.rows) < header.height {
+        if !self.headless
+            && (u32::from(size.cols) < recording.header.width
+                || u32::from(size.rows) < recording.header.height)
+        {
             anyhow::bail!(
                 "{} was recorded with width={} and height={}
                  but the current screen dimensions {}x{} are
-                 too small to display it",
+                 too small to display it.  Use --headless to
+                 play it back cropped or scaled to the current size",
                 self.cast_file.display(),
-                header.width,
-                header.height,
+                recording.header.width,
+                recording.header.height,
                 size.cols,
                 size.rows
             );
         }
 
+        let (tx, rx) = channel();
+        {
+            let mut stdin = tty.reader()?;
+            let tx = tx.clone();
+            std::thread::spawn(move || -> anyhow::Result<()> {
+                let mut buf = [0u8; 8192];
+                loop {
+                    let size = stdin.read(&mut buf)?;
+                    if size == 0 {
+                        break;
+                    }
+                    tx.send(Message::Stdin(buf[0..size].to_vec()))?;
+                }
+                Ok(())
+            });
+        }
+        tty.watch_resizes(tx)?;
+
         tty.set_raw()?;
 
-        let start = Instant::now();
+        if self.headless {
+            let fit = if self.scale { Fit::Scale } else { Fit::Crop };
+            let sink = HeadlessSink::new(tty, &recording, &config, fit)?;
+            Player::new(recording, sink, self.speed).run(rx)
+        } else {
+            Player::new(recording, DirectSink::new(tty), self.speed).run(rx)
+        }
+    }
+}
 
-        for line in cast_file.lines() {
-            let line = line?;
-            let event: Event = serde_json::from_str(&line)?;
-            if event.1 != "o" {
-                continue;
-            }
-            let target = start + Duration::from_secs_f32(event.0);
-            let duration = target.saturating_duration_since(Instant::now());
-            std::thread::sleep(duration);
+#[cfg(test)]
+mod test {
+    use super::*;
 
-            tty.write_all(&event.2.as_bytes())?;
-        }
+    #[test]
+    fn marker_key() {
+        assert_eq!(parse_marker_key("CTRL-]"), Ok(0x1d));
+        assert_eq!(parse_marker_key("ctrl-a"), Ok(0x01));
+        assert_eq!(parse_marker_key("CTRL-@"), Ok(0));
+        assert_eq!(parse_marker_key("m"), Ok(b'm'));
+        assert!(parse_marker_key("CTRL-").is_err());
+        assert!(parse_marker_key("CTRL-ab").is_err());
+        assert!(parse_marker_key("CTRL-1").is_err());
+        assert!(parse_marker_key("ab").is_err());
+        assert!(parse_marker_key("é").is_err());
+        assert!(parse_marker_key("").is_err());
+    }
 
-        tty.set_cooked()?;
+    #[test]
+    fn resize() {
+        assert_eq!(parse_resize("80x24"), Some((80, 24)));
+        assert_eq!(parse_resize("80x"), None);
+        assert_eq!(parse_resize("80"), None);
+        assert_eq!(parse_resize("x24"), None);
+        assert_eq!(parse_resize("80x24x1"), None);
+        assert_eq!(parse_resize("-1x24"), None);
+    }
 
-        Ok(())
+    #[test]
+    fn utf8_buffer() {
+        let mut buf = Utf8Buffer::default();
+        assert_eq!(buf.push(b"hello"), "hello");
+
+        // A sequence split across reads is held back until complete
+        let euro = "€".as_bytes();
+        assert_eq!(buf.push(&[b'a', euro[0]]), "a");
+        assert_eq!(buf.push(&euro[1..2]), "");
+        assert_eq!(buf.push(&[euro[2], b'b']), "€b");
+
+        // Invalid sequences are skipped
+        assert_eq!(buf.push(b"x\xffy"), "x");
+        assert_eq!(buf.push(b""), "y");
+    }
+
+    #[test]
+    fn existing_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.cast");
+        std::fs::write(
+            &path,
+            "{\"version\":2,\"width\":80,\"height\":24,\"title\":\"demo\"}\n\
+             [0.5,\"o\",\"a\"]\n\
+             [1.0,\"r\",\"100x30\"]\n\
+             \n\
+             [2.5,\"m\",\"\"]\n",
+        )
+        .unwrap();
+
+        let (header, last_time, size) = read_existing_recording(&path).unwrap();
+        assert_eq!(header.width, 80);
+        assert_eq!(header.height, 24);
+        assert_eq!(header.title.as_deref(), Some("demo"));
+        assert_eq!(last_time, 2.5);
+        assert_eq!(size, (100, 30));
+
+        // Just the header
+        std::fs::write(&path, "{\"version\":2,\"width\":80,\"height\":24}\n").unwrap();
+        let (_, last_time, size) = read_existing_recording(&path).unwrap();
+        assert_eq!(last_time, 0.);
+        assert_eq!(size, (80, 24));
+
+        std::fs::write(
+            &path,
+            "{\"version\":2,\"width\":80,\"height\":24}\n[0.5,\"o\"\n",
+        )
+        .unwrap();
+        let err = read_existing_recording(&path).unwrap_err();
+        assert!(format!("{:#}", err).contains("line 2"), "{:#}", err);
+
+        assert!(read_existing_recording(&dir.path().join("missing.cast")).is_err());
     }
 }
@@ -0,0 +1,815 @@
+//! Interactive playback of asciicast recordings.
+//!
+//! The recording is loaded into memory so that playback can be paused,
+//! sped up, stepped and seeked.  Events are either written directly to
+//! the controlling terminal, or fed through a headless terminal model
+//! whose screen is then rendered into the controlling terminal, which
+//! allows playing back a recording at a different size than it was
+//! recorded.
+use super::{Event, Header, Message, Tty};
+use anyhow::Context;
+use config::{ConfigHandle, TermConfig};
+use portable_pty::PtySize;
+use std::collections::vec_deque;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termwiz::caps::Capabilities;
+use termwiz::cell::{AttributeChange, Cell, CellAttributes};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, InputParser, KeyCode, KeyEvent, Modifiers};
+use termwiz::render::terminfo::TerminfoRenderer;
+use termwiz::render::RenderTty;
+use termwiz::surface::{Change, CursorVisibility, Line, Position, Surface};
+use wezterm_term::{Terminal, TerminalSize, VisibleRowIndex};
+
+/// How far the arrow keys seek, in seconds
+const SEEK_STEP: f32 = 5.0;
+const MIN_SPEED: f32 = 1.0 / 64.0;
+const MAX_SPEED: f32 = 64.0;
+
+/// A recording that has been loaded into memory
+pub struct Recording {
+    pub header: Header,
+    pub events: Vec<Event>,
+}
+
+impl Recording {
+    /// Loads a recording from `path`.  If `idle_time_limit` (or the
+    /// limit specified in the header) is set, the gaps between events
+    /// are compressed so that they are no longer than that limit.
+    pub fn load(path: &Path, idle_time_limit: Option<f32>) -> anyhow::Result<Self> {
+        let mut file = BufReader::new(
+            std::fs::File::open(path)
+                .with_context(|| format!("reading cast file {}", path.display()))?,
+        );
+        let mut header_line = String::new();
+        file.read_line(&mut header_line)
+            .context("reading Header line")?;
+        let header: Header = serde_json::from_str(&header_line).context("parsing Header")?;
+
+        let idle_time_limit = idle_time_limit.or(header.idle_time_limit);
+        let mut events = vec![];
+        let mut last_recorded = 0.;
+        let mut elapsed = 0.;
+
+        for (idx, line) in file.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut event: Event = serde_json::from_str(&line)
+                .with_context(|| format!("parsing event on line {}", idx + 2))?;
+
+            let mut delta = (event.0 - last_recorded).max(0.);
+            last_recorded = event.0;
+            if let Some(limit) = idle_time_limit {
+                delta = delta.min(limit);
+            }
+            elapsed += delta;
+            event.0 = elapsed;
+            events.push(event);
+        }
+
+        Ok(Self { header, events })
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.events.last().map(|e| e.0).unwrap_or(0.)
+    }
+
+    fn next_marker(&self, position: f32) -> Option<f32> {
+        self.events
+            .iter()
+            .find(|e| e.1 == Event::MARKER && e.0 > position)
+            .map(|e| e.0)
+    }
+
+    fn prev_marker(&self, position: f32) -> Option<f32> {
+        self.events
+            .iter()
+            .rev()
+            .find(|e| e.1 == Event::MARKER && e.0 < position)
+            .map(|e| e.0)
+    }
+
+    /// Returns the size that the recorded terminal had initially
+    fn initial_size(&self) -> TerminalSize {
+        TerminalSize {
+            physical_rows: self.header.height as usize,
+            physical_cols: self.header.width as usize,
+            pixel_width: 0,
+            pixel_height: 0,
+        }
+    }
+}
+
+fn new_terminal(size: TerminalSize, config: &ConfigHandle) -> Terminal {
+    Terminal::new(
+        size,
+        Arc::new(TermConfig::with_config(config.clone())),
+        "WezTerm",
+        config::wezterm_version(),
+        Box::new(std::io::sink()),
+    )
+}
+
+/// Returns the lines that are on the visible screen of `terminal`
+fn visible_lines(terminal: &Terminal) -> vec_deque::Iter<'_, Line> {
+    let screen = terminal.screen();
+    let range = screen.phys_range(&(0..screen.physical_rows as VisibleRowIndex));
+    screen.lines.range(range)
+}
+
+/// Applies an event from the recording to a terminal model
+fn apply_to_terminal(terminal: &mut Terminal, event: &Event) {
+    if event.1 == Event::OUTPUT {
+        terminal.advance_bytes(&event.2);
+    } else if event.1 == Event::RESIZE {
+        if let Some((cols, rows)) = super::parse_resize(&event.2) {
+            terminal.resize(rows as usize, cols as usize, 0, 0);
+        }
+    }
+}
+
+/// Plays the whole recording into a terminal model and returns the
+/// text of its final screen, with trailing whitespace removed
+pub fn final_screen_text(recording: &Recording, config: &ConfigHandle) -> String {
+    let mut terminal = new_terminal(recording.initial_size(), config);
+    for event in &recording.events {
+        apply_to_terminal(&mut terminal, event);
+    }
+
+    let mut lines: Vec<String> = visible_lines(&terminal)
+        .map(|line| line.as_str().trim_end().to_string())
+        .collect();
+    while lines.last().map(String::is_empty).unwrap_or(false) {
+        lines.pop();
+    }
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Describes the state of the player, for display in the status line
+pub struct Status {
+    position: f32,
+    duration: f32,
+    speed: f32,
+    paused: bool,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}x {}/{}  \
+             space=pause  +/-=speed  .=step  [/]=marker  left/right=seek  s=scale  q=quit",
+            if self.paused { "||" } else { ">" },
+            self.speed,
+            format_time(self.position),
+            format_time(self.duration)
+        )
+    }
+}
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.);
+    let minutes = (seconds / 60.).floor();
+    format!("{:02}:{:04.1}", minutes, seconds - minutes * 60.)
+}
+
+/// Something that the events of a recording can be played into
+pub trait Sink {
+    /// Discard the current state, so that the recording can be
+    /// replayed from its start
+    fn reset(&mut self) -> anyhow::Result<()>;
+    /// Apply an event from the recording
+    fn apply(&mut self, event: &Event) -> anyhow::Result<()>;
+    /// Update the display after a batch of events has been applied
+    fn present(&mut self, status: &Status) -> anyhow::Result<()>;
+    /// The controlling terminal was resized
+    fn resized(&mut self, size: PtySize) -> anyhow::Result<()>;
+    /// Switch between scaling and cropping a screen that is
+    /// larger than the controlling terminal
+    fn toggle_fit(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the recorded output directly to the controlling terminal
+pub struct DirectSink {
+    tty: Tty,
+}
+
+impl DirectSink {
+    pub fn new(tty: Tty) -> Self {
+        Self { tty }
+    }
+}
+
+impl Sink for DirectSink {
+    fn reset(&mut self) -> anyhow::Result<()> {
+        // RIS: full reset, which also clears the screen
+        self.tty.write_all(b"\x1bc")
+    }
+
+    fn apply(&mut self, event: &Event) -> anyhow::Result<()> {
+        if event.1 == Event::OUTPUT {
+            self.tty.write_all(event.2.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn present(&mut self, _status: &Status) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn resized(&mut self, _size: PtySize) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Accumulates the output of the renderer so that it can be
+/// written to the tty in a single write
+struct RenderBuffer {
+    buf: Vec<u8>,
+    size: PtySize,
+}
+
+impl std::io::Write for RenderBuffer {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl RenderTty for RenderBuffer {
+    fn get_size_in_cells(&mut self) -> termwiz::Result<(usize, usize)> {
+        Ok((self.size.cols as usize, self.size.rows as usize))
+    }
+}
+
+/// How a screen that is larger than the controlling terminal is fit into it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    /// Show the portion of the screen around the cursor
+    Crop,
+    /// Shrink the whole screen by skipping rows and columns
+    Scale,
+}
+
+/// Returns the index in a dimension of `from` cells that is displayed
+/// at `idx` when that dimension is shrunk to `to` cells
+fn scaled_index(idx: usize, from: usize, to: usize) -> usize {
+    if to >= from {
+        idx
+    } else {
+        idx * from / to
+    }
+}
+
+/// Feeds the recording through a terminal model and renders its
+/// screen into the controlling terminal.  If the recording is larger
+/// than the controlling terminal then it is either scaled down or
+/// cropped, following the cursor, depending on `fit`.  The bottom row
+/// is used to show the status of the player.
+pub struct HeadlessSink {
+    tty: Tty,
+    size: PtySize,
+    fit: Fit,
+    terminal: Terminal,
+    config: ConfigHandle,
+    initial_size: TerminalSize,
+    /// What we believe to be currently displayed on the tty
+    screen: Surface,
+    renderer: TerminfoRenderer,
+}
+
+impl HeadlessSink {
+    pub fn new(
+        tty: Tty,
+        recording: &Recording,
+        config: &ConfigHandle,
+        fit: Fit,
+    ) -> anyhow::Result<Self> {
+        let size = tty.get_size()?;
+        let caps = Capabilities::new_from_env()?;
+        let mut sink = Self {
+            tty,
+            size,
+            fit,
+            terminal: new_terminal(recording.initial_size(), config),
+            config: config.clone(),
+            initial_size: recording.initial_size(),
+            screen: Surface::new(size.cols as usize, size.rows as usize),
+            renderer: TerminfoRenderer::new(caps),
+        };
+        sink.clear()?;
+        Ok(sink)
+    }
+
+    /// Clear the tty and forget what we had previously rendered to it
+    fn clear(&mut self) -> anyhow::Result<()> {
+        self.screen = Surface::new(self.size.cols as usize, self.size.rows as usize);
+        self.render(&[Change::ClearScreen(ColorAttribute::Default)])
+    }
+
+    fn render(&mut self, changes: &[Change]) -> anyhow::Result<()> {
+        let mut out = RenderBuffer {
+            buf: vec![],
+            size: self.size,
+        };
+        self.renderer.render_to(changes, &mut out)?;
+        self.tty.write_all(&out.buf)
+    }
+
+    /// Computes the surface that should be displayed for the current
+    /// state of the terminal model
+    fn build_surface(&self, status: &Status) -> Surface {
+        let cols = self.size.cols as usize;
+        let rows = self.size.rows as usize;
+        let avail_rows = rows.saturating_sub(1);
+        let mut surface = Surface::new(cols, rows);
+
+        let cursor = self.terminal.cursor_pos();
+
+        let (cursor_x, cursor_y, cursor_visible) = match self.fit {
+            Fit::Crop => self.draw_cropped(&mut surface, cols, avail_rows),
+            Fit::Scale => self.draw_scaled(&mut surface, cols, avail_rows),
+        };
+
+        surface.add_changes(vec![
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(rows.saturating_sub(1)),
+            },
+            Change::AllAttributes(CellAttributes::default()),
+            AttributeChange::Reverse(true).into(),
+            Change::Text(config::lua::truncate_right(
+                &status.to_string(),
+                cols.saturating_sub(1),
+            )),
+            AttributeChange::Reverse(false).into(),
+        ]);
+
+        surface.add_changes(vec![
+            Change::CursorPosition {
+                x: Position::Absolute(cursor_x),
+                y: Position::Absolute(cursor_y),
+            },
+            Change::CursorVisibility(if cursor_visible {
+                cursor.visibility
+            } else {
+                CursorVisibility::Hidden
+            }),
+        ]);
+
+        surface
+    }
+
+    /// Draws the portion of the screen that fits into `cols` by `rows`,
+    /// scrolled so that the cursor remains visible.
+    /// Returns the position of the cursor on the surface and whether
+    /// it is within the displayed portion.
+    fn draw_cropped(
+        &self,
+        surface: &mut Surface,
+        cols: usize,
+        rows: usize,
+    ) -> (usize, usize, bool) {
+        let cursor = self.terminal.cursor_pos();
+        let cursor_row = cursor.y.max(0) as usize;
+
+        // Scroll the viewport so that the cursor remains visible
+        let left = (cursor.x + 1).saturating_sub(cols);
+        let top = (cursor_row + 1).saturating_sub(rows);
+
+        for (row, line) in visible_lines(&self.terminal)
+            .skip(top)
+            .take(rows)
+            .enumerate()
+        {
+            surface.add_change(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(row),
+            });
+            let mut attr = CellAttributes::default();
+            surface.add_change(Change::AllAttributes(attr.clone()));
+            for (idx, cell) in line.visible_cells() {
+                if idx < left {
+                    continue;
+                }
+                if idx + cell.width() > left + cols {
+                    break;
+                }
+                if *cell.attrs() != attr {
+                    attr = cell.attrs().clone();
+                    surface.add_change(Change::AllAttributes(attr.clone()));
+                }
+                surface.add_change(cell.str());
+            }
+        }
+
+        let visible = cursor.x >= left
+            && cursor.x < left + cols
+            && cursor_row >= top
+            && cursor_row < top + rows;
+        (
+            cursor.x.saturating_sub(left),
+            cursor_row.saturating_sub(top),
+            visible,
+        )
+    }
+
+    /// Draws the whole screen into `cols` by `rows`, skipping rows and
+    /// columns evenly when the screen is larger than that.
+    /// Returns the position of the cursor on the surface and whether
+    /// it is visible.
+    fn draw_scaled(&self, surface: &mut Surface, cols: usize, rows: usize) -> (usize, usize, bool) {
+        let screen = self.terminal.screen();
+        let src_cols = screen.physical_cols;
+        let src_rows = screen.physical_rows;
+        let out_cols = src_cols.min(cols);
+        let out_rows = src_rows.min(rows);
+        let lines: Vec<&Line> = visible_lines(&self.terminal).collect();
+        let blank = Cell::default();
+
+        for row in 0..out_rows {
+            let line = match lines.get(scaled_index(row, src_rows, out_rows)) {
+                Some(line) => line,
+                None => break,
+            };
+            surface.add_change(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(row),
+            });
+            let mut attr = CellAttributes::default();
+            surface.add_change(Change::AllAttributes(attr.clone()));
+            let mut col = 0;
+            while col < out_cols {
+                let mut cell = line
+                    .cells()
+                    .get(scaled_index(col, src_cols, out_cols))
+                    .unwrap_or(&blank);
+                // A wide character needs room for all of its columns
+                if col + cell.width() > out_cols {
+                    cell = &blank;
+                }
+                if *cell.attrs() != attr {
+                    attr = cell.attrs().clone();
+                    surface.add_change(Change::AllAttributes(attr.clone()));
+                }
+                surface.add_change(cell.str());
+                col += cell.width().max(1);
+            }
+        }
+
+        let cursor = self.terminal.cursor_pos();
+        let cursor_row = cursor.y.max(0) as usize;
+        (
+            cursor.x * out_cols / src_cols.max(1),
+            cursor_row * out_rows / src_rows.max(1),
+            out_cols > 0 && out_rows > 0,
+        )
+    }
+}
+
+impl Sink for HeadlessSink {
+    fn reset(&mut self) -> anyhow::Result<()> {
+        self.terminal = new_terminal(self.initial_size, &self.config);
+        Ok(())
+    }
+
+    fn apply(&mut self, event: &Event) -> anyhow::Result<()> {
+        apply_to_terminal(&mut self.terminal, event);
+        Ok(())
+    }
+
+    fn present(&mut self, status: &Status) -> anyhow::Result<()> {
+        let next = self.build_surface(status);
+        let mut changes = self.screen.diff_screens(&next);
+        let (x, y) = next.cursor_position();
+        changes.push(Change::CursorPosition {
+            x: Position::Absolute(x),
+            y: Position::Absolute(y),
+        });
+        changes.push(Change::CursorVisibility(next.cursor_visibility()));
+        self.screen.add_changes(changes.clone());
+        self.render(&changes)
+    }
+
+    fn resized(&mut self, size: PtySize) -> anyhow::Result<()> {
+        self.size = size;
+        self.clear()
+    }
+
+    fn toggle_fit(&mut self) -> anyhow::Result<()> {
+        self.fit = match self.fit {
+            Fit::Crop => Fit::Scale,
+            Fit::Scale => Fit::Crop,
+        };
+        self.clear()
+    }
+}
+
+impl Drop for HeadlessSink {
+    fn drop(&mut self) {
+        // Leave the cursor below the status line, so that whatever
+        // is printed next doesn't overwrite the final screen
+        let _ = self.render(&[
+            Change::AllAttributes(CellAttributes::default()),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute((self.size.rows as usize).saturating_sub(1)),
+            },
+            Change::CursorVisibility(CursorVisibility::Visible),
+            Change::Text("\r\n".to_string()),
+        ]);
+    }
+}
+
+/// The actions that can be triggered from the keyboard during playback
+enum Action {
+    TogglePause,
+    Step,
+    Faster,
+    Slower,
+    NextMarker,
+    PrevMarker,
+    Seek(f32),
+    ToggleFit,
+    Quit,
+}
+
+fn key_to_action(event: InputEvent) -> Option<Action> {
+    match event {
+        InputEvent::Key(KeyEvent { key, modifiers }) => match (key, modifiers) {
+            (KeyCode::Char(' '), _) => Some(Action::TogglePause),
+            (KeyCode::Char('.'), _) => Some(Action::Step),
+            (KeyCode::Char('+'), _) | (KeyCode::Char('='), _) => Some(Action::Faster),
+            (KeyCode::Char('-'), _) => Some(Action::Slower),
+            (KeyCode::Char(']'), _) => Some(Action::NextMarker),
+            (KeyCode::Char('['), _) => Some(Action::PrevMarker),
+            (KeyCode::RightArrow, _) => Some(Action::Seek(SEEK_STEP)),
+            (KeyCode::LeftArrow, _) => Some(Action::Seek(-SEEK_STEP)),
+            (KeyCode::Char('s'), _) => Some(Action::ToggleFit),
+            (KeyCode::Char('q'), _) | (KeyCode::Escape, _) => Some(Action::Quit),
+            (KeyCode::Char('c'), Modifiers::CTRL) => Some(Action::Quit),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub struct Player<S> {
+    recording: Recording,
+    sink: S,
+    /// The index of the next event to be applied
+    next_event: usize,
+    /// How far into the recording we have played, in seconds
+    position: f32,
+    speed: f32,
+    paused: bool,
+}
+
+impl<S: Sink> Player<S> {
+    pub fn new(recording: Recording, sink: S, speed: f32) -> Self {
+        Self {
+            recording,
+            sink,
+            next_event: 0,
+            position: 0.,
+            speed: speed.clamp(MIN_SPEED, MAX_SPEED),
+            paused: false,
+        }
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            position: self.position,
+            duration: self.recording.duration(),
+            speed: self.speed,
+            paused: self.paused,
+        }
+    }
+
+    fn present(&mut self) -> anyhow::Result<()> {
+        let status = self.status();
+        self.sink.present(&status)
+    }
+
+    /// Applies the events up to and including `position`.
+    /// Returns true if any events were applied.
+    fn apply_until(&mut self, position: f32) -> anyhow::Result<bool> {
+        let start = self.next_event;
+        while let Some(event) = self.recording.events.get(self.next_event) {
+            if event.0 > position {
+                break;
+            }
+            self.sink.apply(event)?;
+            self.next_event += 1;
+        }
+        Ok(self.next_event != start)
+    }
+
+    fn seek(&mut self, target: f32) -> anyhow::Result<()> {
+        let target = target.max(0.).min(self.recording.duration());
+        if target < self.position {
+            self.sink.reset()?;
+            self.next_event = 0;
+        }
+        self.position = target;
+        self.apply_until(target)?;
+        self.present()
+    }
+
+    /// Applies events up to and including the next output event
+    fn step(&mut self) -> anyhow::Result<()> {
+        while let Some(event) = self.recording.events.get(self.next_event) {
+            self.position = event.0;
+            self.sink.apply(event)?;
+            self.next_event += 1;
+            if event.1 == Event::OUTPUT {
+                break;
+            }
+        }
+        self.present()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next_event >= self.recording.events.len()
+    }
+
+    /// Plays the recording, handling input and resize messages from `rx`,
+    /// until the recording ends or the user quits
+    pub fn run(&mut self, rx: Receiver<Message>) -> anyhow::Result<()> {
+        let mut parser = InputParser::new();
+        let mut last = Instant::now();
+
+        self.present()?;
+
+        loop {
+            let now = Instant::now();
+            if !self.paused {
+                self.position += now.duration_since(last).as_secs_f32() * self.speed;
+            }
+            last = now;
+
+            if self.apply_until(self.position)? {
+                self.present()?;
+            }
+
+            if self.is_finished() && !self.paused {
+                break;
+            }
+
+            let msg = if self.paused {
+                rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                let next = self.recording.events[self.next_event].0;
+                let wait = ((next - self.position) / self.speed).max(0.);
+                rx.recv_timeout(Duration::from_secs_f32(wait))
+            };
+
+            match msg {
+                Ok(Message::Stdin(data)) => {
+                    for event in parser.parse_as_vec(&data) {
+                        match key_to_action(event) {
+                            Some(Action::TogglePause) => {
+                                self.paused = !self.paused;
+                                self.present()?;
+                            }
+                            Some(Action::Step) => {
+                                self.paused = true;
+                                self.step()?;
+                            }
+                            Some(Action::Faster) => {
+                                self.speed = (self.speed * 2.).min(MAX_SPEED);
+                                self.present()?;
+                            }
+                            Some(Action::Slower) => {
+                                self.speed = (self.speed / 2.).max(MIN_SPEED);
+                                self.present()?;
+                            }
+                            Some(Action::NextMarker) => {
+                                if let Some(marker) = self.recording.next_marker(self.position) {
+                                    self.seek(marker)?;
+                                }
+                            }
+                            Some(Action::PrevMarker) => {
+                                let target =
+                                    self.recording.prev_marker(self.position).unwrap_or(0.);
+                                self.seek(target)?;
+                            }
+                            Some(Action::Seek(delta)) => {
+                                self.seek(self.position + delta)?;
+                            }
+                            Some(Action::ToggleFit) => {
+                                self.sink.toggle_fit()?;
+                                self.present()?;
+                            }
+                            Some(Action::Quit) => return Ok(()),
+                            None => {}
+                        }
+                    }
+                }
+                Ok(Message::Resized(size)) => {
+                    self.sink.resized(size)?;
+                    self.present()?;
+                }
+                Ok(Message::Stdout(_)) | Ok(Message::Terminated(_)) => {}
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn recording(events: &[(f32, &str, &str)]) -> Recording {
+        Recording {
+            header: Header {
+                version: 2,
+                width: 10,
+                height: 3,
+                ..Default::default()
+            },
+            events: events
+                .iter()
+                .map(|(t, code, data)| Event(*t, code.to_string(), data.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn markers() {
+        let rec = recording(&[
+            (0.5, "o", "a"),
+            (1.0, "m", ""),
+            (2.0, "o", "b"),
+            (3.0, "m", "second"),
+        ]);
+        assert_eq!(rec.next_marker(0.), Some(1.0));
+        assert_eq!(rec.next_marker(1.0), Some(3.0));
+        assert_eq!(rec.next_marker(3.0), None);
+        assert_eq!(rec.prev_marker(3.0), Some(1.0));
+        assert_eq!(rec.prev_marker(1.0), None);
+        assert_eq!(rec.duration(), 3.0);
+    }
+
+    #[test]
+    fn idle_time_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.cast");
+        std::fs::write(
+            &path,
+            "{\"version\":2,\"width\":10,\"height\":3}\n\
+             [1.0,\"o\",\"a\"]\n\
+             [10.0,\"o\",\"b\"]\n\
+             [10.5,\"o\",\"c\"]\n",
+        )
+        .unwrap();
+
+        let rec = Recording::load(&path, Some(2.0)).unwrap();
+        let times: Vec<f32> = rec.events.iter().map(|e| e.0).collect();
+        assert_eq!(times, vec![1.0, 3.0, 3.5]);
+
+        let rec = Recording::load(&path, None).unwrap();
+        let times: Vec<f32> = rec.events.iter().map(|e| e.0).collect();
+        assert_eq!(times, vec![1.0, 10.0, 10.5]);
+    }
+
+    #[test]
+    fn scaling() {
+        // Nothing to do when there is enough room
+        assert_eq!(scaled_index(5, 10, 20), 5);
+        assert_eq!(scaled_index(5, 10, 10), 5);
+        // Shrinking by half shows every other cell
+        let idx: Vec<usize> = (0..5).map(|i| scaled_index(i, 10, 5)).collect();
+        assert_eq!(idx, vec![0, 2, 4, 6, 8]);
+        // The displayed cells always lie within the source
+        let idx: Vec<usize> = (0..24).map(|i| scaled_index(i, 80, 24)).collect();
+        assert_eq!(idx[0], 0);
+        assert!(idx.windows(2).all(|w| w[0] < w[1]));
+        assert!(*idx.last().unwrap() < 80);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_time(0.), "00:00.0");
+        assert_eq!(format_time(75.25), "01:15.2");
+    }
+}
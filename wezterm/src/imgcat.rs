@@ -0,0 +1,946 @@
+//! Implements `wezterm imgcat`, which outputs images to the terminal
+//! using the iTerm2, kitty or sixel image protocols.
+use crate::asciicast::Tty;
+use anyhow::{anyhow, Context};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::imageops::FilterType;
+use image::{AnimationDecoder, ImageFormat};
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+use termwiz::caps::Capabilities;
+use termwiz::escape::apc::{
+    KittyAnimationState, KittyFrameCompositionMode, KittyImage, KittyImageAnimation,
+    KittyImageCompression, KittyImageData, KittyImageFormat, KittyImageFrame, KittyImagePlacement,
+    KittyImageTransmit, KittyImageVerbosity,
+};
+use termwiz::escape::csi::{Cursor, CSI};
+use termwiz::escape::esc::{Esc, EscCode};
+use termwiz::escape::osc::{
+    ITermDimension, ITermFileData, ITermProprietary, OperatingSystemCommand,
+};
+use termwiz::escape::OneBased;
+use termwiz::render::graphics::{kitty_sequences, RgbaImage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    Auto,
+    ITerm,
+    Kitty,
+    Sixel,
+}
+
+impl FromStr for ImageProtocol {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "iterm" | "iterm2" => Ok(Self::ITerm),
+            "kitty" => Ok(Self::Kitty),
+            "sixel" => Ok(Self::Sixel),
+            _ => Err(anyhow!(
+                "invalid protocol {}; expected one of auto, iterm, kitty or sixel",
+                s
+            )),
+        }
+    }
+}
+
+impl ImageProtocol {
+    /// Picks a protocol based on the environment
+    fn detect() -> Self {
+        // These are passed through by tmux and screen, which replace
+        // TERM and TERM_PROGRAM with their own values
+        if std::env::var_os("KITTY_WINDOW_ID").is_some() {
+            return Self::Kitty;
+        }
+        if std::env::var_os("WEZTERM_PANE").is_some()
+            || std::env::var("LC_TERMINAL")
+                .map(|t| t == "iTerm2")
+                .unwrap_or(false)
+        {
+            return Self::ITerm;
+        }
+
+        match Capabilities::new_from_env() {
+            Ok(caps) if caps.kitty_graphics() => Self::Kitty,
+            Ok(caps) if caps.iterm2_image() => Self::ITerm,
+            Ok(caps) if caps.sixel() => Self::Sixel,
+            // The iTerm2 protocol is the most widely supported
+            _ => Self::ITerm,
+        }
+    }
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct ImgCatCommand {
+    /// Specify the display width; defaults to "auto" which automatically selects
+    /// an appropriate size.  You may also use an integer value `N` to specify the
+    /// number of cells, or `Npx` to specify the number of pixels, or `N%` to
+    /// size relative to the terminal width.
+    #[structopt(long = "width")]
+    width: Option<ITermDimension>,
+    /// Specify the display height; defaults to "auto" which automatically selects
+    /// an appropriate size.  You may also use an integer value `N` to specify the
+    /// number of cells, or `Npx` to specify the number of pixels, or `N%` to
+    /// size relative to the terminal height.
+    #[structopt(long = "height")]
+    height: Option<ITermDimension>,
+    /// Do not respect the aspect ratio.  The default is to respect the aspect
+    /// ratio
+    #[structopt(long = "no-preserve-aspect-ratio")]
+    no_preserve_aspect_ratio: bool,
+    /// The image protocol to use; one of "iterm", "kitty", "sixel" or
+    /// "auto".  The default, "auto", selects a protocol based on the
+    /// terminal that is running imgcat.
+    #[structopt(long = "protocol", default_value = "auto")]
+    protocol: ImageProtocol,
+    /// When running inside tmux, the image escape sequences are wrapped
+    /// so that tmux passes them through to the terminal.  This option
+    /// disables that wrapping.  Note that tmux 3.3 and later also require
+    /// `set -g allow-passthrough on`.
+    #[structopt(long = "no-tmux-passthrough")]
+    no_tmux_passthrough: bool,
+    /// The width, in cells, of each thumbnail when displaying a directory
+    #[structopt(long = "thumbnail-width", default_value = "16")]
+    thumbnail_width: usize,
+    /// The height, in cells, of each thumbnail when displaying a directory
+    #[structopt(long = "thumbnail-height", default_value = "8")]
+    thumbnail_height: usize,
+    /// The names of the image files to be displayed.
+    /// Directories are displayed as a grid of thumbnails of the images
+    /// that they contain.
+    /// If omitted, will attempt to read an image from stdin.
+    #[structopt(parse(from_os_str))]
+    file_names: Vec<OsString>,
+}
+
+/// The size of the terminal
+struct Geometry {
+    cols: usize,
+    rows: usize,
+    cell_width: usize,
+    cell_height: usize,
+}
+
+impl Geometry {
+    fn probe() -> Self {
+        match Tty::new().and_then(|tty| tty.get_size()) {
+            Ok(size) if size.cols > 0 && size.rows > 0 => {
+                let cols = size.cols as usize;
+                let rows = size.rows as usize;
+                // Not all terminals report their size in pixels
+                let cell_width = match size.pixel_width as usize / cols {
+                    0 => 8,
+                    n => n,
+                };
+                let cell_height = match size.pixel_height as usize / rows {
+                    0 => 16,
+                    n => n,
+                };
+                Self {
+                    cols,
+                    rows,
+                    cell_width,
+                    cell_height,
+                }
+            }
+            _ => Self {
+                cols: 80,
+                rows: 24,
+                cell_width: 8,
+                cell_height: 16,
+            },
+        }
+    }
+
+    fn pixel_width(&self) -> usize {
+        self.cols * self.cell_width
+    }
+
+    fn pixel_height(&self) -> usize {
+        self.rows * self.cell_height
+    }
+}
+
+/// Where and how large an image is to be displayed
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    pixel_width: u32,
+    pixel_height: u32,
+    cols: u32,
+    rows: u32,
+    /// true when the image is part of a thumbnail grid, in which
+    /// case the cursor must not be moved by the image
+    thumbnail: bool,
+}
+
+impl Placement {
+    fn new(pixel_width: usize, pixel_height: usize, geom: &Geometry, thumbnail: bool) -> Self {
+        let pixel_width = pixel_width.max(1);
+        let pixel_height = pixel_height.max(1);
+        Self {
+            pixel_width: pixel_width as u32,
+            pixel_height: pixel_height as u32,
+            cols: ((pixel_width + geom.cell_width - 1) / geom.cell_width) as u32,
+            rows: ((pixel_height + geom.cell_height - 1) / geom.cell_height) as u32,
+            thumbnail,
+        }
+    }
+}
+
+/// The decoded frames of an image
+struct DecodedImage {
+    format: ImageFormat,
+    frames: Vec<(image::RgbaImage, Duration)>,
+}
+
+impl DecodedImage {
+    fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let format = image::guess_format(data).context("unrecognized image format")?;
+        let frames = match format {
+            ImageFormat::Gif => GifDecoder::new(data)?.into_frames().collect_frames()?,
+            ImageFormat::Png => {
+                let decoder = PngDecoder::new(data)?;
+                if decoder.is_apng() {
+                    decoder.apng().into_frames().collect_frames()?
+                } else {
+                    vec![]
+                }
+            }
+            _ => vec![],
+        };
+
+        let frames = if frames.is_empty() {
+            vec![(image::load_from_memory(data)?.into_rgba8(), Duration::ZERO)]
+        } else {
+            frames
+                .into_iter()
+                .map(|frame| {
+                    let delay: Duration = frame.delay().into();
+                    (frame.into_buffer(), delay)
+                })
+                .collect()
+        };
+
+        Ok(Self { format, frames })
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.frames[0].0.dimensions()
+    }
+
+    fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+}
+
+/// Writes to stdout, wrapping escape sequences for tmux if required
+struct Output<W> {
+    out: W,
+    tmux: bool,
+}
+
+impl<W: Write> Output<W> {
+    fn escape(&mut self, seq: &str) -> anyhow::Result<()> {
+        if self.tmux {
+            write!(
+                self.out,
+                "\x1bPtmux;{}\x1b\\",
+                seq.replace('\x1b', "\x1b\x1b")
+            )?;
+        } else {
+            write!(self.out, "{}", seq)?;
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) -> anyhow::Result<()> {
+        write!(self.out, "{}", text)?;
+        Ok(())
+    }
+}
+
+impl ImgCatCommand {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let protocol = match self.protocol {
+            ImageProtocol::Auto => ImageProtocol::detect(),
+            protocol => protocol,
+        };
+        let mut out = Output {
+            out: std::io::stdout(),
+            tmux: std::env::var_os("TMUX").is_some() && !self.no_tmux_passthrough,
+        };
+        let geom = Geometry::probe();
+
+        if self.file_names.is_empty() {
+            let mut data = Vec::new();
+            std::io::stdin().read_to_end(&mut data)?;
+            self.show_image(protocol, data, &geom, &mut out)?;
+        }
+
+        for file_name in &self.file_names {
+            let path = Path::new(file_name);
+            if path.is_dir() {
+                self.show_directory(protocol, path, &geom, &mut out)?;
+            } else {
+                let data = std::fs::read(path)
+                    .with_context(|| anyhow!("reading image file: {:?}", file_name))?;
+                self.show_image(protocol, data, &geom, &mut out)
+                    .with_context(|| anyhow!("displaying image file: {:?}", file_name))?;
+            }
+        }
+
+        out.out.flush()?;
+        Ok(())
+    }
+
+    fn show_image(
+        &self,
+        protocol: ImageProtocol,
+        data: Vec<u8>,
+        geom: &Geometry,
+        out: &mut Output<impl Write>,
+    ) -> anyhow::Result<()> {
+        if protocol == ImageProtocol::ITerm {
+            // The terminal takes care of sizing the image
+            let osc = OperatingSystemCommand::ITermProprietary(ITermProprietary::File(Box::new(
+                ITermFileData {
+                    name: None,
+                    size: Some(data.len()),
+                    width: self.width.unwrap_or_default(),
+                    height: self.height.unwrap_or_default(),
+                    preserve_aspect_ratio: !self.no_preserve_aspect_ratio,
+                    inline: true,
+                    do_not_move_cursor: false,
+                    data,
+                },
+            )));
+            out.escape(&osc.to_string())?;
+            return out.text("\n");
+        }
+
+        let image = DecodedImage::decode(&data)?;
+        let (width, height) = image.dimensions();
+        let (pixel_width, pixel_height) = self.display_size(width, height, geom);
+        let placement = Placement::new(pixel_width, pixel_height, geom, false);
+        self.show_decoded(protocol, &data, &image, placement, out)?;
+
+        if protocol == ImageProtocol::Kitty && out.tmux {
+            // tmux doesn't know about the space occupied by the image,
+            // so the image doesn't move the cursor and we move it
+            // below the image ourselves
+            out.text(&"\n".repeat(placement.rows as usize))
+        } else {
+            out.text("\n")
+        }
+    }
+
+    fn show_decoded(
+        &self,
+        protocol: ImageProtocol,
+        data: &[u8],
+        image: &DecodedImage,
+        placement: Placement,
+        out: &mut Output<impl Write>,
+    ) -> anyhow::Result<()> {
+        match protocol {
+            ImageProtocol::Kitty => {
+                let do_not_move_cursor = placement.thumbnail || out.tmux;
+                for seq in kitty_image(data, image, placement, do_not_move_cursor) {
+                    out.escape(&seq)?;
+                }
+            }
+            ImageProtocol::Sixel => {
+                let (frame, _) = &image.frames[0];
+                let frame = if frame.dimensions() == (placement.pixel_width, placement.pixel_height)
+                {
+                    frame.clone()
+                } else {
+                    image::imageops::resize(
+                        frame,
+                        placement.pixel_width,
+                        placement.pixel_height,
+                        FilterType::CatmullRom,
+                    )
+                };
+                let (width, height) = frame.dimensions();
+                let sixel = RgbaImage {
+                    width,
+                    height,
+                    data: frame.into_raw(),
+                }
+                .to_sixel();
+                out.escape(&format!(
+                    "{}{}",
+                    sixel,
+                    Esc::Code(EscCode::StringTerminator)
+                ))?;
+            }
+            ImageProtocol::ITerm | ImageProtocol::Auto => {
+                let osc = OperatingSystemCommand::ITermProprietary(ITermProprietary::File(
+                    Box::new(ITermFileData {
+                        name: None,
+                        size: Some(data.len()),
+                        width: ITermDimension::Cells(placement.cols as i64),
+                        height: ITermDimension::Cells(placement.rows as i64),
+                        preserve_aspect_ratio: true,
+                        inline: true,
+                        do_not_move_cursor: placement.thumbnail,
+                        data: data.to_vec(),
+                    }),
+                ));
+                out.escape(&osc.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes the size in pixels at which to display an image,
+    /// following the same rules that wezterm uses for the iTerm2 protocol
+    fn display_size(&self, width: u32, height: u32, geom: &Geometry) -> (usize, usize) {
+        let aspect = width as f32 / height.max(1) as f32;
+        let requested_width = self
+            .width
+            .unwrap_or_default()
+            .to_pixels(geom.cell_width, geom.cols);
+        let requested_height = self
+            .height
+            .unwrap_or_default()
+            .to_pixels(geom.cell_height, geom.rows);
+
+        match (requested_width, requested_height) {
+            (None, None) => {
+                // Take the image's native size, but ensure that it fits
+                fit_within(
+                    width as usize,
+                    height as usize,
+                    geom.pixel_width(),
+                    geom.pixel_height(),
+                )
+            }
+            (Some(w), None) => (w, (w as f32 / aspect) as usize),
+            (None, Some(h)) => ((h as f32 * aspect) as usize, h),
+            (Some(w), Some(_)) if !self.no_preserve_aspect_ratio => {
+                (w, (w as f32 / aspect) as usize)
+            }
+            (Some(w), Some(h)) => (w, h),
+        }
+    }
+
+    /// Displays the images in a directory as a grid of thumbnails,
+    /// each labelled with its file name
+    fn show_directory(
+        &self,
+        protocol: ImageProtocol,
+        dir: &Path,
+        geom: &Geometry,
+        out: &mut Output<impl Write>,
+    ) -> anyhow::Result<()> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .with_context(|| anyhow!("reading directory: {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && ImageFormat::from_path(path).is_ok())
+            .collect();
+        paths.sort();
+
+        let thumb_cols = self.thumbnail_width.max(1);
+        let thumb_rows = self.thumbnail_height.max(1);
+        let per_row = ((geom.cols + 1) / (thumb_cols + 1)).max(1);
+
+        for row in paths.chunks(per_row) {
+            // Make room for the thumbnails, scrolling if necessary,
+            // and then return to the top of that space
+            out.text(&"\n".repeat(thumb_rows))?;
+            out.text(&CSI::Cursor(Cursor::Up(thumb_rows as u32)).to_string())?;
+
+            for (idx, path) in row.iter().enumerate() {
+                let column = OneBased::from_zero_based((idx * (thumb_cols + 1)) as u32);
+                out.text(&CSI::Cursor(Cursor::CharacterAbsolute(column)).to_string())?;
+
+                if let Err(err) =
+                    self.show_thumbnail(protocol, path, thumb_cols, thumb_rows, geom, out)
+                {
+                    log::error!("Unable to display {}: {:#}", path.display(), err);
+                }
+            }
+
+            out.text(&CSI::Cursor(Cursor::Down(thumb_rows as u32)).to_string())?;
+            for (idx, path) in row.iter().enumerate() {
+                let column = OneBased::from_zero_based((idx * (thumb_cols + 1)) as u32);
+                out.text(&CSI::Cursor(Cursor::CharacterAbsolute(column)).to_string())?;
+                let name: String = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().chars().take(thumb_cols).collect())
+                    .unwrap_or_default();
+                out.text(&name)?;
+            }
+            out.text("\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn show_thumbnail(
+        &self,
+        protocol: ImageProtocol,
+        path: &Path,
+        thumb_cols: usize,
+        thumb_rows: usize,
+        geom: &Geometry,
+        out: &mut Output<impl Write>,
+    ) -> anyhow::Result<()> {
+        let data = std::fs::read(path)?;
+        let image = DecodedImage::decode(&data)?;
+        let (width, height) = image.dimensions();
+        let (pixel_width, pixel_height) = fit_within(
+            width as usize,
+            height as usize,
+            thumb_cols * geom.cell_width,
+            thumb_rows * geom.cell_height,
+        );
+        let placement = Placement::new(pixel_width, pixel_height, geom, true);
+
+        // Not every terminal honors requests not to move the cursor,
+        // so we save and restore it around the image
+        out.text(&Esc::Code(EscCode::DecSaveCursorPosition).to_string())?;
+        self.show_decoded(protocol, &data, &image, placement, out)?;
+        out.text(&Esc::Code(EscCode::DecRestoreCursorPosition).to_string())
+    }
+}
+
+/// Scales width and height down, preserving the aspect ratio, so
+/// that they fit within max_width and max_height
+fn fit_within(width: usize, height: usize, max_width: usize, max_height: usize) -> (usize, usize) {
+    if width <= max_width && height <= max_height {
+        return (width, height);
+    }
+    let scale = (max_width as f32 / width as f32).min(max_height as f32 / height as f32);
+    (
+        (width as f32 * scale) as usize,
+        (height as f32 * scale) as usize,
+    )
+}
+
+/// Returns an id for an animated kitty image.  The id only needs
+/// to be unique enough not to collide with other images that are
+/// being displayed.
+fn kitty_image_id() -> u32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    ((std::process::id() ^ nanos) & 0x00ff_ffff).max(1)
+}
+
+/// Returns the kitty escape sequences needed to display an image.
+/// Animations are transmitted frame by frame and then set running.
+fn kitty_image(
+    data: &[u8],
+    image: &DecodedImage,
+    placement: Placement,
+    do_not_move_cursor: bool,
+) -> Vec<String> {
+    let image_id = if image.is_animated() {
+        Some(kitty_image_id())
+    } else {
+        None
+    };
+
+    let transmit = |format, payload: &[u8], width, height| KittyImageTransmit {
+        format: Some(format),
+        data: KittyImageData::Direct(base64::encode(payload)),
+        width,
+        height,
+        image_id,
+        image_number: None,
+        compression: KittyImageCompression::None,
+        more_data_follows: false,
+    };
+
+    let (first, first_delay) = &image.frames[0];
+    let first = if image.format == ImageFormat::Png && !image.is_animated() {
+        // kitty understands PNG, which is more compact than raw pixels
+        transmit(KittyImageFormat::Png, data, None, None)
+    } else {
+        let (width, height) = first.dimensions();
+        transmit(
+            KittyImageFormat::Rgba,
+            first.as_raw().as_slice(),
+            Some(width),
+            Some(height),
+        )
+    };
+
+    let mut sequences = kitty_sequences(KittyImage::TransmitDataAndDisplay {
+        transmit: first,
+        placement: KittyImagePlacement {
+            x: None,
+            y: None,
+            w: None,
+            h: None,
+            x_offset: None,
+            y_offset: None,
+            columns: Some(placement.cols),
+            rows: Some(placement.rows),
+            do_not_move_cursor,
+            placement_id: None,
+            z_index: None,
+        },
+        verbosity: KittyImageVerbosity::Quiet,
+    });
+
+    if !image.is_animated() {
+        return sequences;
+    }
+
+    for (frame, delay) in &image.frames[1..] {
+        let (width, height) = frame.dimensions();
+        sequences.extend(kitty_sequences(KittyImage::TransmitFrame {
+            transmit: transmit(
+                KittyImageFormat::Rgba,
+                frame.as_raw().as_slice(),
+                Some(width),
+                Some(height),
+            ),
+            frame: KittyImageFrame {
+                x: None,
+                y: None,
+                base_frame: None,
+                frame_number: None,
+                duration_ms: Some(delay.as_millis().max(1) as u32),
+                composition_mode: KittyFrameCompositionMode::Overwrite,
+                background_pixel: None,
+            },
+            verbosity: KittyImageVerbosity::Quiet,
+        }));
+    }
+
+    // The first frame was transmitted along with the image, so set
+    // its gap here, and then start the animation looping forever
+    sequences.extend(kitty_sequences(KittyImage::ControlAnimation {
+        animation: KittyImageAnimation {
+            image_id,
+            image_number: None,
+            state: Some(KittyAnimationState::Running),
+            frame_number: Some(1),
+            duration_ms: Some(first_delay.as_millis().max(1) as i32),
+            current_frame: None,
+            loops: Some(1),
+        },
+        verbosity: KittyImageVerbosity::Quiet,
+    }));
+
+    sequences
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::escape::parser::Parser;
+    use termwiz::escape::Action;
+
+    fn command(args: &[&str]) -> ImgCatCommand {
+        ImgCatCommand::from_iter_safe(std::iter::once("imgcat").chain(args.iter().copied()))
+            .unwrap()
+    }
+
+    fn geometry() -> Geometry {
+        Geometry {
+            cols: 80,
+            rows: 24,
+            cell_width: 8,
+            cell_height: 16,
+        }
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(width, height, image::Rgba([0xff, 0, 0, 0xff]));
+        let mut data = vec![];
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        data
+    }
+
+    /// Parses the output, omitting the string terminators that the
+    /// parser reports separately from the sequences that they end
+    fn parse(data: &[u8]) -> Vec<Action> {
+        Parser::new()
+            .parse_as_vec(data)
+            .into_iter()
+            .filter(|action| *action != Action::Esc(Esc::Code(EscCode::StringTerminator)))
+            .collect()
+    }
+
+    fn kitty_actions(sequences: &[String]) -> Vec<KittyImage> {
+        parse(sequences.concat().as_bytes())
+            .into_iter()
+            .map(|action| match action {
+                Action::KittyImage(image) => image,
+                action => panic!("unexpected {:?}", action),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn protocol() {
+        assert_eq!(
+            "auto".parse::<ImageProtocol>().unwrap(),
+            ImageProtocol::Auto
+        );
+        assert_eq!(
+            "iterm".parse::<ImageProtocol>().unwrap(),
+            ImageProtocol::ITerm
+        );
+        assert_eq!(
+            "iterm2".parse::<ImageProtocol>().unwrap(),
+            ImageProtocol::ITerm
+        );
+        assert_eq!(
+            "kitty".parse::<ImageProtocol>().unwrap(),
+            ImageProtocol::Kitty
+        );
+        assert_eq!(
+            "sixel".parse::<ImageProtocol>().unwrap(),
+            ImageProtocol::Sixel
+        );
+        assert!("png".parse::<ImageProtocol>().is_err());
+
+        assert_eq!(command(&[]).protocol, ImageProtocol::Auto);
+        assert_eq!(
+            command(&["--protocol", "sixel"]).protocol,
+            ImageProtocol::Sixel
+        );
+        assert!(ImgCatCommand::from_iter_safe(&["imgcat", "--protocol", "png"]).is_err());
+    }
+
+    #[test]
+    fn size_arguments() {
+        let cmd = command(&["--width", "10", "--height", "50%", "a.png"]);
+        assert_eq!(cmd.width, Some(ITermDimension::Cells(10)));
+        assert_eq!(cmd.height, Some(ITermDimension::Percent(50)));
+        assert_eq!(cmd.file_names, vec![OsString::from("a.png")]);
+
+        let cmd = command(&["--width", "20px", "--height", "auto"]);
+        assert_eq!(cmd.width, Some(ITermDimension::Pixels(20)));
+        assert_eq!(cmd.height, Some(ITermDimension::Automatic));
+
+        let cmd = command(&[]);
+        assert_eq!(cmd.width, None);
+        assert_eq!(cmd.thumbnail_width, 16);
+        assert_eq!(cmd.thumbnail_height, 8);
+
+        assert!(ImgCatCommand::from_iter_safe(&["imgcat", "--width", "wide"]).is_err());
+    }
+
+    #[test]
+    fn display_size() {
+        let geom = geometry();
+
+        // The native size, if it fits the 640x384 pixel terminal
+        assert_eq!(command(&[]).display_size(100, 50, &geom), (100, 50));
+        assert_eq!(command(&[]).display_size(1280, 384, &geom), (640, 192));
+
+        // One dimension follows the other
+        assert_eq!(
+            command(&["--width", "10"]).display_size(100, 50, &geom),
+            (80, 40)
+        );
+        assert_eq!(
+            command(&["--height", "4"]).display_size(100, 50, &geom),
+            (128, 64)
+        );
+        assert_eq!(
+            command(&["--width", "50%"]).display_size(100, 50, &geom),
+            (320, 160)
+        );
+
+        // Both dimensions; the width wins unless the aspect ratio
+        // need not be preserved
+        assert_eq!(
+            command(&["--width", "10", "--height", "10"]).display_size(100, 50, &geom),
+            (80, 40)
+        );
+        assert_eq!(
+            command(&[
+                "--width",
+                "10",
+                "--height",
+                "10",
+                "--no-preserve-aspect-ratio"
+            ])
+            .display_size(100, 50, &geom),
+            (80, 160)
+        );
+    }
+
+    #[test]
+    fn placement() {
+        let geom = geometry();
+        let placement = Placement::new(81, 16, &geom, false);
+        assert_eq!((placement.pixel_width, placement.pixel_height), (81, 16));
+        assert_eq!((placement.cols, placement.rows), (11, 1));
+
+        let placement = Placement::new(0, 0, &geom, true);
+        assert_eq!((placement.pixel_width, placement.pixel_height), (1, 1));
+        assert_eq!((placement.cols, placement.rows), (1, 1));
+    }
+
+    #[test]
+    fn fit() {
+        assert_eq!(fit_within(10, 10, 20, 20), (10, 10));
+        assert_eq!(fit_within(40, 10, 20, 20), (20, 5));
+        assert_eq!(fit_within(10, 40, 20, 20), (5, 20));
+    }
+
+    #[test]
+    fn tmux_passthrough() {
+        let mut out = Output {
+            out: vec![],
+            tmux: true,
+        };
+        out.escape("\x1b_Ga=T\x1b\\").unwrap();
+        out.text("\n").unwrap();
+        assert_eq!(
+            String::from_utf8(out.out).unwrap(),
+            "\x1bPtmux;\x1b\x1b_Ga=T\x1b\x1b\\\x1b\\\n"
+        );
+
+        let mut out = Output {
+            out: vec![],
+            tmux: false,
+        };
+        out.escape("\x1b_Ga=T\x1b\\").unwrap();
+        assert_eq!(String::from_utf8(out.out).unwrap(), "\x1b_Ga=T\x1b\\");
+    }
+
+    #[test]
+    fn kitty_png() {
+        let data = png(4, 2);
+        let image = DecodedImage::decode(&data).unwrap();
+        assert_eq!(image.dimensions(), (4, 2));
+        assert!(!image.is_animated());
+
+        let placement = Placement::new(16, 32, &geometry(), false);
+        let actions = kitty_actions(&kitty_image(&data, &image, placement, true));
+        assert_eq!(actions.len(), 1);
+        match &actions[0] {
+            KittyImage::TransmitDataAndDisplay {
+                transmit,
+                placement,
+                ..
+            } => {
+                // PNG data is sent as-is
+                assert_eq!(transmit.format, Some(KittyImageFormat::Png));
+                assert_eq!(transmit.data, KittyImageData::Direct(base64::encode(&data)));
+                assert_eq!(transmit.image_id, None);
+                assert_eq!((placement.columns, placement.rows), (Some(2), Some(2)));
+                assert!(placement.do_not_move_cursor);
+            }
+            action => panic!("unexpected {:?}", action),
+        }
+    }
+
+    #[test]
+    fn kitty_animation() {
+        let frame = image::RgbaImage::from_pixel(2, 1, image::Rgba([0, 0, 0xff, 0xff]));
+        let image = DecodedImage {
+            format: ImageFormat::Gif,
+            frames: vec![
+                (frame.clone(), Duration::from_millis(100)),
+                (frame, Duration::from_millis(250)),
+            ],
+        };
+        let placement = Placement::new(2, 1, &geometry(), false);
+        let actions = kitty_actions(&kitty_image(&[], &image, placement, false));
+        assert_eq!(actions.len(), 3);
+
+        let image_id = match &actions[0] {
+            KittyImage::TransmitDataAndDisplay { transmit, .. } => {
+                assert_eq!(transmit.format, Some(KittyImageFormat::Rgba));
+                assert_eq!((transmit.width, transmit.height), (Some(2), Some(1)));
+                assert!(transmit.image_id.is_some());
+                transmit.image_id
+            }
+            action => panic!("unexpected {:?}", action),
+        };
+        match &actions[1] {
+            KittyImage::TransmitFrame {
+                transmit, frame, ..
+            } => {
+                assert_eq!(transmit.image_id, image_id);
+                assert_eq!(frame.duration_ms, Some(250));
+            }
+            action => panic!("unexpected {:?}", action),
+        }
+        match &actions[2] {
+            KittyImage::ControlAnimation { animation, .. } => {
+                assert_eq!(animation.image_id, image_id);
+                assert_eq!(animation.state, Some(KittyAnimationState::Running));
+                assert_eq!(animation.frame_number, Some(1));
+                assert_eq!(animation.duration_ms, Some(100));
+            }
+            action => panic!("unexpected {:?}", action),
+        }
+    }
+
+    #[test]
+    fn sixel_is_resized() {
+        let data = png(4, 2);
+        let image = DecodedImage::decode(&data).unwrap();
+        let placement = Placement::new(8, 4, &geometry(), false);
+        let mut out = Output {
+            out: vec![],
+            tmux: false,
+        };
+        command(&[])
+            .show_decoded(ImageProtocol::Sixel, &data, &image, placement, &mut out)
+            .unwrap();
+
+        let actions = parse(&out.out);
+        assert_eq!(actions.len(), 1, "{:?}", actions);
+        match &actions[0] {
+            Action::Sixel(sixel) => {
+                assert_eq!(sixel.pixel_width, Some(8));
+                assert_eq!(sixel.pixel_height, Some(4));
+            }
+            action => panic!("unexpected {:?}", action),
+        }
+    }
+
+    #[test]
+    fn iterm_cells() {
+        let data = png(4, 2);
+        let image = DecodedImage::decode(&data).unwrap();
+        let placement = Placement::new(16, 32, &geometry(), true);
+        let mut out = Output {
+            out: vec![],
+            tmux: false,
+        };
+        command(&[])
+            .show_decoded(ImageProtocol::ITerm, &data, &image, placement, &mut out)
+            .unwrap();
+
+        let actions = parse(&out.out);
+        match &actions[..] {
+            [Action::OperatingSystemCommand(osc)] => match &**osc {
+                OperatingSystemCommand::ITermProprietary(ITermProprietary::File(file)) => {
+                    assert_eq!(file.width, ITermDimension::Cells(2));
+                    assert_eq!(file.height, ITermDimension::Cells(2));
+                    assert!(file.do_not_move_cursor);
+                    assert_eq!(file.data, data);
+                }
+                osc => panic!("unexpected {:?}", osc),
+            },
+            actions => panic!("unexpected {:?}", actions),
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use anyhow::{anyhow, Context};
 use chrono::{DateTime, Utc};
-use config::keyassignment::SpawnTabDomain;
+use codec::MuxEventKind;
+use config::keyassignment::{PaneDirection, SpawnTabDomain};
 use config::wezterm_version;
 use mux::activity::Activity;
 use mux::pane::PaneId;
-use mux::tab::SplitDirection;
+use mux::tab::{PaneEntry, PaneNode, SplitDirection, TabId};
 use mux::window::WindowId;
 use mux::Mux;
 use portable_pty::cmdbuilder::CommandBuilder;
+use portable_pty::PtySize;
+use std::collections::HashMap;
 use std::ffi::OsString;
 use std::io::{Read, Write};
 use std::rc::Rc;
@@ -16,8 +19,11 @@ use tabout::{tabulate_output, Alignment, Column};
 use umask::UmaskSaver;
 use wezterm_client::client::{unix_connect_with_retry, Client};
 use wezterm_gui_subcommands::*;
+use wezterm_term::export::{export_lines, ExportFormat};
+use wezterm_term::Alert;
 
 mod asciicast;
+mod imgcat;
 
 //    let message = "; ❤ 😍🤢\n\x1b[91;mw00t\n\x1b[37;104;m bleet\x1b[0;m.";
 
@@ -77,7 +83,7 @@ enum SubCommand {
     Cli(CliCommand),
 
     #[structopt(name = "imgcat", about = "Output an image to the terminal")]
-    ImageCat(ImgCatCommand),
+    ImageCat(imgcat::ImgCatCommand),
 
     #[structopt(
         name = "set-working-directory",
@@ -119,7 +125,11 @@ struct CliCommand {
 #[derive(Debug, StructOpt, Clone)]
 enum CliSubCommand {
     #[structopt(name = "list", about = "list windows, tabs and panes")]
-    List,
+    List {
+        /// The output format; either table or json
+        #[structopt(long = "format", default_value = "table")]
+        format: CliOutputFormat,
+    },
 
     #[structopt(name = "list-clients", about = "list clients")]
     ListClients,
@@ -216,66 +226,408 @@ Outputs the pane-id for the newly created pane on success"
         /// The text to send. If omitted, will read the text from stdin.
         text: Option<String>,
     },
-}
 
-use termwiz::escape::osc::{
-    ITermDimension, ITermFileData, ITermProprietary, OperatingSystemCommand,
-};
+    /// Retrieve the textual content of a pane and output it to stdout.
+    /// The text may be exported with its colors and attributes by
+    /// specifying a format other than text.
+    #[structopt(name = "get-text")]
+    GetText {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
 
-#[derive(Debug, StructOpt, Clone)]
-struct ImgCatCommand {
-    /// Specify the display width; defaults to "auto" which automatically selects
-    /// an appropriate size.  You may also use an integer value `N` to specify the
-    /// number of cells, or `Npx` to specify the number of pixels, or `N%` to
-    /// size relative to the terminal width.
-    #[structopt(long = "width")]
-    width: Option<ITermDimension>,
-    /// Specify the display height; defaults to "auto" which automatically selects
-    /// an appropriate size.  You may also use an integer value `N` to specify the
-    /// number of cells, or `Npx` to specify the number of pixels, or `N%` to
-    /// size relative to the terminal height.
-    #[structopt(long = "height")]
-    height: Option<ITermDimension>,
-    /// Do not respect the aspect ratio.  The default is to respect the aspect
-    /// ratio
-    #[structopt(long = "no-preserve-aspect-ratio")]
-    no_preserve_aspect_ratio: bool,
-    /// The name of the image file to be displayed.
-    /// If omitted, will attempt to read it from stdin.
-    #[structopt(parse(from_os_str))]
-    file_name: Option<OsString>,
+        /// The starting line number.
+        /// 0 is the first line of terminal screen.
+        /// Negative numbers proceed backwards into the scrollback.
+        /// The default value if unspecified is 0, the first line of
+        /// the terminal screen.
+        #[structopt(long = "start-line", allow_hyphen_values = true)]
+        start_line: Option<isize>,
+
+        /// The ending line number, inclusive.
+        /// 0 is the first line of terminal screen.
+        /// Negative numbers proceed backwards into the scrollback.
+        /// The default value if unspecified is the bottom of
+        /// the terminal screen.
+        #[structopt(long = "end-line", allow_hyphen_values = true)]
+        end_line: Option<isize>,
+
+        /// The format of the output; one of text, html, svg or ansi.
+        #[structopt(long = "format", default_value = "text")]
+        format: ExportFormat,
+    },
+
+    /// Activate (focus) a pane, along with the tab that contains it
+    #[structopt(name = "activate-pane")]
+    ActivatePane {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+    },
+
+    /// Activate an adjacent pane in the specified direction.
+    #[structopt(name = "activate-pane-direction")]
+    ActivatePaneDirection {
+        /// Specify the current pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+
+        /// The direction to switch to; one of Up, Down, Left,
+        /// Right, Next or Prev
+        #[structopt(parse(try_from_str = parse_pane_direction))]
+        direction: PaneDirection,
+    },
+
+    /// Kill a pane
+    #[structopt(name = "kill-pane")]
+    KillPane {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+    },
+
+    /// Zoom, unzoom, or toggle the zoom state of a pane.
+    /// The default is to zoom the pane.
+    #[structopt(name = "zoom-pane")]
+    ZoomPane {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+
+        /// Zooms the pane if it wasn't already zoomed
+        #[structopt(long = "zoom", conflicts_with_all = &["unzoom", "toggle"])]
+        zoom: bool,
+
+        /// Unzooms the pane if it was zoomed
+        #[structopt(long = "unzoom", conflicts_with_all = &["zoom", "toggle"])]
+        unzoom: bool,
+
+        /// Toggles the zoom state of the pane
+        #[structopt(long = "toggle", conflicts_with_all = &["zoom", "unzoom"])]
+        toggle: bool,
+    },
+
+    /// Adjust the size of a pane directionally
+    #[structopt(name = "adjust-pane-size")]
+    AdjustPaneSize {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+
+        /// The direction in which to resize; one of Up, Down,
+        /// Left or Right
+        #[structopt(parse(try_from_str = parse_pane_direction))]
+        direction: PaneDirection,
+
+        /// The number of cells to resize by
+        #[structopt(long = "amount", default_value = "1")]
+        amount: usize,
+    },
+
+    /// Move a pane into a new tab.
+    /// Outputs the tab-id and window-id of the new tab, separated
+    /// by a space.
+    #[structopt(name = "move-pane-to-new-tab")]
+    MovePaneToNewTab {
+        /// Specify the pane that should be moved.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+
+        /// Specify the window into which the new tab will be
+        /// created.
+        /// If omitted, the window associated with the current
+        /// pane is used.
+        #[structopt(long = "window-id")]
+        window_id: Option<WindowId>,
+
+        /// Create the tab in a new window, rather than the window
+        /// currently containing the pane.
+        #[structopt(long = "new-window", conflicts_with = "window_id")]
+        new_window: bool,
+
+        /// When creating a new window, override the default workspace
+        /// name with the provided name.  The default is to use the
+        /// workspace of the window that contains the pane.
+        #[structopt(long = "workspace")]
+        workspace: Option<String>,
+    },
+
+    /// Change the title of a tab.
+    /// An empty title reverts to showing the title of the active pane.
+    #[structopt(name = "set-tab-title")]
+    SetTabTitle {
+        /// Specify the target tab by its id
+        #[structopt(long = "tab-id")]
+        tab_id: Option<TabId>,
+
+        /// Specify the current pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        /// The pane is used to determine the current tab.
+        #[structopt(long = "pane-id", conflicts_with = "tab_id")]
+        pane_id: Option<PaneId>,
+
+        /// The new title for the tab
+        title: String,
+    },
+
+    /// Change the title of a window.
+    /// An empty title reverts to the default title.
+    #[structopt(name = "set-window-title")]
+    SetWindowTitle {
+        /// Specify the target window by its id
+        #[structopt(long = "window-id")]
+        window_id: Option<WindowId>,
+
+        /// Specify the current pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        /// The pane is used to determine the current window.
+        #[structopt(long = "pane-id", conflicts_with = "window_id")]
+        pane_id: Option<PaneId>,
+
+        /// The new title for the window
+        title: String,
+    },
+
+    /// Activate a tab
+    #[structopt(name = "activate-tab")]
+    ActivateTab {
+        /// Specify the target tab by its id
+        #[structopt(long = "tab-id", conflicts_with_all = &["tab_index", "tab_relative"])]
+        tab_id: Option<TabId>,
+
+        /// Specify the target tab by its index within the window
+        /// that holds the current pane.
+        /// Indices are 0-based; negative numbers count back from
+        /// the end, so -1 is the last tab.
+        #[structopt(
+            long = "tab-index",
+            allow_hyphen_values = true,
+            conflicts_with_all = &["tab_id", "tab_relative"]
+        )]
+        tab_index: Option<isize>,
+
+        /// Specify the target tab by its offset from the active
+        /// tab of the window that holds the current pane.
+        /// For example, -1 is the tab to the left and 1 is the
+        /// tab to the right.
+        #[structopt(
+            long = "tab-relative",
+            allow_hyphen_values = true,
+            conflicts_with_all = &["tab_id", "tab_index"]
+        )]
+        tab_relative: Option<isize>,
+
+        /// When used with --tab-relative, prevents wrapping around
+        /// from the last tab to the first, or from the first to
+        /// the last.
+        #[structopt(long = "no-wrap", requires = "tab_relative")]
+        no_wrap: bool,
+
+        /// Specify the current pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        /// The pane is used to determine the current window.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+    },
+
+    /// Rename a workspace
+    #[structopt(name = "rename-workspace")]
+    RenameWorkspace {
+        /// Specify the workspace to rename.
+        /// The default is the workspace of the current pane.
+        #[structopt(long = "workspace")]
+        workspace: Option<String>,
+
+        /// Specify the current pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        /// The pane is used to determine the current workspace.
+        #[structopt(long = "pane-id", conflicts_with = "workspace")]
+        pane_id: Option<PaneId>,
+
+        /// The new name for the workspace
+        new_workspace: String,
+    },
+
+    /// Stream mux notifications, such as pane output, panes and windows
+    /// being added and removed, and alerts, to stdout as newline
+    /// delimited JSON.
+    /// When filters are specified, only the events that relate to the
+    /// specified panes, windows and event types are output.
+    #[structopt(name = "subscribe")]
+    Subscribe {
+        /// Only output events relating to this pane.
+        /// May be specified multiple times.
+        #[structopt(long = "pane-id", number_of_values = 1)]
+        pane_ids: Vec<PaneId>,
+
+        /// Only output events relating to this window, or to
+        /// the panes within it.
+        /// May be specified multiple times.
+        #[structopt(long = "window-id", number_of_values = 1)]
+        window_ids: Vec<WindowId>,
+
+        /// Only output events of this type; one of pane-output,
+        /// pane-added, pane-removed, window-created, window-removed,
+        /// window-invalidated, window-workspace-changed,
+        /// active-workspace-changed, alert or empty.
+        /// May be specified multiple times.
+        #[structopt(long = "event", number_of_values = 1)]
+        events: Vec<MuxEventKind>,
+    },
+
+    /// Download a file from the remote host of a pane that belongs
+    /// to an ssh domain.
+    /// Outputs the local path to which the file was written.
+    #[structopt(name = "sftp-download")]
+    SftpDownload {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+
+        /// The remote file to download.  Relative paths are resolved
+        /// against the current working directory of the pane.
+        remote: String,
+
+        /// Where to save the file.  If this is a directory, the file
+        /// will be placed inside it.  The default is the current
+        /// directory.
+        #[structopt(parse(from_os_str))]
+        local: Option<OsString>,
+    },
+
+    /// Upload a file to the remote host of a pane that belongs
+    /// to an ssh domain.
+    /// Outputs the remote path to which the file was written.
+    #[structopt(name = "sftp-upload")]
+    SftpUpload {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+
+        /// The local file to upload
+        #[structopt(parse(from_os_str))]
+        local: OsString,
+
+        /// Where to save the file on the remote host.  If this is a
+        /// directory, the file will be placed inside it.
+        /// Relative paths are resolved against the current working
+        /// directory of the pane, which is also the default.
+        remote: Option<String>,
+    },
+
+    /// Save the windows, tabs and panes of the multiplexer so that
+    /// they can be restored later.
+    /// Outputs the path of the saved session.
+    #[structopt(name = "save-session")]
+    SaveSession {
+        /// Where to save the session.  The default is the
+        /// `mux_session.session_file` from the configuration.
+        #[structopt(long = "file", parse(from_os_str))]
+        file: Option<OsString>,
+    },
+
+    /// Restore the windows, tabs and panes from a saved session,
+    /// alongside those that are already present.
+    /// Outputs the window-id of each restored window.
+    #[structopt(name = "restore-session")]
+    RestoreSession {
+        /// The session to restore.  The default is the
+        /// `mux_session.session_file` from the configuration.
+        #[structopt(long = "file", parse(from_os_str))]
+        file: Option<OsString>,
+    },
 }
 
-impl ImgCatCommand {
-    fn run(&self) -> anyhow::Result<()> {
-        let mut data = Vec::new();
-        if let Some(file_name) = self.file_name.as_ref() {
-            let mut f = std::fs::File::open(file_name)
-                .with_context(|| anyhow!("reading image file: {:?}", file_name))?;
-            f.read_to_end(&mut data)?;
-        } else {
-            let mut stdin = std::io::stdin();
-            stdin.read_to_end(&mut data)?;
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CliOutputFormat {
+    Table,
+    Json,
+}
 
-        let osc = OperatingSystemCommand::ITermProprietary(ITermProprietary::File(Box::new(
-            ITermFileData {
-                name: None,
-                size: Some(data.len()),
-                width: self.width.unwrap_or_else(Default::default),
-                height: self.height.unwrap_or_else(Default::default),
-                preserve_aspect_ratio: !self.no_preserve_aspect_ratio,
-                inline: true,
-                do_not_move_cursor: false,
-                data,
-            },
-        )));
-        println!("{}", osc);
+impl std::str::FromStr for CliOutputFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "{} is not a valid output format; use table or json",
+                s
+            )),
+        }
+    }
+}
 
-        Ok(())
+fn parse_pane_direction(s: &str) -> Result<PaneDirection, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "up" => Ok(PaneDirection::Up),
+        "down" => Ok(PaneDirection::Down),
+        "left" => Ok(PaneDirection::Left),
+        "right" => Ok(PaneDirection::Right),
+        "next" => Ok(PaneDirection::Next),
+        "prev" => Ok(PaneDirection::Prev),
+        _ => Err(format!(
+            "{} is not a valid direction; use Up, Down, Left, Right, Next or Prev",
+            s
+        )),
     }
 }
 
+/// The representation of an event in the output of `wezterm cli subscribe`
+#[derive(Debug, serde::Serialize)]
+struct CliMuxEvent {
+    event: &'static str,
+    time: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pane_id: Option<PaneId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    window_id: Option<WindowId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workspace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alert: Option<Alert>,
+}
+
+/// The representation of a pane in the output of `wezterm cli list --format json`
+#[derive(Debug, serde::Serialize)]
+struct CliListResultItem {
+    window_id: WindowId,
+    tab_id: TabId,
+    pane_id: PaneId,
+    workspace: String,
+    size: PtySize,
+    title: String,
+    cwd: String,
+    is_active: bool,
+    is_zoomed: bool,
+    tab_title: String,
+    window_title: String,
+}
+
+use termwiz::escape::osc::OperatingSystemCommand;
+
 #[derive(Debug, StructOpt, Clone)]
 struct SetCwdCommand {
     /// The directory to specify.
@@ -323,6 +675,33 @@ fn canon_cwd(cwd: Option<OsString>) -> anyhow::Result<Option<String>> {
     }
 }
 
+/// Resolves a local path against our cwd; the mux server that
+/// performs a transfer may be running with a different cwd
+fn absolute_local_path(path: Option<OsString>) -> anyhow::Result<String> {
+    let cwd = std::env::current_dir()?;
+    let path = match path {
+        Some(path) => cwd.join(path),
+        None => cwd,
+    };
+    Ok(path
+        .to_str()
+        .ok_or_else(|| anyhow!("path is not representable as String"))?
+        .to_string())
+}
+
+/// Resolves a local path for the mux server to read or write.
+/// The server accesses the path on its own host, so we refuse
+/// if it may be running on a different host.
+fn local_path_for_mux(mux_is_local: bool, path: Option<OsString>) -> anyhow::Result<String> {
+    if !mux_is_local {
+        anyhow::bail!(
+            "the mux server is reached via a proxy_command and may be running on \
+             another host, so it cannot access local files"
+        );
+    }
+    absolute_local_path(path)
+}
+
 fn terminate_with_error_message(err: &str) -> ! {
     log::error!("{}; terminating", err);
     std::process::exit(1);
@@ -369,7 +748,7 @@ fn run() -> anyhow::Result<()> {
         SubCommand::SetCwd(cmd) => cmd.run(),
         SubCommand::Cli(cli) => run_cli(config, cli),
         SubCommand::Record(cmd) => cmd.run(config),
-        SubCommand::Replay(cmd) => cmd.run(),
+        SubCommand::Replay(cmd) => cmd.run(config),
     }
 }
 
@@ -446,19 +825,49 @@ async fn resolve_pane_id(client: &Client, pane_id: Option<PaneId>) -> anyhow::Re
     Ok(pane_id)
 }
 
+/// Flattens the pane trees returned by list_panes into their entries
+fn pane_entries(tabs: Vec<PaneNode>) -> Vec<PaneEntry> {
+    let mut entries = vec![];
+    for tabroot in tabs {
+        let mut cursor = tabroot.into_tree().cursor();
+
+        loop {
+            if let Some(entry) = cursor.leaf_mut() {
+                entries.push(entry.clone());
+            }
+            match cursor.preorder_next() {
+                Ok(c) => cursor = c,
+                Err(_) => break,
+            }
+        }
+    }
+    entries
+}
+
+/// Resolves the pane in the same way as resolve_pane_id, and
+/// then looks up its window, tab and workspace
+async fn resolve_pane_entry(client: &Client, pane_id: Option<PaneId>) -> anyhow::Result<PaneEntry> {
+    let pane_id = resolve_pane_id(client, pane_id).await?;
+    pane_entries(client.list_panes().await?.tabs)
+        .into_iter()
+        .find(|entry| entry.pane_id == pane_id)
+        .ok_or_else(|| anyhow!("unable to find pane {}", pane_id))
+}
+
 async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow::Result<()> {
     let mut ui = mux::connui::ConnectionUI::new_headless();
     let initial = true;
 
-    let client = Client::new_default_unix_domain(
-        initial,
-        &mut ui,
-        cli.no_auto_start,
+    let unix_dom = Client::compute_unix_domain(
         cli.prefer_mux,
         cli.class
             .as_deref()
             .unwrap_or(wezterm_gui_subcommands::DEFAULT_WINDOW_CLASS),
     )?;
+    // A mux server that is reached through a proxy command may be
+    // running on another host, where our local paths mean nothing
+    let mux_is_local = unix_dom.proxy_command.is_none();
+    let client = Client::new_unix_domain(None, &unix_dom, initial, &mut ui, cli.no_auto_start)?;
 
     match cli.sub {
         CliSubCommand::ListClients => {
@@ -522,68 +931,101 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
 
             tabulate_output(&cols, &data, &mut std::io::stdout().lock())?;
         }
-        CliSubCommand::List => {
-            let cols = vec![
-                Column {
-                    name: "WINID".to_string(),
-                    alignment: Alignment::Right,
-                },
-                Column {
-                    name: "TABID".to_string(),
-                    alignment: Alignment::Right,
-                },
-                Column {
-                    name: "PANEID".to_string(),
-                    alignment: Alignment::Right,
-                },
-                Column {
-                    name: "WORKSPACE".to_string(),
-                    alignment: Alignment::Left,
-                },
-                Column {
-                    name: "SIZE".to_string(),
-                    alignment: Alignment::Left,
-                },
-                Column {
-                    name: "TITLE".to_string(),
-                    alignment: Alignment::Left,
-                },
-                Column {
-                    name: "CWD".to_string(),
-                    alignment: Alignment::Left,
-                },
-            ];
-            let mut data = vec![];
+        CliSubCommand::List { format } => {
             let panes = client.list_panes().await?;
+            let tab_titles: HashMap<TabId, String> = panes
+                .tabs
+                .iter()
+                .zip(panes.tab_titles.iter())
+                .filter_map(|(tabroot, title)| {
+                    tabroot
+                        .window_and_tab_ids()
+                        .map(|(_window_id, tab_id)| (tab_id, title.clone()))
+                })
+                .collect();
+
+            let window_titles = panes.window_titles;
+
+            let items: Vec<CliListResultItem> = pane_entries(panes.tabs)
+                .into_iter()
+                .map(|entry| CliListResultItem {
+                    tab_title: tab_titles.get(&entry.tab_id).cloned().unwrap_or_default(),
+                    window_title: window_titles
+                        .get(&entry.window_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                    window_id: entry.window_id,
+                    tab_id: entry.tab_id,
+                    pane_id: entry.pane_id,
+                    workspace: entry.workspace,
+                    size: entry.size,
+                    title: entry.title,
+                    cwd: entry
+                        .working_dir
+                        .as_ref()
+                        .map(|url| url.url.as_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    is_active: entry.is_active_pane,
+                    is_zoomed: entry.is_zoomed_pane,
+                })
+                .collect();
 
-            for tabroot in panes.tabs {
-                let mut cursor = tabroot.into_tree().cursor();
-
-                loop {
-                    if let Some(entry) = cursor.leaf_mut() {
-                        data.push(vec![
-                            entry.window_id.to_string(),
-                            entry.tab_id.to_string(),
-                            entry.pane_id.to_string(),
-                            entry.workspace.to_string(),
-                            format!("{}x{}", entry.size.cols, entry.size.rows),
-                            entry.title.clone(),
-                            entry
-                                .working_dir
-                                .as_ref()
-                                .map(|url| url.url.as_str())
-                                .unwrap_or("")
-                                .to_string(),
-                        ]);
-                    }
-                    match cursor.preorder_next() {
-                        Ok(c) => cursor = c,
-                        Err(_) => break,
-                    }
+            match format {
+                CliOutputFormat::Json => {
+                    let mut out = std::io::stdout();
+                    serde_json::to_writer_pretty(&mut out, &items)?;
+                    writeln!(out)?;
+                }
+                CliOutputFormat::Table => {
+                    let cols = vec![
+                        Column {
+                            name: "WINID".to_string(),
+                            alignment: Alignment::Right,
+                        },
+                        Column {
+                            name: "TABID".to_string(),
+                            alignment: Alignment::Right,
+                        },
+                        Column {
+                            name: "PANEID".to_string(),
+                            alignment: Alignment::Right,
+                        },
+                        Column {
+                            name: "WORKSPACE".to_string(),
+                            alignment: Alignment::Left,
+                        },
+                        Column {
+                            name: "SIZE".to_string(),
+                            alignment: Alignment::Left,
+                        },
+                        Column {
+                            name: "TITLE".to_string(),
+                            alignment: Alignment::Left,
+                        },
+                        Column {
+                            name: "CWD".to_string(),
+                            alignment: Alignment::Left,
+                        },
+                    ];
+                    let data: Vec<Vec<String>> = items
+                        .into_iter()
+                        .map(|item| {
+                            vec![
+                                item.window_id.to_string(),
+                                item.tab_id.to_string(),
+                                item.pane_id.to_string(),
+                                item.workspace,
+                                format!("{}x{}", item.size.cols, item.size.rows),
+                                item.title,
+                                item.cwd,
+                            ]
+                        })
+                        .collect();
+
+                    tabulate_output(&cols, &data, &mut std::io::stdout().lock())?;
                 }
             }
-
-            tabulate_output(&cols, &data, &mut std::io::stdout().lock())?;
         }
         CliSubCommand::SplitPane {
             pane_id,
@@ -633,6 +1075,298 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
                 .send_paste(codec::SendPaste { pane_id, data })
                 .await?;
         }
+        CliSubCommand::GetText {
+            pane_id,
+            start_line,
+            end_line,
+            format,
+        } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            let info = client
+                .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
+                .await?;
+            let dims = info.dimensions;
+
+            let start_line = start_line.unwrap_or(0);
+            let end_line = end_line.unwrap_or(dims.viewport_rows as isize - 1);
+            let first_row = (dims.physical_top + start_line).max(dims.scrollback_top);
+            let last_row = dims.physical_top + end_line;
+
+            let lines = if last_row < first_row {
+                vec![]
+            } else {
+                let response = client
+                    .get_lines(codec::GetLines {
+                        pane_id,
+                        lines: vec![first_row..last_row + 1],
+                    })
+                    .await?;
+                let (lines, _images) = response.lines.extract_data();
+                lines.into_iter().map(|(_, line)| line).collect()
+            };
+
+            let palette = client
+                .get_palette(codec::GetPalette { pane_id })
+                .await?
+                .palette;
+
+            print!("{}", export_lines(&lines, &palette, format));
+        }
+        CliSubCommand::ActivatePane { pane_id } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            client
+                .activate_pane(codec::ActivatePane { pane_id })
+                .await?;
+        }
+        CliSubCommand::ActivatePaneDirection { pane_id, direction } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            client
+                .activate_pane_direction(codec::ActivatePaneDirection { pane_id, direction })
+                .await?;
+        }
+        CliSubCommand::KillPane { pane_id } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            client.kill_pane(codec::KillPane { pane_id }).await?;
+        }
+        CliSubCommand::ZoomPane {
+            pane_id,
+            zoom: _,
+            unzoom,
+            toggle,
+        } => {
+            let entry = resolve_pane_entry(&client, pane_id).await?;
+            let zoomed = if toggle {
+                !entry.is_zoomed_pane
+            } else {
+                !unzoom
+            };
+            client
+                .set_zoomed(codec::SetPaneZoomed {
+                    containing_tab_id: entry.tab_id,
+                    pane_id: entry.pane_id,
+                    zoomed,
+                })
+                .await?;
+        }
+        CliSubCommand::AdjustPaneSize {
+            pane_id,
+            direction,
+            amount,
+        } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            client
+                .adjust_pane_size(codec::AdjustPaneSize {
+                    pane_id,
+                    direction,
+                    amount,
+                })
+                .await?;
+        }
+        CliSubCommand::MovePaneToNewTab {
+            pane_id,
+            window_id,
+            new_window,
+            workspace,
+        } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            let moved = client
+                .move_pane_to_new_tab(codec::MovePaneToNewTab {
+                    pane_id,
+                    window_id,
+                    new_window,
+                    workspace,
+                })
+                .await?;
+            log::debug!("{:?}", moved);
+            println!("{} {}", moved.tab_id, moved.window_id);
+        }
+        CliSubCommand::SetTabTitle {
+            tab_id,
+            pane_id,
+            title,
+        } => {
+            let tab_id = match tab_id {
+                Some(tab_id) => tab_id,
+                None => resolve_pane_entry(&client, pane_id).await?.tab_id,
+            };
+            client
+                .set_tab_title(codec::SetTabTitle { tab_id, title })
+                .await?;
+        }
+        CliSubCommand::SetWindowTitle {
+            window_id,
+            pane_id,
+            title,
+        } => {
+            let window_id = match window_id {
+                Some(window_id) => window_id,
+                None => resolve_pane_entry(&client, pane_id).await?.window_id,
+            };
+            client
+                .set_window_title(codec::SetWindowTitle { window_id, title })
+                .await?;
+        }
+        CliSubCommand::ActivateTab {
+            tab_id,
+            tab_index,
+            tab_relative,
+            no_wrap,
+            pane_id,
+        } => {
+            let tab_id = match tab_id {
+                Some(tab_id) => tab_id,
+                None => {
+                    let pane_id = resolve_pane_id(&client, pane_id).await?;
+                    let entries = pane_entries(client.list_panes().await?.tabs);
+                    let current = entries
+                        .iter()
+                        .find(|entry| entry.pane_id == pane_id)
+                        .ok_or_else(|| anyhow!("unable to find pane {}", pane_id))?;
+
+                    // The tabs of the current window, in order
+                    let mut tab_ids: Vec<TabId> = vec![];
+                    for entry in &entries {
+                        if entry.window_id == current.window_id && !tab_ids.contains(&entry.tab_id)
+                        {
+                            tab_ids.push(entry.tab_id);
+                        }
+                    }
+                    let max = tab_ids.len() as isize;
+
+                    let idx = if let Some(tab_index) = tab_index {
+                        let idx = if tab_index < 0 {
+                            max + tab_index
+                        } else {
+                            tab_index
+                        };
+                        if idx < 0 || idx >= max {
+                            anyhow::bail!("tab index {} is out of range", tab_index);
+                        }
+                        idx
+                    } else if let Some(delta) = tab_relative {
+                        let active = tab_ids
+                            .iter()
+                            .position(|&id| id == current.tab_id)
+                            .unwrap_or(0) as isize;
+                        let idx = active + delta;
+                        if no_wrap {
+                            idx.clamp(0, max - 1)
+                        } else {
+                            idx.rem_euclid(max)
+                        }
+                    } else {
+                        anyhow::bail!(
+                            "one of --tab-id, --tab-index or --tab-relative must be specified"
+                        );
+                    };
+                    tab_ids[idx as usize]
+                }
+            };
+            client.activate_tab(codec::ActivateTab { tab_id }).await?;
+        }
+        CliSubCommand::RenameWorkspace {
+            workspace,
+            pane_id,
+            new_workspace,
+        } => {
+            let old_workspace = match workspace {
+                Some(workspace) => workspace,
+                None => resolve_pane_entry(&client, pane_id).await?.workspace,
+            };
+            client
+                .rename_workspace(codec::RenameWorkspace {
+                    old_workspace,
+                    new_workspace,
+                })
+                .await?;
+        }
+        CliSubCommand::Subscribe {
+            pane_ids,
+            window_ids,
+            events,
+        } => {
+            let rx = client
+                .subscribe_to_mux_events(codec::SubscribeToMuxEvents {
+                    pane_ids,
+                    window_ids,
+                    kinds: events,
+                })
+                .await?;
+
+            let mut out = std::io::stdout();
+            while let Ok(event) = rx.recv().await {
+                let event = CliMuxEvent {
+                    event: event.kind.as_str(),
+                    time: Utc::now().to_rfc3339(),
+                    pane_id: event.pane_id,
+                    window_id: event.window_id,
+                    workspace: event.workspace,
+                    alert: event.alert,
+                };
+                serde_json::to_writer(&mut out, &event)?;
+                writeln!(out)?;
+                out.flush()?;
+            }
+        }
+        CliSubCommand::SftpDownload {
+            pane_id,
+            remote,
+            local,
+        } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+
+            let result = client
+                .sftp_transfer(codec::SftpTransfer {
+                    pane_id,
+                    direction: codec::SftpTransferDirection::Download,
+                    remote_path: remote,
+                    local_path: local_path_for_mux(mux_is_local, local)?,
+                })
+                .await?;
+
+            log::debug!("{:?}", result);
+            println!("{}", result.local_path);
+        }
+        CliSubCommand::SftpUpload {
+            pane_id,
+            local,
+            remote,
+        } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+
+            let result = client
+                .sftp_transfer(codec::SftpTransfer {
+                    pane_id,
+                    direction: codec::SftpTransferDirection::Upload,
+                    remote_path: remote.unwrap_or_default(),
+                    local_path: local_path_for_mux(mux_is_local, Some(local))?,
+                })
+                .await?;
+
+            log::debug!("{:?}", result);
+            println!("{}", result.remote_path);
+        }
+        CliSubCommand::SaveSession { file } => {
+            let path = match file {
+                Some(file) => Some(local_path_for_mux(mux_is_local, Some(file))?),
+                None => None,
+            };
+            let saved = client.save_session(codec::SaveSession { path }).await?;
+            log::debug!("{:?}", saved);
+            println!("{}", saved.path);
+        }
+        CliSubCommand::RestoreSession { file } => {
+            let path = match file {
+                Some(file) => Some(local_path_for_mux(mux_is_local, Some(file))?),
+                None => None,
+            };
+            let restored = client
+                .restore_session(codec::RestoreSession { path })
+                .await?;
+            for window_id in restored.window_ids {
+                println!("{}", window_id);
+            }
+        }
         CliSubCommand::SpawnCommand {
             cwd,
             prog,